@@ -157,6 +157,24 @@ fn bench_eval_loop_strings_no_build(bench: &mut Bencher) {
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
+#[bench]
+fn bench_eval_loop_strings_short(bench: &mut Bencher) {
+    let script = r#"
+        let n = 0;
+        for x in range(0, 10000) {
+            let s = "x" + (x % 10);
+            if s == "x1" { n += 1; }
+        }
+    "#;
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let ast = engine.compile(script).unwrap();
+
+    bench.iter(|| engine.consume_ast(&ast).unwrap());
+}
+
 #[bench]
 fn bench_eval_switch(bench: &mut Bencher) {
     let script = r#"
@@ -75,3 +75,65 @@ fn bench_eval_scope_complex(bench: &mut Bencher) {
 
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
+
+#[bench]
+fn bench_eval_scope_loop(bench: &mut Bencher) {
+    let script = r#"
+            let total = 0;
+            for i in range(0, 100) {
+                total += requests_made - requests_succeeded;
+            }
+            total
+        "#;
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let mut scope = Scope::new();
+    scope.push("requests_made", 99 as INT);
+    scope.push("requests_succeeded", 90 as INT);
+
+    for n in 0..20 {
+        scope.push(format!("counter{}", n), n as INT);
+    }
+
+    let ast = engine.compile(script).unwrap();
+    let len = scope.len();
+
+    bench.iter(|| {
+        let result = engine.eval_ast_with_scope::<INT>(&mut scope, &ast).unwrap();
+        scope.rewind(len);
+        result
+    });
+}
+
+#[bench]
+fn bench_eval_scope_loop_large(bench: &mut Bencher) {
+    let script = r#"
+            let total = 0;
+            for i in range(0, 100) {
+                total += requests_made - requests_succeeded;
+            }
+            total
+        "#;
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let mut scope = Scope::new();
+    scope.push("requests_made", 99 as INT);
+    scope.push("requests_succeeded", 90 as INT);
+
+    for n in 0..500 {
+        scope.push(format!("counter{}", n), n as INT);
+    }
+
+    let ast = engine.compile(script).unwrap();
+    let len = scope.len();
+
+    bench.iter(|| {
+        let result = engine.eval_ast_with_scope::<INT>(&mut scope, &ast).unwrap();
+        scope.rewind(len);
+        result
+    });
+}
@@ -3,7 +3,7 @@
 ///! Test 1,000 iterations
 extern crate test;
 
-use rhai::{Engine, OptimizationLevel, INT};
+use rhai::{Bytecode, Engine, OptimizationLevel, INT};
 use test::Bencher;
 
 #[bench]
@@ -24,6 +24,25 @@ fn bench_iterations_1000(bench: &mut Bencher) {
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
+#[bench]
+fn bench_iterations_1000_bytecode(bench: &mut Bencher) {
+    let script = r#"
+            let x = 1_000;
+            
+            while x > 0 {
+                x -= 1;
+            }
+        "#;
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let ast = engine.compile(script).unwrap();
+    let bytecode = Bytecode::compile(&ast);
+
+    bench.iter(|| engine.eval_bytecode::<()>(&bytecode).unwrap());
+}
+
 #[bench]
 fn bench_iterations_fibonacci(bench: &mut Bencher) {
     let script = r#"
@@ -45,3 +64,26 @@ fn bench_iterations_fibonacci(bench: &mut Bencher) {
 
     bench.iter(|| engine.eval_ast::<INT>(&ast).unwrap());
 }
+
+#[bench]
+fn bench_iterations_fibonacci_bytecode(bench: &mut Bencher) {
+    let script = r#"
+        fn fibonacci(n) {
+            if n < 2 {
+                n
+            } else {
+                fibonacci(n-1) + fibonacci(n-2)
+            }
+        }
+
+        fibonacci(20)
+    "#;
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let ast = engine.compile(script).unwrap();
+    let bytecode = Bytecode::compile(&ast);
+
+    bench.iter(|| engine.eval_bytecode::<INT>(&bytecode).unwrap());
+}
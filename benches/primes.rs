@@ -3,7 +3,7 @@
 ///! Test evaluating expressions
 extern crate test;
 
-use rhai::{Engine, OptimizationLevel};
+use rhai::{Bytecode, Engine, OptimizationLevel};
 use test::Bencher;
 
 // This script uses the Sieve of Eratosthenes to calculate prime numbers.
@@ -41,3 +41,14 @@ fn bench_eval_primes(bench: &mut Bencher) {
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
+
+#[bench]
+fn bench_eval_primes_bytecode(bench: &mut Bencher) {
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let ast = engine.compile(SCRIPT).unwrap();
+    let bytecode = Bytecode::compile(&ast);
+
+    bench.iter(|| engine.eval_bytecode::<()>(&bytecode).unwrap());
+}
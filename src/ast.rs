@@ -1,6 +1,7 @@
 //! Module defining the AST (abstract syntax tree).
 
-use crate::dynamic::{AccessMode, Union};
+use crate::dynamic::{AccessMode, MapKey, Union};
+use crate::engine::GlobalFnResolutionCache;
 use crate::fn_native::shared_make_mut;
 use crate::module::NamespaceRef;
 use crate::stdlib::{
@@ -64,6 +65,9 @@ pub struct ScriptFnDef {
     pub externals: StaticVec<ImmutableString>,
     /// Function doc-comments (if any).
     pub comments: StaticVec<String>,
+    /// Results of calls to this function cached for memoization.
+    #[cfg(not(feature = "no_function"))]
+    pub memo: crate::engine::FnMemoCache,
 }
 
 impl fmt::Display for ScriptFnDef {
@@ -86,6 +90,107 @@ impl fmt::Display for ScriptFnDef {
     }
 }
 
+impl ScriptFnDef {
+    /// Can results of calls to this function be cached for memoization?
+    ///
+    /// The function body must not use `this`, import or export modules, capture variables,
+    /// access modules, use custom syntax or create function pointers, and all functions it calls
+    /// (including operators, property getters/setters and indexers) must be pure.
+    ///
+    /// Script-defined functions in `lib` that may be called must satisfy the same conditions.
+    /// Native Rust functions must all be marked pure; functions not registered at all are
+    /// built-in operators, which are pure, or cause an error, which is not cached.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn is_memoizable(&self, engine: &crate::Engine, lib: &[&Module]) -> bool {
+        #[cfg(not(feature = "no_index"))]
+        use crate::engine::{FN_IDX_GET, FN_IDX_SET};
+        use crate::engine::{
+            KEYWORD_DEBUG, KEYWORD_EVAL, KEYWORD_FN_PTR, KEYWORD_FN_PTR_CALL, KEYWORD_FN_PTR_CURRY,
+            KEYWORD_IS_DEF_VAR, KEYWORD_PRINT,
+        };
+
+        fn check_fn(
+            name: &str,
+            engine: &crate::Engine,
+            lib: &[&Module],
+            visited: &mut Vec<usize>,
+        ) -> bool {
+            engine.is_pure_native_fn(lib, name)
+                && lib
+                    .iter()
+                    .flat_map(|m| m.iter_script_fn())
+                    .filter(|(_, _, fn_name, _, _)| *fn_name == name)
+                    .all(|(_, _, _, _, f)| check(f, engine, lib, visited))
+        }
+
+        fn check(
+            fn_def: &ScriptFnDef,
+            engine: &crate::Engine,
+            lib: &[&Module],
+            visited: &mut Vec<usize>,
+        ) -> bool {
+            let addr = fn_def as *const _ as usize;
+
+            // Assume functions already being checked (i.e. recursive calls) to be memoizable
+            if visited.contains(&addr) {
+                return true;
+            }
+            visited.push(addr);
+
+            #[cfg(not(feature = "no_closure"))]
+            if !fn_def.externals.is_empty() {
+                return false;
+            }
+
+            let path = &mut Default::default();
+
+            fn_def.body.statements.iter().all(|stmt| {
+                stmt.walk(path, &mut |path| match path.last().unwrap() {
+                    #[cfg(not(feature = "no_module"))]
+                    ASTNode::Stmt(Stmt::Import(_, _, _)) | ASTNode::Stmt(Stmt::Export(_, _)) => {
+                        false
+                    }
+                    #[cfg(not(feature = "no_closure"))]
+                    ASTNode::Stmt(Stmt::Share(_)) => false,
+                    ASTNode::Stmt(Stmt::Assignment(x, _)) => match x.2 {
+                        Some(ref op) => {
+                            check_fn(&op.op, engine, lib, visited)
+                                && check_fn(&op.op[..op.op.len() - 1], engine, lib, visited)
+                        }
+                        None => true,
+                    },
+                    ASTNode::Expr(Expr::Variable(x)) => {
+                        x.1.is_none() && x.2.name != crate::engine::KEYWORD_THIS
+                    }
+                    ASTNode::Expr(Expr::Property(x)) => {
+                        check_fn(&(x.0).0, engine, lib, visited)
+                            && check_fn(&(x.1).0, engine, lib, visited)
+                    }
+                    #[cfg(not(feature = "no_index"))]
+                    ASTNode::Expr(Expr::Index(_, _)) => {
+                        check_fn(FN_IDX_GET, engine, lib, visited)
+                            && check_fn(FN_IDX_SET, engine, lib, visited)
+                    }
+                    ASTNode::Expr(Expr::FnPointer(_, _)) | ASTNode::Expr(Expr::Custom(_, _)) => {
+                        false
+                    }
+                    ASTNode::Expr(Expr::FnCall(x, _)) if x.capture || x.namespace.is_some() => {
+                        false
+                    }
+                    ASTNode::Expr(Expr::FnCall(x, _)) => match x.name.as_ref() {
+                        KEYWORD_PRINT | KEYWORD_DEBUG | KEYWORD_EVAL | KEYWORD_IS_DEF_VAR
+                        | KEYWORD_FN_PTR | KEYWORD_FN_PTR_CALL | KEYWORD_FN_PTR_CURRY => false,
+                        name => check_fn(name, engine, lib, visited),
+                    },
+                    _ => true,
+                })
+            })
+        }
+
+        check(self, engine, lib, &mut Default::default())
+    }
+}
+
 /// A type containing the metadata of a script-defined function.
 ///
 /// Created by [`AST::iter_functions`].
@@ -155,6 +260,8 @@ pub struct AST {
     /// Embedded module resolver, if any.
     #[cfg(not(feature = "no_module"))]
     resolver: Option<Shared<crate::module::resolvers::StaticModuleResolver>>,
+    /// Function resolution cache warmed by previous evaluations.
+    fn_resolution_cache: GlobalFnResolutionCache,
 }
 
 impl Default for AST {
@@ -166,6 +273,7 @@ impl Default for AST {
             functions: Default::default(),
             #[cfg(not(feature = "no_module"))]
             resolver: None,
+            fn_resolution_cache: Default::default(),
         }
     }
 }
@@ -186,6 +294,7 @@ impl AST {
             functions: functions.into(),
             #[cfg(not(feature = "no_module"))]
             resolver: None,
+            fn_resolution_cache: Default::default(),
         }
     }
     /// Create a new [`AST`] with a source name.
@@ -204,6 +313,7 @@ impl AST {
             functions: functions.into(),
             #[cfg(not(feature = "no_module"))]
             resolver: None,
+            fn_resolution_cache: Default::default(),
         }
     }
     /// Get the source, if any.
@@ -312,6 +422,11 @@ impl AST {
         self.resolver = Some(resolver.into());
         self
     }
+    /// Get the function resolution cache kept across evaluations of the [`AST`].
+    #[inline(always)]
+    pub(crate) fn fn_resolution_cache(&self) -> &GlobalFnResolutionCache {
+        &self.fn_resolution_cache
+    }
     /// Clone the [`AST`]'s functions into a new [`AST`].
     /// No statements are cloned.
     ///
@@ -343,6 +458,7 @@ impl AST {
             functions: functions.into(),
             #[cfg(not(feature = "no_module"))]
             resolver: self.resolver.clone(),
+            fn_resolution_cache: Default::default(),
         }
     }
     /// Clone the [`AST`]'s script statements into a new [`AST`].
@@ -355,6 +471,7 @@ impl AST {
             functions: Default::default(),
             #[cfg(not(feature = "no_module"))]
             resolver: self.resolver.clone(),
+            fn_resolution_cache: Default::default(),
         }
     }
     /// Merge two [`AST`] into one.  Both [`AST`]'s are untouched and a new, merged, version
@@ -541,11 +658,18 @@ impl AST {
         let mut functions = functions.as_ref().clone();
         functions.merge_filtered(&other.functions, &filter);
 
-        if let Some(source) = source {
+        let ast = if let Some(source) = source {
             Self::new_with_source(merged.statements, functions, source)
         } else {
             Self::new(merged.statements, functions)
+        };
+
+        #[cfg(not(feature = "no_function"))]
+        if !other.functions.is_empty() {
+            ast.clear_fn_memo_cache();
         }
+
+        ast
     }
     /// Combine one [`AST`] with another.  The second [`AST`] is consumed.
     ///
@@ -610,6 +734,9 @@ impl AST {
 
         if !other.functions.is_empty() {
             shared_make_mut(&mut self.functions).merge_filtered(&other.functions, &filter);
+            self.fn_resolution_cache = Default::default();
+            #[cfg(not(feature = "no_function"))]
+            self.clear_fn_memo_cache();
         }
         self
     }
@@ -646,6 +773,8 @@ impl AST {
     ) -> &mut Self {
         if !self.functions.is_empty() {
             shared_make_mut(&mut self.functions).retain_script_functions(filter);
+            self.fn_resolution_cache = Default::default();
+            self.clear_fn_memo_cache();
         }
         self
     }
@@ -653,7 +782,6 @@ impl AST {
     ///
     /// Not available under [`no_function`].
     #[cfg(not(feature = "no_function"))]
-    #[cfg(not(feature = "no_module"))]
     #[inline(always)]
     pub(crate) fn iter_fn_def(&self) -> impl Iterator<Item = &ScriptFnDef> {
         self.functions
@@ -670,6 +798,16 @@ impl AST {
             .iter_script_fn()
             .map(|(_, _, _, _, fn_def)| fn_def.as_ref().into())
     }
+    /// Discard all results of calls to the [`AST`]'s functions cached for memoization.
+    ///
+    /// See [`Engine::set_max_fn_memo_size`][crate::Engine::set_max_fn_memo_size].
+    ///
+    /// Not available under `no_function`.
+    #[cfg(not(feature = "no_function"))]
+    #[inline(always)]
+    pub fn clear_fn_memo_cache(&self) {
+        self.iter_fn_def().for_each(|fn_def| fn_def.memo.clear());
+    }
     /// Clear all function definitions in the [`AST`].
     ///
     /// Not available under `no_function`.
@@ -677,6 +815,7 @@ impl AST {
     #[inline(always)]
     pub fn clear_functions(&mut self) {
         self.functions = Default::default();
+        self.fn_resolution_cache = Default::default();
     }
     /// Clear all statements in the [`AST`], leaving only function definitions.
     #[inline(always)]
@@ -1384,6 +1523,8 @@ pub struct FnCallExpr {
     pub hash: FnHash,
     /// Does this function call capture the parent scope?
     pub capture: bool,
+    /// Is this a call to the enclosing script-defined function in tail position?
+    pub tail_call: bool,
     /// List of function call arguments.
     pub args: StaticVec<Expr>,
     /// Namespace of the function, if any. Boxed because it occurs rarely.
@@ -1518,18 +1659,35 @@ pub enum Expr {
     FnPointer(ImmutableString, Position),
     /// [ expr, ... ]
     Array(Box<StaticVec<Expr>>, Position),
-    /// #{ name:expr, ... }
-    Map(Box<StaticVec<(Ident, Expr)>>, Position),
+    /// #{ key:expr, ... }
+    Map(Box<StaticVec<(MapKey, Expr)>>, Position),
     /// ()
     Unit(Position),
-    /// Variable access - (optional index, optional (hash, modules), variable name)
-    Variable(Box<(Option<NonZeroUsize>, Option<(u64, NamespaceRef)>, Ident)>),
+    /// Variable access - (optional index, optional (hash, modules), variable name, optional lookup hash)
+    ///
+    /// The lookup hash is only set for variables not declared within the script itself
+    /// (e.g. variables in an external [`Scope`][crate::Scope] or captured variables),
+    /// so that their positions in the scope can be cached during evaluation.
+    Variable(
+        Box<(
+            Option<NonZeroUsize>,
+            Option<(u64, NamespaceRef)>,
+            Ident,
+            Option<u64>,
+        )>,
+    ),
     /// Property access - ((getter, hash), (setter, hash), prop)
     Property(Box<((ImmutableString, u64), (ImmutableString, u64), Ident)>),
     /// { [statement][Stmt] ... }
     Stmt(Box<StmtBlock>),
     /// func `(` expr `,` ... `)`
     FnCall(Box<FnCallExpr>, Position),
+    /// Body of a call to a script-defined function inlined by the optimizer - (function name, body)
+    ///
+    /// Errors raised within the body are wrapped in
+    /// [`ErrorInFunctionCall`][crate::EvalAltResult::ErrorInFunctionCall] as for the function call.
+    #[cfg(not(feature = "no_function"))]
+    InlinedFnCall(Box<(ImmutableString, StmtBlock)>, Position),
     /// lhs `.` rhs
     Dot(Box<BinaryExpr>, Position),
     /// expr `[` expr `]`
@@ -1587,7 +1745,7 @@ impl Expr {
                 ));
                 map.extend(
                     x.iter()
-                        .map(|(k, v)| (k.name.clone(), v.get_constant_value().unwrap())),
+                        .map(|(k, v)| (k.clone(), v.get_constant_value().unwrap())),
                 );
                 Dynamic(Union::Map(Box::new(map), AccessMode::ReadOnly))
             }
@@ -1603,6 +1761,18 @@ impl Expr {
             _ => None,
         }
     }
+    /// Get the variable access of an [`Expr::Variable`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the expression is not [`Expr::Variable`].
+    #[inline(always)]
+    pub(crate) fn as_variable(&self) -> &crate::engine::VariableAccess {
+        match self {
+            Self::Variable(x) => x.as_ref(),
+            _ => unreachable!("Expr::Variable expected, but gets {:?}", self),
+        }
+    }
     /// Get the [position][Position] of the expression.
     #[inline]
     pub fn position(&self) -> Position {
@@ -1622,6 +1792,8 @@ impl Expr {
             Self::Stmt(x) => x.pos,
             Self::Variable(x) => (x.2).pos,
             Self::FnCall(_, pos) => *pos,
+            #[cfg(not(feature = "no_function"))]
+            Self::InlinedFnCall(_, pos) => *pos,
 
             Self::And(x, _) | Self::Or(x, _) => x.lhs.position(),
 
@@ -1651,6 +1823,8 @@ impl Expr {
             Self::Property(x) => (x.2).pos = new_pos,
             Self::Stmt(x) => x.pos = new_pos,
             Self::FnCall(_, pos) => *pos = new_pos,
+            #[cfg(not(feature = "no_function"))]
+            Self::InlinedFnCall(_, pos) => *pos = new_pos,
             Self::And(_, pos) | Self::Or(_, pos) => *pos = new_pos,
             Self::Unit(pos) => *pos = new_pos,
             Self::Dot(_, pos) | Self::Index(_, pos) => *pos = new_pos,
@@ -1734,6 +1908,9 @@ impl Expr {
             | Self::Or(_, _)
             | Self::Unit(_) => false,
 
+            #[cfg(not(feature = "no_function"))]
+            Self::InlinedFnCall(_, _) => false,
+
             Self::StringConstant(_, _)
             | Self::FnCall(_, _)
             | Self::Stmt(_)
@@ -1786,6 +1963,14 @@ impl Expr {
                     }
                 }
             }
+            #[cfg(not(feature = "no_function"))]
+            Self::InlinedFnCall(x, _) => {
+                for s in &x.1.statements {
+                    if !s.walk(path, on_node) {
+                        return false;
+                    }
+                }
+            }
             Self::Array(x, _) => {
                 for e in x.as_ref() {
                     if !e.walk(path, on_node) {
@@ -0,0 +1,1439 @@
+//! Module that defines the binary format of a compiled [`AST`], for caching.
+
+use crate::ast::{
+    BinaryExpr, CustomExpr, Expr, FnCallExpr, FnHash, Ident, OpAssignment, ReturnType, Stmt,
+    StmtBlock,
+};
+use crate::dynamic::{MapKey, Union};
+use crate::module::NamespaceRef;
+use crate::stdlib::{
+    borrow::Cow,
+    boxed::Box,
+    collections::HashMap,
+    format,
+    iter::empty,
+    num::NonZeroUsize,
+    str,
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::utils::StraightHasherBuilder;
+use crate::{
+    Dynamic, EvalAltResult, FnNamespace, FnPtr, ImmutableString, Module, Position, Shared,
+    StaticVec, AST, INT,
+};
+
+#[cfg(not(feature = "no_function"))]
+use crate::ast::{FnAccess, ScriptFnDef};
+
+#[cfg(not(feature = "no_index"))]
+use crate::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::Map;
+
+/// Magic bytes at the start of a serialized [`AST`].
+const MAGIC: &[u8; 4] = b"RHAI";
+
+/// Version of the binary format.
+///
+/// Bump this whenever the layout of any serialized data structure changes.
+const VERSION: u16 = 7;
+
+/// Maximum nesting depth of statements, expressions, values and modules when loading.
+///
+/// Loading is recursive, so deeper nesting in malformed data could overflow the stack.
+#[cfg(debug_assertions)]
+const MAX_NESTING_DEPTH: usize = 64;
+/// Maximum nesting depth of statements, expressions, values and modules when loading.
+///
+/// Loading is recursive, so deeper nesting in malformed data could overflow the stack.
+#[cfg(not(debug_assertions))]
+const MAX_NESTING_DEPTH: usize = 256;
+
+/// Features affecting the layout of a serialized [`AST`], in bit order.
+const FEATURES: [(&str, bool); 10] = [
+    ("no_float", cfg!(feature = "no_float")),
+    ("f32_float", cfg!(feature = "f32_float")),
+    ("only_i32", cfg!(feature = "only_i32")),
+    ("decimal", cfg!(feature = "decimal")),
+    ("bigint", cfg!(feature = "bigint")),
+    ("no_index", cfg!(feature = "no_index")),
+    ("no_object", cfg!(feature = "no_object")),
+    ("no_function", cfg!(feature = "no_function")),
+    ("no_closure", cfg!(feature = "no_closure")),
+    ("no_module", cfg!(feature = "no_module")),
+];
+
+/// Bit flags of the [features][FEATURES] turned on.
+#[inline(always)]
+fn feature_flags() -> u16 {
+    FEATURES
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, on))| *on)
+        .fold(0, |flags, (bit, _)| flags | (1 << bit))
+}
+
+/// Fingerprint of the hasher used to calculate the pre-calculated hashes stored in an [`AST`].
+///
+/// Hashes are serialized as-is, so an [`AST`] cannot be loaded by a build with a different hasher.
+#[inline(always)]
+fn hasher_fingerprint() -> u64 {
+    crate::calc_fn_hash(empty(), "rhai", 0)
+}
+
+/// Create an error for a failed serialization or deserialization.
+#[inline(always)]
+fn make_err(desc: &str, msg: impl AsRef<str>) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorRuntime(format!("{}: {}", desc, msg.as_ref()).into(), Position::NONE).into()
+}
+
+/// Create an error for a failed serialization.
+#[inline(always)]
+fn save_err(msg: impl AsRef<str>) -> Box<EvalAltResult> {
+    make_err("Cannot serialize AST", msg)
+}
+
+/// Create an error for a failed deserialization.
+#[inline(always)]
+fn load_err(msg: impl AsRef<str>) -> Box<EvalAltResult> {
+    make_err("Cannot load AST", msg)
+}
+
+impl AST {
+    /// Serialize the [`AST`] into a versioned binary format.
+    ///
+    /// The binary data contains the global statements, all script-defined functions,
+    /// the embedded [module resolver][crate::module_resolvers::StaticModuleResolver] (if any)
+    /// and the source, and can be loaded back via [`AST::from_bytes`] without parsing.
+    ///
+    /// # Errors
+    ///
+    /// Native Rust functions, type iterators and values of custom types cannot be serialized.
+    /// They may only occur in modules inserted into the embedded module resolver,
+    /// or in constants propagated by the optimizer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, AST};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile("let x = 40; x + 2")?;
+    ///
+    /// let bytes = ast.to_bytes()?;
+    ///
+    /// let ast = AST::from_bytes(&bytes)?;
+    ///
+    /// assert_eq!(engine.eval_ast::<i64>(&ast)?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Box<EvalAltResult>> {
+        let mut writer = Writer {
+            buf: Vec::new(),
+            modules: Default::default(),
+        };
+
+        writer.buf.extend_from_slice(MAGIC);
+        writer.buf.extend_from_slice(&VERSION.to_le_bytes());
+        writer.buf.extend_from_slice(&feature_flags().to_le_bytes());
+        writer.u64(hasher_fingerprint());
+
+        writer.opt_str(self.source());
+
+        let statements: &[Stmt] = self.as_ref();
+        writer.usize(statements.len());
+        statements.iter().try_for_each(|stmt| writer.stmt(stmt))?;
+
+        writer.module(self.as_ref())?;
+
+        #[cfg(not(feature = "no_module"))]
+        if let Some(resolver) = self.resolver() {
+            writer.bool(true);
+            writer.usize(resolver.len());
+            resolver.iter().try_for_each(|(path, module)| {
+                writer.str(path);
+                writer.module(module)
+            })?;
+        } else {
+            writer.bool(false);
+        }
+
+        Ok(writer.buf)
+    }
+    /// Load an [`AST`] from binary data produced by [`AST::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the binary data is malformed, is of a different format version,
+    /// or was produced by a build with different features (e.g. `no_float`, `only_i32`)
+    /// or a different hasher.
+    ///
+    /// Statements, expressions, values and modules nested more than 256 levels deep
+    /// (64 levels in debug builds) are also rejected.  This is well above the default
+    /// [maximum expression depth][crate::Engine::max_expr_depth] allowed by the parser.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<EvalAltResult>> {
+        let mut reader = Reader {
+            buf: bytes,
+            modules: Default::default(),
+            depth: 0,
+        };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(load_err("not a serialized AST"));
+        }
+
+        let version = reader.u16()?;
+
+        if version != VERSION {
+            return Err(load_err(format!(
+                "format version {} is not supported (expecting {})",
+                version, VERSION
+            )));
+        }
+
+        let flags = reader.u16()?;
+        let mismatched: StaticVec<_> = FEATURES
+            .iter()
+            .enumerate()
+            .filter(|&(bit, &(_, on))| (flags & (1 << bit) != 0) != on)
+            .map(|(_, &(name, _))| name)
+            .collect();
+
+        if !mismatched.is_empty() || flags >> FEATURES.len() != 0 {
+            return Err(load_err(format!(
+                "serialized with different features: {}",
+                mismatched.join(", ")
+            )));
+        }
+
+        if reader.u64()? != hasher_fingerprint() {
+            return Err(load_err("serialized by a build with a different hasher"));
+        }
+
+        let source = reader.opt_str()?;
+
+        let num_statements = reader.usize()?;
+        let mut statements = Vec::with_capacity(num_statements.min(reader.buf.len()));
+        for _ in 0..num_statements {
+            statements.push(reader.stmt()?);
+        }
+
+        let lib = reader.module()?;
+
+        #[allow(unused_mut)]
+        let mut ast = match source {
+            Some(source) => AST::new_with_source(statements, lib, source),
+            None => AST::new(statements, lib),
+        };
+
+        #[cfg(not(feature = "no_module"))]
+        if reader.bool()? {
+            let mut resolver = crate::module_resolvers::StaticModuleResolver::new();
+
+            for _ in 0..reader.usize()? {
+                let path = reader.string()?;
+                let module = crate::fn_native::shared_take_or_clone(reader.module()?);
+                resolver.insert(path, module);
+            }
+
+            ast.set_resolver(resolver);
+        }
+
+        if !reader.buf.is_empty() {
+            return Err(load_err("unexpected data after the end"));
+        }
+
+        Ok(ast)
+    }
+}
+
+/// Writer of the binary format.
+struct Writer {
+    /// Output buffer.
+    buf: Vec<u8>,
+    /// Modules already written, in order, to avoid writing shared modules more than once.
+    modules: Vec<*const Module>,
+}
+
+impl Writer {
+    #[inline(always)]
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+    #[inline(always)]
+    fn bool(&mut self, value: bool) {
+        self.u8(if value { 1 } else { 0 });
+    }
+    #[inline(always)]
+    fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+    /// Write an unsigned integer in LEB128 variable-length encoding.
+    fn usize(&mut self, value: usize) {
+        let mut value = value as u64;
+
+        while value >= 0x80 {
+            self.u8((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.u8(value as u8);
+    }
+    #[inline(always)]
+    #[allow(clippy::unnecessary_cast)]
+    fn int(&mut self, value: INT) {
+        self.u64(value as i64 as u64);
+    }
+    #[cfg(not(feature = "no_float"))]
+    #[inline(always)]
+    #[allow(clippy::unnecessary_cast)]
+    fn float(&mut self, value: crate::FLOAT) {
+        self.u64((value as f64).to_bits());
+    }
+    #[inline(always)]
+    fn str(&mut self, s: &str) {
+        self.usize(s.len());
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+    #[inline(always)]
+    fn opt_str(&mut self, s: Option<&str>) {
+        if let Some(s) = s {
+            self.bool(true);
+            self.str(s);
+        } else {
+            self.bool(false);
+        }
+    }
+    #[inline(always)]
+    fn pos(&mut self, pos: Position) {
+        self.usize(pos.line().unwrap_or(0));
+        self.usize(pos.position().unwrap_or(0));
+    }
+    #[inline(always)]
+    fn ident(&mut self, ident: &Ident) {
+        self.str(&ident.name);
+        self.pos(ident.pos);
+    }
+    fn map_key(&mut self, key: &MapKey) {
+        match key {
+            MapKey::Str(s) => {
+                self.u8(0);
+                self.str(s);
+            }
+            MapKey::Int(n) => {
+                self.u8(1);
+                self.int(*n);
+            }
+            MapKey::Char(ch) => {
+                self.u8(2);
+                self.usize(*ch as usize);
+            }
+            MapKey::Bool(b) => {
+                self.u8(3);
+                self.bool(*b);
+            }
+        }
+    }
+    #[inline(always)]
+    fn opt_ident(&mut self, ident: Option<&Ident>) {
+        if let Some(ident) = ident {
+            self.bool(true);
+            self.ident(ident);
+        } else {
+            self.bool(false);
+        }
+    }
+    fn namespace(&mut self, namespace: Option<&NamespaceRef>) {
+        if let Some(namespace) = namespace {
+            self.bool(true);
+            self.usize(namespace.index().map_or(0, NonZeroUsize::get));
+            self.usize(namespace.len());
+            namespace.iter().for_each(|ident| self.ident(ident));
+        } else {
+            self.bool(false);
+        }
+    }
+    fn dynamic(&mut self, value: &Dynamic) -> Result<(), Box<EvalAltResult>> {
+        match &value.0 {
+            Union::Unit(_, _) => self.u8(0),
+            Union::Bool(x, _) => {
+                self.u8(1);
+                self.bool(*x);
+            }
+            Union::Str(x, _) => {
+                self.u8(2);
+                self.str(x);
+            }
+            Union::Char(x, _) => {
+                self.u8(3);
+                self.usize(*x as usize);
+            }
+            Union::Int(x, _) => {
+                self.u8(4);
+                self.int(*x);
+            }
+            #[cfg(not(feature = "no_float"))]
+            Union::Float(x, _) => {
+                self.u8(5);
+                self.float(**x);
+            }
+            #[cfg(feature = "decimal")]
+            Union::Decimal(x, _) => {
+                self.u8(6);
+                self.buf.extend_from_slice(&x.serialize());
+            }
+            #[cfg(feature = "bigint")]
+            Union::BigInt(x, _) => {
+                let bytes = x.to_signed_bytes_le();
+                self.u8(12);
+                self.usize(bytes.len());
+                self.buf.extend_from_slice(&bytes);
+            }
+            #[cfg(not(feature = "no_index"))]
+            Union::Array(x, _) => {
+                self.u8(7);
+                self.usize(x.len());
+                x.iter().try_for_each(|v| self.dynamic(v))?;
+            }
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(x, _) => {
+                self.u8(10);
+                self.usize(x.len());
+                self.buf.extend_from_slice(x);
+            }
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(x, _) => {
+                self.u8(11);
+                self.usize(x.len());
+                x.iter().try_for_each(|v| self.dynamic(v))?;
+            }
+            #[cfg(not(feature = "no_object"))]
+            Union::Map(x, _) => {
+                self.u8(8);
+                self.usize(x.len());
+                x.iter().try_for_each(|(k, v)| {
+                    self.map_key(k);
+                    self.dynamic(v)
+                })?;
+            }
+            Union::FnPtr(x, _) => {
+                self.u8(9);
+                self.str(x.fn_name());
+                self.usize(x.curry().len());
+                x.curry().iter().try_for_each(|v| self.dynamic(v))?;
+            }
+            #[cfg(not(feature = "no_closure"))]
+            Union::Shared(_, _) => self.dynamic(&value.flatten_clone())?,
+            _ => {
+                return Err(save_err(format!(
+                    "values of type '{}' cannot be serialized",
+                    value.type_name()
+                )))
+            }
+        }
+        Ok(())
+    }
+    fn block(&mut self, block: &StmtBlock) -> Result<(), Box<EvalAltResult>> {
+        self.stmts(&block.statements)?;
+        self.pos(block.pos);
+        Ok(())
+    }
+    fn stmts(&mut self, statements: &[Stmt]) -> Result<(), Box<EvalAltResult>> {
+        self.usize(statements.len());
+        statements.iter().try_for_each(|stmt| self.stmt(stmt))
+    }
+    fn exprs(&mut self, exprs: &[Expr]) -> Result<(), Box<EvalAltResult>> {
+        self.usize(exprs.len());
+        exprs.iter().try_for_each(|expr| self.expr(expr))
+    }
+    fn opt_expr(&mut self, expr: Option<&Expr>) -> Result<(), Box<EvalAltResult>> {
+        if let Some(expr) = expr {
+            self.bool(true);
+            self.expr(expr)
+        } else {
+            self.bool(false);
+            Ok(())
+        }
+    }
+    fn binary(&mut self, tag: u8, x: &BinaryExpr, pos: Position) -> Result<(), Box<EvalAltResult>> {
+        self.u8(tag);
+        self.expr(&x.lhs)?;
+        self.expr(&x.rhs)?;
+        self.pos(pos);
+        Ok(())
+    }
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), Box<EvalAltResult>> {
+        match stmt {
+            Stmt::Noop(pos) => {
+                self.u8(0);
+                self.pos(*pos);
+            }
+            Stmt::If(expr, x, pos) => {
+                self.u8(1);
+                self.expr(expr)?;
+                self.block(&x.0)?;
+                self.block(&x.1)?;
+                self.pos(*pos);
+            }
+            Stmt::Switch(expr, x, pos) => {
+                self.u8(2);
+                self.expr(expr)?;
+                self.usize(x.0.len());
+                x.0.iter().try_for_each(|(hash, block)| {
+                    self.u64(*hash);
+                    self.block(block)
+                })?;
+                self.block(&x.1)?;
+                self.pos(*pos);
+            }
+            Stmt::While(expr, body, pos) => {
+                self.u8(3);
+                self.expr(expr)?;
+                self.block(body)?;
+                self.pos(*pos);
+            }
+            Stmt::Do(body, expr, is_while, pos) => {
+                self.u8(4);
+                self.block(body)?;
+                self.expr(expr)?;
+                self.bool(*is_while);
+                self.pos(*pos);
+            }
+            Stmt::For(expr, x, pos) => {
+                self.u8(5);
+                self.expr(expr)?;
+                self.str(&x.0);
+                self.block(&x.1)?;
+                self.pos(*pos);
+            }
+            Stmt::Let(expr, name, export, pos) | Stmt::Const(expr, name, export, pos) => {
+                self.u8(if matches!(stmt, Stmt::Let(_, _, _, _)) {
+                    6
+                } else {
+                    7
+                });
+                self.expr(expr)?;
+                self.ident(name);
+                self.bool(*export);
+                self.pos(*pos);
+            }
+            Stmt::Assignment(x, pos) => {
+                self.u8(8);
+                self.expr(&x.0)?;
+                self.expr(&x.1)?;
+                if let Some(OpAssignment {
+                    hash_op_assign,
+                    hash_op,
+                    op,
+                }) = &x.2
+                {
+                    self.bool(true);
+                    self.u64(*hash_op_assign);
+                    self.u64(*hash_op);
+                    self.str(op);
+                } else {
+                    self.bool(false);
+                }
+                self.pos(*pos);
+            }
+            Stmt::Block(statements, pos) => {
+                self.u8(9);
+                self.stmts(statements)?;
+                self.pos(*pos);
+            }
+            Stmt::TryCatch(x, try_pos, catch_pos) => {
+                self.u8(10);
+                self.block(&x.0)?;
+                self.opt_ident(x.1.as_ref());
+                self.block(&x.2)?;
+                self.pos(*try_pos);
+                self.pos(*catch_pos);
+            }
+            Stmt::Expr(expr) => {
+                self.u8(11);
+                self.expr(expr)?;
+            }
+            Stmt::Continue(pos) => {
+                self.u8(12);
+                self.pos(*pos);
+            }
+            Stmt::Break(pos) => {
+                self.u8(13);
+                self.pos(*pos);
+            }
+            Stmt::Return(return_type, expr, pos) => {
+                self.u8(14);
+                self.bool(*return_type == ReturnType::Exception);
+                self.opt_expr(expr.as_ref())?;
+                self.pos(*pos);
+            }
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(expr, alias, pos) => {
+                self.u8(15);
+                self.expr(expr)?;
+                self.opt_ident(alias.as_ref());
+                self.pos(*pos);
+            }
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Export(list, pos) => {
+                self.u8(16);
+                self.usize(list.len());
+                list.iter().for_each(|(name, alias)| {
+                    self.ident(name);
+                    self.opt_ident(alias.as_ref());
+                });
+                self.pos(*pos);
+            }
+            #[cfg(not(feature = "no_closure"))]
+            Stmt::Share(name) => {
+                self.u8(17);
+                self.ident(name);
+            }
+        }
+        Ok(())
+    }
+    fn expr(&mut self, expr: &Expr) -> Result<(), Box<EvalAltResult>> {
+        match expr {
+            Expr::DynamicConstant(x, pos) => {
+                self.u8(0);
+                self.dynamic(x)?;
+                self.pos(*pos);
+            }
+            Expr::BoolConstant(x, pos) => {
+                self.u8(1);
+                self.bool(*x);
+                self.pos(*pos);
+            }
+            Expr::IntegerConstant(x, pos) => {
+                self.u8(2);
+                self.int(*x);
+                self.pos(*pos);
+            }
+            #[cfg(not(feature = "no_float"))]
+            Expr::FloatConstant(x, pos) => {
+                self.u8(3);
+                self.float(**x);
+                self.pos(*pos);
+            }
+            Expr::CharConstant(x, pos) => {
+                self.u8(4);
+                self.usize(*x as usize);
+                self.pos(*pos);
+            }
+            Expr::StringConstant(x, pos) => {
+                self.u8(5);
+                self.str(x);
+                self.pos(*pos);
+            }
+            Expr::FnPointer(x, pos) => {
+                self.u8(6);
+                self.str(x);
+                self.pos(*pos);
+            }
+            Expr::Array(x, pos) => {
+                self.u8(7);
+                self.exprs(x)?;
+                self.pos(*pos);
+            }
+            Expr::Map(x, pos) => {
+                self.u8(8);
+                self.usize(x.len());
+                x.iter().try_for_each(|(key, expr)| {
+                    self.map_key(key);
+                    self.expr(expr)
+                })?;
+                self.pos(*pos);
+            }
+            Expr::Unit(pos) => {
+                self.u8(9);
+                self.pos(*pos);
+            }
+            Expr::Variable(x) => {
+                self.u8(10);
+                self.usize(x.0.map_or(0, NonZeroUsize::get));
+                if let Some((hash, namespace)) = &x.1 {
+                    self.bool(true);
+                    self.u64(*hash);
+                    self.namespace(Some(namespace));
+                } else {
+                    self.bool(false);
+                }
+                self.ident(&x.2);
+                if let Some(hash) = x.3 {
+                    self.bool(true);
+                    self.u64(hash);
+                } else {
+                    self.bool(false);
+                }
+            }
+            Expr::Property(x) => {
+                self.u8(11);
+                self.str(&(x.0).0);
+                self.u64((x.0).1);
+                self.str(&(x.1).0);
+                self.u64((x.1).1);
+                self.ident(&x.2);
+            }
+            Expr::Stmt(x) => {
+                self.u8(12);
+                self.block(x)?;
+            }
+            Expr::FnCall(x, pos) => {
+                self.u8(13);
+                if x.hash.is_native_only() {
+                    self.bool(false);
+                } else {
+                    self.bool(true);
+                    self.u64(x.hash.script_hash());
+                }
+                self.u64(x.hash.native_hash());
+                self.bool(x.capture);
+                self.bool(x.tail_call);
+                self.exprs(&x.args)?;
+                self.namespace(x.namespace.as_ref());
+                self.str(&x.name);
+                self.pos(*pos);
+            }
+            Expr::Dot(x, pos) => self.binary(14, x, *pos)?,
+            Expr::Index(x, pos) => self.binary(15, x, *pos)?,
+            Expr::And(x, pos) => self.binary(16, x, *pos)?,
+            Expr::Or(x, pos) => self.binary(17, x, *pos)?,
+            Expr::Custom(x, pos) => {
+                self.u8(18);
+                self.exprs(&x.keywords)?;
+                self.usize(x.tokens.len());
+                x.tokens.iter().for_each(|token| self.str(token));
+                self.u64(x.scope_delta as i64 as u64);
+                self.pos(*pos);
+            }
+            #[cfg(not(feature = "no_function"))]
+            Expr::InlinedFnCall(x, pos) => {
+                self.u8(19);
+                self.str(&x.0);
+                self.block(&x.1)?;
+                self.pos(*pos);
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(feature = "no_function"))]
+    fn fn_def(&mut self, fn_def: &ScriptFnDef) -> Result<(), Box<EvalAltResult>> {
+        self.str(&fn_def.name);
+        self.bool(fn_def.access == FnAccess::Private);
+        self.usize(fn_def.params.len());
+        fn_def.params.iter().for_each(|param| self.str(param));
+        #[cfg(not(feature = "no_closure"))]
+        {
+            self.usize(fn_def.externals.len());
+            fn_def.externals.iter().for_each(|name| self.str(name));
+        }
+        self.usize(fn_def.comments.len());
+        fn_def.comments.iter().for_each(|comment| self.str(comment));
+        self.block(&fn_def.body)?;
+
+        if let Some(ref lib) = fn_def.lib {
+            self.bool(true);
+            self.module(lib)?;
+        } else {
+            self.bool(false);
+        }
+
+        #[cfg(not(feature = "no_module"))]
+        {
+            self.usize(fn_def.mods.len());
+            fn_def.mods.iter_raw().try_for_each(|(name, module)| {
+                self.str(name);
+                self.module(module)
+            })?;
+        }
+
+        Ok(())
+    }
+    fn module(&mut self, module: &Module) -> Result<(), Box<EvalAltResult>> {
+        let ptr = module as *const Module;
+
+        // Shared modules are written only once
+        if let Some(index) = self.modules.iter().position(|&m| m == ptr) {
+            self.usize(index + 1);
+            return Ok(());
+        }
+        self.usize(0);
+
+        if module.count().2 > 0 {
+            return Err(save_err("type iterators cannot be serialized"));
+        }
+
+        self.opt_str(module.id());
+        self.bool(module.is_indexed());
+
+        self.usize(module.count().0);
+        module.iter_var().try_for_each(|(name, value)| {
+            self.str(name);
+            self.dynamic(value)
+        })?;
+
+        let sub_modules: StaticVec<_> = module.iter_sub_modules().collect();
+        self.usize(sub_modules.len());
+        sub_modules.iter().try_for_each(|(name, m)| {
+            self.str(name);
+            self.module(m)
+        })?;
+
+        self.usize(module.count().1);
+        module.iter_fn().try_for_each(|f| {
+            if !f.func.is_script() {
+                return Err(save_err(format!(
+                    "native Rust function '{}' cannot be serialized",
+                    f.name
+                )));
+            }
+            self.bool(f.namespace == FnNamespace::Global);
+            #[cfg(not(feature = "no_function"))]
+            self.fn_def(f.func.get_fn_def())?;
+            Ok(())
+        })?;
+
+        self.modules.push(ptr);
+
+        Ok(())
+    }
+}
+
+/// Reader of the binary format.
+struct Reader<'a> {
+    /// Remaining input.
+    buf: &'a [u8],
+    /// Modules already read, in order.
+    modules: Vec<Shared<Module>>,
+    /// Current nesting depth.
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Read a nested item one level deeper, failing if nested too deeply.
+    #[inline(always)]
+    fn nested<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, Box<EvalAltResult>>,
+    ) -> Result<T, Box<EvalAltResult>> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(load_err("nested too deeply"));
+        }
+
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+        result
+    }
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Box<EvalAltResult>> {
+        if len > self.buf.len() {
+            return Err(load_err("unexpected end of data"));
+        }
+        let (data, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(data)
+    }
+    #[inline(always)]
+    fn u8(&mut self) -> Result<u8, Box<EvalAltResult>> {
+        Ok(self.take(1)?[0])
+    }
+    #[inline(always)]
+    fn bool(&mut self) -> Result<bool, Box<EvalAltResult>> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            n => Err(load_err(format!("invalid boolean {}", n))),
+        }
+    }
+    #[inline(always)]
+    fn u16(&mut self) -> Result<u16, Box<EvalAltResult>> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+    #[inline(always)]
+    fn u64(&mut self) -> Result<u64, Box<EvalAltResult>> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+    /// Read an unsigned integer in LEB128 variable-length encoding.
+    fn usize(&mut self) -> Result<usize, Box<EvalAltResult>> {
+        let mut value = 0_u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value as usize);
+            }
+        }
+
+        Err(load_err("invalid variable-length integer"))
+    }
+    #[inline(always)]
+    fn int(&mut self) -> Result<INT, Box<EvalAltResult>> {
+        Ok(self.u64()? as i64 as INT)
+    }
+    #[cfg(not(feature = "no_float"))]
+    #[inline(always)]
+    #[allow(clippy::unnecessary_cast)]
+    fn float(&mut self) -> Result<crate::FLOAT, Box<EvalAltResult>> {
+        Ok(f64::from_bits(self.u64()?) as crate::FLOAT)
+    }
+    #[inline(always)]
+    fn count(&mut self) -> Result<(usize, usize), Box<EvalAltResult>> {
+        let len = self.usize()?;
+        // Never pre-allocate more than the remaining input
+        Ok((len, len.min(self.buf.len())))
+    }
+    fn str(&mut self) -> Result<&'a str, Box<EvalAltResult>> {
+        let len = self.usize()?;
+        str::from_utf8(self.take(len)?).map_err(|err| load_err(err.to_string()))
+    }
+    #[inline(always)]
+    fn string(&mut self) -> Result<String, Box<EvalAltResult>> {
+        self.str().map(Into::into)
+    }
+    #[inline(always)]
+    fn imm_str(&mut self) -> Result<ImmutableString, Box<EvalAltResult>> {
+        self.str().map(Into::into)
+    }
+    #[inline(always)]
+    fn opt_str(&mut self) -> Result<Option<ImmutableString>, Box<EvalAltResult>> {
+        Ok(if self.bool()? {
+            Some(self.imm_str()?)
+        } else {
+            None
+        })
+    }
+    fn strs(&mut self) -> Result<StaticVec<ImmutableString>, Box<EvalAltResult>> {
+        let (len, capacity) = self.count()?;
+        let mut list = StaticVec::with_capacity(capacity);
+        for _ in 0..len {
+            list.push(self.imm_str()?);
+        }
+        Ok(list)
+    }
+    fn pos(&mut self) -> Result<Position, Box<EvalAltResult>> {
+        let line = self.usize()?;
+        let pos = self.usize()?;
+
+        if line == 0 {
+            Ok(Position::NONE)
+        } else if line > u16::MAX as usize || pos > u16::MAX as usize {
+            Err(load_err("invalid position"))
+        } else {
+            Ok(Position::new(line as u16, pos as u16))
+        }
+    }
+    #[inline(always)]
+    fn ident(&mut self) -> Result<Ident, Box<EvalAltResult>> {
+        Ok(Ident {
+            name: self.imm_str()?,
+            pos: self.pos()?,
+        })
+    }
+    fn map_key(&mut self) -> Result<MapKey, Box<EvalAltResult>> {
+        Ok(match self.u8()? {
+            0 => MapKey::Str(self.imm_str()?),
+            1 => MapKey::Int(self.int()?),
+            2 => MapKey::Char(self.char()?),
+            3 => MapKey::Bool(self.bool()?),
+            tag => return Err(load_err(format!("invalid map key tag {}", tag))),
+        })
+    }
+    #[inline(always)]
+    fn opt_ident(&mut self) -> Result<Option<Ident>, Box<EvalAltResult>> {
+        Ok(if self.bool()? {
+            Some(self.ident()?)
+        } else {
+            None
+        })
+    }
+    fn namespace(&mut self) -> Result<Option<NamespaceRef>, Box<EvalAltResult>> {
+        if !self.bool()? {
+            return Ok(None);
+        }
+
+        let _index = NonZeroUsize::new(self.usize()?);
+        let (len, capacity) = self.count()?;
+        let mut path = StaticVec::with_capacity(capacity);
+        for _ in 0..len {
+            path.push(self.ident()?);
+        }
+
+        #[allow(unused_mut)]
+        let mut namespace: NamespaceRef = path.into();
+        #[cfg(not(feature = "no_module"))]
+        namespace.set_index(_index);
+
+        Ok(Some(namespace))
+    }
+    #[inline(always)]
+    fn dynamic(&mut self) -> Result<Dynamic, Box<EvalAltResult>> {
+        self.nested(Self::read_dynamic)
+    }
+    fn read_dynamic(&mut self) -> Result<Dynamic, Box<EvalAltResult>> {
+        Ok(match self.u8()? {
+            0 => Dynamic::UNIT,
+            1 => self.bool()?.into(),
+            2 => self.imm_str()?.into(),
+            3 => self.char()?.into(),
+            4 => self.int()?.into(),
+            #[cfg(not(feature = "no_float"))]
+            5 => self.float()?.into(),
+            #[cfg(feature = "decimal")]
+            6 => {
+                let mut bytes = [0; 16];
+                bytes.copy_from_slice(self.take(16)?);
+                Dynamic::from(rust_decimal::Decimal::deserialize(bytes))
+            }
+            #[cfg(feature = "bigint")]
+            12 => {
+                let len = self.usize()?;
+                Dynamic::from(num_bigint::BigInt::from_signed_bytes_le(self.take(len)?))
+            }
+            #[cfg(not(feature = "no_index"))]
+            7 => {
+                let (len, capacity) = self.count()?;
+                let mut array = Array::with_capacity(capacity);
+                for _ in 0..len {
+                    array.push(self.dynamic()?);
+                }
+                array.into()
+            }
+            #[cfg(not(feature = "no_object"))]
+            8 => {
+                let (len, capacity) = self.count()?;
+                let mut map = Map::with_capacity(capacity);
+                for _ in 0..len {
+                    let key = self.map_key()?;
+                    map.insert(key, self.dynamic()?);
+                }
+                map.into()
+            }
+            9 => {
+                let name = self.imm_str()?;
+                let (len, capacity) = self.count()?;
+                let mut curry = Vec::with_capacity(capacity);
+                for _ in 0..len {
+                    curry.push(self.dynamic()?);
+                }
+                FnPtr::new_unchecked(name, curry).into()
+            }
+            #[cfg(not(feature = "no_index"))]
+            10 => {
+                let len = self.usize()?;
+                Dynamic::from(self.take(len)?.to_vec())
+            }
+            #[cfg(not(feature = "no_index"))]
+            11 => {
+                let len = self.usize()?;
+                let mut set = crate::Set::new();
+                for _ in 0..len {
+                    set.insert(self.dynamic()?)
+                        .map_err(|typ| load_err(format!("{} cannot be held in a set", typ)))?;
+                }
+                Dynamic::from(set)
+            }
+            tag => return Err(load_err(format!("invalid value tag {}", tag))),
+        })
+    }
+    #[inline(always)]
+    fn char(&mut self) -> Result<char, Box<EvalAltResult>> {
+        let code = self.usize()?;
+        crate::stdlib::char::from_u32(code as u32)
+            .filter(|_| code <= u32::MAX as usize)
+            .ok_or_else(|| load_err(format!("invalid character code {}", code)))
+    }
+    fn block(&mut self) -> Result<StmtBlock, Box<EvalAltResult>> {
+        Ok(StmtBlock {
+            statements: self.stmts()?.into(),
+            pos: self.pos()?,
+        })
+    }
+    fn stmts(&mut self) -> Result<Vec<Stmt>, Box<EvalAltResult>> {
+        let (len, capacity) = self.count()?;
+        let mut statements = Vec::with_capacity(capacity);
+        for _ in 0..len {
+            statements.push(self.stmt()?);
+        }
+        Ok(statements)
+    }
+    fn exprs(&mut self) -> Result<StaticVec<Expr>, Box<EvalAltResult>> {
+        let (len, capacity) = self.count()?;
+        let mut exprs = StaticVec::with_capacity(capacity);
+        for _ in 0..len {
+            exprs.push(self.expr()?);
+        }
+        Ok(exprs)
+    }
+    #[inline(always)]
+    fn opt_expr(&mut self) -> Result<Option<Expr>, Box<EvalAltResult>> {
+        Ok(if self.bool()? {
+            Some(self.expr()?)
+        } else {
+            None
+        })
+    }
+    #[inline(always)]
+    fn binary(&mut self) -> Result<(Box<BinaryExpr>, Position), Box<EvalAltResult>> {
+        let lhs = self.expr()?;
+        let rhs = self.expr()?;
+        Ok((Box::new(BinaryExpr { lhs, rhs }), self.pos()?))
+    }
+    #[inline(always)]
+    fn stmt(&mut self) -> Result<Stmt, Box<EvalAltResult>> {
+        self.nested(Self::read_stmt)
+    }
+    fn read_stmt(&mut self) -> Result<Stmt, Box<EvalAltResult>> {
+        Ok(match self.u8()? {
+            0 => Stmt::Noop(self.pos()?),
+            1 => {
+                let expr = self.expr()?;
+                let if_block = self.block()?;
+                let else_block = self.block()?;
+                Stmt::If(expr, Box::new((if_block, else_block)), self.pos()?)
+            }
+            2 => {
+                let expr = self.expr()?;
+                let (len, capacity) = self.count()?;
+                let mut table = HashMap::with_capacity_and_hasher(capacity, StraightHasherBuilder);
+                for _ in 0..len {
+                    let hash = self.u64()?;
+                    table.insert(hash, self.block()?);
+                }
+                let def_block = self.block()?;
+                Stmt::Switch(expr, Box::new((table.into(), def_block)), self.pos()?)
+            }
+            3 => {
+                let expr = self.expr()?;
+                let body = self.block()?;
+                Stmt::While(expr, Box::new(body), self.pos()?)
+            }
+            4 => {
+                let body = self.block()?;
+                let expr = self.expr()?;
+                let is_while = self.bool()?;
+                Stmt::Do(Box::new(body), expr, is_while, self.pos()?)
+            }
+            5 => {
+                let expr = self.expr()?;
+                let name = self.string()?;
+                let body = self.block()?;
+                Stmt::For(expr, Box::new((name, body)), self.pos()?)
+            }
+            tag @ 6 | tag @ 7 => {
+                let expr = self.expr()?;
+                let name = self.ident()?;
+                let export = self.bool()?;
+                let pos = self.pos()?;
+                if tag == 6 {
+                    Stmt::Let(expr, name, export, pos)
+                } else {
+                    Stmt::Const(expr, name, export, pos)
+                }
+            }
+            8 => {
+                let lhs = self.expr()?;
+                let rhs = self.expr()?;
+                let op = if self.bool()? {
+                    Some(OpAssignment {
+                        hash_op_assign: self.u64()?,
+                        hash_op: self.u64()?,
+                        op: Cow::Owned(self.string()?),
+                    })
+                } else {
+                    None
+                };
+                Stmt::Assignment(Box::new((lhs, rhs, op)), self.pos()?)
+            }
+            9 => {
+                let statements = self.stmts()?;
+                Stmt::Block(statements, self.pos()?)
+            }
+            10 => {
+                let try_block = self.block()?;
+                let var = self.opt_ident()?;
+                let catch_block = self.block()?;
+                let try_pos = self.pos()?;
+                Stmt::TryCatch(
+                    Box::new((try_block, var, catch_block)),
+                    try_pos,
+                    self.pos()?,
+                )
+            }
+            11 => Stmt::Expr(self.expr()?),
+            12 => Stmt::Continue(self.pos()?),
+            13 => Stmt::Break(self.pos()?),
+            14 => {
+                let return_type = if self.bool()? {
+                    ReturnType::Exception
+                } else {
+                    ReturnType::Return
+                };
+                let expr = self.opt_expr()?;
+                Stmt::Return(return_type, expr, self.pos()?)
+            }
+            #[cfg(not(feature = "no_module"))]
+            15 => {
+                let expr = self.expr()?;
+                let alias = self.opt_ident()?;
+                Stmt::Import(expr, alias, self.pos()?)
+            }
+            #[cfg(not(feature = "no_module"))]
+            16 => {
+                let (len, capacity) = self.count()?;
+                let mut list = Vec::with_capacity(capacity);
+                for _ in 0..len {
+                    let name = self.ident()?;
+                    list.push((name, self.opt_ident()?));
+                }
+                Stmt::Export(list, self.pos()?)
+            }
+            #[cfg(not(feature = "no_closure"))]
+            17 => Stmt::Share(self.ident()?),
+            tag => return Err(load_err(format!("invalid statement tag {}", tag))),
+        })
+    }
+    #[inline(always)]
+    fn expr(&mut self) -> Result<Expr, Box<EvalAltResult>> {
+        self.nested(Self::read_expr)
+    }
+    fn read_expr(&mut self) -> Result<Expr, Box<EvalAltResult>> {
+        Ok(match self.u8()? {
+            0 => {
+                let value = self.dynamic()?;
+                Expr::DynamicConstant(Box::new(value), self.pos()?)
+            }
+            1 => {
+                let value = self.bool()?;
+                Expr::BoolConstant(value, self.pos()?)
+            }
+            2 => {
+                let value = self.int()?;
+                Expr::IntegerConstant(value, self.pos()?)
+            }
+            #[cfg(not(feature = "no_float"))]
+            3 => {
+                let value = self.float()?;
+                Expr::FloatConstant(value.into(), self.pos()?)
+            }
+            4 => {
+                let value = self.char()?;
+                Expr::CharConstant(value, self.pos()?)
+            }
+            5 => {
+                let value = self.imm_str()?;
+                Expr::StringConstant(value, self.pos()?)
+            }
+            6 => {
+                let value = self.imm_str()?;
+                Expr::FnPointer(value, self.pos()?)
+            }
+            7 => {
+                let items = self.exprs()?;
+                Expr::Array(Box::new(items), self.pos()?)
+            }
+            8 => {
+                let (len, capacity) = self.count()?;
+                let mut items = StaticVec::with_capacity(capacity);
+                for _ in 0..len {
+                    let key = self.map_key()?;
+                    items.push((key, self.expr()?));
+                }
+                Expr::Map(Box::new(items), self.pos()?)
+            }
+            9 => Expr::Unit(self.pos()?),
+            10 => {
+                let index = NonZeroUsize::new(self.usize()?);
+                let namespace = if self.bool()? {
+                    let hash = self.u64()?;
+                    let namespace = self
+                        .namespace()?
+                        .ok_or_else(|| load_err("missing namespace"))?;
+                    Some((hash, namespace))
+                } else {
+                    None
+                };
+                let name = self.ident()?;
+                let hash = if self.bool()? {
+                    Some(self.u64()?)
+                } else {
+                    None
+                };
+                Expr::Variable(Box::new((index, namespace, name, hash)))
+            }
+            11 => {
+                let getter = (self.imm_str()?, self.u64()?);
+                let setter = (self.imm_str()?, self.u64()?);
+                Expr::Property(Box::new((getter, setter, self.ident()?)))
+            }
+            12 => Expr::Stmt(Box::new(self.block()?)),
+            13 => {
+                let script = if self.bool()? {
+                    Some(self.u64()?)
+                } else {
+                    None
+                };
+                let native = self.u64()?;
+                let hash = match script {
+                    Some(script) => FnHash::from_script_and_native(script, native),
+                    None => FnHash::from_native(native),
+                };
+                let capture = self.bool()?;
+                let tail_call = self.bool()?;
+                let args = self.exprs()?;
+                let namespace = self.namespace()?;
+                let name = Cow::Owned(self.string()?);
+                let x = FnCallExpr {
+                    hash,
+                    capture,
+                    tail_call,
+                    args,
+                    namespace,
+                    name,
+                };
+                Expr::FnCall(Box::new(x), self.pos()?)
+            }
+            14 => {
+                let (x, pos) = self.binary()?;
+                Expr::Dot(x, pos)
+            }
+            15 => {
+                let (x, pos) = self.binary()?;
+                Expr::Index(x, pos)
+            }
+            16 => {
+                let (x, pos) = self.binary()?;
+                Expr::And(x, pos)
+            }
+            17 => {
+                let (x, pos) = self.binary()?;
+                Expr::Or(x, pos)
+            }
+            18 => {
+                let keywords = self.exprs()?;
+                let tokens = self.strs()?.into_iter().collect();
+                let scope_delta = self.u64()? as i64 as isize;
+                let x = CustomExpr {
+                    keywords,
+                    tokens,
+                    scope_delta,
+                };
+                Expr::Custom(Box::new(x), self.pos()?)
+            }
+            #[cfg(not(feature = "no_function"))]
+            19 => {
+                let name = self.imm_str()?;
+                let body = self.block()?;
+                Expr::InlinedFnCall(Box::new((name, body)), self.pos()?)
+            }
+            tag => return Err(load_err(format!("invalid expression tag {}", tag))),
+        })
+    }
+    #[cfg(not(feature = "no_function"))]
+    fn fn_def(&mut self) -> Result<ScriptFnDef, Box<EvalAltResult>> {
+        let name = self.imm_str()?;
+        let access = if self.bool()? {
+            FnAccess::Private
+        } else {
+            FnAccess::Public
+        };
+        let params = self.strs()?;
+        #[cfg(not(feature = "no_closure"))]
+        let externals = self.strs()?;
+        let comments = self.strs()?.into_iter().map(Into::into).collect();
+        let body = self.block()?;
+        let lib = if self.bool()? {
+            Some(self.module()?)
+        } else {
+            None
+        };
+
+        #[cfg(not(feature = "no_module"))]
+        let mut mods: crate::engine::Imports = Default::default();
+        #[cfg(not(feature = "no_module"))]
+        for _ in 0..self.usize()? {
+            let name = self.imm_str()?;
+            mods.push(name, self.module()?);
+        }
+
+        Ok(ScriptFnDef {
+            body,
+            lib,
+            #[cfg(not(feature = "no_module"))]
+            mods,
+            name,
+            access,
+            params,
+            #[cfg(not(feature = "no_closure"))]
+            externals,
+            comments,
+            memo: Default::default(),
+        })
+    }
+    #[inline(always)]
+    fn module(&mut self) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        self.nested(Self::read_module)
+    }
+    fn read_module(&mut self) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        let index = self.usize()?;
+
+        if index > 0 {
+            return self
+                .modules
+                .get(index - 1)
+                .cloned()
+                .ok_or_else(|| load_err(format!("invalid module reference {}", index)));
+        }
+
+        let mut module = Module::new();
+
+        module.set_id(self.opt_str()?);
+        let indexed = self.bool()?;
+
+        for _ in 0..self.usize()? {
+            let name = self.imm_str()?;
+            module.set_var(name, self.dynamic()?);
+        }
+
+        for _ in 0..self.usize()? {
+            let name = self.imm_str()?;
+            module.set_sub_module(name, self.module()?);
+        }
+
+        let num_fns = self.usize()?;
+
+        #[cfg(feature = "no_function")]
+        if num_fns > 0 {
+            return Err(load_err("script-defined functions are not supported"));
+        }
+
+        #[cfg(not(feature = "no_function"))]
+        for _ in 0..num_fns {
+            let namespace = if self.bool()? {
+                FnNamespace::Global
+            } else {
+                FnNamespace::Internal
+            };
+            let hash = module.set_script_fn(self.fn_def()?);
+            module.update_fn_namespace(hash, namespace);
+        }
+
+        if indexed {
+            module.build_index();
+        }
+
+        let module: Shared<Module> = module.into();
+        self.modules.push(module.clone());
+
+        Ok(module)
+    }
+}
@@ -0,0 +1,3032 @@
+//! Module that defines the [`Bytecode`] compiler and the virtual machine running it.
+
+use crate::ast::{CustomExpr, Expr, FnCallExpr, FnHash, Ident, OpAssignment, ReturnType, Stmt};
+use crate::continuation::{Continuation, Execution};
+use crate::coverage::{coverable_position, switch_branch_index};
+use crate::dynamic::{AccessMode, Union};
+use crate::engine::{
+    EvalContext, EvalEnv, Imports, State, VariableAccess, KEYWORD_EVAL, KEYWORD_FN_PTR,
+    KEYWORD_FN_PTR_CALL, KEYWORD_FN_PTR_CURRY, KEYWORD_IS_DEF_VAR, KEYWORD_THIS,
+};
+use crate::fn_call::ensure_no_data_race;
+use crate::fn_native::DynamicIterator;
+use crate::module::NamespaceRef;
+use crate::r#unsafe::unsafe_cast_var_name_to_lifetime;
+use crate::stdlib::{
+    borrow::Cow,
+    boxed::Box,
+    cell::Cell,
+    cmp::max,
+    collections::HashMap,
+    convert::TryFrom,
+    hash::{Hash, Hasher},
+    iter::{empty, once},
+    mem,
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::utils::{get_hasher, StraightHasherBuilder};
+use crate::{
+    calc_fn_hash, calc_fn_params_hash, combine_hashes, Dynamic, Engine, EvalAltResult, FnPtr,
+    ImmutableString, Module, NativeCallContext, Position, RhaiResult, Scope, Shared, StaticVec,
+    AST,
+};
+
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+use crate::engine::{ChainArgument, ChainLink, ChainPath, ChainType, PropertyAccess};
+
+#[cfg(not(feature = "no_function"))]
+use crate::ast::ScriptFnDef;
+
+#[cfg(not(feature = "no_function"))]
+use crate::fn_call::{push_params, ScriptFnFrame};
+
+#[cfg(not(feature = "no_function"))]
+use crate::fn_native::FnCallArgs;
+
+#[cfg(not(feature = "no_index"))]
+use crate::{engine::TYPICAL_ARRAY_SIZE, Array};
+
+#[cfg(not(feature = "no_object"))]
+use crate::{engine::TYPICAL_MAP_SIZE, Map, MapKey};
+
+/// Index of a register, relative to the registers of a call frame.
+type Reg = usize;
+
+/// Register holding the result of a call frame.
+const RESULT: Reg = 0;
+
+/// A list of bytecode instructions, with the number of registers they use.
+#[derive(Debug)]
+struct Code {
+    ops: Vec<Op>,
+    num_regs: usize,
+}
+
+/// A single bytecode instruction.
+///
+/// Instructions read their operands from, and put their results into, registers of the call frame.
+/// Operands are _consumed_ when read, leaving `()` in their registers.
+/// Each statement puts its value into a register given by the enclosing block.
+#[derive(Debug, Clone)]
+enum Op {
+    /// Start a statement - count the operation and record coverage at a [position][Position]
+    /// (if any).
+    Stmt(Position, Position),
+    /// End a statement - check the value in a register against limits.
+    EndStmt(Reg, Position),
+    /// Put `()` into a register.
+    Unit(Reg),
+    /// Move a value from a register into a new variable, with its access mode and whether it is
+    /// exported.
+    Let(Reg, Box<(ImmutableString, AccessMode, bool)>),
+    /// Assign a value from a register to a variable.
+    Assign(Reg, Box<AssignOp>),
+    /// Start a statements block.
+    BeginBlock,
+    /// End the current statements block.
+    EndBlock,
+    /// Record a branch taken in a statement (at [position][Position], with the number of branches),
+    /// for coverage.
+    Branch(Position, usize, usize),
+    /// Jump to an instruction unconditionally.
+    Jump(usize),
+    /// Read a boolean value from a register (the type of which is checked at a
+    /// [position][Position]), and jump to an instruction if it has a particular value.
+    JumpIf(Reg, bool, Position, usize),
+    /// Read a value from a register and jump to the matching case of a `switch` statement.
+    Switch(Reg, Box<SwitchOp>),
+    /// Start a loop, with the instructions to jump to on `continue` and `break`.
+    BeginLoop(usize, usize),
+    /// End the current loop.
+    EndLoop,
+    /// Read a value from a register and start a `for` loop over it, with the name of the loop
+    /// variable, the [position][Position] of the value and the instruction to jump to on `break`.
+    ForStart(Reg, Box<(String, Position)>, usize),
+    /// Put the next value into the loop variable of the current `for` loop, counting the operation
+    /// at a [position][Position], or jump to an instruction when there are no more values.
+    ForNext(Position, usize),
+    /// End the current `for` loop.
+    EndFor,
+    /// Break out of (`true`), or continue (`false`), the current loop.
+    Break(bool, Position),
+    /// Return from the function, with the value in a register if any.
+    Return(Option<Reg>, Position),
+    /// Throw an exception, with the value in a register if any.
+    Throw(Option<Reg>, Position),
+    /// Start a `try` block, with the register to put the error value into and the instruction of
+    /// its `catch` block.
+    BeginTry(Reg, usize),
+    /// End the current `try` block and jump to an instruction.
+    EndTry(usize),
+    /// Start a `catch` block - move the error value from a register into a new variable, if any.
+    Catch(Reg, Option<ImmutableString>),
+    /// End the current `catch` block.
+    EndCatch,
+    /// Start the body of an inlined call to a script-defined function, with the name of the
+    /// function and the [position][Position] of the call.
+    #[cfg(not(feature = "no_function"))]
+    BeginInline(Box<(ImmutableString, Position)>),
+    /// End the body of the current inlined function call.
+    #[cfg(not(feature = "no_function"))]
+    EndInline,
+    /// Guard against too many modules at a [position][Position].
+    #[cfg(not(feature = "no_module"))]
+    CheckModules(Position),
+    /// Read a module path from a register and import the module, with an optional name and the
+    /// [position][Position] of the path.
+    #[cfg(not(feature = "no_module"))]
+    Import(Reg, Box<(Option<ImmutableString>, Position)>),
+    /// Export variables.
+    #[cfg(not(feature = "no_module"))]
+    Export(Box<[(Ident, Option<Ident>)]>),
+    /// Turn a variable into a shared value.
+    #[cfg(not(feature = "no_closure"))]
+    Share(ImmutableString),
+
+    /// Count an operation at a [position][Position].
+    Count(Position),
+    /// Count an operation and put a constant value into a register, at a [position][Position].
+    Load(Reg, Dynamic, Position),
+    /// Count an operation at a [position][Position] and get the value of `this`, which is never
+    /// bound (the variable is at the second [position][Position]).
+    This(Position, Position),
+    /// Count an operation and put the value of a variable into a register.
+    Get(Reg, Box<VariableAccess>),
+    /// Check the value in a register at a [position][Position].
+    Check(Reg, Position),
+    /// Read a number of values from consecutive registers into an array, put into the first
+    /// register and checked at a [position][Position].
+    #[cfg(not(feature = "no_index"))]
+    Array(Reg, Reg, usize, Position),
+    /// Read values from consecutive registers into an object map with the keys, put into the
+    /// first register and checked at a [position][Position].
+    #[cfg(not(feature = "no_object"))]
+    Map(Reg, Reg, Box<StaticVec<MapKey>>, Position),
+    /// Read a boolean value from a register (the type of which is checked at a
+    /// [position][Position]) - if it has a particular value, put it back and jump to an instruction.
+    ShortCircuit(Reg, bool, Position, usize),
+    /// Check that the value in a register is boolean, at a [position][Position].
+    Bool(Reg, Position),
+    /// Evaluate a dot/index chain.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    Chain(Box<ChainOp>),
+    /// Put the variables in the scope visible to a function call capturing them into a register.
+    Capture(Reg),
+    /// Call a function in normal function-call style.
+    Call(Box<CallOp>),
+    /// Jump to an instruction if the function pointer in a register has curried arguments.
+    FnPtrCall(Reg, usize),
+    /// Jump to an instruction unless a function call with a name and script hash is a tail call
+    /// to the script-defined function being evaluated.
+    #[cfg(not(feature = "no_function"))]
+    TailCallCheck(Box<(Cow<'static, str>, u64)>, usize),
+    /// Make a tail call to the script-defined function being evaluated.
+    #[cfg(not(feature = "no_function"))]
+    TailCall(Box<TailCallOp>),
+    /// Call a namespace-qualified function.
+    QualifiedCall(Box<QualifiedCallOp>),
+    /// Check that the value in a register is a function pointer, at a [position][Position].
+    AssertFnPtr(Reg, Position),
+    /// Check that the value in a register is a string, at a [position][Position].
+    #[cfg(not(feature = "no_function"))]
+    AssertString(Reg, Position),
+    /// Turn the function name in a register into a function pointer, checked at a
+    /// [position][Position].
+    MakeFnPtr(Reg, Position),
+    /// Read a function pointer followed by a number of values from consecutive registers, and put
+    /// the function pointer with the values curried into the first register.
+    Curry(Reg, Reg, usize),
+    /// Replace the value in a register with whether it is shared.
+    #[cfg(not(feature = "no_closure"))]
+    IsShared(Reg),
+    /// Read a function name and a number of parameters (both checked at a [position][Position])
+    /// from consecutive registers, and put whether such a script-defined function exists into the
+    /// first register.
+    #[cfg(not(feature = "no_function"))]
+    IsDefFn(Reg, Reg, Position),
+    /// Replace the variable name in a register (checked at a [position][Position]) with whether
+    /// the variable exists.
+    IsDefVar(Reg, Position),
+    /// Replace the script in a register (checked at the first [position][Position]) with the result
+    /// of evaluating it in place, for a call to `eval` at the second [position][Position].
+    EvalScript(Reg, Position, Position),
+    /// Evaluate a custom syntax expression at a [position][Position] into a register.
+    ///
+    /// Custom syntax is implemented by functions that take the [expressions][Expr] themselves,
+    /// which are therefore evaluated by the tree-walking evaluator.
+    Custom(Reg, Box<CustomExpr>, Position),
+}
+
+impl Op {
+    /// Get the register for the result, and the [position][Position], of a function call
+    /// instruction.
+    #[inline(always)]
+    fn call_target(&self) -> (Reg, Position) {
+        match self {
+            Self::Call(x) => (x.dst, x.pos),
+            Self::QualifiedCall(x) => (x.dst, x.pos),
+            op => unreachable!("function call instruction expected, but gets {:?}", op),
+        }
+    }
+}
+
+/// An assignment to a variable.
+#[derive(Debug, Clone)]
+struct AssignOp {
+    /// The variable assigned to.
+    var: VariableAccess,
+    /// Operator of the assignment, if any.
+    op_info: Option<OpAssignment>,
+    /// Position of the operator.
+    op_pos: Position,
+    /// Position of the value.
+    rhs_pos: Position,
+}
+
+/// Jump table of a `switch` statement.
+#[derive(Debug, Clone)]
+struct SwitchOp {
+    /// Instructions of the cases and their indices (in source order), keyed by the hash of the
+    /// matching value.
+    table: HashMap<u64, (usize, usize), StraightHasherBuilder>,
+    /// Instruction of the default case.
+    default: usize,
+    /// Position of the `switch` statement.
+    pos: Position,
+}
+
+/// Argument of a dot/index chain, with the registers of its values.
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+#[derive(Debug, Clone)]
+enum ChainArg {
+    /// Dot-property access.
+    Property(Position),
+    /// Arguments to a dot-function call, in consecutive registers.
+    FnCallArgs(Reg, StaticVec<Position>),
+    /// Index value.
+    IndexValue(Reg, Position),
+}
+
+/// A dot/index chain.
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+#[derive(Debug, Clone)]
+struct ChainOp {
+    /// Links of the chain.
+    steps: Box<[ChainStep]>,
+    /// Arguments of the chain, in the order kept by the tree-walking evaluator.
+    args: StaticVec<ChainArg>,
+    /// Register and [position][Position] of the value the chain starts with, unless it is a
+    /// variable.
+    value: Option<(Reg, Position)>,
+    /// Register to put the result in, or holding the value to assign to the chain.
+    dst: Reg,
+    /// Position of the chain.
+    pos: Position,
+    /// Operator and [positions][Position] of the operator and the value, if a value is assigned
+    /// to the chain.
+    assignment: Option<(Option<OpAssignment>, Position, Position)>,
+}
+
+/// A link of a dot/index chain compiled from its [expression][Expr], with the type and
+/// [position][Position] of the chain to the next link, if any.
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+#[derive(Debug, Clone)]
+pub(crate) struct ChainStep {
+    link: StepLink,
+    pub(crate) next: Option<(ChainType, Position)>,
+}
+
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+impl ChainStep {
+    /// Get the link of the chain.
+    #[inline(always)]
+    pub(crate) fn link(&self) -> ChainLink<'_> {
+        match &self.link {
+            StepLink::Variable(x) => ChainLink::Variable(x),
+            StepLink::Property(x) => ChainLink::Property(x),
+            #[cfg(not(feature = "no_object"))]
+            StepLink::Method(x, pos) => ChainLink::Method(&x.0, x.1, *pos),
+            StepLink::Value(pos) => ChainLink::Value(*pos),
+        }
+    }
+}
+
+/// A link of a dot/index chain, as kept in a [`ChainStep`].
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+#[derive(Debug, Clone)]
+enum StepLink {
+    /// Variable access.
+    Variable(Box<VariableAccess>),
+    /// Property access - ((getter, hash), (setter, hash), prop).
+    Property(Box<PropertyAccess>),
+    /// Method call - (function name, hash), position.
+    #[cfg(not(feature = "no_object"))]
+    Method(Box<(Cow<'static, str>, FnHash)>, Position),
+    /// Any other expression, the value of which is an index.
+    Value(Position),
+}
+
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+impl From<&Expr> for StepLink {
+    fn from(expr: &Expr) -> Self {
+        match expr {
+            Expr::Variable(x) => Self::Variable(x.clone()),
+            Expr::Property(x) => Self::Property(x.clone()),
+            #[cfg(not(feature = "no_object"))]
+            Expr::FnCall(x, pos) if x.namespace.is_none() => {
+                Self::Method(Box::new((x.name.clone(), x.hash)), *pos)
+            }
+            expr => Self::Value(expr.position()),
+        }
+    }
+}
+
+/// A function call in normal function-call style.
+#[derive(Debug, Clone)]
+struct CallOp {
+    /// Name of the function, unless called via a function pointer.
+    name: Cow<'static, str>,
+    /// Pre-calculated hash.
+    hash: FnHash,
+    /// Position of the function call.
+    pos: Position,
+    /// Register to put the result in.
+    dst: Reg,
+    /// First of the consecutive registers of the argument values.
+    args: Reg,
+    /// Number of argument values in registers.
+    num_args: usize,
+    /// First argument, if it is a variable not in a register.
+    first_var: Option<VariableAccess>,
+    /// Register of the captured scope, if any.
+    capture: Option<Reg>,
+    /// Register of the function pointer to call, if any.
+    fn_ptr: Option<Reg>,
+}
+
+/// A tail call to the script-defined function being evaluated.
+#[cfg(not(feature = "no_function"))]
+#[derive(Debug, Clone)]
+struct TailCallOp {
+    /// Name of the function.
+    name: Cow<'static, str>,
+    /// Position of the function call.
+    pos: Position,
+    /// Register to put the result in.
+    dst: Reg,
+    /// First of the consecutive registers of the argument values.
+    args: Reg,
+    /// Number of argument values.
+    num_args: usize,
+    /// Is the first argument a variable?
+    is_first_var: bool,
+}
+
+/// A namespace-qualified function call.
+#[derive(Debug, Clone)]
+struct QualifiedCallOp {
+    /// Namespace of the function.
+    namespace: NamespaceRef,
+    /// Name of the function.
+    name: Cow<'static, str>,
+    /// Pre-calculated hash.
+    hash: u64,
+    /// Position of the function call.
+    pos: Position,
+    /// Register to put the result in.
+    dst: Reg,
+    /// First of the consecutive registers of the argument values.
+    args: Reg,
+    /// Number of argument values in registers.
+    num_args: usize,
+    /// First argument, if it is a variable not in a register.
+    first_var: Option<VariableAccess>,
+}
+
+/// A compiled [`AST`] in the form of flat lists of bytecode instructions for a register-based
+/// virtual machine, which can be evaluated via [`Engine::eval_bytecode`].
+///
+/// All statements and expressions, as well as the bodies of script-defined functions, are compiled.
+/// The virtual machine keeps values being evaluated in registers and calls to script-defined
+/// functions in explicit call frames instead of recursing.
+/// It uses the same [`Dynamic`] values, function resolution and limits as the tree-walking
+/// evaluator, so the results are identical to evaluating the [`AST`] itself.
+///
+/// Variables are kept in the [`Scope`] as usual, because they can be looked up by name
+/// (e.g. by `eval`, `is_def_var` or closures).
+/// Custom syntax, and scripts passed to `eval`, are evaluated by the tree-walking evaluator.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Bytecode, Engine};
+///
+/// let engine = Engine::new();
+///
+/// let ast = engine.compile("let x = 0; while x < 42 { x += 1; } x")?;
+///
+/// let bytecode = Bytecode::compile(&ast);
+///
+/// assert_eq!(engine.eval_bytecode::<i64>(&bytecode)?, 42);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bytecode {
+    /// The [`AST`] compiled from, holding the source, functions and embedded module resolver.
+    ast: AST,
+    /// Bytecode instructions of the global statements.
+    main: Shared<Code>,
+    /// Bytecode instructions of the script-defined functions, keyed by their addresses.
+    #[cfg(not(feature = "no_function"))]
+    functions: HashMap<usize, Shared<Code>>,
+}
+
+impl Bytecode {
+    /// Compile an [`AST`] into [`Bytecode`].
+    pub fn compile(ast: &AST) -> Self {
+        Self {
+            ast: ast.clone(),
+            main: Compiler::compile_main(ast.as_ref()),
+            #[cfg(not(feature = "no_function"))]
+            functions: ast
+                .lib()
+                .iter_script_fn()
+                .filter(|(_, _, _, _, f)| !f.body.is_empty())
+                .map(|(_, _, _, _, f)| (fn_def_address(f), Compiler::compile_fn(f)))
+                .collect(),
+        }
+    }
+    /// Get the [`AST`] this [`Bytecode`] is compiled from.
+    #[inline(always)]
+    pub fn ast(&self) -> &AST {
+        &self.ast
+    }
+    /// Get the number of bytecode instructions, including those of script-defined functions.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        #[cfg(not(feature = "no_function"))]
+        return self.main.ops.len() + self.functions.values().map(|f| f.ops.len()).sum::<usize>();
+        #[cfg(feature = "no_function")]
+        return self.main.ops.len();
+    }
+    /// Is this [`Bytecode`] empty?
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Get the address of a script-defined function, which identifies it.
+#[cfg(not(feature = "no_function"))]
+#[inline(always)]
+fn fn_def_address(fn_def: &ScriptFnDef) -> usize {
+    fn_def as *const ScriptFnDef as usize
+}
+
+/// Compiler from [`AST`] nodes into bytecode instructions.
+///
+/// Registers are allocated like a stack: those of the operands of an instruction are freed as
+/// soon as the instruction is emitted.
+#[derive(Default)]
+struct Compiler {
+    ops: Vec<Op>,
+    /// Number of registers in use.
+    num_regs: usize,
+    /// Maximum number of registers ever in use.
+    max_regs: usize,
+}
+
+impl Compiler {
+    /// Compile the global statements, which are evaluated in place.
+    fn compile_main(statements: &[Stmt]) -> Shared<Code> {
+        let mut compiler = Self::default();
+        let result = compiler.alloc();
+        statements
+            .iter()
+            .for_each(|stmt| compiler.compile_stmt(stmt, result));
+        compiler.finish()
+    }
+    /// Compile the body of a script-defined function.
+    #[cfg(not(feature = "no_function"))]
+    fn compile_fn(fn_def: &ScriptFnDef) -> Shared<Code> {
+        let mut compiler = Self::default();
+        let result = compiler.alloc();
+        compiler.compile_block(&fn_def.body.statements, result);
+        compiler.finish()
+    }
+    /// Finish the compilation.
+    #[inline(always)]
+    fn finish(self) -> Shared<Code> {
+        Code {
+            ops: self.ops,
+            num_regs: self.max_regs,
+        }
+        .into()
+    }
+    /// Allocate a register.
+    #[inline(always)]
+    fn alloc(&mut self) -> Reg {
+        self.num_regs += 1;
+        self.max_regs = max(self.max_regs, self.num_regs);
+        self.num_regs - 1
+    }
+    /// Add an instruction, returning its index.
+    #[inline(always)]
+    fn emit(&mut self, op: Op) -> usize {
+        self.ops.push(op);
+        self.ops.len() - 1
+    }
+    /// Get the index of the next instruction.
+    #[inline(always)]
+    fn here(&self) -> usize {
+        self.ops.len()
+    }
+    /// Set the jump target of an instruction to the next instruction.
+    fn patch(&mut self, index: usize) {
+        let target = self.ops.len();
+
+        match &mut self.ops[index] {
+            Op::Jump(t)
+            | Op::JumpIf(_, _, _, t)
+            | Op::ForStart(_, _, t)
+            | Op::ForNext(_, t)
+            | Op::BeginTry(_, t)
+            | Op::EndTry(t)
+            | Op::ShortCircuit(_, _, _, t)
+            | Op::FnPtrCall(_, t) => *t = target,
+            #[cfg(not(feature = "no_function"))]
+            Op::TailCallCheck(_, t) => *t = target,
+            op => unreachable!("jump instruction expected, but gets {:?}", op),
+        }
+    }
+    /// Compile a block of statements, the value of which is put into a register.
+    fn compile_block(&mut self, statements: &[Stmt], dst: Reg) {
+        if statements.is_empty() {
+            self.emit(Op::Unit(dst));
+        } else {
+            self.emit(Op::BeginBlock);
+            statements
+                .iter()
+                .for_each(|stmt| self.compile_stmt(stmt, dst));
+            self.emit(Op::EndBlock);
+        }
+    }
+    /// Compile a statement, the value of which is put into a register.
+    fn compile_stmt(&mut self, stmt: &Stmt, dst: Reg) {
+        let pos = stmt.position();
+        let coverable_pos = coverable_position(stmt).unwrap_or(Position::NONE);
+        self.emit(Op::Stmt(pos, coverable_pos));
+
+        match stmt {
+            Stmt::Noop(_) => {
+                self.emit(Op::Unit(dst));
+            }
+
+            Stmt::Expr(expr) => self.compile_expr(expr, dst),
+
+            // var op= rhs
+            Stmt::Assignment(x, op_pos) if x.0.get_variable_access(false).is_some() => {
+                let (lhs_expr, rhs_expr, op_info) = x.as_ref();
+                self.compile_expr(rhs_expr, dst);
+                self.emit(Op::Assign(
+                    dst,
+                    Box::new(AssignOp {
+                        var: lhs_expr.as_variable().clone(),
+                        op_info: op_info.clone(),
+                        op_pos: *op_pos,
+                        rhs_pos: rhs_expr.position(),
+                    }),
+                ));
+            }
+
+            // lhs op= rhs
+            Stmt::Assignment(x, _op_pos) => {
+                let (lhs_expr, rhs_expr, _op_info) = x.as_ref();
+                self.compile_expr(rhs_expr, dst);
+
+                match lhs_expr {
+                    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+                    Expr::Index(_, _) | Expr::Dot(_, _) => {
+                        let assignment = (_op_info.clone(), *_op_pos, rhs_expr.position());
+                        self.compile_chain(lhs_expr, dst, Some(assignment));
+                    }
+                    _ => unreachable!("cannot assign to expression: {:?}", lhs_expr),
+                }
+            }
+
+            Stmt::Block(statements, _) => self.compile_block(statements, dst),
+
+            Stmt::If(expr, x, _) => {
+                self.compile_expr(expr, dst);
+                let jump_else = self.emit(Op::JumpIf(dst, false, expr.position(), 0));
+
+                self.emit(Op::Branch(pos, 2, 0));
+                self.compile_block(&x.0.statements, dst);
+                let jump_end = self.emit(Op::Jump(0));
+
+                self.patch(jump_else);
+                self.emit(Op::Branch(pos, 2, 1));
+                self.compile_block(&x.1.statements, dst);
+
+                self.patch(jump_end);
+            }
+
+            Stmt::Switch(expr, x, _) => {
+                let (table, def_stmt) = x.as_ref();
+
+                self.compile_expr(expr, dst);
+                let switch = self.emit(Op::Switch(
+                    dst,
+                    Box::new(SwitchOp {
+                        table: Default::default(),
+                        default: 0,
+                        pos,
+                    }),
+                ));
+
+                let mut jumps: StaticVec<_> = Default::default();
+                let mut cases: HashMap<_, _, _> =
+                    HashMap::with_capacity_and_hasher(table.len(), StraightHasherBuilder);
+
+                table.iter().for_each(|(&hash, block)| {
+                    let branch = switch_branch_index(table.values(), block);
+                    cases.insert(hash, (self.here(), branch));
+                    self.compile_block(&block.statements, dst);
+                    jumps.push(self.emit(Op::Jump(0)));
+                });
+
+                let default = self.here();
+                self.compile_block(&def_stmt.statements, dst);
+                jumps.into_iter().for_each(|jump| self.patch(jump));
+
+                if let Op::Switch(_, x) = &mut self.ops[switch] {
+                    x.table = cases;
+                    x.default = default;
+                }
+            }
+
+            // While loops never check the value of the statement
+            Stmt::While(expr, body, _) => {
+                let begin = self.emit(Op::BeginLoop(0, 0));
+                let next = self.here();
+
+                let jump_exit = if !expr.is_unit() {
+                    self.compile_expr(expr, dst);
+                    Some(self.emit(Op::JumpIf(dst, false, expr.position(), 0)))
+                } else {
+                    None
+                };
+
+                if !body.statements.is_empty() {
+                    self.compile_block(&body.statements, dst);
+                }
+                self.emit(Op::Jump(next));
+
+                let exit = self.here();
+                if let Some(jump_exit) = jump_exit {
+                    self.patch(jump_exit);
+                }
+                self.ops[begin] = Op::BeginLoop(next, exit);
+                self.emit(Op::EndLoop);
+                self.emit(Op::Unit(dst));
+                return;
+            }
+
+            // Do loops never check the value of the statement
+            Stmt::Do(body, expr, is_while, _) => {
+                let begin = self.emit(Op::BeginLoop(0, 0));
+                let next = self.here();
+
+                if !body.statements.is_empty() {
+                    self.compile_block(&body.statements, dst);
+                }
+                self.compile_expr(expr, dst);
+                self.emit(Op::JumpIf(dst, *is_while, expr.position(), next));
+
+                let exit = self.here();
+                self.ops[begin] = Op::BeginLoop(next, exit);
+                self.emit(Op::EndLoop);
+                self.emit(Op::Unit(dst));
+                return;
+            }
+
+            Stmt::For(expr, x, _) => {
+                let (name, body) = x.as_ref();
+
+                self.compile_expr(expr, dst);
+                let start = self.emit(Op::ForStart(
+                    dst,
+                    Box::new((name.clone(), expr.position())),
+                    0,
+                ));
+
+                let next = self.emit(Op::ForNext(body.pos, 0));
+                if !body.statements.is_empty() {
+                    self.compile_block(&body.statements, dst);
+                }
+                self.emit(Op::Jump(next));
+
+                self.patch(start);
+                self.patch(next);
+                self.emit(Op::EndFor);
+                self.emit(Op::Unit(dst));
+            }
+
+            Stmt::Continue(pos) => {
+                self.emit(Op::Break(false, *pos));
+                return;
+            }
+
+            Stmt::Break(pos) => {
+                self.emit(Op::Break(true, *pos));
+                return;
+            }
+
+            Stmt::TryCatch(x, _, _) => {
+                let (try_body, err_var, catch_body) = x.as_ref();
+
+                let begin = self.emit(Op::BeginTry(dst, 0));
+                self.compile_block(&try_body.statements, dst);
+                let end = self.emit(Op::EndTry(0));
+
+                self.patch(begin);
+                self.emit(Op::Catch(dst, err_var.as_ref().map(|v| v.name.clone())));
+                self.compile_block(&catch_body.statements, dst);
+                self.emit(Op::EndCatch);
+
+                self.patch(end);
+                self.emit(Op::Unit(dst));
+            }
+
+            Stmt::Return(return_type, expr, pos) => {
+                let value = expr.as_ref().map(|expr| {
+                    self.compile_expr(expr, dst);
+                    dst
+                });
+                self.emit(match return_type {
+                    ReturnType::Return => Op::Return(value, *pos),
+                    ReturnType::Exception => Op::Throw(value, *pos),
+                });
+                return;
+            }
+
+            Stmt::Let(expr, Ident { name, .. }, export, _)
+            | Stmt::Const(expr, Ident { name, .. }, export, _) => {
+                let entry_type = match stmt {
+                    Stmt::Let(_, _, _, _) => AccessMode::ReadWrite,
+                    _ => AccessMode::ReadOnly,
+                };
+
+                self.compile_expr(expr, dst);
+                self.emit(Op::Let(dst, Box::new((name.clone(), entry_type, *export))));
+            }
+
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(expr, export, pos) => {
+                self.emit(Op::CheckModules(*pos));
+                self.compile_expr(expr, dst);
+                let name = export.as_ref().map(|x| x.name.clone());
+                self.emit(Op::Import(dst, Box::new((name, expr.position()))));
+            }
+
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Export(list, _) => {
+                self.emit(Op::Export(list.clone().into_boxed_slice()));
+                self.emit(Op::Unit(dst));
+            }
+
+            #[cfg(not(feature = "no_closure"))]
+            Stmt::Share(x) => {
+                self.emit(Op::Share(x.name.clone()));
+                self.emit(Op::Unit(dst));
+            }
+        }
+
+        self.emit(Op::EndStmt(dst, pos));
+    }
+    /// Compile an expression, the value of which is put into a register.
+    ///
+    /// All registers allocated for evaluating the expression are freed afterwards.
+    fn compile_expr(&mut self, expr: &Expr, dst: Reg) {
+        let num_regs = self.num_regs;
+        self.compile_expr_raw(expr, dst);
+        self.num_regs = num_regs;
+    }
+    /// Compile a list of expressions, the values of which are put into consecutive registers.
+    /// Returns the first of the registers, which stay allocated.
+    fn compile_args<'e>(&mut self, args: impl IntoIterator<Item = &'e Expr>) -> Reg {
+        let first = self.num_regs;
+        args.into_iter().for_each(|arg| {
+            let reg = self.alloc();
+            self.compile_expr(arg, reg);
+        });
+        first
+    }
+    /// Compile an expression, without freeing the registers allocated for it.
+    fn compile_expr_raw(&mut self, expr: &Expr, dst: Reg) {
+        let pos = expr.position();
+
+        match expr {
+            Expr::DynamicConstant(x, _) => {
+                self.emit(Op::Load(dst, x.as_ref().clone(), pos));
+            }
+            Expr::IntegerConstant(x, _) => {
+                self.emit(Op::Load(dst, (*x).into(), pos));
+            }
+            #[cfg(not(feature = "no_float"))]
+            Expr::FloatConstant(x, _) => {
+                self.emit(Op::Load(dst, (*x).into(), pos));
+            }
+            Expr::StringConstant(x, _) => {
+                self.emit(Op::Load(dst, x.clone().into(), pos));
+            }
+            Expr::CharConstant(x, _) => {
+                self.emit(Op::Load(dst, (*x).into(), pos));
+            }
+            Expr::FnPointer(x, _) => {
+                let value = FnPtr::new_unchecked(x.clone(), Default::default()).into();
+                self.emit(Op::Load(dst, value, pos));
+            }
+            Expr::BoolConstant(x, _) => {
+                self.emit(Op::Load(dst, (*x).into(), pos));
+            }
+            Expr::Unit(_) => {
+                self.emit(Op::Load(dst, Dynamic::UNIT, pos));
+            }
+
+            Expr::Variable(x) if (x.2).name == KEYWORD_THIS => {
+                self.emit(Op::This(pos, (x.2).pos));
+            }
+            Expr::Variable(x) => {
+                self.emit(Op::Get(dst, x.clone()));
+            }
+
+            // Statement block
+            Expr::Stmt(x) if x.is_empty() => {
+                self.emit(Op::Load(dst, Dynamic::UNIT, pos));
+            }
+            Expr::Stmt(x) => {
+                self.emit(Op::Count(pos));
+                self.compile_block(&x.statements, dst);
+                self.emit(Op::Check(dst, pos));
+            }
+
+            // Inlined function call
+            #[cfg(not(feature = "no_function"))]
+            Expr::InlinedFnCall(x, _) => {
+                self.emit(Op::Count(pos));
+                self.emit(Op::BeginInline(Box::new((x.0.clone(), pos))));
+                self.compile_block(&x.1.statements, dst);
+                self.emit(Op::EndInline);
+                self.emit(Op::Check(dst, pos));
+            }
+
+            #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+            Expr::Index(_, _) | Expr::Dot(_, _) => {
+                self.emit(Op::Count(pos));
+                self.compile_chain(expr, dst, None);
+            }
+
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(x, _) => {
+                self.emit(Op::Count(pos));
+                let first = self.compile_args(x.iter());
+                self.emit(Op::Array(dst, first, x.len(), pos));
+            }
+
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(x, _) => {
+                self.emit(Op::Count(pos));
+                let first = self.compile_args(x.iter().map(|(_, item)| item));
+                let keys = x.iter().map(|(key, _)| key.clone()).collect();
+                self.emit(Op::Map(dst, first, Box::new(keys), pos));
+            }
+
+            // Tail call to the script-defined function being evaluated
+            #[cfg(not(feature = "no_function"))]
+            Expr::FnCall(x, pos) if x.tail_call && !x.hash.is_native_only() => {
+                self.emit(Op::Count(*pos));
+
+                let FnCallExpr {
+                    name, hash, args, ..
+                } = x.as_ref();
+
+                let check = self.emit(Op::TailCallCheck(
+                    Box::new((name.clone(), hash.script_hash())),
+                    0,
+                ));
+                let num_regs = self.num_regs;
+                let first = self.compile_args(args.iter());
+                self.emit(Op::TailCall(Box::new(TailCallOp {
+                    name: name.clone(),
+                    pos: *pos,
+                    dst,
+                    args: first,
+                    num_args: args.len(),
+                    is_first_var: !args.is_empty() && args[0].get_variable_access(false).is_some(),
+                })));
+                self.num_regs = num_regs;
+                let end = self.emit(Op::Jump(0));
+
+                // Normal function call
+                self.patch(check);
+                self.compile_fn_call(x, *pos, dst);
+                self.patch(end);
+            }
+
+            // Normal function call
+            Expr::FnCall(x, pos) if x.namespace.is_none() => {
+                self.emit(Op::Count(*pos));
+                self.compile_fn_call(x, *pos, dst);
+            }
+
+            // Namespace-qualified function call
+            Expr::FnCall(x, pos) => {
+                self.emit(Op::Count(*pos));
+
+                let FnCallExpr {
+                    name,
+                    namespace,
+                    hash,
+                    args,
+                    ..
+                } = x.as_ref();
+
+                let first_var = if !args.is_empty() && args[0].get_variable_access(true).is_some() {
+                    Some(args[0].as_variable().clone())
+                } else {
+                    None
+                };
+                let skip = if first_var.is_some() { 1 } else { 0 };
+                let first = self.compile_args(args.iter().skip(skip));
+
+                self.emit(Op::QualifiedCall(Box::new(QualifiedCallOp {
+                    namespace: namespace.clone().unwrap(),
+                    name: name.clone(),
+                    hash: hash.native_hash(),
+                    pos: *pos,
+                    dst,
+                    args: first,
+                    num_args: args.len() - skip,
+                    first_var,
+                })));
+            }
+
+            Expr::And(x, _) | Expr::Or(x, _) => {
+                // Short-circuit on `false` for `&&` and on `true` for `||`
+                let value = matches!(expr, Expr::Or(_, _));
+
+                self.emit(Op::Count(pos));
+                self.compile_expr(&x.lhs, dst);
+                let jump = self.emit(Op::ShortCircuit(dst, value, x.lhs.position(), 0));
+                self.compile_expr(&x.rhs, dst);
+                self.emit(Op::Bool(dst, x.rhs.position()));
+                self.patch(jump);
+            }
+
+            Expr::Custom(custom, _) => {
+                self.emit(Op::Custom(dst, custom.clone(), pos));
+            }
+
+            _ => unreachable!("expression cannot be evaluated: {:?}", expr),
+        }
+    }
+    /// Compile a function call in normal function-call style, except for counting the operation.
+    fn compile_fn_call(&mut self, x: &FnCallExpr, pos: Position, dst: Reg) {
+        let FnCallExpr { name, args, .. } = x;
+
+        match name.as_ref() {
+            // Handle call()
+            KEYWORD_FN_PTR_CALL if !args.is_empty() => {
+                let fn_ptr = self.alloc();
+                self.compile_expr(&args[0], fn_ptr);
+                self.emit(Op::AssertFnPtr(fn_ptr, args[0].position()));
+
+                let args = &args[1..];
+
+                if !args.is_empty() && args[0].get_variable_access(false).is_some() {
+                    // Without curried arguments, a variable as the first argument is passed
+                    // by reference
+                    let jump_curry = self.emit(Op::FnPtrCall(fn_ptr, 0));
+                    self.compile_call(x, pos, dst, &args[1..], Some(&args[0]), Some(fn_ptr));
+                    let jump_end = self.emit(Op::Jump(0));
+
+                    self.patch(jump_curry);
+                    self.compile_call(x, pos, dst, args, None, Some(fn_ptr));
+                    self.patch(jump_end);
+                } else {
+                    self.compile_call(x, pos, dst, args, None, Some(fn_ptr));
+                }
+            }
+
+            // Handle Fn()
+            KEYWORD_FN_PTR if args.len() == 1 => {
+                self.compile_expr(&args[0], dst);
+                self.emit(Op::MakeFnPtr(dst, args[0].position()));
+            }
+
+            // Handle curry()
+            KEYWORD_FN_PTR_CURRY if args.len() > 1 => {
+                let fn_ptr = self.alloc();
+                self.compile_expr(&args[0], fn_ptr);
+                self.emit(Op::AssertFnPtr(fn_ptr, args[0].position()));
+                self.compile_args(args[1..].iter());
+                self.emit(Op::Curry(dst, fn_ptr, args.len() - 1));
+            }
+
+            // Handle is_shared()
+            #[cfg(not(feature = "no_closure"))]
+            crate::engine::KEYWORD_IS_SHARED if args.len() == 1 => {
+                self.compile_expr(&args[0], dst);
+                self.emit(Op::IsShared(dst));
+            }
+
+            // Handle is_def_fn()
+            #[cfg(not(feature = "no_function"))]
+            crate::engine::KEYWORD_IS_DEF_FN if args.len() == 2 => {
+                let fn_name = self.alloc();
+                self.compile_expr(&args[0], fn_name);
+                self.emit(Op::AssertString(fn_name, args[0].position()));
+                let num_params = self.alloc();
+                self.compile_expr(&args[1], num_params);
+                self.emit(Op::IsDefFn(dst, fn_name, args[0].position()));
+            }
+
+            // Handle is_def_var()
+            KEYWORD_IS_DEF_VAR if args.len() == 1 => {
+                self.compile_expr(&args[0], dst);
+                self.emit(Op::IsDefVar(dst, args[0].position()));
+            }
+
+            // Handle eval()
+            KEYWORD_EVAL if args.len() == 1 => {
+                self.compile_expr(&args[0], dst);
+                self.emit(Op::EvalScript(dst, args[0].position(), pos));
+            }
+
+            _ => {
+                if !args.is_empty() && args[0].get_variable_access(false).is_some() {
+                    // A variable as the first argument is passed by reference
+                    self.compile_call(x, pos, dst, &args[1..], Some(&args[0]), None);
+                } else {
+                    self.compile_call(x, pos, dst, args, None, None);
+                }
+            }
+        }
+    }
+    /// Compile a call to a function in normal function-call style, with the arguments following
+    /// a variable as the first argument (if any), and the register of a function pointer to call
+    /// (if any).
+    fn compile_call(
+        &mut self,
+        x: &FnCallExpr,
+        pos: Position,
+        dst: Reg,
+        args: &[Expr],
+        first_var: Option<&Expr>,
+        fn_ptr: Option<Reg>,
+    ) {
+        let num_regs = self.num_regs;
+
+        let capture = if x.capture {
+            let reg = self.alloc();
+            self.emit(Op::Capture(reg));
+            Some(reg)
+        } else {
+            None
+        };
+        let first = self.compile_args(args.iter());
+
+        self.emit(Op::Call(Box::new(CallOp {
+            name: x.name.clone(),
+            hash: x.hash,
+            pos,
+            dst,
+            args: first,
+            num_args: args.len(),
+            first_var: first_var.map(|expr| expr.as_variable().clone()),
+            capture,
+            fn_ptr,
+        })));
+
+        self.num_regs = num_regs;
+    }
+    /// Compile a dot/index chain, the value of which is put into a register, or with the value
+    /// in the register assigned to it.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    fn compile_chain(
+        &mut self,
+        expr: &Expr,
+        dst: Reg,
+        assignment: Option<(Option<OpAssignment>, Position, Position)>,
+    ) {
+        let (x, chain_type) = match expr {
+            Expr::Index(x, _) => (x.as_ref(), ChainType::Index),
+            Expr::Dot(x, _) => (x.as_ref(), ChainType::Dot),
+            _ => unreachable!("index or dot chain expected, but gets {:?}", expr),
+        };
+
+        let num_regs = self.num_regs;
+
+        let mut args = Default::default();
+        self.compile_chain_args(&x.rhs, chain_type, &mut args);
+
+        let value = match x.lhs {
+            Expr::Variable(_) => None,
+            _ if assignment.is_some() => unreachable!("cannot assign to an expression"),
+            _ => {
+                let reg = self.alloc();
+                self.compile_expr(&x.lhs, reg);
+                Some((reg, x.lhs.position()))
+            }
+        };
+
+        // Flatten the links of the chain
+        let mut steps = Vec::new();
+        let mut link = expr;
+
+        loop {
+            match link {
+                Expr::Index(x, pos) | Expr::Dot(x, pos) => {
+                    let chain_type = match link {
+                        Expr::Index(_, _) => ChainType::Index,
+                        _ => ChainType::Dot,
+                    };
+                    steps.push(ChainStep {
+                        link: (&x.lhs).into(),
+                        next: Some((chain_type, *pos)),
+                    });
+                    link = &x.rhs;
+                }
+                _ => {
+                    steps.push(ChainStep {
+                        link: link.into(),
+                        next: None,
+                    });
+                    break;
+                }
+            }
+        }
+
+        self.emit(Op::Chain(Box::new(ChainOp {
+            steps: steps.into_boxed_slice(),
+            args,
+            value,
+            dst,
+            pos: expr.position(),
+            assignment,
+        })));
+
+        self.num_regs = num_regs;
+    }
+    /// Compile the arguments of a dot/index chain, in the order evaluated by the tree-walking
+    /// evaluator.
+    ///
+    /// The registers of their values stay allocated.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    fn compile_chain_args(
+        &mut self,
+        expr: &Expr,
+        parent_chain_type: ChainType,
+        args: &mut StaticVec<ChainArg>,
+    ) {
+        self.emit(Op::Count(expr.position()));
+
+        match expr {
+            Expr::Index(x, _) | Expr::Dot(x, _) => {
+                let arg = self.compile_chain_arg(&x.lhs, parent_chain_type);
+
+                let chain_type = match expr {
+                    Expr::Index(_, _) => ChainType::Index,
+                    _ => ChainType::Dot,
+                };
+                self.compile_chain_args(&x.rhs, chain_type, args);
+
+                args.push(arg);
+            }
+            _ => {
+                let arg = self.compile_chain_arg(expr, parent_chain_type);
+                args.push(arg);
+            }
+        }
+    }
+    /// Compile a single argument of a dot/index chain.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    fn compile_chain_arg(&mut self, expr: &Expr, parent_chain_type: ChainType) -> ChainArg {
+        match expr {
+            Expr::Property(x) if parent_chain_type == ChainType::Dot => ChainArg::Property(x.2.pos),
+            Expr::Property(_) => unreachable!("unexpected Expr::Property for indexing"),
+            Expr::FnCall(x, _) if parent_chain_type == ChainType::Dot && x.namespace.is_none() => {
+                let first = self.compile_args(x.args.iter());
+                ChainArg::FnCallArgs(first, x.args.iter().map(|arg| arg.position()).collect())
+            }
+            Expr::FnCall(_, _) if parent_chain_type == ChainType::Dot => {
+                unreachable!("function call in dot chain should not be namespace-qualified")
+            }
+            _ => {
+                let reg = self.alloc();
+                self.compile_expr(expr, reg);
+                ChainArg::IndexValue(reg, expr.position())
+            }
+        }
+    }
+}
+
+/// Saved state of a statements block, restored at the end of the block.
+struct BlockRegion {
+    scope_len: usize,
+    mods_len: usize,
+    always_search: bool,
+    /// Is a function resolution cache pushed for modules imported within the block?
+    fn_resolution_cache: bool,
+}
+
+/// A `for` loop being evaluated.
+struct ForRegion {
+    /// Iterator over the values of the loop variable.
+    iter: DynamicIterator,
+    /// Index of the loop variable in the scope.
+    index: usize,
+    /// Instruction to jump to on `continue`.
+    next: usize,
+    /// Instruction to jump to on `break`.
+    exit: usize,
+}
+
+/// A region of instructions being evaluated within a call frame, which handles errors
+/// (including `break`, `continue` and `return`) raised within it.
+enum Region {
+    /// A statements block.
+    Block(BlockRegion),
+    /// A `while`, `loop` or `do` loop, with the instructions to jump to on `continue` and `break`.
+    Loop(usize, usize),
+    /// A `for` loop.
+    For(Box<ForRegion>),
+    /// A `try` block, with the instruction of its `catch` block and the register to put the
+    /// error value into.
+    Try(usize, Reg),
+    /// A `catch` block, with the error caught.
+    Catch(usize, Box<EvalAltResult>),
+    /// The body of an inlined function call, with the name of the function and the
+    /// [position][Position] of the call.
+    #[cfg(not(feature = "no_function"))]
+    Inline(ImmutableString, Position),
+}
+
+/// A call to a script-defined function being evaluated in a call frame.
+#[cfg(not(feature = "no_function"))]
+struct FnCall {
+    fn_def: Shared<ScriptFnDef>,
+    /// State saved when the call started.
+    frame: ScriptFnFrame,
+    /// Position of the function call.
+    pos: Position,
+    /// Source of the caller.
+    source: Option<ImmutableString>,
+}
+
+/// A call frame of the virtual machine.
+struct Frame {
+    /// Bytecode instructions being evaluated.
+    code: Shared<Code>,
+    /// Index of the next instruction.
+    ip: usize,
+    /// Regions of instructions being evaluated, the innermost last.
+    regions: StaticVec<Region>,
+    /// Index of the first register of the frame.
+    base: usize,
+    /// Call level.
+    level: usize,
+    /// Encapsulated environments of the script-defined functions being evaluated, innermost first.
+    env: StaticVec<Shared<Module>>,
+    /// Index of the [`Scope`] of the frame, or [`None`] for the [`Scope`] evaluated with.
+    scope: Option<usize>,
+    /// Call to a script-defined function evaluated in the frame, if any.
+    #[cfg(not(feature = "no_function"))]
+    call: Option<Box<FnCall>>,
+}
+
+/// A call to a script-defined function, to start in a new call frame.
+#[cfg(not(feature = "no_function"))]
+struct PendingCall {
+    fn_def: Shared<ScriptFnDef>,
+    args: StaticVec<Dynamic>,
+    /// Source of the function.
+    source: Option<ImmutableString>,
+    /// Variables captured from the scope of the caller.
+    captured: Option<Scope<'static>>,
+    /// Position of the function call.
+    pos: Position,
+}
+
+/// A call to a script-defined function, which never happens without script-defined functions.
+#[cfg(feature = "no_function")]
+enum PendingCall {}
+
+/// How a call frame continues to evaluate.
+enum Resume {
+    /// Continue with the next instruction.
+    Next,
+    /// Continue with the result of a function call.
+    Value(Dynamic),
+    /// Continue by raising an error.
+    Error(Box<EvalAltResult>),
+}
+
+/// How a call frame stops evaluating.
+enum Exit {
+    /// All instructions of the frame are evaluated, with a result.
+    Return(RhaiResult),
+    /// A script-defined function is called.
+    Call(Box<PendingCall>),
+    /// A native function call suspends the evaluation, with a token, at a [position][Position].
+    Suspend(Dynamic, Position),
+}
+
+/// Result of running the virtual machine.
+enum Outcome {
+    /// All call frames are evaluated, with a result.
+    Completed(Dynamic),
+    /// A native function call suspends the evaluation, with a token, at a [position][Position].
+    Suspended(Dynamic, Position),
+}
+
+/// State of the virtual machine.
+pub(crate) struct Vm {
+    /// Can the evaluation be suspended?
+    resumable: bool,
+    /// Scopes of calls to script-defined functions.
+    scopes: Vec<Scope<'static>>,
+    /// Call frames, the innermost last.
+    frames: Vec<Frame>,
+    /// Registers of all call frames.
+    regs: Vec<Dynamic>,
+    /// Bytecode instructions of script-defined functions not compiled into the [`Bytecode`]
+    /// (e.g. those in modules), keyed by their addresses.
+    #[cfg(not(feature = "no_function"))]
+    functions: HashMap<usize, (Shared<ScriptFnDef>, Shared<Code>)>,
+}
+
+impl Vm {
+    /// Create a new virtual machine to evaluate the global statements of [`Bytecode`].
+    pub(crate) fn new(bytecode: &Bytecode, resumable: bool) -> Self {
+        let mut vm = Self {
+            resumable,
+            scopes: Default::default(),
+            frames: Default::default(),
+            regs: Default::default(),
+            #[cfg(not(feature = "no_function"))]
+            functions: Default::default(),
+        };
+        vm.push_frame(bytecode.main.clone(), 0, Default::default(), None);
+        vm
+    }
+    /// Push a new call frame to evaluate bytecode instructions, allocating its registers.
+    fn push_frame(
+        &mut self,
+        code: Shared<Code>,
+        level: usize,
+        env: StaticVec<Shared<Module>>,
+        scope: Option<usize>,
+    ) -> &mut Frame {
+        let base = self.regs.len();
+        self.regs.resize(base + code.num_regs, Dynamic::UNIT);
+
+        self.frames.push(Frame {
+            code,
+            ip: 0,
+            regions: Default::default(),
+            base,
+            level,
+            env,
+            scope,
+            #[cfg(not(feature = "no_function"))]
+            call: None,
+        });
+        self.frames.last_mut().unwrap()
+    }
+    /// Pop the innermost call frame, freeing its registers.
+    #[cfg(not(feature = "no_function"))]
+    fn pop_frame(&mut self) {
+        let frame = self.frames.pop().expect("call frame should exist");
+        self.regs.truncate(frame.base);
+
+        if frame.scope.is_some() {
+            self.scopes.pop();
+        }
+    }
+    /// Get the bytecode instructions of a script-defined function.
+    #[cfg(not(feature = "no_function"))]
+    fn fn_code(&mut self, bytecode: &Bytecode, fn_def: &Shared<ScriptFnDef>) -> Shared<Code> {
+        let address = fn_def_address(fn_def);
+
+        match bytecode.functions.get(&address) {
+            Some(code) => code.clone(),
+            None => self
+                .functions
+                .entry(address)
+                .or_insert_with(|| (fn_def.clone(), Compiler::compile_fn(fn_def)))
+                .1
+                .clone(),
+        }
+    }
+}
+
+impl Engine {
+    /// Run [`Bytecode`].
+    pub(crate) fn run_bytecode(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        bytecode: &Bytecode,
+    ) -> RhaiResult {
+        let vm = &mut Vm::new(bytecode, false);
+
+        match self.run_vm(scope, mods, state, bytecode, vm, Resume::Next)? {
+            Outcome::Completed(value) => Ok(value),
+            Outcome::Suspended(_, _) => unreachable!("evaluation should not be suspended"),
+        }
+    }
+    /// Create a virtual machine to evaluate a call to a script-defined function of [`Bytecode`],
+    /// putting the arguments into the [`Scope`].
+    /// The evaluation can be suspended.
+    ///
+    /// Returns the result instead, as `Ok(Err(value))`, if the function need not be evaluated.
+    ///
+    /// # WARNING
+    ///
+    /// All function call arguments are _consumed_.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn start_fn_call(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        bytecode: &Bytecode,
+        fn_def: &Shared<ScriptFnDef>,
+        args: &mut FnCallArgs,
+    ) -> Result<Result<Vm, Dynamic>, Box<EvalAltResult>> {
+        let lib = &[bytecode.ast.lib()];
+        let env = &mut EvalEnv {
+            scope,
+            mods,
+            state,
+            lib,
+        };
+
+        let fn_frame = match self.enter_script_fn(env, false, fn_def, args, Position::NONE, 0)? {
+            Ok(fn_frame) => fn_frame,
+            Err(value) => return Ok(Err(value)),
+        };
+
+        let mut vm = Vm {
+            resumable: true,
+            scopes: Default::default(),
+            frames: Default::default(),
+            regs: Default::default(),
+            functions: Default::default(),
+        };
+        let code = vm.fn_code(bytecode, fn_def);
+
+        let frame = vm.push_frame(code, 0, Default::default(), None);
+        if let Some(ref fn_lib) = fn_def.lib {
+            frame.env.push(fn_lib.clone());
+        }
+        frame.call = Some(Box::new(FnCall {
+            fn_def: fn_def.clone(),
+            frame: fn_frame,
+            pos: Position::NONE,
+            source: env.state.source.clone(),
+        }));
+
+        Ok(Ok(vm))
+    }
+    /// Run a suspended evaluation kept in a [`Continuation`], resuming it with a value unless it
+    /// is yet to start.
+    pub(crate) fn run_continuation(
+        &self,
+        scope: &mut Scope,
+        mut continuation: Box<Continuation>,
+        value: Option<Dynamic>,
+    ) -> Result<Execution, Box<EvalAltResult>> {
+        let Continuation {
+            token,
+            pos,
+            bytecode,
+            vm,
+            state,
+            mods,
+        } = continuation.as_mut();
+
+        let resume = value.map_or(Resume::Next, Resume::Value);
+
+        // The execution time is limited for each run
+        #[cfg(not(feature = "unchecked"))]
+        #[cfg(not(feature = "no_std"))]
+        {
+            state.started = None;
+        }
+
+        // Termination requests apply to the runs in progress when they are made
+        state.generation = None;
+
+        let result = self.run_vm(scope, mods, state, bytecode, vm, resume);
+
+        // Variables within blocks borrow their names from the bytecode, which goes away with
+        // the continuation
+        *scope = mem::take(scope).into_owned();
+
+        match result {
+            Ok(Outcome::Suspended(t, p)) => {
+                *token = t;
+                *pos = p;
+                Ok(Execution::Suspended(continuation))
+            }
+            result => {
+                self.keep_fn_resolution_cache(state, mods, bytecode.ast());
+
+                match result? {
+                    Outcome::Completed(value) => Ok(Execution::Completed(value)),
+                    Outcome::Suspended(_, _) => unreachable!("suspension is handled above"),
+                }
+            }
+        }
+    }
+    /// Run the virtual machine until all call frames are evaluated.
+    fn run_vm(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        bytecode: &Bytecode,
+        vm: &mut Vm,
+        mut resume: Resume,
+    ) -> Result<Outcome, Box<EvalAltResult>> {
+        loop {
+            let frame = vm.frames.last_mut().expect("call frame should exist");
+
+            let fn_env = frame.env.clone();
+            let lib = fn_env
+                .iter()
+                .map(|m| m.as_ref())
+                .chain(once(bytecode.ast.lib()))
+                .collect::<StaticVec<_>>();
+            let lib = lib.as_ref();
+
+            let regs = &mut vm.regs[frame.base..];
+
+            let exit = match frame.scope {
+                Some(index) => {
+                    let env = &mut EvalEnv {
+                        scope: &mut vm.scopes[index],
+                        mods,
+                        state,
+                        lib,
+                    };
+                    self.run_frame(env, frame, regs, resume)
+                }
+                None => {
+                    let env = &mut EvalEnv {
+                        scope,
+                        mods,
+                        state,
+                        lib,
+                    };
+                    self.run_frame(env, frame, regs, resume)
+                }
+            };
+
+            match exit {
+                #[cfg(not(feature = "no_function"))]
+                Exit::Return(result) if frame.call.is_some() => {
+                    let result = match frame.scope {
+                        Some(index) => {
+                            self.end_fn_call(&mut vm.scopes[index], mods, state, frame, result)
+                        }
+                        None => self.end_fn_call(scope, mods, state, frame, result),
+                    };
+
+                    resume = match result {
+                        // Tail call - evaluate the function again
+                        None => Resume::Next,
+                        Some(result) => {
+                            vm.pop_frame();
+
+                            if vm.frames.is_empty() {
+                                return result.map(Outcome::Completed);
+                            }
+
+                            match result {
+                                Ok(value) => Resume::Value(value),
+                                Err(err) => Resume::Error(err),
+                            }
+                        }
+                    };
+                }
+
+                Exit::Return(result) => {
+                    return result.map(Outcome::Completed).or_else(|err| match *err {
+                        EvalAltResult::Return(out, _) => Ok(Outcome::Completed(out)),
+                        EvalAltResult::LoopBreak(_, _) => {
+                            unreachable!("no outer loop scope to break out of")
+                        }
+                        _ => Err(err),
+                    })
+                }
+
+                Exit::Suspend(token, pos) if vm.resumable => {
+                    return Ok(Outcome::Suspended(token, pos))
+                }
+
+                // Suspension is an error unless the evaluation is resumable
+                Exit::Suspend(token, pos) => {
+                    resume = Resume::Error(EvalAltResult::ErrorSuspended(token, pos).into())
+                }
+
+                #[cfg(feature = "no_function")]
+                Exit::Call(call) => match *call {},
+
+                #[cfg(not(feature = "no_function"))]
+                Exit::Call(call) => {
+                    let PendingCall {
+                        fn_def,
+                        mut args,
+                        source,
+                        captured,
+                        pos,
+                    } = *call;
+
+                    let level = frame.level + 1;
+                    let mut fn_env = fn_env.clone();
+                    if let Some(ref fn_lib) = fn_def.lib {
+                        fn_env.insert(0, fn_lib.clone());
+                    }
+
+                    let mut fn_scope = Scope::new();
+
+                    // Move captured variables into scope
+                    #[cfg(not(feature = "no_closure"))]
+                    if let Some(captured) = captured {
+                        if !fn_def.externals.is_empty() {
+                            captured
+                                .into_iter()
+                                .filter(|(name, _, _)| fn_def.externals.iter().any(|ex| ex == name))
+                                .for_each(|(name, value, _)| {
+                                    // Consume the scope values.
+                                    fn_scope.push_dynamic(name, value);
+                                });
+                        }
+                    }
+                    #[cfg(feature = "no_closure")]
+                    drop(captured);
+
+                    let orig_source = mem::replace(&mut state.source, source);
+                    let args = &mut args.iter_mut().collect::<StaticVec<_>>();
+
+                    let env = &mut EvalEnv {
+                        scope: &mut fn_scope,
+                        mods,
+                        state,
+                        lib,
+                    };
+
+                    resume = match self.enter_script_fn(env, false, &fn_def, args, pos, level) {
+                        Ok(Ok(fn_frame)) => {
+                            let code = vm.fn_code(bytecode, &fn_def);
+                            vm.scopes.push(fn_scope);
+                            let index = vm.scopes.len() - 1;
+                            let frame = vm.push_frame(code, level, fn_env, Some(index));
+                            frame.call = Some(Box::new(FnCall {
+                                fn_def,
+                                frame: fn_frame,
+                                pos,
+                                source: orig_source,
+                            }));
+                            Resume::Next
+                        }
+                        Ok(Err(value)) => {
+                            state.source = orig_source;
+                            Resume::Value(value)
+                        }
+                        Err(err) => {
+                            state.source = orig_source;
+                            Resume::Error(err)
+                        }
+                    };
+                }
+            }
+        }
+    }
+    /// End the evaluation of a call frame of a script-defined function, returning the result of
+    /// the call, or [`None`] if the function is evaluated again for a tail call.
+    #[cfg(not(feature = "no_function"))]
+    fn end_fn_call<'s>(
+        &self,
+        scope: &mut Scope<'s>,
+        mods: &mut Imports,
+        state: &mut State,
+        frame: &mut Frame,
+        result: RhaiResult,
+    ) -> Option<RhaiResult> {
+        let call = frame.call.as_ref().expect("function call should exist");
+        let result = self.map_script_fn_result(state, &call.fn_def, result, call.pos);
+
+        match state.tail_call_args.take() {
+            // Tail call - evaluate the function again with the new arguments
+            // instead of recursing
+            Some(values) if result.is_ok() => {
+                self.rewind_scope(scope, state, call.frame.scope_len);
+                push_params(scope, &call.fn_def, values.into_iter());
+                frame.ip = 0;
+                None
+            }
+            _ => {
+                let FnCall {
+                    fn_def,
+                    frame: fn_frame,
+                    source,
+                    ..
+                } = *frame.call.take().unwrap();
+
+                let result = self.leave_script_fn(scope, mods, state, &fn_def, fn_frame, result);
+
+                // Restore the original source
+                state.source = source;
+
+                Some(result)
+            }
+        }
+    }
+    /// Run the instructions of a call frame, until all are evaluated or a script-defined function
+    /// is called.
+    fn run_frame(
+        &self,
+        env: &mut EvalEnv,
+        frame: &mut Frame,
+        regs: &mut [Dynamic],
+        resume: Resume,
+    ) -> Exit {
+        let code = frame.code.clone();
+        let level = frame.level;
+
+        let mut pending = match resume {
+            Resume::Next => None,
+            Resume::Value(value) => {
+                let (dst, pos) = code.ops[frame.ip - 1].call_target();
+                self.check_data_size(Ok(value), pos)
+                    .map(|value| regs[dst] = value)
+                    .err()
+            }
+            Resume::Error(err) => Some(err),
+        };
+
+        loop {
+            if let Some(err) = pending.take() {
+                if let Err(err) = self.unwind(env, frame, regs, err) {
+                    return Exit::Return(Err(err));
+                }
+            }
+
+            let op = match code.ops.get(frame.ip) {
+                Some(op) => op,
+                None => return Exit::Return(Ok(mem::take(&mut regs[RESULT]))),
+            };
+            frame.ip += 1;
+
+            let result: Result<(), Box<EvalAltResult>> = match op {
+                Op::Stmt(pos, coverable_pos) => self.inc_operations(env.state, *pos).map(|_| {
+                    if self.coverage.is_some() {
+                        let source = env.state.source.as_ref().map(|s| s.as_str());
+                        self.record_coverage(|c| c.mark_statement_at(source, *coverable_pos));
+                    }
+                }),
+
+                Op::EndStmt(reg, pos) => {
+                    let value = mem::take(&mut regs[*reg]).flatten();
+                    let result = self.check_data_size(Ok(value), *pos);
+                    self.check_memory_usage(env.scope, env.state, result, *pos)
+                        .map(|value| regs[*reg] = value)
+                }
+
+                Op::Unit(reg) => {
+                    regs[*reg] = Dynamic::UNIT;
+                    Ok(())
+                }
+
+                Op::Let(reg, x) => {
+                    let (name, entry_type, export) = x.as_ref();
+                    let value = mem::take(&mut regs[*reg]).flatten();
+
+                    let (var_name, _alias): (Cow<'_, str>, _) = if env.state.is_global() {
+                        (
+                            name.to_string().into(),
+                            if *export { Some(name.clone()) } else { None },
+                        )
+                    } else if *export {
+                        unreachable!("exported variable not on global level");
+                    } else {
+                        (unsafe_cast_var_name_to_lifetime(name).into(), None)
+                    };
+
+                    env.scope.push_dynamic_value(var_name, *entry_type, value);
+
+                    #[cfg(not(feature = "no_module"))]
+                    if let Some(alias) = _alias {
+                        env.scope.add_entry_alias(env.scope.len() - 1, alias);
+                    }
+
+                    Ok(())
+                }
+
+                Op::Assign(reg, x) => {
+                    let rhs_val = mem::take(&mut regs[*reg]).flatten();
+                    self.assign_var(env, x, rhs_val)
+                }
+
+                Op::BeginBlock => {
+                    frame.regions.push(Region::Block(BlockRegion {
+                        scope_len: env.scope.len(),
+                        mods_len: env.mods.len(),
+                        always_search: env.state.always_search,
+                        fn_resolution_cache: false,
+                    }));
+                    env.state.scope_level += 1;
+                    Ok(())
+                }
+
+                Op::EndBlock => {
+                    match frame.regions.pop() {
+                        Some(Region::Block(block)) => self.end_block(env, block),
+                        _ => unreachable!("statements block expected"),
+                    }
+                    Ok(())
+                }
+
+                Op::Branch(pos, num_branches, branch) => {
+                    if self.coverage.is_some() {
+                        let source = env.state.source.as_ref().map(|s| s.as_str());
+                        self.record_coverage(|c| {
+                            c.mark_branch_at(source, *pos, *num_branches, *branch)
+                        });
+                    }
+                    Ok(())
+                }
+
+                Op::Jump(target) => {
+                    frame.ip = *target;
+                    Ok(())
+                }
+
+                Op::JumpIf(reg, value, pos, target) => mem::take(&mut regs[*reg])
+                    .as_bool()
+                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, *pos))
+                    .map(|condition| {
+                        if condition == *value {
+                            frame.ip = *target;
+                        }
+                    }),
+
+                Op::Switch(reg, x) => {
+                    let value = mem::take(&mut regs[*reg]);
+
+                    let case = if value.is_hashable() {
+                        let hasher = &mut get_hasher();
+                        value.hash(hasher);
+                        x.table.get(&hasher.finish()).cloned()
+                    } else {
+                        // Non-hashable values never match any specific clause
+                        None
+                    };
+                    let (target, branch) = case.unwrap_or((x.default, x.table.len()));
+
+                    if self.coverage.is_some() {
+                        let source = env.state.source.as_ref().map(|s| s.as_str());
+                        self.record_coverage(|c| {
+                            c.mark_branch_at(source, x.pos, x.table.len() + 1, branch)
+                        });
+                    }
+
+                    frame.ip = target;
+                    Ok(())
+                }
+
+                Op::BeginLoop(next, exit) => {
+                    frame.regions.push(Region::Loop(*next, *exit));
+                    Ok(())
+                }
+
+                Op::EndLoop => {
+                    frame.regions.pop();
+                    Ok(())
+                }
+
+                Op::ForStart(reg, x, exit) => {
+                    let (name, pos) = x.as_ref();
+                    let iter_obj = mem::take(&mut regs[*reg]);
+                    let next = frame.ip;
+
+                    self.start_for(env.scope, env.mods, env.state, iter_obj, name, *pos)
+                        .map(|(iter, index)| {
+                            frame.regions.push(Region::For(Box::new(ForRegion {
+                                iter,
+                                index,
+                                next,
+                                exit: *exit,
+                            })))
+                        })
+                }
+
+                Op::ForNext(pos, exit) => {
+                    let (value, index) = match frame.regions.last_mut() {
+                        Some(Region::For(x)) => (x.iter.next(), x.index),
+                        _ => unreachable!("for loop expected"),
+                    };
+
+                    match value {
+                        Some(value) => {
+                            let loop_var = env.scope.get_mut_by_index(index);
+                            let value = value.flatten();
+
+                            if cfg!(not(feature = "no_closure")) && loop_var.is_shared() {
+                                *loop_var.write_lock().unwrap() = value;
+                            } else {
+                                *loop_var = value;
+                            }
+
+                            #[cfg(not(feature = "unchecked"))]
+                            if self.limits.max_memory.is_some() {
+                                env.state.memory.set_modified(index);
+                            }
+
+                            self.inc_operations(env.state, *pos)
+                        }
+                        None => {
+                            frame.ip = *exit;
+                            Ok(())
+                        }
+                    }
+                }
+
+                Op::EndFor => {
+                    match frame.regions.pop() {
+                        Some(Region::For(x)) => {
+                            env.state.scope_level -= 1;
+                            self.rewind_scope(env.scope, env.state, x.index);
+                        }
+                        _ => unreachable!("for loop expected"),
+                    }
+                    Ok(())
+                }
+
+                Op::Break(is_break, pos) => EvalAltResult::LoopBreak(*is_break, *pos).into(),
+
+                Op::Return(reg, pos) => {
+                    let value = match reg {
+                        Some(reg) => mem::take(&mut regs[*reg]).flatten(),
+                        None => Default::default(),
+                    };
+                    EvalAltResult::Return(value, *pos).into()
+                }
+
+                Op::Throw(reg, pos) => {
+                    let value = match reg {
+                        Some(reg) => mem::take(&mut regs[*reg]).flatten(),
+                        None => Dynamic::UNIT,
+                    };
+                    EvalAltResult::ErrorRuntime(value, *pos).into()
+                }
+
+                Op::BeginTry(reg, catch) => {
+                    frame.regions.push(Region::Try(*catch, *reg));
+                    Ok(())
+                }
+
+                Op::EndTry(target) => {
+                    frame.regions.pop();
+                    frame.ip = *target;
+                    Ok(())
+                }
+
+                Op::Catch(reg, name) => {
+                    let err_value = mem::take(&mut regs[*reg]);
+                    env.state.scope_level += 1;
+
+                    if let Some(name) = name {
+                        env.scope
+                            .push(unsafe_cast_var_name_to_lifetime(name), err_value);
+                    }
+                    Ok(())
+                }
+
+                Op::EndCatch => {
+                    match frame.regions.pop() {
+                        Some(Region::Catch(scope_len, _)) => {
+                            env.state.scope_level -= 1;
+                            self.rewind_scope(env.scope, env.state, scope_len);
+                        }
+                        _ => unreachable!("catch block expected"),
+                    }
+                    Ok(())
+                }
+
+                #[cfg(not(feature = "no_function"))]
+                Op::BeginInline(x) => {
+                    let (name, pos) = x.as_ref();
+
+                    // Check for stack overflow
+                    #[cfg(not(feature = "unchecked"))]
+                    if level + 1 > self.max_call_levels() {
+                        pending = Some(EvalAltResult::ErrorStackOverflow(*pos).into());
+                        continue;
+                    }
+
+                    frame.regions.push(Region::Inline(name.clone(), *pos));
+                    Ok(())
+                }
+
+                #[cfg(not(feature = "no_function"))]
+                Op::EndInline => {
+                    frame.regions.pop();
+                    Ok(())
+                }
+
+                #[cfg(not(feature = "no_module"))]
+                Op::CheckModules(_pos) => {
+                    // Guard against too many modules
+                    #[cfg(not(feature = "unchecked"))]
+                    if env.state.modules >= self.max_modules() {
+                        let err = EvalAltResult::ErrorTooManyModules(*_pos).into();
+                        return self.exit_with(env, frame, regs, err);
+                    }
+                    Ok(())
+                }
+
+                #[cfg(not(feature = "no_module"))]
+                Op::Import(reg, x) => {
+                    let (name, pos) = x.as_ref();
+                    let mods_len = env.mods.len();
+                    let path = mem::take(&mut regs[*reg]);
+
+                    self.import_module(env.mods, env.state, path, name, *pos)
+                        .map(|_| {
+                            // Get the extra modules - see if any functions are marked global.
+                            // Without global functions, the extra modules never affect function
+                            // resolution.
+                            if env
+                                .mods
+                                .scan_raw()
+                                .skip(mods_len)
+                                .any(|(_, m)| m.contains_indexed_global_functions())
+                            {
+                                match frame.regions.last_mut() {
+                                    // When new module is imported with global functions, push
+                                    // a new cache
+                                    Some(Region::Block(block)) if !block.fn_resolution_cache => {
+                                        env.state.push_fn_resolution_cache();
+                                        block.fn_resolution_cache = true;
+                                    }
+                                    // When there is already a new cache, or the statements are
+                                    // evaluated in-place, just clear the current cache
+                                    _ => env.state.fn_resolution_cache_mut().clear(),
+                                }
+                            }
+                        })
+                }
+
+                #[cfg(not(feature = "no_module"))]
+                Op::Export(list) => {
+                    let mut result = Ok(());
+
+                    for (Ident { name, pos, .. }, rename) in list.iter() {
+                        // Mark scope variables as public
+                        if let Some(index) = env.scope.get_index(name).map(|(i, _)| i) {
+                            let alias = rename.as_ref().map(|x| &x.name).unwrap_or_else(|| name);
+                            env.scope.add_entry_alias(index, alias.clone());
+                        } else {
+                            result =
+                                EvalAltResult::ErrorVariableNotFound(name.to_string(), *pos).into();
+                            break;
+                        }
+                    }
+
+                    result
+                }
+
+                #[cfg(not(feature = "no_closure"))]
+                Op::Share(name) => {
+                    if let Some((index, _)) = env.scope.get_index(name) {
+                        let val = env.scope.get_mut_by_index(index);
+
+                        if !val.is_shared() {
+                            // Replace the variable with a shared value.
+                            *val = mem::take(val).into_shared();
+                        }
+                    }
+                    Ok(())
+                }
+
+                Op::Count(pos) => self.inc_operations(env.state, *pos),
+
+                Op::Load(reg, value, pos) => self
+                    .inc_operations(env.state, *pos)
+                    .and_then(|_| self.check_data_size(Ok(value.clone()), *pos))
+                    .map(|value| regs[*reg] = value),
+
+                Op::This(pos, var_pos) => self
+                    .inc_operations(env.state, *pos)
+                    .and_then(|_| EvalAltResult::ErrorUnboundThis(*var_pos).into()),
+
+                Op::Get(reg, var) => {
+                    let pos = var.2.pos;
+                    let EvalEnv {
+                        scope,
+                        mods,
+                        state,
+                        lib,
+                    } = env;
+                    let this_ptr = &mut None;
+
+                    self.inc_operations(state, pos)
+                        .and_then(|_| self.search_namespace(scope, mods, state, lib, this_ptr, var))
+                        .map(|(target, _)| target.take_or_clone())
+                        .and_then(|value| self.check_data_size(Ok(value), pos))
+                        .map(|value| regs[*reg] = value)
+                }
+
+                Op::Check(reg, pos) => {
+                    let value = mem::take(&mut regs[*reg]);
+                    self.check_data_size(Ok(value), *pos)
+                        .map(|value| regs[*reg] = value)
+                }
+
+                #[cfg(not(feature = "no_index"))]
+                Op::Array(dst, first, num_items, pos) => {
+                    let mut arr = Array::with_capacity(max(TYPICAL_ARRAY_SIZE, *num_items));
+                    arr.extend(
+                        regs[*first..*first + num_items]
+                            .iter_mut()
+                            .map(|v| mem::take(v).flatten()),
+                    );
+                    let value = Dynamic(Union::Array(Box::new(arr), AccessMode::ReadWrite));
+                    self.check_data_size(Ok(value), *pos)
+                        .map(|value| regs[*dst] = value)
+                }
+
+                #[cfg(not(feature = "no_object"))]
+                Op::Map(dst, first, keys, pos) => {
+                    let mut map = Map::with_capacity(max(TYPICAL_MAP_SIZE, keys.len()));
+                    keys.iter()
+                        .zip(regs[*first..*first + keys.len()].iter_mut())
+                        .for_each(|(key, value)| {
+                            map.insert(key.clone(), mem::take(value).flatten());
+                        });
+                    let value = Dynamic(Union::Map(Box::new(map), AccessMode::ReadWrite));
+                    self.check_data_size(Ok(value), *pos)
+                        .map(|value| regs[*dst] = value)
+                }
+
+                Op::ShortCircuit(reg, value, pos, target) => mem::take(&mut regs[*reg])
+                    .as_bool()
+                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, *pos))
+                    .map(|condition| {
+                        if condition == *value {
+                            regs[*reg] = condition.into();
+                            frame.ip = *target;
+                        }
+                    }),
+
+                Op::Bool(reg, pos) => regs[*reg]
+                    .as_bool()
+                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, *pos))
+                    .map(|condition| regs[*reg] = condition.into()),
+
+                #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+                Op::Chain(x) => self.eval_chain(env, regs, x, level),
+
+                Op::Capture(reg) => {
+                    regs[*reg] = if !env.scope.is_empty() {
+                        Dynamic::from(env.scope.clone_visible().into_owned())
+                    } else {
+                        Dynamic::UNIT
+                    };
+                    Ok(())
+                }
+
+                Op::Call(x) => match self.exec_call_op(env, regs, x) {
+                    Ok(Ok(value)) => self
+                        .check_data_size(Ok(value), x.pos)
+                        .map(|value| regs[x.dst] = value),
+                    Ok(Err(call)) => return Exit::Call(call),
+                    Err(err) => match *err {
+                        EvalAltResult::ErrorSuspended(token, pos) => {
+                            return Exit::Suspend(token, pos)
+                        }
+                        err => Err(err.into()),
+                    },
+                },
+
+                Op::FnPtrCall(reg, target) => {
+                    let fn_ptr = regs[*reg].read_lock::<FnPtr>().unwrap();
+                    if !fn_ptr.curry().is_empty() {
+                        frame.ip = *target;
+                    }
+                    Ok(())
+                }
+
+                #[cfg(not(feature = "no_function"))]
+                Op::TailCallCheck(x, target) => {
+                    let (name, hash) = x.as_ref();
+                    let EvalEnv {
+                        mods, state, lib, ..
+                    } = env;
+                    let fn_def = state.fn_def;
+
+                    let is_same_fn = fn_def.is_some()
+                        && match self
+                            .resolve_function(mods, state, lib, name, *hash, None, false, false)
+                        {
+                            Some(entry) if entry.func.is_script() => {
+                                fn_def == Some(fn_def_address(entry.func.get_fn_def()))
+                            }
+                            _ => false,
+                        };
+
+                    if !is_same_fn {
+                        frame.ip = *target;
+                    }
+                    Ok(())
+                }
+
+                #[cfg(not(feature = "no_function"))]
+                Op::TailCall(x) => {
+                    let mut values: StaticVec<_> = regs[x.args..x.args + x.num_args]
+                        .iter_mut()
+                        .map(mem::take)
+                        .collect();
+
+                    // A variable as the first argument is passed as a copy, the same as
+                    // a normal function call
+                    if x.is_first_var {
+                        values[0] = mem::take(&mut values[0]).flatten();
+                    }
+
+                    let result = ensure_no_data_race(
+                        &x.name,
+                        values.iter_mut().collect::<StaticVec<_>>().as_mut(),
+                        false,
+                    );
+
+                    result
+                        .and_then(|_| self.inc_operations(env.state, x.pos))
+                        .map(|_| {
+                            env.state.tail_call_args = Some(values);
+                            regs[x.dst] = Dynamic::UNIT;
+                        })
+                }
+
+                Op::QualifiedCall(x) => match self.exec_qualified_call_op(env, regs, x) {
+                    Ok(Ok(value)) => self
+                        .check_data_size(Ok(value), x.pos)
+                        .map(|value| regs[x.dst] = value),
+                    Ok(Err(call)) => return Exit::Call(call),
+                    Err(err) => match *err {
+                        EvalAltResult::ErrorSuspended(token, pos) => {
+                            return Exit::Suspend(token, pos)
+                        }
+                        err => Err(err.into()),
+                    },
+                },
+
+                Op::AssertFnPtr(reg, pos) => {
+                    let fn_ptr = &regs[*reg];
+
+                    if fn_ptr.is::<FnPtr>() {
+                        Ok(())
+                    } else {
+                        Err(self.make_type_mismatch_err::<FnPtr>(
+                            self.map_type_name(fn_ptr.type_name()),
+                            *pos,
+                        ))
+                    }
+                }
+
+                #[cfg(not(feature = "no_function"))]
+                Op::AssertString(reg, pos) => mem::take(&mut regs[*reg])
+                    .take_immutable_string()
+                    .map_err(|typ| self.make_type_mismatch_err::<ImmutableString>(typ, *pos))
+                    .map(|s| regs[*reg] = s.into()),
+
+                Op::MakeFnPtr(reg, pos) => mem::take(&mut regs[*reg])
+                    .take_immutable_string()
+                    .map_err(|typ| self.make_type_mismatch_err::<ImmutableString>(typ, *pos))
+                    .and_then(FnPtr::try_from)
+                    .map(|fn_ptr| regs[*reg] = fn_ptr.into())
+                    .map_err(|err| err.fill_position(*pos)),
+
+                Op::Curry(dst, first, num_args) => {
+                    let (name, mut curry) =
+                        mem::take(&mut regs[*first]).cast::<FnPtr>().take_data();
+                    curry.extend(
+                        regs[*first + 1..*first + 1 + num_args]
+                            .iter_mut()
+                            .map(mem::take),
+                    );
+                    regs[*dst] = FnPtr::new_unchecked(name, curry).into();
+                    Ok(())
+                }
+
+                #[cfg(not(feature = "no_closure"))]
+                Op::IsShared(reg) => {
+                    regs[*reg] = regs[*reg].is_shared().into();
+                    Ok(())
+                }
+
+                #[cfg(not(feature = "no_function"))]
+                Op::IsDefFn(dst, first, pos) => mem::take(&mut regs[*first + 1])
+                    .as_int()
+                    .map_err(|err| self.make_type_mismatch_err::<crate::INT>(err, *pos))
+                    .map(|num_params| {
+                        let fn_name = mem::take(&mut regs[*first])
+                            .take_immutable_string()
+                            .unwrap();
+
+                        regs[*dst] = if num_params < 0 {
+                            Dynamic::FALSE
+                        } else {
+                            let hash_script = calc_fn_hash(empty(), &fn_name, num_params as usize);
+                            self.has_script_fn(Some(env.mods), env.state, env.lib, hash_script)
+                                .into()
+                        };
+                    }),
+
+                Op::IsDefVar(reg, pos) => mem::take(&mut regs[*reg])
+                    .take_immutable_string()
+                    .map_err(|typ| self.make_type_mismatch_err::<ImmutableString>(typ, *pos))
+                    .map(|var_name| regs[*reg] = env.scope.contains(&var_name).into()),
+
+                Op::EvalScript(reg, script_pos, pos) => {
+                    let EvalEnv {
+                        scope,
+                        mods,
+                        state,
+                        lib,
+                    } = env;
+                    let prev_len = scope.len();
+
+                    mem::take(&mut regs[*reg])
+                        .take_immutable_string()
+                        .map_err(|typ| {
+                            self.make_type_mismatch_err::<ImmutableString>(typ, *script_pos)
+                        })
+                        .and_then(|script| {
+                            let result = self.eval_script_expr_in_place(
+                                scope,
+                                mods,
+                                state,
+                                lib,
+                                &script,
+                                *script_pos,
+                                level + 1,
+                            );
+
+                            // IMPORTANT! If the eval defines new variables in the current scope,
+                            //            all variable offsets from this point on will be mis-aligned.
+                            if scope.len() != prev_len {
+                                state.always_search = true;
+                            }
+
+                            result.map_err(|err| {
+                                Box::new(EvalAltResult::ErrorInFunctionCall(
+                                    KEYWORD_EVAL.to_string(),
+                                    state
+                                        .source
+                                        .as_ref()
+                                        .map_or_else(|| "", |s| s.as_str())
+                                        .to_string(),
+                                    err,
+                                    *pos,
+                                ))
+                            })
+                        })
+                        .map(|value| regs[*reg] = value)
+                }
+
+                Op::Custom(reg, custom, pos) => {
+                    let EvalEnv {
+                        scope,
+                        mods,
+                        state,
+                        lib,
+                    } = env;
+
+                    self.inc_operations(state, *pos)
+                        .and_then(|_| {
+                            let mut context = EvalContext {
+                                engine: self,
+                                scope,
+                                mods,
+                                state,
+                                lib,
+                                this_ptr: &mut None,
+                                level,
+                            };
+                            self.eval_custom_syntax(&mut context, custom)
+                        })
+                        .and_then(|value| self.check_data_size(Ok(value), *pos))
+                        .map(|value| regs[*reg] = value)
+                }
+            };
+
+            if let Err(err) = result {
+                pending = Some(err);
+            }
+        }
+    }
+    /// Raise an error within a call frame, stopping its evaluation if it is not handled.
+    #[cfg(not(feature = "no_module"))]
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    fn exit_with(
+        &self,
+        env: &mut EvalEnv,
+        frame: &mut Frame,
+        regs: &mut [Dynamic],
+        err: Box<EvalAltResult>,
+    ) -> Exit {
+        match self.unwind(env, frame, regs, err) {
+            Ok(_) => unreachable!("too many modules should not be caught"),
+            Err(err) => Exit::Return(Err(err)),
+        }
+    }
+    /// Unwind the regions of a call frame for an error, until a region handles it.
+    /// The error is returned if no region handles it.
+    fn unwind(
+        &self,
+        env: &mut EvalEnv,
+        frame: &mut Frame,
+        regs: &mut [Dynamic],
+        mut err: Box<EvalAltResult>,
+    ) -> Result<(), Box<EvalAltResult>> {
+        while let Some(region) = frame.regions.pop() {
+            match region {
+                Region::Block(block) => self.end_block(env, block),
+
+                // Break out of, or continue, the loop
+                Region::Loop(next, exit) => {
+                    if let EvalAltResult::LoopBreak(is_break, _) = *err {
+                        frame.ip = if is_break { exit } else { next };
+                        frame.regions.push(Region::Loop(next, exit));
+                        return Ok(());
+                    }
+                }
+
+                // Break out of, or continue, the loop, leaving the loop variable and the scope
+                // level as they are for other errors
+                Region::For(x) => {
+                    if let EvalAltResult::LoopBreak(is_break, _) = *err {
+                        frame.ip = if is_break { x.exit } else { x.next };
+                        frame.regions.push(Region::For(x));
+                        return Ok(());
+                    }
+                }
+
+                // Catch the error
+                Region::Try(catch, reg) => {
+                    if !err.is_pseudo_error() && err.is_catchable() {
+                        regs[reg] = self.make_catch_value(env.state, &mut err);
+                        frame.ip = catch;
+                        frame.regions.push(Region::Catch(env.scope.len(), err));
+                        return Ok(());
+                    }
+                }
+
+                Region::Catch(scope_len, mut caught) => {
+                    env.state.scope_level -= 1;
+                    self.rewind_scope(env.scope, env.state, scope_len);
+
+                    // Re-throw exception
+                    if let EvalAltResult::ErrorRuntime(Dynamic(Union::Unit(_, _)), pos) = *err {
+                        caught.set_position(pos);
+                        err = caught;
+                    }
+                }
+
+                // Wrap the error as for a call to the function
+                #[cfg(not(feature = "no_function"))]
+                Region::Inline(name, pos) => {
+                    err = match self.map_fn_body_result(env.state, &name, None, Err(err), pos) {
+                        Ok(_) => unreachable!("inlined functions do not return"),
+                        Err(err) => err,
+                    };
+                }
+            }
+        }
+
+        Err(err)
+    }
+    /// End a statements block, restoring the state saved when it started.
+    fn end_block(&self, env: &mut EvalEnv, block: BlockRegion) {
+        if block.fn_resolution_cache {
+            // If imports list is modified, pop the functions lookup cache
+            env.state.pop_fn_resolution_cache();
+        }
+
+        self.rewind_scope(env.scope, env.state, block.scope_len);
+        env.mods.truncate(block.mods_len);
+        env.state.scope_level -= 1;
+
+        // The impact of new local variables goes away at the end of a block
+        // because any new variables introduced will go out of scope
+        env.state.always_search = block.always_search;
+    }
+    /// Assign a value to a variable, via an optional operator.
+    fn assign_var(
+        &self,
+        env: &mut EvalEnv,
+        x: &AssignOp,
+        rhs_val: Dynamic,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let EvalEnv {
+            scope,
+            mods,
+            state,
+            lib,
+        } = env;
+        let AssignOp {
+            var,
+            op_info,
+            op_pos,
+            rhs_pos,
+        } = x;
+
+        let this_ptr = &mut None;
+        let (lhs_ptr, pos) = self.search_namespace(scope, mods, state, lib, this_ptr, var)?;
+
+        if !lhs_ptr.is_ref() {
+            return EvalAltResult::ErrorAssignmentToConstant(var.2.name.to_string(), pos).into();
+        }
+
+        self.inc_operations(state, pos)?;
+
+        if lhs_ptr.as_ref().is_read_only() {
+            // Assignment to constant variable
+            EvalAltResult::ErrorAssignmentToConstant(var.2.name.to_string(), pos).into()
+        } else {
+            self.eval_op_assignment(
+                mods, state, lib, op_info, *op_pos, lhs_ptr, rhs_val, *rhs_pos,
+            )?;
+            self.set_var_modified(scope, state, var);
+            Ok(())
+        }
+    }
+    /// Start a `for` loop over a value, adding the loop variable.
+    /// Returns the iterator over the values of the loop variable and its index in the [`Scope`].
+    fn start_for(
+        &self,
+        scope: &mut Scope,
+        mods: &Imports,
+        state: &mut State,
+        iter_obj: Dynamic,
+        name: &str,
+        pos: Position,
+    ) -> Result<(DynamicIterator, usize), Box<EvalAltResult>> {
+        let iter_obj = iter_obj.flatten();
+        let iter_type = iter_obj.type_id();
+
+        // Search order:
+        // 1) Global namespace - functions registered via Engine::register_XXX
+        // 2) Global modules - packages
+        // 3) Imported modules - functions marked with global namespace
+        // 4) Global sub-modules - functions marked with global namespace
+        let func = self
+            .global_namespace
+            .get_iter(iter_type)
+            .or_else(|| {
+                self.global_modules
+                    .iter()
+                    .find_map(|m| m.get_iter(iter_type))
+            })
+            .or_else(|| mods.get_iter(iter_type))
+            .or_else(|| {
+                self.global_sub_modules
+                    .values()
+                    .find_map(|m| m.get_qualified_iter(iter_type))
+            })
+            .ok_or_else(|| EvalAltResult::ErrorFor(pos))?;
+
+        // Add the loop variable
+        let var_name: Cow<'_, str> = if state.is_global() {
+            name.to_string().into()
+        } else {
+            unsafe_cast_var_name_to_lifetime(name).into()
+        };
+        scope.push(var_name, ());
+        state.scope_level += 1;
+
+        Ok((func(iter_obj), scope.len() - 1))
+    }
+    /// Import a module from a path, with an optional name.
+    #[cfg(not(feature = "no_module"))]
+    fn import_module(
+        &self,
+        mods: &mut Imports,
+        state: &mut State,
+        path: Dynamic,
+        name: &Option<ImmutableString>,
+        pos: Position,
+    ) -> Result<(), Box<EvalAltResult>> {
+        use crate::ModuleResolver;
+
+        let path = path
+            .try_cast::<ImmutableString>()
+            .ok_or_else(|| self.make_type_mismatch_err::<ImmutableString>("", pos))?;
+
+        let module = state
+            .resolver
+            .as_ref()
+            .and_then(|r| match r.resolve(self, &path, pos) {
+                Ok(m) => Some(Ok(m)),
+                Err(err) => match *err {
+                    EvalAltResult::ErrorModuleNotFound(_, _) => None,
+                    _ => Some(Err(err)),
+                },
+            })
+            .unwrap_or_else(|| self.module_resolver.resolve(self, &path, pos))?;
+
+        if let Some(name) = name {
+            if !module.is_indexed() {
+                // Index the module (making a clone copy if necessary) if it is not indexed
+                let mut module = crate::fn_native::shared_take_or_clone(module);
+                module.build_index();
+                mods.push(name.clone(), module);
+            } else {
+                mods.push(name.clone(), module);
+            }
+        }
+
+        state.modules += 1;
+
+        Ok(())
+    }
+    /// Evaluate a dot/index chain with the values of its arguments in registers.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    fn eval_chain(
+        &self,
+        env: &mut EvalEnv,
+        regs: &mut [Dynamic],
+        x: &ChainOp,
+        level: usize,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let ChainOp {
+            steps,
+            args,
+            value,
+            dst,
+            pos,
+            assignment,
+        } = x;
+
+        let mut idx_values: StaticVec<_> = args
+            .iter()
+            .map(|arg| match arg {
+                ChainArg::Property(pos) => ChainArgument::Property(*pos),
+                ChainArg::FnCallArgs(first, positions) => (
+                    regs[*first..*first + positions.len()]
+                        .iter_mut()
+                        .map(|v| mem::take(v).flatten())
+                        .collect(),
+                    positions.clone(),
+                )
+                    .into(),
+                ChainArg::IndexValue(reg, pos) => {
+                    (mem::take(&mut regs[*reg]).flatten(), *pos).into()
+                }
+            })
+            .collect();
+
+        // The value the chain starts with is the last argument
+        if let Some((reg, pos)) = value {
+            idx_values.push((mem::take(&mut regs[*reg]), *pos).into());
+        }
+
+        let new_val = assignment.as_ref().map(|(op_info, op_pos, rhs_pos)| {
+            let rhs_val = mem::take(&mut regs[*dst]).flatten();
+            ((rhs_val, *rhs_pos), (op_info, *op_pos))
+        });
+
+        let chain = ChainPath::Compiled(steps);
+        let result =
+            self.eval_dot_index_chain_raw(env, &mut None, chain, &mut idx_values, level, new_val)?;
+
+        regs[*dst] = match assignment {
+            Some(_) => Dynamic::UNIT,
+            None => self.check_data_size(Ok(result), *pos)?,
+        };
+        Ok(())
+    }
+    /// Call a function in normal function-call style, with the argument values in registers.
+    ///
+    /// Returns the call to start in a new call frame instead, as `Ok(Err(call))`, if the function
+    /// is script-defined.
+    fn exec_call_op(
+        &self,
+        env: &mut EvalEnv,
+        regs: &mut [Dynamic],
+        x: &CallOp,
+    ) -> Result<Result<Dynamic, Box<PendingCall>>, Box<EvalAltResult>> {
+        let EvalEnv {
+            scope,
+            mods,
+            state,
+            lib,
+        } = env;
+        let CallOp {
+            name,
+            hash,
+            pos,
+            args: first,
+            num_args,
+            first_var,
+            capture,
+            fn_ptr,
+            ..
+        } = x;
+
+        let mut arg_values: StaticVec<_> = regs[*first..*first + num_args]
+            .iter_mut()
+            .map(mem::take)
+            .collect();
+
+        let captured = capture.and_then(|reg| mem::take(&mut regs[reg]).try_cast::<Scope>());
+
+        // Redirect function call
+        let redirected;
+        let mut name = name.as_ref();
+        let mut hash = *hash;
+        let mut curry = StaticVec::new();
+
+        if let Some(reg) = fn_ptr {
+            let fn_ptr = mem::take(&mut regs[*reg]).cast::<FnPtr>();
+            curry.extend(fn_ptr.curry().iter().cloned());
+
+            // Redirect function name
+            redirected = fn_ptr.take_data().0;
+            name = &redirected;
+
+            // Recalculate hash
+            let num_args = num_args + if first_var.is_some() { 1 } else { 0 };
+            let args_len = num_args + curry.len();
+            hash = if !hash.is_native_only() {
+                FnHash::from_script(calc_fn_hash(empty(), name, args_len))
+            } else {
+                FnHash::from_native(calc_fn_hash(empty(), name, args_len))
+            };
+        }
+
+        let this_ptr = &mut None;
+        let mut args: StaticVec<_>;
+        let mut is_ref = false;
+
+        if let Some(first_var) = first_var {
+            // func(x, ...) -> x.func(...)
+            let (mut target, pos) =
+                self.search_namespace(scope, mods, state, lib, this_ptr, first_var)?;
+
+            if target.as_ref().is_read_only() {
+                target = target.into_owned();
+            }
+
+            self.inc_operations(state, pos)?;
+
+            args = if target.is_shared() || target.is_value() {
+                arg_values.insert(0, target.take_or_clone().flatten());
+                arg_values.iter_mut().collect()
+            } else {
+                // Turn it into a method call only if the object is not shared and not a simple value
+                is_ref = true;
+                once(target.take_ref().unwrap())
+                    .chain(arg_values.iter_mut())
+                    .collect()
+            };
+        } else {
+            args = curry.iter_mut().chain(arg_values.iter_mut()).collect();
+        }
+
+        // Check for data race.
+        if cfg!(not(feature = "no_closure")) {
+            ensure_no_data_race(name, &args, is_ref)?;
+        }
+
+        if let Some(value) = self.exec_keyword_fn_call(mods, state, lib, name, &args, *pos)? {
+            return Ok(Ok(value));
+        }
+
+        // Script function call
+        #[cfg(not(feature = "no_function"))]
+        if !hash.is_native_only() {
+            if let Some(entry) = self
+                .resolve_function(
+                    mods,
+                    state,
+                    lib,
+                    name,
+                    hash.script_hash(),
+                    None,
+                    false,
+                    false,
+                )
+                .clone()
+            {
+                assert!(entry.func.is_script());
+
+                let fn_def = entry.func.get_fn_def();
+
+                if fn_def.body.is_empty() {
+                    return Ok(Ok(Dynamic::UNIT));
+                }
+
+                // The first argument is passed as a copy
+                let args = args
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, arg)| {
+                        if i == 0 && is_ref {
+                            arg.clone()
+                        } else {
+                            mem::take(*arg)
+                        }
+                    })
+                    .collect();
+
+                return Ok(Err(Box::new(PendingCall {
+                    fn_def: fn_def.clone(),
+                    args,
+                    source: entry.source,
+                    captured,
+                    pos: *pos,
+                })));
+            }
+        }
+
+        #[cfg(feature = "no_function")]
+        drop(captured);
+
+        // Native function call
+        let hash = hash.native_hash();
+        let (result, modified) =
+            self.call_native_fn(mods, state, lib, name, hash, &mut args, is_ref, false, *pos)?;
+
+        // Release the reference to the first argument before measuring it again
+        drop(args);
+
+        if is_ref && modified {
+            self.set_var_modified(scope, state, first_var.as_ref().unwrap());
+        }
+
+        Ok(Ok(result))
+    }
+    /// Call a namespace-qualified function, with the argument values in registers.
+    ///
+    /// Returns the call to start in a new call frame instead, as `Ok(Err(call))`, if the function
+    /// is script-defined.
+    fn exec_qualified_call_op(
+        &self,
+        env: &mut EvalEnv,
+        regs: &mut [Dynamic],
+        x: &QualifiedCallOp,
+    ) -> Result<Result<Dynamic, Box<PendingCall>>, Box<EvalAltResult>> {
+        let EvalEnv {
+            scope,
+            mods,
+            state,
+            lib,
+        } = env;
+        let QualifiedCallOp {
+            namespace,
+            name,
+            hash,
+            pos,
+            args: first,
+            num_args,
+            first_var,
+            ..
+        } = x;
+
+        let values = regs[*first..*first + num_args].iter_mut().map(mem::take);
+
+        let this_ptr = &mut None;
+        let mut arg_values: StaticVec<_>;
+        let mut first_arg_value = None;
+        let mut args: StaticVec<_>;
+
+        if let Some(first_var) = first_var {
+            // func(x, ...) -> x.func(...)
+            arg_values = once(Dynamic::UNIT).chain(values).collect();
+
+            // Get target reference to first argument
+            let (target, pos) =
+                self.search_scope_only(scope, mods, state, lib, this_ptr, first_var)?;
+
+            self.inc_operations(state, pos)?;
+
+            if target.is_shared() || target.is_value() {
+                arg_values[0] = target.take_or_clone().flatten();
+                args = arg_values.iter_mut().collect();
+            } else {
+                let (first, rest) = arg_values.split_first_mut().unwrap();
+                first_arg_value = Some(first);
+                args = once(target.take_ref().unwrap())
+                    .chain(rest.iter_mut())
+                    .collect();
+            }
+        } else {
+            arg_values = values.collect();
+            args = arg_values.iter_mut().collect();
+        }
+        let module = self.search_imports(mods, state, namespace).ok_or_else(|| {
+            EvalAltResult::ErrorModuleNotFound(namespace[0].name.to_string(), namespace[0].pos)
+        })?;
+
+        // First search in script-defined functions (can override built-in)
+        let func = match module.get_qualified_fn(*hash) {
+            // Then search in Rust functions
+            None => {
+                self.inc_operations(state, *pos)?;
+
+                let hash_params = calc_fn_params_hash(args.iter().map(|a| a.type_id()));
+                let hash_qualified_fn = combine_hashes(*hash, hash_params);
+
+                module.get_qualified_fn(hash_qualified_fn)
+            }
+            r => r,
+        };
+
+        let is_ref = first_arg_value.is_some();
+
+        // Clone first argument if the function is not a method after-all
+        if let Some(first) = first_arg_value {
+            if !func.map(|f| f.is_method()).unwrap_or(true) {
+                let first_val = args[0].clone();
+                args[0] = first;
+                *args[0] = first_val;
+            }
+        }
+
+        let is_method = matches!(func, Some(f) if f.is_method());
+
+        let result = match func {
+            #[cfg(not(feature = "no_function"))]
+            Some(f) if f.is_script() => {
+                let fn_def = f.get_fn_def();
+
+                if fn_def.body.is_empty() {
+                    Ok(Dynamic::UNIT)
+                } else {
+                    return Ok(Err(Box::new(PendingCall {
+                        fn_def: fn_def.clone(),
+                        args: args.iter_mut().map(|arg| mem::take(*arg)).collect(),
+                        source: module.id_raw().cloned(),
+                        captured: None,
+                        pos: *pos,
+                    })));
+                }
+            }
+
+            Some(f) if f.is_plugin_fn() || f.is_native() => {
+                let operations = Cell::new(state.operations);
+                let generation = self.generation(state);
+                let context =
+                    NativeCallContext::from((self, name.as_ref(), module.id(), &**mods, *lib))
+                        .with_operations(&operations, generation);
+                let result = if f.is_plugin_fn() {
+                    f.get_plugin_fn().clone().call(context, args.as_mut())
+                } else {
+                    f.get_native_fn()(context, args.as_mut())
+                };
+                state.operations = operations.get();
+                result.map_err(|err| err.fill_position(*pos))
+            }
+
+            Some(f) => unreachable!("unknown function type: {:?}", f),
+
+            None => EvalAltResult::ErrorFunctionNotFound(
+                self.gen_call_signature(Some(namespace), name, args.as_ref()),
+                *pos,
+            )
+            .into(),
+        };
+
+        // Release the reference to the first argument before measuring it again
+        drop(args);
+
+        if is_ref && is_method {
+            self.set_var_modified(scope, state, first_var.as_ref().unwrap());
+        }
+
+        result.map(Ok)
+    }
+}
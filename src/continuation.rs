@@ -0,0 +1,80 @@
+//! Module that defines the [`Continuation`] type for resuming suspended evaluations.
+
+use crate::bytecode::{Bytecode, Vm};
+use crate::engine::{Imports, State};
+use crate::stdlib::{boxed::Box, fmt};
+use crate::{Dynamic, Position};
+
+/// Result of a resumable evaluation.
+///
+/// See [`Engine::eval_ast_resumable`][crate::Engine::eval_ast_resumable] for details.
+#[derive(Debug)]
+pub enum Execution {
+    /// The evaluation has run to completion. Wrapped value is the result.
+    Completed(Dynamic),
+    /// The evaluation is suspended. Wrapped value is the [`Continuation`] to resume it with.
+    Suspended(Box<Continuation>),
+}
+
+impl Execution {
+    /// Has the evaluation run to completion?
+    #[inline(always)]
+    pub fn is_completed(&self) -> bool {
+        matches!(self, Self::Completed(_))
+    }
+    /// Is the evaluation suspended?
+    #[inline(always)]
+    pub fn is_suspended(&self) -> bool {
+        matches!(self, Self::Suspended(_))
+    }
+}
+
+/// A suspended evaluation, which can be stored and resumed later via
+/// [`Engine::resume`][crate::Engine::resume].
+///
+/// A [`Continuation`] holds the complete state of the evaluation at the suspension point,
+/// including the call stack of script-defined functions, except for the [`Scope`][crate::Scope]
+/// evaluated with, which must be passed again when resuming.
+///
+/// A [`Continuation`] can only be resumed once, and therefore cannot be cloned.
+///
+/// # Thread Safety
+///
+/// Currently, [`Continuation`] is neither [`Send`] nor [`Sync`].
+/// Turn on the `sync` feature to make it [`Send`] `+` [`Sync`].
+pub struct Continuation {
+    /// Token passed by the native function that suspended the evaluation.
+    pub(crate) token: Dynamic,
+    /// [Position][Position] of the function call that suspended the evaluation.
+    pub(crate) pos: Position,
+    /// The evaluation being suspended.
+    pub(crate) bytecode: Bytecode,
+    /// State of the virtual machine evaluating the [`Bytecode`].
+    pub(crate) vm: Vm,
+    /// Evaluation state.
+    pub(crate) state: State,
+    /// Imported modules.
+    pub(crate) mods: Imports,
+}
+
+impl fmt::Debug for Continuation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Continuation")
+            .field("token", &self.token)
+            .field("pos", &self.pos)
+            .finish()
+    }
+}
+
+impl Continuation {
+    /// Get the token passed by the native function that suspended the evaluation.
+    #[inline(always)]
+    pub fn token(&self) -> &Dynamic {
+        &self.token
+    }
+    /// Get the [position][Position] of the function call that suspended the evaluation.
+    #[inline(always)]
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+}
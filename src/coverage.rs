@@ -0,0 +1,307 @@
+//! Module that defines the [`Coverage`] recorder for collecting code coverage of scripts.
+
+use crate::ast::{Stmt, StmtBlock};
+use crate::stdlib::{
+    collections::BTreeMap,
+    fmt::Write,
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::{Position, AST};
+
+/// Coverage information of a single script source.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+struct SourceCoverage {
+    /// Number of times each statement (keyed by [position][Position]) is executed.
+    statements: BTreeMap<Position, u64>,
+    /// Number of times each branch of an `if` or `switch` statement (keyed by [position][Position])
+    /// is taken.
+    ///
+    /// For `if` statements, the first branch is the `if` block and the second branch is the `else` block.
+    ///
+    /// For `switch` statements, branches are the case blocks in source order, followed by the
+    /// default case.
+    branches: BTreeMap<Position, Vec<u64>>,
+}
+
+/// A recorder collecting code coverage information during script evaluation.
+///
+/// Each executed statement, and each taken branch of an `if` or `switch` statement, is recorded
+/// keyed by the source of the script and its [position][Position].
+///
+/// Enable coverage recording via [`Engine::enable_coverage`][crate::Engine::enable_coverage].
+/// Statements from scripts without a source are recorded under an empty source name.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::Engine;
+///
+/// let mut engine = Engine::new();
+/// engine.enable_coverage(true);
+///
+/// let mut ast = engine.compile("let x = 42; if x > 0 { x } else { -x }")?;
+/// ast.set_source("test.rhai");
+///
+/// engine.consume_ast(&ast)?;
+///
+/// let coverage = engine.coverage().expect("coverage should be enabled");
+///
+/// assert_eq!(coverage.statement_hits("test.rhai", rhai::Position::new(1, 1)), Some(1));
+/// assert!(coverage.to_lcov().contains("SF:test.rhai"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Coverage {
+    sources: BTreeMap<String, SourceCoverage>,
+}
+
+impl Coverage {
+    /// Create a new, empty [`Coverage`] recorder.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Is this [`Coverage`] recorder empty?
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+    /// Clear all recorded coverage information.
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.sources.clear();
+    }
+    /// Get an iterator over the names of all sources with recorded coverage information.
+    #[inline(always)]
+    pub fn iter_sources(&self) -> impl Iterator<Item = &str> {
+        self.sources.keys().map(|s| s.as_str())
+    }
+    /// Get the number of times the statement at a particular [position][Position] is executed.
+    ///
+    /// Returns [`None`] if there is no statement recorded at that position.
+    #[inline]
+    pub fn statement_hits(&self, source: &str, pos: Position) -> Option<u64> {
+        self.sources
+            .get(source)
+            .and_then(|s| s.statements.get(&pos))
+            .cloned()
+    }
+    /// Get the number of times each branch of the `if` or `switch` statement at a particular
+    /// [position][Position] is taken.
+    ///
+    /// For `if` statements, the first branch is the `if` block and the second branch is the `else` block.
+    ///
+    /// For `switch` statements, branches are the case blocks in source order, followed by the
+    /// default case.
+    ///
+    /// Returns [`None`] if there is no branching statement recorded at that position.
+    #[inline]
+    pub fn branch_hits(&self, source: &str, pos: Position) -> Option<&[u64]> {
+        self.sources
+            .get(source)
+            .and_then(|s| s.branches.get(&pos))
+            .map(|v| v.as_slice())
+    }
+    /// Register all statements and branches in an [`AST`], including function bodies (if any),
+    /// so that statements never executed are reported with zero hits.
+    pub fn register_ast(&mut self, ast: &AST) {
+        let source = self
+            .sources
+            .entry(ast.source().unwrap_or_default().to_string())
+            .or_default();
+
+        let mut on_node = |path: &[crate::ast::ASTNode]| {
+            if let Some(crate::ast::ASTNode::Stmt(stmt)) = path.last() {
+                if let Some(pos) = coverable_position(stmt) {
+                    source.statements.entry(pos).or_insert(0);
+
+                    if let Some(num_branches) = num_branches(stmt) {
+                        let branches = source.branches.entry(pos).or_default();
+                        if branches.len() < num_branches {
+                            branches.resize(num_branches, 0);
+                        }
+                    }
+                }
+            }
+            true
+        };
+
+        let path = &mut Default::default();
+
+        let statements: &[Stmt] = ast.as_ref();
+
+        for stmt in statements {
+            stmt.walk(path, &mut on_node);
+        }
+        #[cfg(not(feature = "no_function"))]
+        for stmt in ast.iter_fn_def().flat_map(|f| f.body.statements.iter()) {
+            stmt.walk(path, &mut on_node);
+        }
+    }
+    /// Record the execution of a statement.
+    #[inline(always)]
+    pub(crate) fn mark_statement(&mut self, source: Option<&str>, stmt: &Stmt) {
+        if let Some(pos) = coverable_position(stmt) {
+            self.mark_statement_at(source, pos);
+        }
+    }
+    /// Record the execution of a statement at a particular [position][Position].
+    pub(crate) fn mark_statement_at(&mut self, source: Option<&str>, pos: Position) {
+        if pos.is_none() {
+            return;
+        }
+
+        *self
+            .sources
+            .entry(source.unwrap_or_default().to_string())
+            .or_default()
+            .statements
+            .entry(pos)
+            .or_insert(0) += 1;
+    }
+    /// Record a branch taken in an `if` or `switch` statement.
+    #[inline(always)]
+    pub(crate) fn mark_branch(&mut self, source: Option<&str>, stmt: &Stmt, branch: usize) {
+        if let Some(num_branches) = num_branches(stmt) {
+            self.mark_branch_at(source, stmt.position(), num_branches, branch);
+        }
+    }
+    /// Record a branch taken in a statement at a particular [position][Position],
+    /// given the number of branches.
+    pub(crate) fn mark_branch_at(
+        &mut self,
+        source: Option<&str>,
+        pos: Position,
+        num_branches: usize,
+        branch: usize,
+    ) {
+        let branches = self
+            .sources
+            .entry(source.unwrap_or_default().to_string())
+            .or_default()
+            .branches
+            .entry(pos)
+            .or_default();
+
+        if branches.len() < num_branches {
+            branches.resize(num_branches, 0);
+        }
+        branches[branch] += 1;
+    }
+    /// Merge coverage information from another [`Coverage`] recorder into this one.
+    pub fn merge(&mut self, other: &Self) -> &mut Self {
+        other.sources.iter().for_each(|(name, other)| {
+            let source = self.sources.entry(name.clone()).or_default();
+
+            other.statements.iter().for_each(|(&pos, &hits)| {
+                *source.statements.entry(pos).or_insert(0) += hits;
+            });
+            other.branches.iter().for_each(|(&pos, hits)| {
+                let branches = source.branches.entry(pos).or_default();
+                if branches.len() < hits.len() {
+                    branches.resize(hits.len(), 0);
+                }
+                branches
+                    .iter_mut()
+                    .zip(hits.iter())
+                    .for_each(|(b, &h)| *b += h);
+            });
+        });
+        self
+    }
+    /// Export the recorded coverage information in [LCOV](http://ltp.sourceforge.net/coverage/lcov/geninfo.1.php)
+    /// tracefile format.
+    ///
+    /// A line is reported with the maximum number of hits among all statements starting on that line.
+    pub fn to_lcov(&self) -> String {
+        let mut output = String::new();
+
+        for (name, source) in &self.sources {
+            let _ = writeln!(output, "TN:");
+            let _ = writeln!(output, "SF:{}", name);
+
+            // Branches
+            let mut branches_found = 0;
+            let mut branches_hit = 0;
+
+            for (block, (pos, hits)) in source.branches.iter().enumerate() {
+                let line = pos.line().unwrap_or(0);
+                let executed = matches!(source.statements.get(pos), Some(&n) if n > 0);
+
+                for (branch, &taken) in hits.iter().enumerate() {
+                    branches_found += 1;
+
+                    if !executed {
+                        let _ = writeln!(output, "BRDA:{},{},{},-", line, block, branch);
+                    } else {
+                        if taken > 0 {
+                            branches_hit += 1;
+                        }
+                        let _ = writeln!(output, "BRDA:{},{},{},{}", line, block, branch, taken);
+                    }
+                }
+            }
+
+            let _ = writeln!(output, "BRF:{}", branches_found);
+            let _ = writeln!(output, "BRH:{}", branches_hit);
+
+            // Lines
+            let mut lines: BTreeMap<usize, u64> = Default::default();
+
+            source.statements.iter().for_each(|(pos, &hits)| {
+                if let Some(line) = pos.line() {
+                    let count = lines.entry(line).or_insert(0);
+                    if hits > *count {
+                        *count = hits;
+                    }
+                }
+            });
+
+            lines.iter().for_each(|(line, hits)| {
+                let _ = writeln!(output, "DA:{},{}", line, hits);
+            });
+
+            let _ = writeln!(output, "LF:{}", lines.len());
+            let _ = writeln!(output, "LH:{}", lines.values().filter(|&&n| n > 0).count());
+            let _ = writeln!(output, "end_of_record");
+        }
+
+        output
+    }
+}
+
+/// Get the [position][Position] of a statement that is recorded for coverage,
+/// or [`None`] if the statement is not a real, executable statement.
+#[inline(always)]
+pub(crate) fn coverable_position(stmt: &Stmt) -> Option<Position> {
+    match stmt {
+        Stmt::Noop(_) => None,
+        #[cfg(not(feature = "no_closure"))]
+        Stmt::Share(_) => None,
+        _ if stmt.position().is_none() => None,
+        _ => Some(stmt.position()),
+    }
+}
+
+/// Get the number of branches of an `if` or `switch` statement,
+/// or [`None`] if the statement does not branch.
+#[inline(always)]
+fn num_branches(stmt: &Stmt) -> Option<usize> {
+    match stmt {
+        Stmt::If(_, _, _) => Some(2),
+        Stmt::Switch(_, x, _) => Some(x.0.len() + 1),
+        _ => None,
+    }
+}
+
+/// Get the index of a case block within a `switch` statement, in source order.
+#[inline(always)]
+pub(crate) fn switch_branch_index<'a>(
+    cases: impl Iterator<Item = &'a StmtBlock>,
+    block: &StmtBlock,
+) -> usize {
+    cases.filter(|case| case.pos < block.pos).count()
+}
@@ -0,0 +1,904 @@
+//! Module defining the [`DateTime`] and [`TimeSpan`] types.
+#![cfg(not(feature = "no_std"))]
+
+use crate::stdlib::{
+    cmp::Ordering,
+    fmt,
+    fmt::Write,
+    format,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    string::String,
+};
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+const SECS_PER_DAY: i64 = 86_400;
+
+/// Earliest year representable by a [`DateTime`].
+const MIN_YEAR: i64 = -9999;
+/// Latest year representable by a [`DateTime`].
+const MAX_YEAR: i64 = 9999;
+
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Is a year a leap year in the proleptic Gregorian calendar?
+#[inline(always)]
+fn is_leap_year(year: i64) -> bool {
+    year.rem_euclid(4) == 0 && (year.rem_euclid(100) != 0 || year.rem_euclid(400) == 0)
+}
+
+/// Number of days in a month.
+#[inline]
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Number of days from 1970-01-01 to a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Date in the proleptic Gregorian calendar that is a number of days from 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// A span of time, with nanosecond precision, that can be negative.
+///
+/// Shown as `duration` by `type_of`, and formatted as an ISO-8601 duration such as `P1DT2H30M`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TimeSpan {
+    /// Whole seconds, rounded towards negative infinity.
+    secs: i64,
+    /// Nanoseconds to add to `secs`, always within `0..1_000_000_000`.
+    nanos: u32,
+}
+
+impl TimeSpan {
+    /// A [`TimeSpan`] of zero length.
+    pub const ZERO: Self = Self { secs: 0, nanos: 0 };
+
+    /// Create a new [`TimeSpan`] from a number of seconds and additional nanoseconds.
+    ///
+    /// Returns [`None`] if the result overflows.
+    #[inline]
+    pub fn new(secs: i64, nanos: i64) -> Option<Self> {
+        let secs = secs.checked_add(nanos.div_euclid(NANOS_PER_SEC as i64))?;
+        let nanos = nanos.rem_euclid(NANOS_PER_SEC as i64) as u32;
+        Some(Self { secs, nanos })
+    }
+    /// Create a new [`TimeSpan`] from a number of seconds.
+    #[inline(always)]
+    pub fn from_secs(secs: i64) -> Self {
+        Self { secs, nanos: 0 }
+    }
+    /// Create a new [`TimeSpan`] from a number of milliseconds.
+    #[inline(always)]
+    pub fn from_millis(millis: i64) -> Self {
+        Self {
+            secs: millis.div_euclid(1000),
+            nanos: millis.rem_euclid(1000) as u32 * 1_000_000,
+        }
+    }
+    /// Create a new [`TimeSpan`] from a number of seconds, which may contain a fractional part.
+    ///
+    /// Returns [`None`] if the number is not finite or is out of range.
+    #[inline]
+    pub fn from_secs_f64(secs: f64) -> Option<Self> {
+        if !secs.is_finite() || secs.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        let whole = secs.floor();
+        let nanos = ((secs - whole) * NANOS_PER_SEC as f64).round() as i64;
+        Self::new(whole as i64, nanos)
+    }
+    /// Number of whole seconds in the [`TimeSpan`], rounded towards negative infinity.
+    ///
+    /// Use [`subsec_nanos`][TimeSpan::subsec_nanos] for the remaining nanoseconds, which are never negative.
+    #[inline(always)]
+    pub fn secs(&self) -> i64 {
+        self.secs
+    }
+    /// Nanoseconds to add to [`secs`][TimeSpan::secs], always within `0..1_000_000_000`.
+    #[inline(always)]
+    pub fn subsec_nanos(&self) -> u32 {
+        self.nanos
+    }
+    /// Number of whole seconds in the [`TimeSpan`], truncated towards zero.
+    #[inline(always)]
+    pub fn whole_secs(&self) -> i64 {
+        if self.secs < 0 && self.nanos > 0 {
+            self.secs + 1
+        } else {
+            self.secs
+        }
+    }
+    /// Number of whole milliseconds in the [`TimeSpan`], truncated towards zero.
+    ///
+    /// Returns [`None`] if the result overflows.
+    #[inline]
+    pub fn whole_millis(&self) -> Option<i64> {
+        let millis = self.secs.checked_mul(1000)?;
+        let extra = (self.nanos / 1_000_000) as i64;
+        if self.secs < 0 && !self.nanos.is_multiple_of(1_000_000) {
+            millis.checked_add(extra + 1)
+        } else {
+            millis.checked_add(extra)
+        }
+    }
+    /// Number of seconds in the [`TimeSpan`], including the fractional part.
+    #[inline(always)]
+    pub fn as_secs_f64(&self) -> f64 {
+        self.secs as f64 + self.nanos as f64 / NANOS_PER_SEC as f64
+    }
+    /// Is the [`TimeSpan`] negative?
+    #[inline(always)]
+    pub fn is_negative(&self) -> bool {
+        self.secs < 0
+    }
+    /// Add another [`TimeSpan`], returning [`None`] if the result overflows.
+    #[inline]
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        Self::new(
+            self.secs.checked_add(other.secs)?,
+            self.nanos as i64 + other.nanos as i64,
+        )
+    }
+    /// Subtract another [`TimeSpan`], returning [`None`] if the result overflows.
+    #[inline]
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        Self::new(
+            self.secs.checked_sub(other.secs)?,
+            self.nanos as i64 - other.nanos as i64,
+        )
+    }
+    /// Negate the [`TimeSpan`], returning [`None`] if the result overflows.
+    #[inline]
+    pub fn checked_neg(&self) -> Option<Self> {
+        Self::new(self.secs.checked_neg()?, -(self.nanos as i64))
+    }
+    /// Absolute value of the [`TimeSpan`], returning [`None`] if the result overflows.
+    #[inline(always)]
+    pub fn checked_abs(&self) -> Option<Self> {
+        if self.is_negative() {
+            self.checked_neg()
+        } else {
+            Some(*self)
+        }
+    }
+    /// Multiply the [`TimeSpan`] by an integer, returning [`None`] if the result overflows.
+    #[inline]
+    pub fn checked_mul(&self, factor: i64) -> Option<Self> {
+        let nanos = self.nanos as i128 * factor as i128;
+        let secs = (self.secs as i128)
+            .checked_mul(factor as i128)?
+            .checked_add(nanos.div_euclid(NANOS_PER_SEC as i128))?;
+
+        if secs < i64::MIN as i128 || secs > i64::MAX as i128 {
+            None
+        } else {
+            Some(Self {
+                secs: secs as i64,
+                nanos: nanos.rem_euclid(NANOS_PER_SEC as i128) as u32,
+            })
+        }
+    }
+    /// Divide the [`TimeSpan`] by an integer, rounding towards negative infinity.
+    ///
+    /// Returns [`None`] if the divisor is zero or the result overflows.
+    #[inline]
+    pub fn checked_div(&self, divisor: i64) -> Option<Self> {
+        if divisor == 0 {
+            return None;
+        }
+        let total = self.secs as i128 * NANOS_PER_SEC as i128 + self.nanos as i128;
+        let total = total.checked_div(divisor as i128)?;
+        let secs = total.div_euclid(NANOS_PER_SEC as i128);
+
+        if secs < i64::MIN as i128 || secs > i64::MAX as i128 {
+            None
+        } else {
+            Some(Self {
+                secs: secs as i64,
+                nanos: total.rem_euclid(NANOS_PER_SEC as i128) as u32,
+            })
+        }
+    }
+}
+
+impl fmt::Display for TimeSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (secs, nanos) = if self.is_negative() {
+            f.write_str("-")?;
+
+            if self.nanos > 0 {
+                (
+                    (self.secs as i128 + 1).unsigned_abs(),
+                    NANOS_PER_SEC - self.nanos,
+                )
+            } else {
+                ((self.secs as i128).unsigned_abs(), 0)
+            }
+        } else {
+            (self.secs as u128, self.nanos)
+        };
+
+        let days = secs / SECS_PER_DAY as u128;
+        let hours = secs / 3600 % 24;
+        let minutes = secs / 60 % 60;
+        let seconds = secs % 60;
+
+        f.write_str("P")?;
+        if days > 0 {
+            write!(f, "{}D", days)?;
+        }
+        if hours == 0 && minutes == 0 && seconds == 0 && nanos == 0 {
+            return if days == 0 {
+                f.write_str("T0S")
+            } else {
+                Ok(())
+            };
+        }
+        f.write_str("T")?;
+        if hours > 0 {
+            write!(f, "{}H", hours)?;
+        }
+        if minutes > 0 {
+            write!(f, "{}M", minutes)?;
+        }
+        if seconds > 0 || nanos > 0 {
+            write!(f, "{}", seconds)?;
+            if nanos > 0 {
+                write!(f, ".{}", format!("{:09}", nanos).trim_end_matches('0'))?;
+            }
+            f.write_str("S")?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the non-zero fractional part of a second with 3, 6 or 9 digits.
+fn write_fraction(f: &mut impl Write, nanos: u32) -> fmt::Result {
+    if nanos == 0 {
+        Ok(())
+    } else if nanos.is_multiple_of(1_000_000) {
+        write!(f, ".{:03}", nanos / 1_000_000)
+    } else if nanos.is_multiple_of(1_000) {
+        write!(f, ".{:06}", nanos / 1_000)
+    } else {
+        write!(f, ".{:09}", nanos)
+    }
+}
+
+/// A date and time in the proleptic Gregorian calendar, with nanosecond precision and a fixed
+/// offset from UTC.
+///
+/// Years from -9999 to 9999 are supported.
+///
+/// Two [`DateTime`]'s are equal if they represent the same instant, even if their offsets differ.
+///
+/// Shown as `datetime` by `type_of`, and formatted as RFC 3339 such as `2021-03-04T05:06:07Z`.
+#[derive(Debug, Clone, Copy)]
+pub struct DateTime {
+    /// Seconds since 1970-01-01T00:00:00Z.
+    secs: i64,
+    /// Nanoseconds to add to `secs`, always within `0..1_000_000_000`.
+    nanos: u32,
+    /// Offset from UTC in seconds, positive towards the east.
+    offset: i32,
+}
+
+impl DateTime {
+    /// The Unix epoch, 1970-01-01T00:00:00Z.
+    pub const UNIX_EPOCH: Self = Self {
+        secs: 0,
+        nanos: 0,
+        offset: 0,
+    };
+
+    /// Create a new [`DateTime`], checking that it is within the supported range.
+    fn checked(secs: i64, nanos: u32, offset: i32) -> Option<Self> {
+        let min = days_from_civil(MIN_YEAR, 1, 1) * SECS_PER_DAY;
+        let max = days_from_civil(MAX_YEAR + 1, 1, 1) * SECS_PER_DAY - 1;
+        let local = secs.checked_add(offset as i64)?;
+
+        if secs < min || secs > max || local < min || local > max {
+            None
+        } else {
+            Some(Self {
+                secs,
+                nanos,
+                offset,
+            })
+        }
+    }
+    /// Create a new [`DateTime`] from a local date and time with an offset from UTC.
+    fn from_local(days: i64, secs_of_day: i64, nanos: u32, offset: i32) -> Option<Self> {
+        let local = days.checked_mul(SECS_PER_DAY)?.checked_add(secs_of_day)?;
+        Self::checked(local.checked_sub(offset as i64)?, nanos, offset)
+    }
+    /// Create a new UTC [`DateTime`] from a date and time.
+    ///
+    /// Returns [`None`] if any component is out of range.
+    pub fn new(
+        year: i64,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Option<Self> {
+        if !(MIN_YEAR..=MAX_YEAR).contains(&year)
+            || !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return None;
+        }
+
+        let secs_of_day = hour as i64 * 3600 + minute as i64 * 60 + second as i64;
+        Self::from_local(days_from_civil(year, month, day), secs_of_day, 0, 0)
+    }
+    /// Create a new UTC [`DateTime`] from the number of seconds and additional nanoseconds
+    /// since 1970-01-01T00:00:00Z.
+    ///
+    /// Returns [`None`] if the result is out of range.
+    #[inline]
+    pub fn from_timestamp(secs: i64, nanos: u32) -> Option<Self> {
+        let secs = secs.checked_add((nanos / NANOS_PER_SEC) as i64)?;
+        Self::checked(secs, nanos % NANOS_PER_SEC, 0)
+    }
+    /// The current date and time in UTC, according to the system clock.
+    ///
+    /// Not available on WASM targets, where the system clock may not exist (reading it panics
+    /// under `wasm32-unknown-unknown`).
+    /// Use [`Engine::set_clock`][crate::Engine::set_clock] to provide a clock to scripts instead.
+    #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+    pub fn now() -> Self {
+        use crate::stdlib::time::SystemTime;
+
+        match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(d) => Self::from_timestamp(d.as_secs() as i64, d.subsec_nanos()),
+            Err(err) => {
+                let d = err.duration();
+                TimeSpan::new(-(d.as_secs() as i64), -(d.subsec_nanos() as i64))
+                    .and_then(|t| Self::from_timestamp(t.secs, t.nanos))
+            }
+        }
+        .unwrap_or(Self::UNIX_EPOCH)
+    }
+    /// Number of seconds since 1970-01-01T00:00:00Z, rounded towards negative infinity.
+    #[inline(always)]
+    pub fn timestamp(&self) -> i64 {
+        self.secs
+    }
+    /// Nanoseconds to add to [`timestamp`][DateTime::timestamp].
+    #[inline(always)]
+    pub fn nanosecond(&self) -> u32 {
+        self.nanos
+    }
+    /// Offset from UTC in seconds, positive towards the east.
+    #[inline(always)]
+    pub fn offset(&self) -> i32 {
+        self.offset
+    }
+    /// The same instant with a different offset from UTC, in seconds.
+    ///
+    /// Returns [`None`] if the offset is not less than 24 hours or the result is out of range.
+    #[inline]
+    pub fn with_offset(&self, offset: i32) -> Option<Self> {
+        if (offset as i64).abs() >= SECS_PER_DAY {
+            None
+        } else {
+            Self::checked(self.secs, self.nanos, offset)
+        }
+    }
+    /// Local number of days since 1970-01-01 and seconds since midnight.
+    #[inline(always)]
+    fn local(&self) -> (i64, i64) {
+        let local = self.secs + self.offset as i64;
+        (
+            local.div_euclid(SECS_PER_DAY),
+            local.rem_euclid(SECS_PER_DAY),
+        )
+    }
+    /// Local year, month and day.
+    #[inline(always)]
+    pub fn date(&self) -> (i64, u32, u32) {
+        civil_from_days(self.local().0)
+    }
+    /// Local year.
+    #[inline(always)]
+    pub fn year(&self) -> i64 {
+        self.date().0
+    }
+    /// Local month, starting from 1 for January.
+    #[inline(always)]
+    pub fn month(&self) -> u32 {
+        self.date().1
+    }
+    /// Local day of the month, starting from 1.
+    #[inline(always)]
+    pub fn day(&self) -> u32 {
+        self.date().2
+    }
+    /// Local hour, from 0 to 23.
+    #[inline(always)]
+    pub fn hour(&self) -> u32 {
+        (self.local().1 / 3600) as u32
+    }
+    /// Local minute, from 0 to 59.
+    #[inline(always)]
+    pub fn minute(&self) -> u32 {
+        (self.local().1 / 60 % 60) as u32
+    }
+    /// Local second, from 0 to 59.
+    #[inline(always)]
+    pub fn second(&self) -> u32 {
+        (self.local().1 % 60) as u32
+    }
+    /// Local day of the week, from 1 for Monday to 7 for Sunday.
+    #[inline(always)]
+    pub fn weekday(&self) -> u32 {
+        (self.local().0 + 3).rem_euclid(7) as u32 + 1
+    }
+    /// Local day of the year, starting from 1 for January 1st.
+    #[inline(always)]
+    pub fn day_of_year(&self) -> u32 {
+        let (days, _) = self.local();
+        let (year, _, _) = civil_from_days(days);
+        (days - days_from_civil(year, 1, 1) + 1) as u32
+    }
+    /// Add a [`TimeSpan`], returning [`None`] if the result is out of range.
+    #[inline]
+    pub fn checked_add(&self, span: &TimeSpan) -> Option<Self> {
+        let t = TimeSpan::new(self.secs, self.nanos as i64)?.checked_add(span)?;
+        Self::checked(t.secs, t.nanos, self.offset)
+    }
+    /// Subtract a [`TimeSpan`], returning [`None`] if the result is out of range.
+    #[inline]
+    pub fn checked_sub(&self, span: &TimeSpan) -> Option<Self> {
+        let t = TimeSpan::new(self.secs, self.nanos as i64)?.checked_sub(span)?;
+        Self::checked(t.secs, t.nanos, self.offset)
+    }
+    /// The [`TimeSpan`] from another [`DateTime`] to this one, which is negative if the other one
+    /// is later.
+    #[inline]
+    pub fn since(&self, other: &Self) -> TimeSpan {
+        // Both values are within the supported range, so this never overflows
+        TimeSpan::new(
+            self.secs - other.secs,
+            self.nanos as i64 - other.nanos as i64,
+        )
+        .expect("date-time difference is in range")
+    }
+    /// Add a number of calendar months, keeping the local time of day.
+    ///
+    /// The day is clamped to the last day of the resulting month, so adding one month to
+    /// January 31st gives the last day of February.
+    ///
+    /// Returns [`None`] if the result is out of range.
+    pub fn add_months(&self, months: i64) -> Option<Self> {
+        let (days, secs_of_day) = self.local();
+        let (year, month, day) = civil_from_days(days);
+        let total = (year * 12 + month as i64 - 1).checked_add(months)?;
+        let year = total.div_euclid(12);
+        let month = total.rem_euclid(12) as u32 + 1;
+
+        if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+            return None;
+        }
+
+        let day = day.min(days_in_month(year, month));
+        Self::from_local(
+            days_from_civil(year, month, day),
+            secs_of_day,
+            self.nanos,
+            self.offset,
+        )
+    }
+    /// Add a number of calendar days, keeping the local time of day.
+    ///
+    /// Returns [`None`] if the result is out of range.
+    #[inline]
+    pub fn add_days(&self, days: i64) -> Option<Self> {
+        self.checked_add(&TimeSpan::from_secs(days.checked_mul(SECS_PER_DAY)?))
+    }
+    /// Format the [`DateTime`] with a `strftime`-style format string.
+    ///
+    /// Supported specifiers:
+    ///
+    /// | Specifier | Meaning                                    | Example      |
+    /// | :-------: | ------------------------------------------ | ------------ |
+    /// |   `%Y`    | year                                       | `2021`       |
+    /// |   `%y`    | year without the century, zero-padded      | `21`         |
+    /// |   `%m`    | month, zero-padded                         | `03`         |
+    /// |   `%b`    | abbreviated month name                     | `Mar`        |
+    /// |   `%B`    | full month name                            | `March`      |
+    /// |   `%d`    | day of the month, zero-padded              | `04`         |
+    /// |   `%e`    | day of the month, space-padded             | ` 4`         |
+    /// |   `%j`    | day of the year, zero-padded               | `063`        |
+    /// |   `%a`    | abbreviated weekday name                   | `Thu`        |
+    /// |   `%A`    | full weekday name                          | `Thursday`   |
+    /// |   `%u`    | day of the week, from 1 (Monday)           | `4`          |
+    /// |   `%w`    | day of the week, from 0 (Sunday)           | `4`          |
+    /// |   `%H`    | hour (00-23), zero-padded                  | `05`         |
+    /// |   `%I`    | hour (01-12), zero-padded                  | `05`         |
+    /// |   `%p`    | `AM` or `PM`                               | `AM`         |
+    /// |   `%M`    | minute, zero-padded                        | `06`         |
+    /// |   `%S`    | second, zero-padded                        | `07`         |
+    /// |   `%f`    | nanoseconds, 9 digits                      | `026490000`  |
+    /// |   `%3f`   | milliseconds, 3 digits (also `%6f`, `%9f`) | `026`        |
+    /// |   `%z`    | offset from UTC                            | `+0930`      |
+    /// |   `%:z`   | offset from UTC with a colon               | `+09:30`     |
+    /// |   `%s`    | seconds since 1970-01-01T00:00:00Z         | `1614834367` |
+    /// |   `%F`    | same as `%Y-%m-%d`                         |              |
+    /// |   `%T`    | same as `%H:%M:%S`                         |              |
+    /// |   `%%`    | a literal `%`                              |              |
+    ///
+    /// Returns an error message if the format string contains an unsupported specifier.
+    pub fn format(&self, format: &str) -> Result<String, String> {
+        let (year, month, day) = self.date();
+        let hour = self.hour();
+        let mut output = String::new();
+        let mut chars = format.chars();
+
+        while let Some(ch) = chars.next() {
+            if ch != '%' {
+                output.push(ch);
+                continue;
+            }
+
+            let spec = chars.next();
+
+            // Writing into a `String` never fails
+            let _ = match spec {
+                Some('Y') => write_year(&mut output, year),
+                Some('y') => write!(output, "{:02}", year.rem_euclid(100)),
+                Some('m') => write!(output, "{:02}", month),
+                Some('b') => write!(output, "{}", &MONTH_NAMES[month as usize - 1][..3]),
+                Some('B') => write!(output, "{}", MONTH_NAMES[month as usize - 1]),
+                Some('d') => write!(output, "{:02}", day),
+                Some('e') => write!(output, "{:2}", day),
+                Some('j') => write!(output, "{:03}", self.day_of_year()),
+                Some('a') => write!(
+                    output,
+                    "{}",
+                    &WEEKDAY_NAMES[self.weekday() as usize - 1][..3]
+                ),
+                Some('A') => write!(output, "{}", WEEKDAY_NAMES[self.weekday() as usize - 1]),
+                Some('u') => write!(output, "{}", self.weekday()),
+                Some('w') => write!(output, "{}", self.weekday() % 7),
+                Some('H') => write!(output, "{:02}", hour),
+                Some('I') => write!(output, "{:02}", (hour + 11) % 12 + 1),
+                Some('p') => output.write_str(if hour < 12 { "AM" } else { "PM" }),
+                Some('M') => write!(output, "{:02}", self.minute()),
+                Some('S') => write!(output, "{:02}", self.second()),
+                Some('f') => write!(output, "{:09}", self.nanos),
+                Some('3') if chars.as_str().starts_with('f') => {
+                    chars.next();
+                    write!(output, "{:03}", self.nanos / 1_000_000)
+                }
+                Some('6') if chars.as_str().starts_with('f') => {
+                    chars.next();
+                    write!(output, "{:06}", self.nanos / 1_000)
+                }
+                Some('9') if chars.as_str().starts_with('f') => {
+                    chars.next();
+                    write!(output, "{:09}", self.nanos)
+                }
+                Some('z') => write_offset(&mut output, self.offset, ""),
+                Some(':') if chars.as_str().starts_with('z') => {
+                    chars.next();
+                    write_offset(&mut output, self.offset, ":")
+                }
+                Some('s') => write!(output, "{}", self.secs),
+                Some('F') => write_year(&mut output, year)
+                    .and_then(|_| write!(output, "-{:02}-{:02}", month, day)),
+                Some('T') => write!(
+                    output,
+                    "{:02}:{:02}:{:02}",
+                    hour,
+                    self.minute(),
+                    self.second()
+                ),
+                Some('%') => output.write_str("%"),
+                Some(ch) => return Err(format!("Unsupported format specifier: '%{}'", ch)),
+                None => return Err("Incomplete format specifier at end of string".into()),
+            };
+        }
+
+        Ok(output)
+    }
+}
+
+/// Write a year with at least four digits.
+#[inline]
+fn write_year(f: &mut impl Write, year: i64) -> fmt::Result {
+    if year < 0 {
+        write!(f, "-{:04}", -year)
+    } else {
+        write!(f, "{:04}", year)
+    }
+}
+
+/// Write an offset from UTC as hours and minutes.
+#[inline]
+fn write_offset(f: &mut impl Write, offset: i32, separator: &str) -> fmt::Result {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs() / 60;
+    write!(
+        f,
+        "{}{:02}{}{:02}",
+        sign,
+        offset / 60,
+        separator,
+        offset % 60
+    )
+}
+
+impl PartialEq for DateTime {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.secs == other.secs && self.nanos == other.nanos
+    }
+}
+
+impl Eq for DateTime {}
+
+impl PartialOrd for DateTime {
+    #[inline(always)]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DateTime {
+    #[inline(always)]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.secs, self.nanos).cmp(&(other.secs, other.nanos))
+    }
+}
+
+impl Hash for DateTime {
+    #[inline(always)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.secs.hash(state);
+        self.nanos.hash(state);
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.date();
+
+        write_year(f, year)?;
+        write!(
+            f,
+            "-{:02}-{:02}T{:02}:{:02}:{:02}",
+            month,
+            day,
+            self.hour(),
+            self.minute(),
+            self.second()
+        )?;
+        write_fraction(f, self.nanos)?;
+
+        if self.offset == 0 {
+            f.write_str("Z")
+        } else {
+            write_offset(f, self.offset, ":")
+        }
+    }
+}
+
+/// Parser for ISO-8601 date-time strings.
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// Look at the next byte without consuming it.
+    #[inline(always)]
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+    /// Consume the next byte if it is one of the given bytes.
+    #[inline(always)]
+    fn eat(&mut self, bytes: &[u8]) -> Option<u8> {
+        match self.peek() {
+            Some(b) if bytes.contains(&b) => {
+                self.pos += 1;
+                Some(b)
+            }
+            _ => None,
+        }
+    }
+    /// Consume an exact number of decimal digits.
+    fn digits(&mut self, count: usize, what: &'static str) -> Result<u32, &'static str> {
+        let mut value = 0;
+
+        for _ in 0..count {
+            match self.peek() {
+                Some(b @ b'0'..=b'9') => {
+                    value = value * 10 + (b - b'0') as u32;
+                    self.pos += 1;
+                }
+                _ => return Err(what),
+            }
+        }
+
+        Ok(value)
+    }
+    /// Consume an offset from UTC, in the form `Z`, `+hh`, `+hh:mm` or `+hhmm`.
+    fn offset(&mut self) -> Result<i32, &'static str> {
+        if self.eat(b"Zz").is_some() {
+            return Ok(0);
+        }
+
+        let sign = match self.eat(b"+-") {
+            Some(b'-') => -1,
+            Some(_) => 1,
+            None => return Err("invalid offset"),
+        };
+        let hours = self.digits(2, "invalid offset hours")?;
+        let minutes = if self.eat(b":").is_some() || self.peek().is_some() {
+            self.digits(2, "invalid offset minutes")?
+        } else {
+            0
+        };
+
+        if hours > 23 || minutes > 59 {
+            Err("offset out of range")
+        } else {
+            Ok(sign * (hours * 3600 + minutes * 60) as i32)
+        }
+    }
+}
+
+/// Parse an offset from UTC in the form `Z`, `+hh`, `+hh:mm` or `+hhmm`, returning seconds.
+pub(crate) fn parse_offset(s: &str) -> Result<i32, &'static str> {
+    let mut p = Parser {
+        input: s.trim().as_bytes(),
+        pos: 0,
+    };
+    let offset = p.offset()?;
+
+    if p.peek().is_some() {
+        Err("unexpected trailing characters")
+    } else {
+        Ok(offset)
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = &'static str;
+
+    /// Parse an ISO-8601 date-time string, such as `2021-03-04`, `2021-03-04T05:06:07Z`
+    /// or `2021-03-04 05:06:07.123+08:00`.
+    ///
+    /// A missing time is taken as midnight, and a missing offset is taken as UTC.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut p = Parser {
+            input: s.trim().as_bytes(),
+            pos: 0,
+        };
+
+        let sign = match p.eat(b"+-") {
+            Some(b'-') => -1,
+            _ => 1,
+        };
+        let year = sign * p.digits(4, "invalid year")? as i64;
+        p.eat(b"-").ok_or("invalid date")?;
+        let month = p.digits(2, "invalid month")?;
+        p.eat(b"-").ok_or("invalid date")?;
+        let day = p.digits(2, "invalid day")?;
+
+        if !(1..=12).contains(&month) {
+            return Err("month out of range");
+        }
+        if day < 1 || day > days_in_month(year, month) {
+            return Err("day out of range");
+        }
+
+        let mut secs_of_day = 0;
+        let mut nanos = 0;
+        let mut offset = 0;
+
+        if p.peek().is_some() {
+            p.eat(b"Tt ").ok_or("invalid time")?;
+            let hour = p.digits(2, "invalid hour")?;
+            p.eat(b":").ok_or("invalid time")?;
+            let minute = p.digits(2, "invalid minute")?;
+            let second = if p.eat(b":").is_some() {
+                p.digits(2, "invalid second")?
+            } else {
+                0
+            };
+
+            if hour > 23 || minute > 59 || second > 59 {
+                return Err("time out of range");
+            }
+
+            if p.eat(b".,").is_some() {
+                let mut count = 0;
+                while let Some(b @ b'0'..=b'9') = p.peek() {
+                    if count == 9 {
+                        return Err("too many digits in fraction of second");
+                    }
+                    nanos = nanos * 10 + (b - b'0') as u32;
+                    count += 1;
+                    p.pos += 1;
+                }
+                if count == 0 {
+                    return Err("invalid fraction of second");
+                }
+                nanos *= 10_u32.pow(9 - count);
+            }
+
+            secs_of_day = hour as i64 * 3600 + minute as i64 * 60 + second as i64;
+
+            if p.peek().is_some() {
+                offset = p.offset()?;
+            }
+        }
+
+        if p.peek().is_some() {
+            return Err("unexpected trailing characters");
+        }
+
+        Self::from_local(
+            days_from_civil(year, month, day),
+            secs_of_day,
+            nanos,
+            offset,
+        )
+        .ok_or("date out of range")
+    }
+}
@@ -7,10 +7,11 @@ use crate::stdlib::{
     boxed::Box,
     fmt,
     hash::{Hash, Hasher},
+    mem::size_of,
     ops::{Deref, DerefMut},
     string::String,
 };
-use crate::{FnPtr, ImmutableString, INT};
+use crate::{FnPtr, ImmutableString, StaticVec, INT};
 
 #[cfg(not(feature = "no_float"))]
 use crate::{ast::FloatWrapper, FLOAT};
@@ -18,8 +19,11 @@ use crate::{ast::FloatWrapper, FLOAT};
 #[cfg(feature = "decimal")]
 use rust_decimal::Decimal;
 
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
 #[cfg(not(feature = "no_index"))]
-use crate::Array;
+use crate::{Array, Blob, Set};
 
 #[cfg(not(feature = "no_object"))]
 use crate::Map;
@@ -163,9 +167,18 @@ pub enum Union {
     /// A fixed-precision decimal value.
     #[cfg(feature = "decimal")]
     Decimal(Box<Decimal>, AccessMode),
+    /// An arbitrary-precision integer value.
+    #[cfg(feature = "bigint")]
+    BigInt(Box<BigInt>, AccessMode),
     /// An array value.
     #[cfg(not(feature = "no_index"))]
     Array(Box<Array>, AccessMode),
+    /// A byte array value.
+    #[cfg(not(feature = "no_index"))]
+    Blob(Box<Blob>, AccessMode),
+    /// A set value.
+    #[cfg(not(feature = "no_index"))]
+    Set(Box<Set>, AccessMode),
     /// An object map value.
     #[cfg(not(feature = "no_object"))]
     Map(Box<Map>, AccessMode),
@@ -323,8 +336,14 @@ impl Dynamic {
             Union::Float(_, _) => TypeId::of::<FLOAT>(),
             #[cfg(feature = "decimal")]
             Union::Decimal(_, _) => TypeId::of::<Decimal>(),
+            #[cfg(feature = "bigint")]
+            Union::BigInt(_, _) => TypeId::of::<BigInt>(),
             #[cfg(not(feature = "no_index"))]
             Union::Array(_, _) => TypeId::of::<Array>(),
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(_, _) => TypeId::of::<Blob>(),
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(_, _) => TypeId::of::<Set>(),
             #[cfg(not(feature = "no_object"))]
             Union::Map(_, _) => TypeId::of::<Map>(),
             Union::FnPtr(_, _) => TypeId::of::<FnPtr>(),
@@ -361,8 +380,14 @@ impl Dynamic {
             Union::Float(_, _) => type_name::<FLOAT>(),
             #[cfg(feature = "decimal")]
             Union::Decimal(_, _) => "decimal",
+            #[cfg(feature = "bigint")]
+            Union::BigInt(_, _) => "bigint",
             #[cfg(not(feature = "no_index"))]
             Union::Array(_, _) => "array",
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(_, _) => "blob",
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(_, _) => "set",
             #[cfg(not(feature = "no_object"))]
             Union::Map(_, _) => "map",
             Union::FnPtr(_, _) => "Fn",
@@ -382,6 +407,86 @@ impl Dynamic {
             Union::Shared(cell, _) => (*cell.read().unwrap()).type_name(),
         }
     }
+    /// Get the approximate amount of heap memory, in bytes, used by the value held by this
+    /// [`Dynamic`], not counting the [`Dynamic`] itself.
+    ///
+    /// This is only an estimate. A shared value is counted once for each reference to it,
+    /// and a shared value that is currently locked for writing is not counted.
+    pub fn memory_usage(&self) -> usize {
+        fn calc_usage(value: &Dynamic, _visiting: &mut StaticVec<usize>) -> usize {
+            match &value.0 {
+                Union::Str(s, _) => size_of::<String>() + s.len(),
+                #[cfg(feature = "decimal")]
+                Union::Decimal(_, _) => size_of::<Decimal>(),
+                #[cfg(feature = "bigint")]
+                Union::BigInt(x, _) => size_of::<BigInt>() + x.bits().div_ceil(8) as usize,
+                #[cfg(not(feature = "no_index"))]
+                Union::Array(arr, _) => {
+                    size_of::<Array>()
+                        + arr.capacity() * size_of::<Dynamic>()
+                        + arr.iter().map(|v| calc_usage(v, _visiting)).sum::<usize>()
+                }
+                #[cfg(not(feature = "no_index"))]
+                Union::Blob(blob, _) => size_of::<Blob>() + blob.capacity(),
+                #[cfg(not(feature = "no_index"))]
+                Union::Set(set, _) => {
+                    size_of::<Set>()
+                        + set.len() * size_of::<Dynamic>()
+                        + set.iter().map(|v| calc_usage(v, _visiting)).sum::<usize>()
+                }
+                #[cfg(not(feature = "no_object"))]
+                Union::Map(map, _) => {
+                    size_of::<Map>()
+                        + map.len() * (size_of::<MapKey>() + size_of::<Dynamic>())
+                        + map
+                            .iter()
+                            .map(|(k, v)| k.as_str().map_or(0, str::len) + calc_usage(v, _visiting))
+                            .sum::<usize>()
+                }
+                Union::FnPtr(f, _) => {
+                    size_of::<FnPtr>()
+                        + f.fn_name().len()
+                        + f.curry()
+                            .iter()
+                            .map(|v| size_of::<Dynamic>() + calc_usage(v, _visiting))
+                            .sum::<usize>()
+                }
+                #[cfg(not(feature = "no_std"))]
+                Union::TimeStamp(_, _) => size_of::<Instant>(),
+
+                Union::Variant(value, _) => {
+                    size_of::<Box<dyn Variant>>() + crate::stdlib::mem::size_of_val(&***value)
+                }
+
+                #[cfg(not(feature = "no_closure"))]
+                Union::Shared(cell, _) => {
+                    let ptr = crate::Shared::as_ptr(cell) as usize;
+
+                    // Guard against a shared value containing itself
+                    if _visiting.contains(&ptr) {
+                        return 0;
+                    }
+
+                    #[cfg(not(feature = "sync"))]
+                    let value = cell.try_borrow().ok();
+                    #[cfg(feature = "sync")]
+                    let value = cell.try_read().ok();
+
+                    size_of::<crate::Locked<Dynamic>>()
+                        + value.map_or(0, |v| {
+                            _visiting.push(ptr);
+                            let usage = calc_usage(&v, _visiting);
+                            _visiting.pop();
+                            usage
+                        })
+                }
+
+                _ => 0,
+            }
+        }
+
+        calc_usage(self, &mut Default::default())
+    }
 }
 
 impl Hash for Dynamic {
@@ -394,8 +499,14 @@ impl Hash for Dynamic {
             Union::Int(i, _) => i.hash(state),
             #[cfg(not(feature = "no_float"))]
             Union::Float(f, _) => f.hash(state),
+            #[cfg(feature = "bigint")]
+            Union::BigInt(x, _) => x.hash(state),
             #[cfg(not(feature = "no_index"))]
             Union::Array(a, _) => (**a).hash(state),
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(a, _) => (**a).hash(state),
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(a, _) => (**a).hash(state),
             #[cfg(not(feature = "no_object"))]
             Union::Map(m, _) => {
                 let mut buf: crate::StaticVec<_> = m.iter().collect();
@@ -422,6 +533,105 @@ impl Hash for Dynamic {
     }
 }
 
+/// A key of an [object map][crate::Map].
+///
+/// Object maps can be keyed by strings, integers, characters and booleans.
+/// Property access (e.g. `map.prop`) always uses string keys.
+#[derive(Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum MapKey {
+    /// A string key.
+    Str(ImmutableString),
+    /// An integer key.
+    Int(INT),
+    /// A character key.
+    Char(char),
+    /// A boolean key.
+    Bool(bool),
+}
+
+impl MapKey {
+    /// Get the key as a string slice.
+    /// Returns [`None`] if the key is not a string.
+    #[inline(always)]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+    /// Get the name of the type of the key.
+    #[inline(always)]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Str(_) => "string",
+            Self::Int(_) => type_name::<INT>(),
+            Self::Char(_) => "char",
+            Self::Bool(_) => "bool",
+        }
+    }
+}
+
+impl fmt::Display for MapKey {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(s) => fmt::Display::fmt(s, f),
+            Self::Int(n) => fmt::Display::fmt(n, f),
+            Self::Char(ch) => fmt::Display::fmt(ch, f),
+            Self::Bool(b) => fmt::Display::fmt(b, f),
+        }
+    }
+}
+
+impl fmt::Debug for MapKey {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(s) => fmt::Debug::fmt(s.as_str(), f),
+            Self::Int(n) => fmt::Debug::fmt(n, f),
+            Self::Char(ch) => fmt::Debug::fmt(ch, f),
+            Self::Bool(b) => fmt::Debug::fmt(b, f),
+        }
+    }
+}
+
+impl From<ImmutableString> for MapKey {
+    #[inline(always)]
+    fn from(value: ImmutableString) -> Self {
+        Self::Str(value)
+    }
+}
+impl From<&str> for MapKey {
+    #[inline(always)]
+    fn from(value: &str) -> Self {
+        Self::Str(value.into())
+    }
+}
+impl From<String> for MapKey {
+    #[inline(always)]
+    fn from(value: String) -> Self {
+        Self::Str(value.into())
+    }
+}
+impl From<INT> for MapKey {
+    #[inline(always)]
+    fn from(value: INT) -> Self {
+        Self::Int(value)
+    }
+}
+impl From<char> for MapKey {
+    #[inline(always)]
+    fn from(value: char) -> Self {
+        Self::Char(value)
+    }
+}
+impl From<bool> for MapKey {
+    #[inline(always)]
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
 /// Map the name of a standard type into a friendly form.
 #[inline(always)]
 pub(crate) fn map_std_type_name(name: &str) -> &str {
@@ -440,10 +650,25 @@ pub(crate) fn map_std_type_name(name: &str) -> &str {
         if name == type_name::<Decimal>() {
             return "decimal";
         }
+        #[cfg(feature = "bigint")]
+        if name == type_name::<BigInt>() {
+            return "bigint";
+        }
+        if name == type_name::<MapKey>() {
+            return "string, integer, character or boolean";
+        }
         #[cfg(not(feature = "no_index"))]
         if name == type_name::<Array>() {
             return "array";
         }
+        #[cfg(not(feature = "no_index"))]
+        if name == type_name::<Blob>() {
+            return "blob";
+        }
+        #[cfg(not(feature = "no_index"))]
+        if name == type_name::<Set>() {
+            return "set";
+        }
         #[cfg(not(feature = "no_object"))]
         if name == type_name::<Map>() {
             return "map";
@@ -452,11 +677,35 @@ pub(crate) fn map_std_type_name(name: &str) -> &str {
         if name == type_name::<Instant>() {
             return "timestamp";
         }
+        #[cfg(not(feature = "no_std"))]
+        if name == type_name::<crate::DateTime>() {
+            return "datetime";
+        }
+        #[cfg(not(feature = "no_std"))]
+        if name == type_name::<crate::TimeSpan>() {
+            return "duration";
+        }
+        if name == type_name::<crate::Regex>() {
+            return "regex";
+        }
 
         name
     }
 }
 
+/// Format a BLOB as hex bytes, e.g. `[01 a2 ff]`.
+#[cfg(not(feature = "no_index"))]
+fn format_blob(blob: &[u8], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("[")?;
+    blob.iter().enumerate().try_for_each(|(i, byte)| {
+        if i > 0 {
+            f.write_str(" ")?;
+        }
+        write!(f, "{:02x}", byte)
+    })?;
+    f.write_str("]")
+}
+
 impl fmt::Display for Dynamic {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.0 {
@@ -469,8 +718,14 @@ impl fmt::Display for Dynamic {
             Union::Float(value, _) => fmt::Display::fmt(value, f),
             #[cfg(feature = "decimal")]
             Union::Decimal(value, _) => fmt::Display::fmt(value, f),
+            #[cfg(feature = "bigint")]
+            Union::BigInt(value, _) => fmt::Display::fmt(value, f),
             #[cfg(not(feature = "no_index"))]
             Union::Array(value, _) => fmt::Debug::fmt(value, f),
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(value, _) => format_blob(value, f),
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(value, _) => fmt::Debug::fmt(value, f),
             #[cfg(not(feature = "no_object"))]
             Union::Map(value, _) => {
                 f.write_str("#")?;
@@ -548,8 +803,14 @@ impl fmt::Debug for Dynamic {
             Union::Float(value, _) => fmt::Debug::fmt(value, f),
             #[cfg(feature = "decimal")]
             Union::Decimal(value, _) => fmt::Debug::fmt(value, f),
+            #[cfg(feature = "bigint")]
+            Union::BigInt(value, _) => fmt::Display::fmt(value, f),
             #[cfg(not(feature = "no_index"))]
             Union::Array(value, _) => fmt::Debug::fmt(value, f),
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(value, _) => format_blob(value, f),
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(value, _) => fmt::Debug::fmt(value, f),
             #[cfg(not(feature = "no_object"))]
             Union::Map(value, _) => {
                 f.write_str("#")?;
@@ -642,8 +903,16 @@ impl Clone for Dynamic {
             Union::Decimal(ref value, _) => {
                 Self(Union::Decimal(value.clone(), AccessMode::ReadWrite))
             }
+            #[cfg(feature = "bigint")]
+            Union::BigInt(ref value, _) => {
+                Self(Union::BigInt(value.clone(), AccessMode::ReadWrite))
+            }
             #[cfg(not(feature = "no_index"))]
             Union::Array(ref value, _) => Self(Union::Array(value.clone(), AccessMode::ReadWrite)),
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(ref value, _) => Self(Union::Blob(value.clone(), AccessMode::ReadWrite)),
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(ref value, _) => Self(Union::Set(value.clone(), AccessMode::ReadWrite)),
             #[cfg(not(feature = "no_object"))]
             Union::Map(ref value, _) => Self(Union::Map(value.clone(), AccessMode::ReadWrite)),
             Union::FnPtr(ref value, _) => Self(Union::FnPtr(value.clone(), AccessMode::ReadWrite)),
@@ -708,8 +977,14 @@ impl Dynamic {
             Union::Float(_, access) => access,
             #[cfg(feature = "decimal")]
             Union::Decimal(_, access) => access,
+            #[cfg(feature = "bigint")]
+            Union::BigInt(_, access) => access,
             #[cfg(not(feature = "no_index"))]
             Union::Array(_, access) => access,
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(_, access) => access,
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(_, access) => access,
             #[cfg(not(feature = "no_object"))]
             Union::Map(_, access) => access,
             #[cfg(not(feature = "no_std"))]
@@ -733,8 +1008,14 @@ impl Dynamic {
             Union::Float(_, access) => *access = typ,
             #[cfg(feature = "decimal")]
             Union::Decimal(_, access) => *access = typ,
+            #[cfg(feature = "bigint")]
+            Union::BigInt(_, access) => *access = typ,
             #[cfg(not(feature = "no_index"))]
             Union::Array(_, access) => *access = typ,
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(_, access) => *access = typ,
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(_, access) => *access = typ,
             #[cfg(not(feature = "no_object"))]
             Union::Map(_, access) => *access = typ,
             #[cfg(not(feature = "no_std"))]
@@ -784,10 +1065,16 @@ impl Dynamic {
 
             #[cfg(not(feature = "no_float"))]
             Union::Float(_, _) => true,
+            #[cfg(feature = "bigint")]
+            Union::BigInt(_, _) => true,
+            #[cfg(not(feature = "no_index"))]
+            Union::Array(a, _) => a.iter().all(Self::is_hashable),
             #[cfg(not(feature = "no_index"))]
-            Union::Array(_, _) => true,
+            Union::Blob(_, _) => true,
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(_, _) => true,
             #[cfg(not(feature = "no_object"))]
-            Union::Map(_, _) => true,
+            Union::Map(m, _) => m.values().all(Self::is_hashable),
 
             #[cfg(not(feature = "no_closure"))]
             Union::Shared(cell, _) => {
@@ -802,6 +1089,44 @@ impl Dynamic {
             _ => false,
         }
     }
+    /// Are two hashable values of the same type and holding the same data?
+    ///
+    /// Floating-point numbers compare by their bit patterns, to be consistent with their hashes.
+    pub(crate) fn is_same_hashable(&self, other: &Self) -> bool {
+        #[cfg(not(feature = "no_closure"))]
+        if self.is_shared() || other.is_shared() {
+            return self
+                .flatten_clone()
+                .is_same_hashable(&other.flatten_clone());
+        }
+
+        match (&self.0, &other.0) {
+            (Union::Unit(_, _), Union::Unit(_, _)) => true,
+            (Union::Bool(a, _), Union::Bool(b, _)) => a == b,
+            (Union::Str(a, _), Union::Str(b, _)) => a == b,
+            (Union::Char(a, _), Union::Char(b, _)) => a == b,
+            (Union::Int(a, _), Union::Int(b, _)) => a == b,
+            #[cfg(not(feature = "no_float"))]
+            (Union::Float(a, _), Union::Float(b, _)) => a.to_ne_bytes() == b.to_ne_bytes(),
+            #[cfg(feature = "bigint")]
+            (Union::BigInt(a, _), Union::BigInt(b, _)) => a == b,
+            #[cfg(not(feature = "no_index"))]
+            (Union::Array(a, _), Union::Array(b, _)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.is_same_hashable(b))
+            }
+            #[cfg(not(feature = "no_index"))]
+            (Union::Blob(a, _), Union::Blob(b, _)) => a == b,
+            #[cfg(not(feature = "no_index"))]
+            (Union::Set(a, _), Union::Set(b, _)) => a == b,
+            #[cfg(not(feature = "no_object"))]
+            (Union::Map(a, _), Union::Map(b, _)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(k, v)| b.get(k).is_some_and(|w| v.is_same_hashable(w)))
+            }
+            _ => false,
+        }
+    }
     /// Create a [`Dynamic`] from any type.  A [`Dynamic`] value is simply returned as is.
     ///
     /// # Safety
@@ -858,6 +1183,13 @@ impl Dynamic {
                 .clone()
                 .into();
         }
+        #[cfg(feature = "bigint")]
+        if TypeId::of::<T>() == TypeId::of::<BigInt>() {
+            return <dyn Any>::downcast_ref::<BigInt>(&value)
+                .unwrap()
+                .clone()
+                .into();
+        }
         if TypeId::of::<T>() == TypeId::of::<bool>() {
             return <dyn Any>::downcast_ref::<bool>(&value)
                 .unwrap()
@@ -902,6 +1234,14 @@ impl Dynamic {
                 Ok(array) => return (*array).into(),
                 Err(val) => val,
             };
+            boxed = match unsafe_cast_box::<_, Blob>(boxed) {
+                Ok(blob) => return Self(Union::Blob(blob, AccessMode::ReadWrite)),
+                Err(val) => val,
+            };
+            boxed = match unsafe_cast_box::<_, Set>(boxed) {
+                Ok(set) => return Self(Union::Set(set, AccessMode::ReadWrite)),
+                Err(val) => val,
+            };
         }
 
         #[cfg(not(feature = "no_object"))]
@@ -1014,6 +1354,14 @@ impl Dynamic {
             };
         }
 
+        #[cfg(feature = "bigint")]
+        if TypeId::of::<T>() == TypeId::of::<BigInt>() {
+            return match self.0 {
+                Union::BigInt(value, _) => unsafe_try_cast(*value),
+                _ => None,
+            };
+        }
+
         if TypeId::of::<T>() == TypeId::of::<bool>() {
             return match self.0 {
                 Union::Bool(value, _) => unsafe_try_cast(value),
@@ -1050,6 +1398,22 @@ impl Dynamic {
             };
         }
 
+        #[cfg(not(feature = "no_index"))]
+        if TypeId::of::<T>() == TypeId::of::<Blob>() {
+            return match self.0 {
+                Union::Blob(value, _) => unsafe_cast_box::<_, T>(value).ok().map(|v| *v),
+                _ => None,
+            };
+        }
+
+        #[cfg(not(feature = "no_index"))]
+        if TypeId::of::<T>() == TypeId::of::<Set>() {
+            return match self.0 {
+                Union::Set(value, _) => unsafe_cast_box::<_, T>(value).ok().map(|v| *v),
+                _ => None,
+            };
+        }
+
         #[cfg(not(feature = "no_object"))]
         if TypeId::of::<T>() == TypeId::of::<Map>() {
             return match self.0 {
@@ -1336,6 +1700,13 @@ impl Dynamic {
                 _ => None,
             };
         }
+        #[cfg(feature = "bigint")]
+        if TypeId::of::<T>() == TypeId::of::<BigInt>() {
+            return match &self.0 {
+                Union::BigInt(value, _) => <dyn Any>::downcast_ref::<T>(value.as_ref()),
+                _ => None,
+            };
+        }
         if TypeId::of::<T>() == TypeId::of::<bool>() {
             return match &self.0 {
                 Union::Bool(value, _) => <dyn Any>::downcast_ref::<T>(value),
@@ -1367,6 +1738,20 @@ impl Dynamic {
                 _ => None,
             };
         }
+        #[cfg(not(feature = "no_index"))]
+        if TypeId::of::<T>() == TypeId::of::<Blob>() {
+            return match &self.0 {
+                Union::Blob(value, _) => <dyn Any>::downcast_ref::<T>(value.as_ref()),
+                _ => None,
+            };
+        }
+        #[cfg(not(feature = "no_index"))]
+        if TypeId::of::<T>() == TypeId::of::<Set>() {
+            return match &self.0 {
+                Union::Set(value, _) => <dyn Any>::downcast_ref::<T>(value.as_ref()),
+                _ => None,
+            };
+        }
         #[cfg(not(feature = "no_object"))]
         if TypeId::of::<T>() == TypeId::of::<Map>() {
             return match &self.0 {
@@ -1432,6 +1817,13 @@ impl Dynamic {
                 _ => None,
             };
         }
+        #[cfg(feature = "bigint")]
+        if TypeId::of::<T>() == TypeId::of::<BigInt>() {
+            return match &mut self.0 {
+                Union::BigInt(value, _) => <dyn Any>::downcast_mut::<T>(value.as_mut()),
+                _ => None,
+            };
+        }
         if TypeId::of::<T>() == TypeId::of::<bool>() {
             return match &mut self.0 {
                 Union::Bool(value, _) => <dyn Any>::downcast_mut::<T>(value),
@@ -1457,6 +1849,20 @@ impl Dynamic {
                 _ => None,
             };
         }
+        #[cfg(not(feature = "no_index"))]
+        if TypeId::of::<T>() == TypeId::of::<Blob>() {
+            return match &mut self.0 {
+                Union::Blob(value, _) => <dyn Any>::downcast_mut::<T>(value.as_mut()),
+                _ => None,
+            };
+        }
+        #[cfg(not(feature = "no_index"))]
+        if TypeId::of::<T>() == TypeId::of::<Set>() {
+            return match &mut self.0 {
+                Union::Set(value, _) => <dyn Any>::downcast_mut::<T>(value.as_mut()),
+                _ => None,
+            };
+        }
         #[cfg(not(feature = "no_object"))]
         if TypeId::of::<T>() == TypeId::of::<Map>() {
             return match &mut self.0 {
@@ -1516,6 +1922,25 @@ impl Dynamic {
             _ => Err(self.type_name()),
         }
     }
+    /// Convert the [`Dynamic`] into a [`MapKey`] for an [object map][crate::Map].
+    /// Returns the name of the actual type if it cannot be used as a key.
+    ///
+    /// Only strings, integers, characters and booleans can be used as keys.
+    #[inline]
+    pub fn as_map_key(&self) -> Result<MapKey, &'static str> {
+        match &self.0 {
+            Union::Str(s, _) => Ok(MapKey::Str(s.clone())),
+            Union::Int(n, _) => Ok(MapKey::Int(*n)),
+            Union::Char(ch, _) => Ok(MapKey::Char(*ch)),
+            Union::Bool(b, _) => Ok(MapKey::Bool(*b)),
+            #[cfg(not(feature = "no_closure"))]
+            Union::Shared(_, _) => self
+                .flatten_clone()
+                .as_map_key()
+                .map_err(|_| self.type_name()),
+            _ => Err(self.type_name()),
+        }
+    }
     /// Cast the [`Dynamic`] as the system floating-point type [`FLOAT`] and return it.
     /// Returns the name of the actual type if the cast fails.
     ///
@@ -1544,6 +1969,23 @@ impl Dynamic {
             _ => Err(self.type_name()),
         }
     }
+    /// _(BIGINT)_ Cast the [`Dynamic`] as a [`BigInt`] and return it.
+    /// Returns the name of the actual type if the cast fails.
+    ///
+    /// Exported under the `bigint` feature only.
+    #[cfg(feature = "bigint")]
+    #[inline(always)]
+    pub fn as_bigint(&self) -> Result<BigInt, &'static str> {
+        match &self.0 {
+            Union::BigInt(n, _) => Ok((**n).clone()),
+            #[cfg(not(feature = "no_closure"))]
+            Union::Shared(_, _) => self
+                .read_lock::<BigInt>()
+                .map(|v| (*v).clone())
+                .ok_or_else(|| self.type_name()),
+            _ => Err(self.type_name()),
+        }
+    }
     /// Cast the [`Dynamic`] as a [`bool`] and return it.
     /// Returns the name of the actual type if the cast fails.
     #[inline(always)]
@@ -1654,6 +2096,13 @@ impl From<Decimal> for Dynamic {
         ))
     }
 }
+#[cfg(feature = "bigint")]
+impl From<BigInt> for Dynamic {
+    #[inline(always)]
+    fn from(value: BigInt) -> Self {
+        Self(Union::BigInt(Box::new(value), AccessMode::ReadWrite))
+    }
+}
 impl From<char> for Dynamic {
     #[inline(always)]
     fn from(value: char) -> Self {
@@ -1696,8 +2145,19 @@ impl<T: Variant + Clone> crate::stdlib::iter::FromIterator<T> for Dynamic {
         ))
     }
 }
+impl From<MapKey> for Dynamic {
+    #[inline(always)]
+    fn from(value: MapKey) -> Self {
+        match value {
+            MapKey::Str(s) => s.into(),
+            MapKey::Int(n) => n.into(),
+            MapKey::Char(ch) => ch.into(),
+            MapKey::Bool(b) => b.into(),
+        }
+    }
+}
 #[cfg(not(feature = "no_object"))]
-impl<K: Into<ImmutableString>, T: Variant + Clone> From<crate::stdlib::collections::HashMap<K, T>>
+impl<K: Into<MapKey>, T: Variant + Clone> From<crate::stdlib::collections::HashMap<K, T>>
     for Dynamic
 {
     #[inline(always)]
@@ -1713,6 +2173,22 @@ impl<K: Into<ImmutableString>, T: Variant + Clone> From<crate::stdlib::collectio
         ))
     }
 }
+#[cfg(not(feature = "no_object"))]
+#[cfg(feature = "ordered_map")]
+impl<K: Into<MapKey>, T: Variant + Clone> From<indexmap::IndexMap<K, T>> for Dynamic {
+    #[inline(always)]
+    fn from(value: indexmap::IndexMap<K, T>) -> Self {
+        Self(Union::Map(
+            Box::new(
+                value
+                    .into_iter()
+                    .map(|(k, v)| (k.into(), Dynamic::from(v)))
+                    .collect(),
+            ),
+            AccessMode::ReadWrite,
+        ))
+    }
+}
 impl From<FnPtr> for Dynamic {
     #[inline(always)]
     fn from(value: FnPtr) -> Self {
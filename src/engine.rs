@@ -1,9 +1,12 @@
 //! Main module defining the script evaluation [`Engine`].
 
-use crate::ast::{Expr, FnCallExpr, FnHash, Ident, OpAssignment, ReturnType, Stmt, StmtBlock};
+use crate::ast::{
+    CustomExpr, Expr, FnCallExpr, FnHash, Ident, OpAssignment, ReturnType, Stmt, StmtBlock,
+};
+use crate::coverage::{switch_branch_index, Coverage};
 use crate::dynamic::{map_std_type_name, AccessMode, Union, Variant};
 use crate::fn_native::{
-    CallableFunction, IteratorFn, OnDebugCallback, OnPrintCallback, OnProgressCallback,
+    CallableFunction, IteratorFn, Locked, OnDebugCallback, OnPrintCallback, OnProgressCallback,
     OnVarCallback,
 };
 use crate::module::NamespaceRef;
@@ -20,6 +23,10 @@ use crate::stdlib::{
     num::{NonZeroU8, NonZeroUsize},
     ops::DerefMut,
     string::{String, ToString},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
     vec::Vec,
 };
 use crate::syntax::CustomSyntax;
@@ -32,15 +39,28 @@ use crate::{
 #[cfg(not(feature = "no_index"))]
 use crate::{calc_fn_hash, stdlib::iter::empty, Array};
 
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+use crate::bytecode::ChainStep;
+
 #[cfg(not(feature = "no_index"))]
 pub const TYPICAL_ARRAY_SIZE: usize = 8; // Small arrays are typical
 
 #[cfg(not(feature = "no_object"))]
-use crate::Map;
+use crate::{Map, MapKey};
 
 #[cfg(not(feature = "no_object"))]
 pub const TYPICAL_MAP_SIZE: usize = 8; // Small maps are typical
 
+#[cfg(not(feature = "unchecked"))]
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use crate::stdlib::time::{Duration, Instant};
+
+#[cfg(not(feature = "unchecked"))]
+#[cfg(not(feature = "no_std"))]
+#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
+use instant::{Duration, Instant};
+
 pub type Precedence = NonZeroU8;
 
 /// _(INTERNALS)_ A stack of imported [modules][Module].
@@ -128,6 +148,11 @@ impl Imports {
     pub fn contains_fn(&self, hash: u64) -> bool {
         self.1.iter().any(|m| m.contains_qualified_fn(hash))
     }
+    /// Does any of the imported [modules][Module] in this stack contain indexed global functions?
+    #[inline(always)]
+    pub fn contains_indexed_global_functions(&self) -> bool {
+        self.1.iter().any(|m| m.contains_indexed_global_functions())
+    }
     /// Get specified function via its hash key.
     #[inline(always)]
     pub fn get_fn(&self, hash: u64) -> Option<(&CallableFunction, Option<&ImmutableString>)> {
@@ -282,6 +307,100 @@ impl From<(Dynamic, Position)> for ChainArgument {
     }
 }
 
+/// A variable access - (optional index, optional (hash, modules), variable name, optional lookup hash),
+/// as held by [`Expr::Variable`].
+pub(crate) type VariableAccess = (
+    Option<NonZeroUsize>,
+    Option<(u64, NamespaceRef)>,
+    Ident,
+    Option<u64>,
+);
+
+/// A property access - ((getter, hash), (setter, hash), prop), as held by [`Expr::Property`].
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+pub(crate) type PropertyAccess = ((ImmutableString, u64), (ImmutableString, u64), Ident);
+
+/// A link of a dot/index chain.
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ChainLink<'a> {
+    /// Variable access.
+    Variable(&'a VariableAccess),
+    /// Property access - ((getter, hash), (setter, hash), prop).
+    Property(&'a PropertyAccess),
+    /// Method call - (function name, hash, position).
+    #[cfg(not(feature = "no_object"))]
+    Method(&'a str, FnHash, Position),
+    /// Any other expression, the value of which is an index.
+    Value(Position),
+}
+
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+impl ChainLink<'_> {
+    /// Get the [position][Position] of the link.
+    #[inline(always)]
+    pub fn position(&self) -> Position {
+        match self {
+            Self::Variable(x) => x.2.pos,
+            Self::Property(x) => x.2.pos,
+            #[cfg(not(feature = "no_object"))]
+            Self::Method(_, _, pos) => *pos,
+            Self::Value(pos) => *pos,
+        }
+    }
+}
+
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+impl<'a> From<&'a Expr> for ChainLink<'a> {
+    #[inline(always)]
+    fn from(expr: &'a Expr) -> Self {
+        match expr {
+            Expr::Variable(x) => Self::Variable(x),
+            Expr::Property(x) => Self::Property(x),
+            #[cfg(not(feature = "no_object"))]
+            Expr::FnCall(x, pos) if x.namespace.is_none() => Self::Method(&x.name, x.hash, *pos),
+            expr => Self::Value(expr.position()),
+        }
+    }
+}
+
+/// A dot/index chain, either as an [expression][Expr] or compiled into bytecode.
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ChainPath<'a> {
+    /// A chain expression, or any other expression as the last link of a chain.
+    Expr(&'a Expr),
+    /// Links of a chain compiled into bytecode.
+    Compiled(&'a [ChainStep]),
+}
+
+#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+impl<'a> ChainPath<'a> {
+    /// Split the first link off the chain, returning the type and [position][Position] of the
+    /// chain to the rest of the links, if any.
+    #[inline(always)]
+    pub fn split(self) -> (ChainLink<'a>, Option<(ChainType, Position, Self)>) {
+        match self {
+            Self::Expr(Expr::Index(x, pos)) => (
+                (&x.lhs).into(),
+                Some((ChainType::Index, *pos, Self::Expr(&x.rhs))),
+            ),
+            Self::Expr(Expr::Dot(x, pos)) => (
+                (&x.lhs).into(),
+                Some((ChainType::Dot, *pos, Self::Expr(&x.rhs))),
+            ),
+            Self::Expr(expr) => (expr.into(), None),
+            Self::Compiled(steps) => {
+                let (step, rest) = steps.split_first().expect("chain should not be empty");
+                let next = step
+                    .next
+                    .map(|(chain_type, pos)| (chain_type, pos, Self::Compiled(rest)));
+                (step.link(), next)
+            }
+        }
+    }
+}
+
 /// A type that encapsulates a mutation target for an expression with side effects.
 #[derive(Debug)]
 pub enum Target<'a> {
@@ -298,6 +417,10 @@ pub enum Target<'a> {
     /// This is necessary because directly pointing to a char inside a String is impossible.
     #[cfg(not(feature = "no_index"))]
     StringChar(&'a mut Dynamic, usize, Dynamic),
+    /// The target is a byte inside a BLOB.
+    /// This is necessary because directly pointing to a byte (in [`Dynamic`] form) inside a BLOB is impossible.
+    #[cfg(not(feature = "no_index"))]
+    BlobByte(&'a mut Dynamic, usize, Dynamic),
 }
 
 impl<'a> Target<'a> {
@@ -312,7 +435,7 @@ impl<'a> Target<'a> {
             Self::LockGuard(_) => true,
             Self::Value(_) => false,
             #[cfg(not(feature = "no_index"))]
-            Self::StringChar(_, _, _) => false,
+            Self::StringChar(_, _, _) | Self::BlobByte(_, _, _) => false,
         }
     }
     /// Is the `Target` an owned value?
@@ -326,7 +449,7 @@ impl<'a> Target<'a> {
             Self::LockGuard(_) => false,
             Self::Value(_) => true,
             #[cfg(not(feature = "no_index"))]
-            Self::StringChar(_, _, _) => false,
+            Self::StringChar(_, _, _) | Self::BlobByte(_, _, _) => false,
         }
     }
     /// Is the `Target` a shared value?
@@ -340,7 +463,7 @@ impl<'a> Target<'a> {
             Self::LockGuard(_) => true,
             Self::Value(r) => r.is_shared(),
             #[cfg(not(feature = "no_index"))]
-            Self::StringChar(_, _, _) => false,
+            Self::StringChar(_, _, _) | Self::BlobByte(_, _, _) => false,
         }
     }
     /// Is the `Target` a specific type?
@@ -355,6 +478,8 @@ impl<'a> Target<'a> {
             Target::Value(r) => r.is::<T>(),
             #[cfg(not(feature = "no_index"))]
             Target::StringChar(_, _, _) => TypeId::of::<T>() == TypeId::of::<char>(),
+            #[cfg(not(feature = "no_index"))]
+            Target::BlobByte(_, _, _) => TypeId::of::<T>() == TypeId::of::<crate::INT>(),
         }
     }
     /// Get the value of the `Target` as a `Dynamic`, cloning a referenced value if necessary.
@@ -368,6 +493,8 @@ impl<'a> Target<'a> {
             Self::Value(v) => v,       // Owned value is simply taken
             #[cfg(not(feature = "no_index"))]
             Self::StringChar(_, _, ch) => ch, // Character is taken
+            #[cfg(not(feature = "no_index"))]
+            Self::BlobByte(_, _, byte) => byte, // Byte is taken
         }
     }
     /// Take a `&mut Dynamic` reference from the `Target`.
@@ -397,6 +524,11 @@ impl<'a> Target<'a> {
                 let char_value = ch.clone();
                 self.set_value(char_value, Position::NONE).unwrap();
             }
+            #[cfg(not(feature = "no_index"))]
+            Self::BlobByte(_, _, byte) => {
+                let byte_value = byte.clone();
+                self.set_value(byte_value, Position::NONE).unwrap();
+            }
         }
     }
     /// Update the value of the `Target`.
@@ -433,6 +565,19 @@ impl<'a> Target<'a> {
                     .map(|(i, ch)| if i == index { new_ch } else { ch })
                     .collect();
             }
+            #[cfg(not(feature = "no_index"))]
+            Self::BlobByte(blob, index, _) => {
+                // Replace the byte at the specified index position, keeping only the lowest 8 bits
+                let new_byte = new_val.as_int().map_err(|err| {
+                    Box::new(EvalAltResult::ErrorMismatchDataType(
+                        type_name::<crate::INT>().to_string(),
+                        err.to_string(),
+                        _pos,
+                    ))
+                })?;
+
+                blob.write_lock::<crate::Blob>().unwrap()[*index] = (new_byte & 0xff) as u8;
+            }
         }
 
         Ok(())
@@ -464,7 +609,7 @@ impl AsRef<Dynamic> for Target<'_> {
             Self::LockGuard((r, _)) => &**r,
             Self::Value(ref r) => r,
             #[cfg(not(feature = "no_index"))]
-            Self::StringChar(_, _, ref r) => r,
+            Self::StringChar(_, _, ref r) | Self::BlobByte(_, _, ref r) => r,
         }
     }
 }
@@ -479,7 +624,7 @@ impl AsMut<Dynamic> for Target<'_> {
             Self::LockGuard((r, _)) => r.deref_mut(),
             Self::Value(ref mut r) => r,
             #[cfg(not(feature = "no_index"))]
-            Self::StringChar(_, _, ref mut r) => r,
+            Self::StringChar(_, _, ref mut r) | Self::BlobByte(_, _, ref mut r) => r,
         }
     }
 }
@@ -503,6 +648,304 @@ pub struct FnResolutionCacheEntry {
 /// A function resolution cache.
 pub type FnResolutionCache = HashMap<u64, Option<FnResolutionCacheEntry>, StraightHasherBuilder>;
 
+/// Get a new unique identifier for the functions and modules loaded into an [`Engine`].
+#[inline(always)]
+pub(crate) fn next_global_fn_id() -> usize {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A function resolution cache kept by an [`AST`] across evaluations.
+///
+/// It is only valid for the [`Engine`], with the same set of loaded functions and modules,
+/// that built it.
+#[derive(Debug, Default)]
+pub(crate) struct GlobalFnResolutionCache(Locked<Option<(usize, FnResolutionCache)>>);
+
+impl Clone for GlobalFnResolutionCache {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        #[cfg(not(feature = "sync"))]
+        let cache = self.0.borrow().clone();
+        #[cfg(feature = "sync")]
+        let cache = self.0.read().unwrap().clone();
+
+        Self(Locked::new(cache))
+    }
+}
+
+impl GlobalFnResolutionCache {
+    /// Take the cache out, if it was built by an [`Engine`] with the given functions identifier.
+    #[inline(always)]
+    pub fn take(&self, global_fn_id: usize) -> Option<FnResolutionCache> {
+        #[cfg(not(feature = "sync"))]
+        let cache = self.0.borrow_mut().take();
+        #[cfg(feature = "sync")]
+        let cache = self.0.write().unwrap().take();
+
+        cache
+            .filter(|(id, _)| *id == global_fn_id)
+            .map(|(_, cache)| cache)
+    }
+    /// Put a cache built by an [`Engine`] with the given functions identifier.
+    #[inline(always)]
+    pub fn put(&self, global_fn_id: usize, cache: FnResolutionCache) {
+        #[cfg(not(feature = "sync"))]
+        let mut value = self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        let mut value = self.0.write().unwrap();
+
+        *value = Some((global_fn_id, cache));
+    }
+}
+
+/// _(INTERNALS)_ Results of calls to a script-defined function, cached for memoization.
+/// Exported under the `internals` feature only.
+///
+/// The cache is only valid for the [`Engine`], with the same set of loaded functions and modules,
+/// that built it. Cloning it always creates an empty cache.
+///
+/// # Volatile Data Structure
+///
+/// This type is volatile and may change.
+#[cfg(not(feature = "no_function"))]
+#[derive(Debug, Default)]
+pub struct FnMemoCache(Locked<Option<FnMemo>>);
+
+/// Results of calls to a script-defined function.
+#[cfg(not(feature = "no_function"))]
+#[derive(Debug)]
+struct FnMemo {
+    /// Identifier of the functions and modules loaded into the [`Engine`] that built the cache.
+    global_fn_id: usize,
+    /// Can results of calls to the function be cached?
+    is_memoizable: bool,
+    /// Argument values and cached results, keyed by the hash of the argument values.
+    results: HashMap<u64, StaticVec<(StaticVec<Dynamic>, Dynamic)>, StraightHasherBuilder>,
+    /// Keys of the cached results, in order of insertion.
+    keys: crate::stdlib::collections::VecDeque<u64>,
+}
+
+#[cfg(not(feature = "no_function"))]
+impl Clone for FnMemoCache {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Default::default()
+    }
+}
+
+#[cfg(not(feature = "no_function"))]
+impl FnMemoCache {
+    /// Can results of calls to a script-defined function be cached by an [`Engine`]
+    /// with the given functions identifier?
+    ///
+    /// Results cached by an [`Engine`] with a different functions identifier are discarded.
+    #[inline]
+    pub(crate) fn is_memoizable(
+        &self,
+        engine: &Engine,
+        fn_def: &crate::ast::ScriptFnDef,
+        lib: &[&Module],
+    ) -> bool {
+        #[cfg(not(feature = "sync"))]
+        let mut memo = self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        let mut memo = self.0.write().unwrap();
+
+        match memo.as_ref() {
+            Some(m) if m.global_fn_id == engine.global_fn_id => m.is_memoizable,
+            _ => {
+                let is_memoizable = fn_def.is_memoizable(engine, lib);
+
+                *memo = Some(FnMemo {
+                    global_fn_id: engine.global_fn_id,
+                    is_memoizable,
+                    results: Default::default(),
+                    keys: Default::default(),
+                });
+
+                is_memoizable
+            }
+        }
+    }
+    /// Get a cached result by the hash of the argument values, comparing the argument values.
+    #[inline]
+    pub(crate) fn get(&self, hash: u64, args: &[&mut Dynamic]) -> Option<Dynamic> {
+        #[cfg(not(feature = "sync"))]
+        let memo = self.0.borrow();
+        #[cfg(feature = "sync")]
+        let memo = self.0.read().unwrap();
+
+        memo.as_ref()
+            .and_then(|m| m.results.get(&hash))
+            .and_then(|results| {
+                results.iter().find(|(values, _)| {
+                    values.len() == args.len()
+                        && values.iter().zip(args).all(|(v, a)| v.is_same_hashable(a))
+                })
+            })
+            .map(|(_, value)| value.clone())
+    }
+    /// Cache a result by the hash of the argument values, discarding the oldest cached results
+    /// to keep no more than `max_size` results.
+    #[inline]
+    pub(crate) fn put(&self, hash: u64, args: StaticVec<Dynamic>, value: Dynamic, max_size: usize) {
+        #[cfg(not(feature = "sync"))]
+        let mut memo = self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        let mut memo = self.0.write().unwrap();
+
+        if let Some(m) = memo.as_mut() {
+            let results = m.results.entry(hash).or_default();
+
+            match results.iter_mut().find(|(values, _)| {
+                values.len() == args.len()
+                    && values
+                        .iter()
+                        .zip(args.iter())
+                        .all(|(v, a)| v.is_same_hashable(a))
+            }) {
+                Some((_, cached)) => *cached = value,
+                None => {
+                    results.push((args, value));
+                    m.keys.push_back(hash);
+                }
+            }
+
+            while m.keys.len() > max_size {
+                // Results with the same hash are in order of insertion
+                let key = m.keys.pop_front().unwrap();
+                let results = m.results.get_mut(&key).unwrap();
+                results.remove(0);
+                if results.is_empty() {
+                    m.results.remove(&key);
+                }
+            }
+        }
+    }
+    /// Number of cached results.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        #[cfg(not(feature = "sync"))]
+        let memo = self.0.borrow();
+        #[cfg(feature = "sync")]
+        let memo = self.0.read().unwrap();
+
+        memo.as_ref().map_or(0, |m| m.keys.len())
+    }
+    /// Is the cache empty?
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Discard all cached results.
+    #[inline(always)]
+    pub fn clear(&self) {
+        #[cfg(not(feature = "sync"))]
+        let mut memo = self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        let mut memo = self.0.write().unwrap();
+
+        *memo = None;
+    }
+}
+
+/// _(INTERNALS)_ Approximate memory usage of the variables in all active scopes, tracked
+/// incrementally across function calls.
+/// Exported under the `internals` feature only.
+///
+/// A variable is measured when it is first seen in a [`Scope`], and measured again only after it
+/// may have been modified.
+///
+/// Not available under `unchecked`.
+///
+/// # Volatile Data Structure
+///
+/// This type is volatile and may change.
+#[cfg(not(feature = "unchecked"))]
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUsage {
+    /// Approximate memory usage of each variable measured, from the outermost scope.
+    vars: Vec<usize>,
+    /// Offsets into the variables where the scopes of the active function calls start.
+    frames: StaticVec<usize>,
+    /// Offsets into the variables of those that may have been modified since they were measured.
+    modified: StaticVec<usize>,
+    /// Total approximate memory usage of all variables measured.
+    total: usize,
+}
+
+#[cfg(not(feature = "unchecked"))]
+impl MemoryUsage {
+    /// Offset into the variables where the current scope starts.
+    #[inline(always)]
+    fn base(&self) -> usize {
+        self.frames.last().cloned().unwrap_or(0)
+    }
+    /// Start measuring the scope of a new function call.
+    #[inline(always)]
+    pub fn push_frame(&mut self) {
+        self.frames.push(self.vars.len());
+    }
+    /// Stop measuring the scope of the current function call.
+    #[inline(always)]
+    pub fn pop_frame(&mut self) {
+        let base = self.frames.pop().unwrap_or(0);
+        self.truncate(base);
+    }
+    /// Stop measuring all variables in the current scope beyond a number of variables.
+    #[inline(always)]
+    pub fn rewind(&mut self, len: usize) {
+        self.truncate(self.base() + len);
+    }
+    /// Mark a variable in the current scope as possibly modified.
+    #[inline(always)]
+    pub fn set_modified(&mut self, index: usize) {
+        let offset = self.base() + index;
+
+        if offset < self.vars.len() && !self.modified.contains(&offset) {
+            self.modified.push(offset);
+        }
+    }
+    /// Measure all new and modified variables in the current scope, and return the total
+    /// approximate memory usage, in bytes, of all variables measured.
+    pub fn update(&mut self, scope: &Scope) -> usize {
+        let base = self.base();
+        self.truncate(base + scope.len());
+
+        let vars = &mut self.vars;
+        let total = &mut self.total;
+
+        // Variables in outer scopes are measured when their scopes become current again
+        self.modified.retain(|&mut offset| {
+            if offset < base {
+                return true;
+            }
+            let size = scope.memory_usage_by_index(offset - base);
+            *total = *total - vars[offset] + size;
+            vars[offset] = size;
+            false
+        });
+
+        while self.vars.len() < base + scope.len() {
+            let size = scope.memory_usage_by_index(self.vars.len() - base);
+            self.vars.push(size);
+            self.total += size;
+        }
+
+        self.total
+    }
+    /// Stop measuring all variables beyond an offset.
+    #[inline(always)]
+    fn truncate(&mut self, len: usize) {
+        if len < self.vars.len() {
+            self.total -= self.vars[len..].iter().sum::<usize>();
+            self.vars.truncate(len);
+            self.modified.retain(|&mut offset| offset < len);
+        }
+    }
+}
+
 /// _(INTERNALS)_ A type that holds all the current states of the [`Engine`].
 /// Exported under the `internals` feature only.
 ///
@@ -524,6 +967,19 @@ pub struct State {
     pub operations: u64,
     /// Number of modules loaded.
     pub modules: usize,
+    /// Time when the evaluation started, if the execution time is limited.
+    ///
+    /// Not available under `unchecked` or `no_std`.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    pub started: Option<Instant>,
+    /// Generation of the evaluation for the [`TerminationHandle`], assigned when it starts.
+    pub generation: Option<u64>,
+    /// Approximate memory usage of all variables, tracked only if the memory usage is limited.
+    ///
+    /// Not available under `unchecked`.
+    #[cfg(not(feature = "unchecked"))]
+    pub memory: MemoryUsage,
     /// Embedded module resolver.
     #[cfg(not(feature = "no_module"))]
     pub resolver: Option<Shared<crate::module::resolvers::StaticModuleResolver>>,
@@ -531,6 +987,17 @@ pub struct State {
     fn_resolution_caches: StaticVec<FnResolutionCache>,
     /// Free resolution caches.
     fn_resolution_caches_free_list: Vec<FnResolutionCache>,
+    /// Address of the script-defined function being evaluated, if any, to detect tail calls to it.
+    #[cfg(not(feature = "no_function"))]
+    pub fn_def: Option<usize>,
+    /// Argument values of a pending tail call to the script-defined function being evaluated.
+    #[cfg(not(feature = "no_function"))]
+    pub tail_call_args: Option<StaticVec<Dynamic>>,
+    /// Cached positions in the scope of variables not declared within the script,
+    /// keyed by the hash of the variable name.
+    var_lookup_cache: StaticVec<(u64, usize)>,
+    /// Start offsets into the variable lookup cache of the enclosing function calls.
+    var_lookup_cache_frames: StaticVec<usize>,
 }
 
 impl State {
@@ -556,6 +1023,23 @@ impl State {
                 .unwrap_or_default(),
         );
     }
+    /// Set the global (i.e. outermost) function resolution cache.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are already function resolution caches in the stack.
+    pub fn set_global_fn_resolution_cache(&mut self, cache: FnResolutionCache) {
+        assert!(self.fn_resolution_caches.is_empty());
+        self.fn_resolution_caches.push(cache);
+    }
+    /// Take the global (i.e. outermost) function resolution cache.
+    pub fn take_global_fn_resolution_cache(&mut self) -> Option<FnResolutionCache> {
+        if self.fn_resolution_caches.is_empty() {
+            None
+        } else {
+            Some(self.fn_resolution_caches.remove(0))
+        }
+    }
     /// Remove the current function resolution cache from the stack and make the last one current.
     ///
     /// # Panics
@@ -566,6 +1050,60 @@ impl State {
         cache.clear();
         self.fn_resolution_caches_free_list.push(cache);
     }
+    /// Get the cached position in the scope of a variable not declared within the script.
+    #[inline(always)]
+    pub fn get_cached_var_index(&self, hash: u64) -> Option<usize> {
+        let start = self.var_lookup_cache_frames.last().cloned().unwrap_or(0);
+
+        self.var_lookup_cache[start..]
+            .iter()
+            .find(|(h, _)| *h == hash)
+            .map(|&(_, index)| index)
+    }
+    /// Cache the position in the scope of a variable not declared within the script.
+    #[inline(always)]
+    pub fn set_cached_var_index(&mut self, hash: u64, index: usize) {
+        let start = self.var_lookup_cache_frames.last().cloned().unwrap_or(0);
+
+        match self.var_lookup_cache[start..]
+            .iter_mut()
+            .find(|(h, _)| *h == hash)
+        {
+            Some(entry) => entry.1 = index,
+            None => self.var_lookup_cache.push((hash, index)),
+        }
+    }
+    /// Start an empty variable lookup cache for a new scope (e.g. a function call).
+    #[cfg(not(feature = "no_function"))]
+    #[inline(always)]
+    pub fn push_var_lookup_cache(&mut self) {
+        self.var_lookup_cache_frames
+            .push(self.var_lookup_cache.len());
+    }
+    /// Discard the current variable lookup cache and make the last one current.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no more variable lookup caches in the stack.
+    #[cfg(not(feature = "no_function"))]
+    #[inline(always)]
+    pub fn pop_var_lookup_cache(&mut self) {
+        let start = self.var_lookup_cache_frames.pop().unwrap();
+        self.var_lookup_cache.truncate(start);
+    }
+}
+
+/// The environment of an evaluation - the [`Scope`], the imported modules, the [`State`] and the
+/// functions library - bundled together to be passed around.
+pub(crate) struct EvalEnv<'a, 'x> {
+    /// Scope of the evaluation.
+    pub scope: &'a mut Scope<'x>,
+    /// Imported modules.
+    pub mods: &'a mut Imports,
+    /// State of the evaluation.
+    pub state: &'a mut State,
+    /// Functions library.
+    pub lib: &'a [&'a Module],
 }
 
 /// _(INTERNALS)_ A type containing all the limits imposed by the [`Engine`].
@@ -593,6 +1131,13 @@ pub struct Limits {
     pub max_function_expr_depth: Option<NonZeroUsize>,
     /// Maximum number of operations allowed to run.
     pub max_operations: Option<crate::stdlib::num::NonZeroU64>,
+    /// Maximum amount of time allowed for a script to run.
+    ///
+    /// Not available under `no_std`.
+    #[cfg(not(feature = "no_std"))]
+    pub max_execution_time: Option<Duration>,
+    /// Maximum approximate amount of memory, in bytes, held by all variables and temporary values.
+    pub max_memory: Option<NonZeroUsize>,
     /// Maximum number of [modules][Module] allowed to load.
     ///
     /// Set to zero to effectively disable loading any [module][Module].
@@ -612,6 +1157,83 @@ pub struct Limits {
     /// Not available under `no_object`.
     #[cfg(not(feature = "no_object"))]
     pub max_map_size: Option<NonZeroUsize>,
+    /// Maximum number of instructions in a compiled regular expression.
+    pub max_regex_size: Option<NonZeroUsize>,
+    /// Maximum number of steps taken by a single regular expression search.
+    pub max_regex_steps: Option<crate::stdlib::num::NonZeroU64>,
+}
+
+/// Reason for terminating a running script, used as the token of
+/// [`EvalAltResult::ErrorTerminated`] when the script is terminated by the [`Engine`] itself.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum TerminationReason {
+    /// The script is terminated via a [`TerminationHandle`].
+    Cancelled,
+    /// The script exceeded the maximum execution time.
+    TimedOut,
+}
+
+/// A cloneable handle for terminating scripts running in an [`Engine`], possibly from another thread.
+///
+/// A script terminated via this handle returns [`EvalAltResult::ErrorTerminated`] with
+/// [`TerminationReason::Cancelled`] as the token.
+///
+/// A termination request applies to all scripts running at the time of the request, and has no
+/// effect on scripts started afterwards.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, EvalAltResult, RegisterFn, TerminationReason};
+///
+/// let mut engine = Engine::new();
+/// let handle = engine.termination_handle();
+///
+/// // Typically called from another thread while the script is running
+/// engine.register_fn("cancel", move || handle.terminate());
+///
+/// match *engine
+///     .consume("let x = 0; loop { x += 1; if x == 1000 { cancel(); } }")
+///     .expect_err("should error")
+/// {
+///     EvalAltResult::ErrorTerminated(token, _) => {
+///         assert_eq!(token.cast::<TerminationReason>(), TerminationReason::Cancelled)
+///     }
+///     err => panic!("unexpected error: {}", err),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TerminationHandle(Arc<TerminationGenerations>);
+
+/// Generations of the scripts running in an [`Engine`], for terminating them.
+#[derive(Debug, Default)]
+struct TerminationGenerations {
+    /// Generation of the next script to start.
+    next: AtomicU64,
+    /// Scripts of generations below this are terminated.
+    terminated: AtomicU64,
+}
+
+impl TerminationHandle {
+    /// Terminate all scripts running at the time of the call.
+    #[inline(always)]
+    pub fn terminate(&self) {
+        let next = self.0.next.load(Ordering::SeqCst);
+        self.0.terminated.fetch_max(next, Ordering::SeqCst);
+    }
+    /// Start a script, returning its generation.
+    #[inline(always)]
+    pub(crate) fn start(&self) -> u64 {
+        self.0.next.fetch_add(1, Ordering::SeqCst)
+    }
+    /// Is the script of a generation terminated?
+    #[inline(always)]
+    pub(crate) fn is_terminated(&self, generation: u64) -> bool {
+        generation < self.0.terminated.load(Ordering::Relaxed)
+    }
 }
 
 /// Context of a script evaluation process.
@@ -715,6 +1337,9 @@ pub struct Engine {
     pub(crate) global_modules: StaticVec<Shared<Module>>,
     /// A collection of all sub-modules directly loaded into the Engine.
     pub(crate) global_sub_modules: HashMap<ImmutableString, Shared<Module>>,
+    /// Identifier of the functions and modules loaded into the Engine.
+    /// It changes whenever they do, invalidating function resolution caches kept by [`AST`]'s.
+    pub(crate) global_fn_id: usize,
 
     /// A module resolution service.
     #[cfg(not(feature = "no_module"))]
@@ -738,6 +1363,13 @@ pub struct Engine {
     pub(crate) debug: OnDebugCallback,
     /// Callback closure for progress reporting.
     pub(crate) progress: Option<OnProgressCallback>,
+    /// Handle for terminating running scripts.
+    pub(crate) termination: TerminationHandle,
+    /// Callback closure for reading the current date and time.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) clock: Option<crate::fn_native::OnClockCallback>,
+    /// Cache of compiled regular expressions.
+    pub(crate) regex_cache: crate::regex::RegexCache,
 
     /// Optimize the AST after compilation.
     pub(crate) optimization_level: OptimizationLevel,
@@ -748,6 +1380,14 @@ pub struct Engine {
 
     /// Disable doc-comments?
     pub(crate) disable_doc_comments: bool,
+
+    /// Code coverage recorder, if enabled.
+    pub(crate) coverage: Option<Locked<Coverage>>,
+
+    /// Maximum number of results cached for memoization per script-defined function.
+    /// Zero disables memoization.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) max_fn_memo_size: usize,
 }
 
 impl fmt::Debug for Engine {
@@ -814,6 +1454,7 @@ impl Engine {
             global_namespace: Default::default(),
             global_modules: Default::default(),
             global_sub_modules: Default::default(),
+            global_fn_id: next_global_fn_id(),
 
             #[cfg(not(feature = "no_module"))]
             #[cfg(not(feature = "no_std"))]
@@ -837,6 +1478,11 @@ impl Engine {
 
             // progress callback
             progress: None,
+            termination: Default::default(),
+
+            #[cfg(not(feature = "no_std"))]
+            clock: None,
+            regex_cache: Default::default(),
 
             // optimization level
             optimization_level: if cfg!(feature = "no_optimize") {
@@ -853,6 +1499,9 @@ impl Engine {
                 #[cfg(not(feature = "no_function"))]
                 max_function_expr_depth: NonZeroUsize::new(MAX_FUNCTION_EXPR_DEPTH),
                 max_operations: None,
+                #[cfg(not(feature = "no_std"))]
+                max_execution_time: None,
+                max_memory: None,
                 #[cfg(not(feature = "no_module"))]
                 max_modules: usize::MAX,
                 max_string_size: None,
@@ -860,9 +1509,16 @@ impl Engine {
                 max_array_size: None,
                 #[cfg(not(feature = "no_object"))]
                 max_map_size: None,
+                max_regex_size: None,
+                max_regex_steps: None,
             },
 
             disable_doc_comments: false,
+
+            coverage: None,
+
+            #[cfg(not(feature = "no_function"))]
+            max_fn_memo_size: 0,
         };
 
         engine.register_global_module(StandardPackage::new().as_shared_module());
@@ -879,6 +1535,7 @@ impl Engine {
             global_namespace: Default::default(),
             global_modules: Default::default(),
             global_sub_modules: Default::default(),
+            global_fn_id: next_global_fn_id(),
 
             #[cfg(not(feature = "no_module"))]
             module_resolver: Box::new(crate::module::resolvers::DummyModuleResolver::new()),
@@ -893,6 +1550,11 @@ impl Engine {
             print: Box::new(|_| {}),
             debug: Box::new(|_, _, _| {}),
             progress: None,
+            termination: Default::default(),
+
+            #[cfg(not(feature = "no_std"))]
+            clock: None,
+            regex_cache: Default::default(),
 
             optimization_level: if cfg!(feature = "no_optimize") {
                 OptimizationLevel::None
@@ -908,6 +1570,9 @@ impl Engine {
                 #[cfg(not(feature = "no_function"))]
                 max_function_expr_depth: NonZeroUsize::new(MAX_FUNCTION_EXPR_DEPTH),
                 max_operations: None,
+                #[cfg(not(feature = "no_std"))]
+                max_execution_time: None,
+                max_memory: None,
                 #[cfg(not(feature = "no_module"))]
                 max_modules: usize::MAX,
                 max_string_size: None,
@@ -915,9 +1580,16 @@ impl Engine {
                 max_array_size: None,
                 #[cfg(not(feature = "no_object"))]
                 max_map_size: None,
+                max_regex_size: None,
+                max_regex_steps: None,
             },
 
             disable_doc_comments: false,
+
+            coverage: None,
+
+            #[cfg(not(feature = "no_function"))]
+            max_fn_memo_size: 0,
         }
     }
 
@@ -957,37 +1629,31 @@ impl Engine {
         state: &mut State,
         lib: &[&Module],
         this_ptr: &'s mut Option<&mut Dynamic>,
-        expr: &Expr,
+        var: &VariableAccess,
     ) -> Result<(Target<'s>, Position), Box<EvalAltResult>> {
-        match expr {
-            Expr::Variable(v) => match v.as_ref() {
-                // Qualified variable
-                (_, Some((hash_var, modules)), Ident { name, pos, .. }) => {
-                    let module = self.search_imports(mods, state, modules).ok_or_else(|| {
-                        EvalAltResult::ErrorModuleNotFound(
-                            modules[0].name.to_string(),
-                            modules[0].pos,
-                        )
-                    })?;
-                    let target = module.get_qualified_var(*hash_var).map_err(|mut err| {
-                        match *err {
-                            EvalAltResult::ErrorVariableNotFound(ref mut err_name, _) => {
-                                *err_name = format!("{}{}", modules, name);
-                            }
-                            _ => (),
+        match var {
+            // Qualified variable
+            (_, Some((hash_var, modules)), Ident { name, pos, .. }, _) => {
+                let module = self.search_imports(mods, state, modules).ok_or_else(|| {
+                    EvalAltResult::ErrorModuleNotFound(modules[0].name.to_string(), modules[0].pos)
+                })?;
+                let target = module.get_qualified_var(*hash_var).map_err(|mut err| {
+                    match *err {
+                        EvalAltResult::ErrorVariableNotFound(ref mut err_name, _) => {
+                            *err_name = format!("{}{}", modules, name);
                         }
-                        err.fill_position(*pos)
-                    })?;
+                        _ => (),
+                    }
+                    err.fill_position(*pos)
+                })?;
 
-                    // Module variables are constant
-                    let mut target = target.clone();
-                    target.set_access_mode(AccessMode::ReadOnly);
-                    Ok((target.into(), *pos))
-                }
-                // Normal variable access
-                _ => self.search_scope_only(scope, mods, state, lib, this_ptr, expr),
-            },
-            _ => unreachable!("Expr::Variable expected, but gets {:?}", expr),
+                // Module variables are constant
+                let mut target = target.clone();
+                target.set_access_mode(AccessMode::ReadOnly);
+                Ok((target.into(), *pos))
+            }
+            // Normal variable access
+            _ => self.search_scope_only(scope, mods, state, lib, this_ptr, var),
         }
     }
 
@@ -999,12 +1665,9 @@ impl Engine {
         state: &mut State,
         lib: &[&Module],
         this_ptr: &'s mut Option<&mut Dynamic>,
-        expr: &Expr,
+        var: &VariableAccess,
     ) -> Result<(Target<'s>, Position), Box<EvalAltResult>> {
-        let (index, _, Ident { name, pos, .. }) = match expr {
-            Expr::Variable(v) => v.as_ref(),
-            _ => unreachable!("Expr::Variable expected, but gets {:?}", expr),
-        };
+        let (index, _, Ident { name, pos, .. }, hash) = var;
 
         // Check if the variable is `this`
         if name.as_str() == KEYWORD_THIS {
@@ -1038,14 +1701,29 @@ impl Engine {
             }
         }
 
+        // Variables not declared within the script can only be found below the local variables
+        // of the current scope, which do not move until it ends, so their positions can be cached
+        let hash = if state.always_search { None } else { *hash };
+
         let index = if let Some(index) = index {
             scope.len() - index.get()
+        } else if let Some(index) = hash
+            .and_then(|hash| state.get_cached_var_index(hash))
+            .filter(|&index| scope.get_name_by_index(index) == Some(name.as_str()))
+        {
+            index
         } else {
             // Find the variable in the scope
-            scope
+            let index = scope
                 .get_index(name)
                 .ok_or_else(|| EvalAltResult::ErrorVariableNotFound(name.to_string(), *pos))?
-                .0
+                .0;
+
+            if let Some(hash) = hash {
+                state.set_cached_var_index(hash, index);
+            }
+
+            index
         };
 
         let val = scope.get_mut_by_index(index);
@@ -1069,7 +1747,7 @@ impl Engine {
         lib: &[&Module],
         this_ptr: &mut Option<&mut Dynamic>,
         target: &mut Target,
-        rhs: &Expr,
+        rhs: ChainPath,
         idx_values: &mut StaticVec<ChainArgument>,
         chain_type: ChainType,
         level: usize,
@@ -1079,11 +1757,8 @@ impl Engine {
 
         let is_ref = target.is_ref();
 
-        let next_chain = match rhs {
-            Expr::Index(_, _) => ChainType::Index,
-            Expr::Dot(_, _) => ChainType::Dot,
-            _ => ChainType::NonChaining,
-        };
+        let (link, next) = rhs.split();
+        let next_chain = next.map_or(ChainType::NonChaining, |(chain_type, _, _)| chain_type);
 
         // Pop the last index value
         let idx_val = idx_values.pop().unwrap();
@@ -1093,12 +1768,12 @@ impl Engine {
         match chain_type {
             #[cfg(not(feature = "no_index"))]
             ChainType::Index => {
-                let pos = rhs.position();
+                let pos = link.position();
 
-                match rhs {
+                match next {
                     // xxx[idx].expr... | xxx[idx][expr]...
-                    Expr::Dot(x, x_pos) | Expr::Index(x, x_pos) => {
-                        let idx_pos = x.lhs.position();
+                    Some((_, x_pos, rest)) => {
+                        let idx_pos = link.position();
                         let idx_val = idx_val.as_index_value();
                         let obj_ptr = &mut self.get_indexed_mut(
                             mods, state, lib, target_val, idx_val, idx_pos, false, is_ref, true,
@@ -1106,13 +1781,13 @@ impl Engine {
                         )?;
 
                         self.eval_dot_index_chain_helper(
-                            mods, state, lib, this_ptr, obj_ptr, &x.rhs, idx_values, next_chain,
+                            mods, state, lib, this_ptr, obj_ptr, rest, idx_values, next_chain,
                             level, new_val,
                         )
-                        .map_err(|err| err.fill_position(*x_pos))
+                        .map_err(|err| err.fill_position(x_pos))
                     }
                     // xxx[rhs] op= new_val
-                    _ if new_val.is_some() => {
+                    None if new_val.is_some() => {
                         let idx_val = idx_val.as_index_value();
                         let mut idx_val2 = idx_val.clone();
 
@@ -1166,7 +1841,7 @@ impl Engine {
                         Ok((Dynamic::UNIT, true))
                     }
                     // xxx[rhs]
-                    _ => {
+                    None => {
                         let idx_val = idx_val.as_index_value();
                         self.get_indexed_mut(
                             mods, state, lib, target_val, idx_val, pos, false, is_ref, true, level,
@@ -1178,25 +1853,22 @@ impl Engine {
 
             #[cfg(not(feature = "no_object"))]
             ChainType::Dot => {
-                match rhs {
+                match (link, next) {
                     // xxx.fn_name(arg_expr_list)
-                    Expr::FnCall(x, pos) if x.namespace.is_none() && new_val.is_none() => {
-                        let FnCallExpr { name, hash, .. } = x.as_ref();
+                    (ChainLink::Method(name, hash, pos), None) if new_val.is_none() => {
                         let mut args = idx_val.as_fn_call_args();
                         self.make_method_call(
-                            mods, state, lib, name, *hash, target, &mut args, *pos, level,
+                            mods, state, lib, name, hash, target, &mut args, pos, level,
                         )
                     }
                     // xxx.fn_name(...) = ???
-                    Expr::FnCall(_, _) if new_val.is_some() => {
+                    (ChainLink::Method(_, _, _), None) => {
                         unreachable!("method call cannot be assigned to")
                     }
-                    // xxx.module::fn_name(...) - syntax error
-                    Expr::FnCall(_, _) => {
-                        unreachable!("function call in dot chain should not be namespace-qualified")
-                    }
                     // {xxx:map}.id op= ???
-                    Expr::Property(x) if target_val.is::<Map>() && new_val.is_some() => {
+                    (ChainLink::Property(x), None)
+                        if target_val.is::<Map>() && new_val.is_some() =>
+                    {
                         let Ident { name, pos, .. } = &x.2;
                         let index = name.clone().into();
                         let val = self.get_indexed_mut(
@@ -1209,7 +1881,7 @@ impl Engine {
                         Ok((Dynamic::UNIT, true))
                     }
                     // {xxx:map}.id
-                    Expr::Property(x) if target_val.is::<Map>() => {
+                    (ChainLink::Property(x), None) if target_val.is::<Map>() => {
                         let Ident { name, pos, .. } = &x.2;
                         let index = name.clone().into();
                         let val = self.get_indexed_mut(
@@ -1219,8 +1891,8 @@ impl Engine {
                         Ok((val.take_or_clone(), false))
                     }
                     // xxx.id = ???
-                    Expr::Property(x) if new_val.is_some() => {
-                        let (_, (setter, hash_set), Ident { pos, .. }) = x.as_ref();
+                    (ChainLink::Property(x), None) if new_val.is_some() => {
+                        let (_, (setter, hash_set), Ident { pos, .. }) = x;
                         let hash = FnHash::from_native(*hash_set);
                         let mut new_val = new_val;
                         let mut args = [target_val, &mut (new_val.as_mut().unwrap().0).0];
@@ -1231,8 +1903,8 @@ impl Engine {
                         .map(|(v, _)| (v, true))
                     }
                     // xxx.id
-                    Expr::Property(x) => {
-                        let ((getter, hash_get), _, Ident { pos, .. }) = x.as_ref();
+                    (ChainLink::Property(x), None) => {
+                        let ((getter, hash_get), _, Ident { pos, .. }) = x;
                         let hash = FnHash::from_native(*hash_get);
                         let mut args = [target_val];
                         self.exec_fn_call(
@@ -1242,9 +1914,9 @@ impl Engine {
                         .map(|(v, _)| (v, false))
                     }
                     // {xxx:map}.sub_lhs[expr] | {xxx:map}.sub_lhs.expr
-                    Expr::Index(x, x_pos) | Expr::Dot(x, x_pos) if target_val.is::<Map>() => {
-                        let mut val = match &x.lhs {
-                            Expr::Property(p) => {
+                    (link, Some((_, x_pos, rest))) if target_val.is::<Map>() => {
+                        let mut val = match link {
+                            ChainLink::Property(p) => {
                                 let Ident { name, pos, .. } = &p.2;
                                 let index = name.clone().into();
                                 self.get_indexed_mut(
@@ -1253,35 +1925,29 @@ impl Engine {
                                 )?
                             }
                             // {xxx:map}.fn_name(arg_expr_list)[expr] | {xxx:map}.fn_name(arg_expr_list).expr
-                            Expr::FnCall(x, pos) if x.namespace.is_none() => {
-                                let FnCallExpr { name, hash, .. } = x.as_ref();
+                            ChainLink::Method(name, hash, pos) => {
                                 let mut args = idx_val.as_fn_call_args();
                                 let (val, _) = self.make_method_call(
-                                    mods, state, lib, name, *hash, target, &mut args, *pos, level,
+                                    mods, state, lib, name, hash, target, &mut args, pos, level,
                                 )?;
                                 val.into()
                             }
-                            // {xxx:map}.module::fn_name(...) - syntax error
-                            Expr::FnCall(_, _) => unreachable!(
-                                "function call in dot chain should not be namespace-qualified"
-                            ),
                             // Others - syntax error
-                            expr => unreachable!("invalid dot expression: {:?}", expr),
+                            link => unreachable!("invalid dot expression: {:?}", link),
                         };
 
                         self.eval_dot_index_chain_helper(
-                            mods, state, lib, this_ptr, &mut val, &x.rhs, idx_values, next_chain,
+                            mods, state, lib, this_ptr, &mut val, rest, idx_values, next_chain,
                             level, new_val,
                         )
-                        .map_err(|err| err.fill_position(*x_pos))
+                        .map_err(|err| err.fill_position(x_pos))
                     }
                     // xxx.sub_lhs[expr] | xxx.sub_lhs.expr
-                    Expr::Index(x, x_pos) | Expr::Dot(x, x_pos) => {
-                        match &x.lhs {
+                    (link, Some((_, x_pos, rest))) => {
+                        match link {
                             // xxx.prop[expr] | xxx.prop.expr
-                            Expr::Property(p) => {
-                                let ((getter, hash_get), (setter, hash_set), Ident { pos, .. }) =
-                                    p.as_ref();
+                            ChainLink::Property(p) => {
+                                let ((getter, hash_get), (setter, hash_set), Ident { pos, .. }) = p;
                                 let hash_get = FnHash::from_native(*hash_get);
                                 let hash_set = FnHash::from_native(*hash_set);
                                 let arg_values = &mut [target_val, &mut Default::default()];
@@ -1300,13 +1966,13 @@ impl Engine {
                                         lib,
                                         this_ptr,
                                         &mut val.into(),
-                                        &x.rhs,
+                                        rest,
                                         idx_values,
                                         next_chain,
                                         level,
                                         new_val,
                                     )
-                                    .map_err(|err| err.fill_position(*x_pos))?;
+                                    .map_err(|err| err.fill_position(x_pos))?;
 
                                 // Feed the value back via a setter just in case it has been updated
                                 if updated || may_be_changed {
@@ -1331,31 +1997,26 @@ impl Engine {
                                 Ok((result, may_be_changed))
                             }
                             // xxx.fn_name(arg_expr_list)[expr] | xxx.fn_name(arg_expr_list).expr
-                            Expr::FnCall(f, pos) if f.namespace.is_none() => {
-                                let FnCallExpr { name, hash, .. } = f.as_ref();
+                            ChainLink::Method(name, hash, pos) => {
                                 let mut args = idx_val.as_fn_call_args();
                                 let (mut val, _) = self.make_method_call(
-                                    mods, state, lib, name, *hash, target, &mut args, *pos, level,
+                                    mods, state, lib, name, hash, target, &mut args, pos, level,
                                 )?;
                                 let val = &mut val;
                                 let target = &mut val.into();
 
                                 self.eval_dot_index_chain_helper(
-                                    mods, state, lib, this_ptr, target, &x.rhs, idx_values,
+                                    mods, state, lib, this_ptr, target, rest, idx_values,
                                     next_chain, level, new_val,
                                 )
-                                .map_err(|err| err.fill_position(*pos))
+                                .map_err(|err| err.fill_position(pos))
                             }
-                            // xxx.module::fn_name(...) - syntax error
-                            Expr::FnCall(_, _) => unreachable!(
-                                "function call in dot chain should not be namespace-qualified"
-                            ),
                             // Others - syntax error
-                            expr => unreachable!("invalid dot expression: {:?}", expr),
+                            link => unreachable!("invalid dot expression: {:?}", link),
                         }
                     }
                     // Syntax error
-                    _ => EvalAltResult::ErrorDotExpr("".into(), rhs.position()).into(),
+                    (link, None) => EvalAltResult::ErrorDotExpr("".into(), link.position()).into(),
                 }
             }
 
@@ -1376,9 +2037,9 @@ impl Engine {
         level: usize,
         new_val: Option<((Dynamic, Position), (&Option<OpAssignment>, Position))>,
     ) -> RhaiResult {
-        let (crate::ast::BinaryExpr { lhs, rhs }, chain_type, op_pos) = match expr {
-            Expr::Index(x, pos) => (x.as_ref(), ChainType::Index, *pos),
-            Expr::Dot(x, pos) => (x.as_ref(), ChainType::Dot, *pos),
+        let (crate::ast::BinaryExpr { lhs, rhs }, chain_type) = match expr {
+            Expr::Index(x, _) => (x.as_ref(), ChainType::Index),
+            Expr::Dot(x, _) => (x.as_ref(), ChainType::Dot),
             _ => unreachable!("index or dot chain expected, but gets {:?}", expr),
         };
 
@@ -1388,19 +2049,62 @@ impl Engine {
             scope, mods, state, lib, this_ptr, rhs, chain_type, idx_values, 0, level,
         )?;
 
+        match lhs {
+            Expr::Variable(_) => (),
+            _ if new_val.is_some() => unreachable!("cannot assign to an expression"),
+            _ => {
+                let value = self.eval_expr(scope, mods, state, lib, this_ptr, lhs, level)?;
+                idx_values.push((value, lhs.position()).into());
+            }
+        }
+
+        let env = &mut EvalEnv {
+            scope,
+            mods,
+            state,
+            lib,
+        };
+        let chain = ChainPath::Expr(expr);
+        self.eval_dot_index_chain_raw(env, this_ptr, chain, idx_values, level, new_val)
+    }
+
+    /// Evaluate a dot/index chain, given the values of its arguments.
+    ///
+    /// Unless the chain starts with a variable, the value it starts with is the last of the
+    /// argument values.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    pub(crate) fn eval_dot_index_chain_raw(
+        &self,
+        env: &mut EvalEnv,
+        this_ptr: &mut Option<&mut Dynamic>,
+        chain: ChainPath,
+        idx_values: &mut StaticVec<ChainArgument>,
+        level: usize,
+        new_val: Option<((Dynamic, Position), (&Option<OpAssignment>, Position))>,
+    ) -> RhaiResult {
+        let EvalEnv {
+            scope,
+            mods,
+            state,
+            lib,
+        } = env;
+
+        let (lhs, rhs) = chain.split();
+        let (chain_type, op_pos, rhs) = rhs.expect("dot/index chain expected");
+
         match lhs {
             // id.??? or id[???]
-            Expr::Variable(x) => {
+            ChainLink::Variable(var) => {
                 let Ident {
                     name: var_name,
                     pos: var_pos,
                     ..
-                } = &x.2;
+                } = &var.2;
 
                 self.inc_operations(state, *var_pos)?;
 
                 let (target, pos) =
-                    self.search_namespace(scope, mods, state, lib, this_ptr, lhs)?;
+                    self.search_namespace(scope, mods, state, lib, this_ptr, var)?;
 
                 // Constants cannot be modified
                 if target.as_ref().is_read_only() && new_val.is_some() {
@@ -1408,19 +2112,29 @@ impl Engine {
                         .into();
                 }
 
-                let obj_ptr = &mut target.into();
-                self.eval_dot_index_chain_helper(
-                    mods, state, lib, &mut None, obj_ptr, rhs, idx_values, chain_type, level,
-                    new_val,
-                )
-                .map(|(v, _)| v)
-                .map_err(|err| err.fill_position(op_pos))
+                let (result, modified) = {
+                    let obj_ptr = &mut target.into();
+                    self.eval_dot_index_chain_helper(
+                        mods, state, lib, &mut None, obj_ptr, rhs, idx_values, chain_type, level,
+                        new_val,
+                    )
+                    .map_err(|err| err.fill_position(op_pos))?
+                };
+
+                if modified {
+                    self.set_var_modified(scope, state, var);
+                }
+
+                Ok(result)
             }
             // {expr}.??? = ??? or {expr}[???] = ???
             _ if new_val.is_some() => unreachable!("cannot assign to an expression"),
             // {expr}.??? or {expr}[???]
-            expr => {
-                let value = self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)?;
+            _ => {
+                let value = match idx_values.pop() {
+                    Some(ChainArgument::IndexValue(value, _)) => value,
+                    _ => unreachable!("value of the start of the chain expected"),
+                };
                 let obj_ptr = &mut value.into();
                 self.eval_dot_index_chain_helper(
                     mods, state, lib, this_ptr, obj_ptr, rhs, idx_values, chain_type, level,
@@ -1571,21 +2285,41 @@ impl Engine {
                 }
             }
 
+            #[cfg(not(feature = "no_index"))]
+            Dynamic(Union::Blob(blob, _)) => {
+                // val_blob[idx]
+                let index = idx
+                    .as_int()
+                    .map_err(|err| self.make_type_mismatch_err::<crate::INT>(err, idx_pos))?;
+
+                let blob_len = blob.len();
+
+                if index >= 0 {
+                    let offset = index as usize;
+                    let byte = *blob
+                        .get(offset)
+                        .ok_or_else(|| EvalAltResult::ErrorArrayBounds(blob_len, index, idx_pos))?;
+                    Ok(Target::BlobByte(
+                        target,
+                        offset,
+                        (byte as crate::INT).into(),
+                    ))
+                } else {
+                    EvalAltResult::ErrorArrayBounds(blob_len, index, idx_pos).into()
+                }
+            }
+
             #[cfg(not(feature = "no_object"))]
             Dynamic(Union::Map(map, _)) => {
                 // val_map[idx]
-                Ok(if _create {
-                    let index = idx.take_immutable_string().map_err(|err| {
-                        self.make_type_mismatch_err::<ImmutableString>(err, idx_pos)
-                    })?;
+                let index = idx
+                    .as_map_key()
+                    .map_err(|err| self.make_type_mismatch_err::<MapKey>(err, idx_pos))?;
 
+                Ok(if _create {
                     map.entry(index).or_insert_with(Default::default).into()
                 } else {
-                    let index = idx.read_lock::<ImmutableString>().ok_or_else(|| {
-                        self.make_type_mismatch_err::<ImmutableString>("", idx_pos)
-                    })?;
-
-                    map.get_mut(&*index)
+                    map.get_mut(&index)
                         .map(Target::from)
                         .unwrap_or_else(|| Target::from(()))
                 })
@@ -1666,8 +2400,8 @@ impl Engine {
                 .as_deref()
                 .cloned()
                 .ok_or_else(|| EvalAltResult::ErrorUnboundThis((x.2).pos).into()),
-            Expr::Variable(_) => self
-                .search_namespace(scope, mods, state, lib, this_ptr, expr)
+            Expr::Variable(x) => self
+                .search_namespace(scope, mods, state, lib, this_ptr, x)
                 .map(|(val, _)| val.take_or_clone()),
 
             // Statement block
@@ -1677,6 +2411,31 @@ impl Engine {
                 self.eval_stmt_block(scope, mods, state, lib, this_ptr, statements, true, level)
             }
 
+            // Inlined function call
+            #[cfg(not(feature = "no_function"))]
+            Expr::InlinedFnCall(x, pos) => {
+                let (name, body) = x.as_ref();
+
+                // Check for stack overflow
+                #[cfg(not(feature = "unchecked"))]
+                if level + 1 > self.max_call_levels() {
+                    return Err(Box::new(EvalAltResult::ErrorStackOverflow(*pos)));
+                }
+
+                let statements = &body.statements;
+                let result = self.eval_stmt_block(
+                    scope,
+                    mods,
+                    state,
+                    lib,
+                    &mut None,
+                    statements,
+                    true,
+                    level + 1,
+                );
+                self.map_fn_body_result(state, name, None, result, *pos)
+            }
+
             // lhs[idx_expr]
             #[cfg(not(feature = "no_index"))]
             Expr::Index(_, _) => {
@@ -1706,7 +2465,7 @@ impl Engine {
             Expr::Map(x, _) => {
                 let mut map =
                     Map::with_capacity(crate::stdlib::cmp::max(TYPICAL_MAP_SIZE, x.len()));
-                for (Ident { name: key, .. }, expr) in x.as_ref() {
+                for (key, expr) in x.as_ref() {
                     map.insert(
                         key.clone(),
                         self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)?
@@ -1716,6 +2475,23 @@ impl Engine {
                 Ok(Dynamic(Union::Map(Box::new(map), AccessMode::ReadWrite)))
             }
 
+            // Tail call to the script-defined function being evaluated
+            #[cfg(not(feature = "no_function"))]
+            Expr::FnCall(x, pos)
+                if x.tail_call
+                    && !x.hash.is_native_only()
+                    && this_ptr.is_none()
+                    && state.fn_def.is_some() =>
+            {
+                let env = &mut EvalEnv {
+                    scope,
+                    mods,
+                    state,
+                    lib,
+                };
+                self.make_tail_call(env, this_ptr, x, *pos, level)
+            }
+
             // Normal function call
             Expr::FnCall(x, pos) if x.namespace.is_none() => {
                 let FnCallExpr {
@@ -1776,15 +2552,6 @@ impl Engine {
             Expr::Unit(_) => Ok(Dynamic::UNIT),
 
             Expr::Custom(custom, _) => {
-                let expressions = custom
-                    .keywords
-                    .iter()
-                    .map(Into::into)
-                    .collect::<StaticVec<_>>();
-                let custom_def = self
-                    .custom_syntax
-                    .get(custom.tokens.first().unwrap())
-                    .unwrap();
                 let mut context = EvalContext {
                     engine: self,
                     scope,
@@ -1794,7 +2561,7 @@ impl Engine {
                     this_ptr,
                     level,
                 };
-                (custom_def.func)(&mut context, &expressions)
+                self.eval_custom_syntax(&mut context, custom)
             }
 
             _ => unreachable!("expression cannot be evaluated: {:?}", expr),
@@ -1803,6 +2570,24 @@ impl Engine {
         self.check_data_size(result, expr.position())
     }
 
+    /// Evaluate a custom syntax expression.
+    pub(crate) fn eval_custom_syntax(
+        &self,
+        context: &mut EvalContext,
+        custom: &CustomExpr,
+    ) -> RhaiResult {
+        let expressions = custom
+            .keywords
+            .iter()
+            .map(Into::into)
+            .collect::<StaticVec<_>>();
+        let custom_def = self
+            .custom_syntax
+            .get(custom.tokens.first().unwrap())
+            .unwrap();
+        (custom_def.func)(context, &expressions)
+    }
+
     /// Evaluate a statements block.
     pub(crate) fn eval_stmt_block(
         &self,
@@ -1867,7 +2652,7 @@ impl Engine {
         }
 
         if restore_prev_state {
-            scope.rewind(prev_scope_len);
+            self.rewind_scope(scope, state, prev_scope_len);
             mods.truncate(prev_mods_len);
             state.scope_level -= 1;
 
@@ -1956,6 +2741,12 @@ impl Engine {
     ) -> RhaiResult {
         self.inc_operations(state, stmt.position())?;
 
+        if self.coverage.is_some() {
+            self.record_coverage(|c| {
+                c.mark_statement(state.source.as_ref().map(|s| s.as_str()), stmt)
+            });
+        }
+
         let result = match stmt {
             // No-op
             Stmt::Noop(_) => Ok(Dynamic::UNIT),
@@ -1971,8 +2762,9 @@ impl Engine {
                 let rhs_val = self
                     .eval_expr(scope, mods, state, lib, this_ptr, rhs_expr, level)?
                     .flatten();
+                let var = lhs_expr.as_variable();
                 let (lhs_ptr, pos) =
-                    self.search_namespace(scope, mods, state, lib, this_ptr, lhs_expr)?;
+                    self.search_namespace(scope, mods, state, lib, this_ptr, var)?;
 
                 if !lhs_ptr.is_ref() {
                     return EvalAltResult::ErrorAssignmentToConstant(
@@ -2001,6 +2793,7 @@ impl Engine {
                         rhs_val,
                         rhs_expr.position(),
                     )?;
+                    self.set_var_modified(scope, state, var);
                     Ok(Dynamic::UNIT)
                 }
             }
@@ -2061,6 +2854,17 @@ impl Engine {
                     .as_bool()
                     .map_err(|err| self.make_type_mismatch_err::<bool>(err, expr.position()))
                     .and_then(|guard_val| {
+                        if self.coverage.is_some() {
+                            let branch = if guard_val { 0 } else { 1 };
+                            self.record_coverage(|c| {
+                                c.mark_branch(
+                                    state.source.as_ref().map(|s| s.as_str()),
+                                    stmt,
+                                    branch,
+                                )
+                            });
+                        }
+
                         if guard_val {
                             if !if_stmt.is_empty() {
                                 self.eval_stmt_block(
@@ -2092,7 +2896,20 @@ impl Engine {
                     value.hash(hasher);
                     let hash = hasher.finish();
 
-                    table.get(&hash).map(|StmtBlock { statements, .. }| {
+                    table.get(&hash).map(|block| {
+                        if self.coverage.is_some() {
+                            let branch = switch_branch_index(table.values(), block);
+                            self.record_coverage(|c| {
+                                c.mark_branch(
+                                    state.source.as_ref().map(|s| s.as_str()),
+                                    stmt,
+                                    branch,
+                                )
+                            });
+                        }
+
+                        let statements = &block.statements;
+
                         if !statements.is_empty() {
                             self.eval_stmt_block(
                                 scope, mods, state, lib, this_ptr, statements, true, level,
@@ -2106,6 +2923,13 @@ impl Engine {
                     None
                 }
                 .unwrap_or_else(|| {
+                    if self.coverage.is_some() {
+                        let branch = table.len();
+                        self.record_coverage(|c| {
+                            c.mark_branch(state.source.as_ref().map(|s| s.as_str()), stmt, branch)
+                        });
+                    }
+
                     // Default match clause
                     let def_stmt = &def_stmt.statements;
                     if !def_stmt.is_empty() {
@@ -2236,6 +3060,11 @@ impl Engine {
                             *loop_var = value;
                         }
 
+                        #[cfg(not(feature = "unchecked"))]
+                        if self.limits.max_memory.is_some() {
+                            state.memory.set_modified(index);
+                        }
+
                         self.inc_operations(state, *pos)?;
 
                         if statements.is_empty() {
@@ -2255,7 +3084,7 @@ impl Engine {
                     }
 
                     state.scope_level -= 1;
-                    scope.rewind(scope.len() - 1);
+                    self.rewind_scope(scope, state, index);
                     Ok(Dynamic::UNIT)
                 } else {
                     EvalAltResult::ErrorFor(expr.position()).into()
@@ -2291,49 +3120,7 @@ impl Engine {
                     Err(err) if err.is_pseudo_error() => Err(err),
                     Err(err) if !err.is_catchable() => Err(err),
                     Err(mut err) => {
-                        let err_value = match *err {
-                            EvalAltResult::ErrorRuntime(ref x, _) => x.clone(),
-
-                            #[cfg(feature = "no_object")]
-                            _ => {
-                                err.take_position();
-                                err.to_string().into()
-                            }
-                            #[cfg(not(feature = "no_object"))]
-                            _ => {
-                                use crate::INT;
-
-                                let mut err_map: Map = Default::default();
-                                let err_pos = err.take_position();
-
-                                err_map.insert("message".into(), err.to_string().into());
-
-                                if let Some(ref source) = state.source {
-                                    err_map.insert("source".into(), source.clone().into());
-                                }
-
-                                if err_pos.is_none() {
-                                    // No position info
-                                } else {
-                                    err_map.insert(
-                                        "line".into(),
-                                        (err_pos.line().unwrap() as INT).into(),
-                                    );
-                                    err_map.insert(
-                                        "position".into(),
-                                        if err_pos.is_beginning_of_line() {
-                                            0
-                                        } else {
-                                            err_pos.position().unwrap() as INT
-                                        }
-                                        .into(),
-                                    );
-                                }
-
-                                err.dump_fields(&mut err_map);
-                                err_map.into()
-                            }
-                        };
+                        let err_value = self.make_catch_value(state, &mut err);
 
                         let orig_scope_len = scope.len();
                         state.scope_level += 1;
@@ -2347,7 +3134,7 @@ impl Engine {
                         );
 
                         state.scope_level -= 1;
-                        scope.rewind(orig_scope_len);
+                        self.rewind_scope(scope, state, orig_scope_len);
 
                         match result {
                             Ok(_) => Ok(Dynamic::UNIT),
@@ -2501,21 +3288,66 @@ impl Engine {
             }
         };
 
-        self.check_data_size(result, stmt.position())
+        let result = self.check_data_size(result, stmt.position());
+        self.check_memory_usage(scope, state, result, stmt.position())
+    }
+
+    /// Make the value of an error caught by a `catch` block, taking the position out of the error.
+    pub(crate) fn make_catch_value(&self, _state: &State, err: &mut EvalAltResult) -> Dynamic {
+        match *err {
+            EvalAltResult::ErrorRuntime(ref x, _) => x.clone(),
+
+            #[cfg(feature = "no_object")]
+            _ => {
+                err.take_position();
+                err.to_string().into()
+            }
+            #[cfg(not(feature = "no_object"))]
+            _ => {
+                use crate::INT;
+
+                let mut err_map: Map = Default::default();
+                let err_pos = err.take_position();
+
+                err_map.insert("message".into(), err.to_string().into());
+
+                if let Some(ref source) = _state.source {
+                    err_map.insert("source".into(), source.clone().into());
+                }
+
+                if err_pos.is_none() {
+                    // No position info
+                } else {
+                    err_map.insert("line".into(), (err_pos.line().unwrap() as INT).into());
+                    err_map.insert(
+                        "position".into(),
+                        if err_pos.is_beginning_of_line() {
+                            0
+                        } else {
+                            err_pos.position().unwrap() as INT
+                        }
+                        .into(),
+                    );
+                }
+
+                err.dump_fields(&mut err_map);
+                err_map.into()
+            }
+        }
     }
 
     /// Check a result to ensure that the data size is within allowable limit.
     /// [`Position`] in [`EvalAltResult`] may be None and should be set afterwards.
     #[cfg(feature = "unchecked")]
     #[inline(always)]
-    fn check_data_size(&self, result: RhaiResult, _pos: Position) -> RhaiResult {
+    pub(crate) fn check_data_size(&self, result: RhaiResult, _pos: Position) -> RhaiResult {
         result
     }
 
     /// Check a result to ensure that the data size is within allowable limit.
     #[cfg(not(feature = "unchecked"))]
     #[inline(always)]
-    fn check_data_size(&self, result: RhaiResult, pos: Position) -> RhaiResult {
+    pub(crate) fn check_data_size(&self, result: RhaiResult, pos: Position) -> RhaiResult {
         // Simply return all errors
         if result.is_err() {
             return result;
@@ -2545,11 +3377,12 @@ impl Engine {
                     let mut maps = 0;
 
                     arr.iter().for_each(|value| match value {
-                        Dynamic(Union::Array(_, _)) => {
+                        Dynamic(Union::Array(_, _)) | Dynamic(Union::Set(_, _)) => {
                             let (a, m, _) = calc_size(value);
                             arrays += a;
                             maps += m;
                         }
+                        Dynamic(Union::Blob(blob, _)) => arrays += blob.len(),
                         #[cfg(not(feature = "no_object"))]
                         Dynamic(Union::Map(_, _)) => {
                             let (a, m, _) = calc_size(value);
@@ -2568,11 +3401,13 @@ impl Engine {
 
                     map.values().for_each(|value| match value {
                         #[cfg(not(feature = "no_index"))]
-                        Dynamic(Union::Array(_, _)) => {
+                        Dynamic(Union::Array(_, _)) | Dynamic(Union::Set(_, _)) => {
                             let (a, m, _) = calc_size(value);
                             arrays += a;
                             maps += m;
                         }
+                        #[cfg(not(feature = "no_index"))]
+                        Dynamic(Union::Blob(blob, _)) => arrays += blob.len(),
                         Dynamic(Union::Map(_, _)) => {
                             let (a, m, _) = calc_size(value);
                             arrays += a;
@@ -2583,6 +3418,17 @@ impl Engine {
 
                     (arrays, maps, 0)
                 }
+                #[cfg(not(feature = "no_index"))]
+                Dynamic(Union::Blob(blob, _)) => (blob.len(), 0, 0),
+                #[cfg(not(feature = "no_index"))]
+                Dynamic(Union::Set(set, _)) => {
+                    set.iter().fold((0, 0, 0), |(arrays, maps, _), value| {
+                        match calc_size(value) {
+                            (0, 0, _) => (arrays + 1, maps, 0),
+                            (a, m, _) => (arrays + a, maps + m, 0),
+                        }
+                    })
+                }
                 Dynamic(Union::Str(s, _)) => (0, 0, s.len()),
                 _ => (0, 0, 0),
             }
@@ -2598,6 +3444,17 @@ impl Engine {
             return EvalAltResult::ErrorDataTooLarge("Length of string".to_string(), pos).into();
         }
 
+        // A big integer is limited by the length of its decimal representation
+        #[cfg(feature = "bigint")]
+        if let Some(max) = self.limits.max_string_size {
+            if let Dynamic(Union::BigInt(ref x, _)) = result.as_ref().unwrap() {
+                if crate::packages::arithmetic::bigint_digits(x.bits()) > max.get() as u64 {
+                    return EvalAltResult::ErrorDataTooLarge("Size of number".to_string(), pos)
+                        .into();
+                }
+            }
+        }
+
         #[cfg(not(feature = "no_index"))]
         if _arr
             > self
@@ -2621,6 +3478,84 @@ impl Engine {
         result
     }
 
+    /// Check a result to ensure that the total memory usage is within allowable limit.
+    #[cfg(feature = "unchecked")]
+    #[inline(always)]
+    pub(crate) fn check_memory_usage(
+        &self,
+        _scope: &Scope,
+        _state: &mut State,
+        result: RhaiResult,
+        _pos: Position,
+    ) -> RhaiResult {
+        result
+    }
+
+    /// Check a result to ensure that the total memory usage, i.e. all variables in all active
+    /// scopes plus the result value, is within allowable limit.
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    pub(crate) fn check_memory_usage(
+        &self,
+        scope: &Scope,
+        state: &mut State,
+        result: RhaiResult,
+        pos: Position,
+    ) -> RhaiResult {
+        if let Some(max_memory) = self.limits.max_memory {
+            if let Ok(ref value) = result {
+                if state.memory.update(scope) + value.memory_usage() > max_memory.get() {
+                    return EvalAltResult::ErrorDataTooLarge("Memory usage".to_string(), pos)
+                        .into();
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Mark a variable as possibly modified, so that its memory usage is measured again.
+    #[cfg(feature = "unchecked")]
+    #[inline(always)]
+    pub(crate) fn set_var_modified(
+        &self,
+        _scope: &Scope,
+        _state: &mut State,
+        _var: &VariableAccess,
+    ) {
+    }
+
+    /// Mark a variable as possibly modified, so that its memory usage is measured again.
+    #[cfg(not(feature = "unchecked"))]
+    pub(crate) fn set_var_modified(&self, scope: &Scope, state: &mut State, var: &VariableAccess) {
+        if self.limits.max_memory.is_none() {
+            return;
+        }
+
+        if let (index, None, Ident { name, .. }, _) = var {
+            let index = match index {
+                Some(index) if !state.always_search => Some(scope.len() - index.get()),
+                _ => scope.get_index(name).map(|(index, _)| index),
+            };
+
+            if let Some(index) = index {
+                state.memory.set_modified(index);
+            }
+        }
+    }
+
+    /// Rewind a [`Scope`] to a number of variables.
+    /// The variables removed are no longer counted in the memory usage.
+    #[inline(always)]
+    pub(crate) fn rewind_scope(&self, scope: &mut Scope, _state: &mut State, len: usize) {
+        scope.rewind(len);
+
+        #[cfg(not(feature = "unchecked"))]
+        if self.limits.max_memory.is_some() {
+            _state.memory.rewind(len);
+        }
+    }
+
     /// Check if the number of operations stay within limit.
     #[inline]
     pub(crate) fn inc_operations(
@@ -2630,15 +3565,60 @@ impl Engine {
     ) -> Result<(), Box<EvalAltResult>> {
         state.operations += 1;
 
+        #[cfg(not(feature = "unchecked"))]
+        #[cfg(not(feature = "no_std"))]
+        // Guard against running too long
+        if let Some(max_execution_time) = self.limits.max_execution_time {
+            let started = *state.started.get_or_insert_with(Instant::now);
+
+            if started.elapsed() > max_execution_time {
+                return EvalAltResult::ErrorTerminated(
+                    Dynamic::from(TerminationReason::TimedOut),
+                    pos,
+                )
+                .into();
+            }
+        }
+
+        let generation = self.generation(state);
+        self.check_operations(state.operations, generation, pos)
+    }
+
+    /// Get the generation of an evaluation for the [`TerminationHandle`], starting it if needed.
+    #[inline(always)]
+    pub(crate) fn generation(&self, state: &mut State) -> u64 {
+        *state
+            .generation
+            .get_or_insert_with(|| self.termination.start())
+    }
+
+    /// Check if a number of operations performed stays within limit, also reporting progress and
+    /// terminating the evaluation of a generation if requested.
+    #[inline]
+    pub(crate) fn check_operations(
+        &self,
+        operations: u64,
+        generation: u64,
+        pos: Position,
+    ) -> Result<(), Box<EvalAltResult>> {
         #[cfg(not(feature = "unchecked"))]
         // Guard against too many operations
-        if self.max_operations() > 0 && state.operations > self.max_operations() {
+        if self.max_operations() > 0 && operations > self.max_operations() {
             return EvalAltResult::ErrorTooManyOperations(pos).into();
         }
 
+        // Terminate script if requested via a termination handle
+        if self.termination.is_terminated(generation) {
+            return EvalAltResult::ErrorTerminated(
+                Dynamic::from(TerminationReason::Cancelled),
+                pos,
+            )
+            .into();
+        }
+
         // Report progress - only in steps
         if let Some(progress) = &self.progress {
-            if let Some(token) = progress(state.operations) {
+            if let Some(token) = progress(operations) {
                 // Terminate script if progress returns a termination token
                 return EvalAltResult::ErrorTerminated(token, pos).into();
             }
@@ -2647,6 +3627,17 @@ impl Engine {
         Ok(())
     }
 
+    /// Record code coverage information, if enabled.
+    #[inline(always)]
+    pub(crate) fn record_coverage(&self, f: impl FnOnce(&mut Coverage)) {
+        if let Some(ref coverage) = self.coverage {
+            #[cfg(not(feature = "sync"))]
+            f(&mut coverage.borrow_mut());
+            #[cfg(feature = "sync")]
+            f(&mut coverage.write().unwrap());
+        }
+    }
+
     /// Pretty-print a type name.
     ///
     /// If a type is registered via [`register_type_with_name`][Engine::register_type_with_name],
@@ -1,8 +1,10 @@
 //! Module that defines the extern API of [`Engine`].
 
+use crate::bytecode::{Bytecode, Vm};
+use crate::continuation::{Continuation, Execution};
 use crate::dynamic::Variant;
-use crate::engine::{EvalContext, Imports, State};
-use crate::fn_native::{FnCallArgs, SendSync};
+use crate::engine::{next_global_fn_id, EvalContext, Imports, State};
+use crate::fn_native::{AsyncCall, FnCallArgs, SendSync};
 use crate::optimize::OptimizationLevel;
 use crate::stdlib::{
     any::{type_name, TypeId},
@@ -13,7 +15,7 @@ use crate::stdlib::{
 };
 use crate::{
     scope::Scope, Dynamic, Engine, EvalAltResult, FnAccess, FnNamespace, Module, NativeCallContext,
-    ParseError, Position, RhaiResult, Shared, AST,
+    ParseError, Position, RhaiResult, Shared, StaticVec, AST,
 };
 
 #[cfg(not(feature = "no_index"))]
@@ -56,6 +58,7 @@ impl Engine {
             arg_types,
             func,
         );
+        self.global_fn_id = next_global_fn_id();
         self
     }
     /// Register a custom type for use with the [`Engine`].
@@ -153,6 +156,7 @@ impl Engine {
     where
         T: Variant + Clone + IntoIterator,
         <T as IntoIterator>::Item: Variant + Clone,
+        <T as IntoIterator>::IntoIter: SendSync + 'static,
     {
         self.global_namespace.set_iterable::<T>();
         self
@@ -731,6 +735,7 @@ impl Engine {
     pub fn register_global_module(&mut self, module: Shared<Module>) -> &mut Self {
         // Insert the module into the front
         self.global_modules.insert(0, module);
+        self.global_fn_id = next_global_fn_id();
         self
     }
     /// Register a shared [`Module`] into the global namespace of [`Engine`].
@@ -815,6 +820,7 @@ impl Engine {
         }
 
         register_static_module_raw(&mut self.global_sub_modules, name.as_ref(), module);
+        self.global_fn_id = next_global_fn_id();
         self
     }
 
@@ -1151,7 +1157,7 @@ impl Engine {
     ///
     /// ```
     /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
-    /// use rhai::{Engine, Map};
+    /// use rhai::{Engine, Map, MapKey};
     ///
     /// let engine = Engine::new();
     ///
@@ -1160,12 +1166,12 @@ impl Engine {
     ///         .replace("{", "#{").as_str(), true)?;
     ///
     /// assert_eq!(map.len(), 4);
-    /// assert_eq!(map["a"].as_int().unwrap(), 123);
-    /// assert_eq!(map["b"].as_int().unwrap(), 42);
-    /// assert!(map["d"].is::<()>());
+    /// assert_eq!(map[&MapKey::from("a")].as_int().unwrap(), 123);
+    /// assert_eq!(map[&MapKey::from("b")].as_int().unwrap(), 42);
+    /// assert!(map[&MapKey::from("d")].is::<()>());
     ///
-    /// let c = map["c"].read_lock::<Map>().unwrap();
-    /// assert_eq!(c["x"].as_bool().unwrap(), false);
+    /// let c = map[&MapKey::from("c")].read_lock::<Map>().unwrap();
+    /// assert_eq!(c[&MapKey::from("x")].as_bool().unwrap(), false);
     /// # Ok(())
     /// # }
     /// ```
@@ -1532,16 +1538,346 @@ impl Engine {
         ast: &'a AST,
         level: usize,
     ) -> RhaiResult {
+        self.record_coverage(|c| c.register_ast(ast));
+
         let mut state: State = Default::default();
         state.source = ast.clone_source();
         #[cfg(not(feature = "no_module"))]
         {
             state.resolver = ast.resolver();
         }
+        self.load_fn_resolution_cache(&mut state, mods, ast);
 
-        let statements = ast.statements();
-        let lib = &[ast.lib()];
-        self.eval_global_statements(scope, mods, &mut state, statements, lib, level)
+        // Evaluate all scripts in bytecode instead, to check that the results are identical
+        #[cfg(feature = "bytecode_eval")]
+        let result = {
+            let _ = level;
+            self.run_bytecode(scope, mods, &mut state, &Bytecode::compile(ast))
+        };
+        #[cfg(not(feature = "bytecode_eval"))]
+        let result = {
+            let statements = ast.statements();
+            let lib = &[ast.lib()];
+            self.eval_global_statements(scope, mods, &mut state, statements, lib, level)
+        };
+
+        self.keep_fn_resolution_cache(&mut state, mods, ast);
+        result
+    }
+    /// Start the function resolution cache of a new [`State`] with the one kept by an [`AST`]
+    /// from previous evaluations, if it is still valid.
+    #[inline(always)]
+    pub(crate) fn load_fn_resolution_cache(&self, state: &mut State, mods: &Imports, ast: &AST) {
+        // Imported modules with global functions affect function resolution
+        if !mods.contains_indexed_global_functions() {
+            if let Some(cache) = ast.fn_resolution_cache().take(self.global_fn_id) {
+                state.set_global_fn_resolution_cache(cache);
+            }
+        }
+    }
+    /// Keep the function resolution cache of a [`State`] within an [`AST`] for later evaluations.
+    #[inline(always)]
+    pub(crate) fn keep_fn_resolution_cache(&self, state: &mut State, mods: &Imports, ast: &AST) {
+        // Functions resolved with imported modules with global functions cannot be reused
+        if !mods.contains_indexed_global_functions() {
+            if let Some(cache) = state.take_global_fn_resolution_cache() {
+                ast.fn_resolution_cache().put(self.global_fn_id, cache);
+            }
+        }
+    }
+    /// Evaluate an [`AST`] with own scope, allowing native functions to suspend the evaluation.
+    ///
+    /// A native function suspends the evaluation by returning
+    /// [`EvalAltResult::ErrorSuspended`] with a token describing what it is waiting for.
+    /// Instead of an error, a [`Continuation`] is then returned which can be stored and later
+    /// resumed with a value via [`resume`][Engine::resume].
+    ///
+    /// The [`AST`] is compiled into [`Bytecode`] and run with explicit call frames, which are kept
+    /// in the [`Continuation`] together with the rest of the evaluation state.  Resuming continues
+    /// right after the function call that suspended the evaluation, so nothing is evaluated twice.
+    ///
+    /// Suspension is supported for function calls at any call depth, including within
+    /// script-defined functions.  The [`max_call_levels`][Engine::max_call_levels] limit applies
+    /// as usual.  A native function suspending within a method call, a property access,
+    /// custom syntax, `eval` or a function pointer called by another native function
+    /// cannot be resumed, and an error is returned instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Dynamic, Engine, EvalAltResult, Execution, Position, RegisterResultFn, Scope};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_result_fn("wait_for", |event: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+    ///     EvalAltResult::ErrorSuspended(event.into(), Position::NONE).into()
+    /// });
+    ///
+    /// let ast = engine.compile(r#"let x = 40; x += wait_for("door_open"); x"#)?;
+    /// let mut scope = Scope::new();
+    ///
+    /// let continuation = match engine.eval_ast_resumable(&mut scope, &ast)? {
+    ///     Execution::Suspended(continuation) => *continuation,
+    ///     Execution::Completed(_) => unreachable!(),
+    /// };
+    ///
+    /// assert_eq!(continuation.token().clone().cast::<String>(), "door_open");
+    ///
+    /// // Resume later...
+    /// match engine.resume(&mut scope, continuation, Dynamic::from(2_i64))? {
+    ///     Execution::Completed(result) => assert_eq!(result.cast::<i64>(), 42),
+    ///     Execution::Suspended(_) => unreachable!(),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn eval_ast_resumable(
+        &self,
+        scope: &mut Scope,
+        ast: &AST,
+    ) -> Result<Execution, Box<EvalAltResult>> {
+        self.record_coverage(|c| c.register_ast(ast));
+
+        let mut state: State = Default::default();
+        state.source = ast.clone_source();
+        #[cfg(not(feature = "no_module"))]
+        {
+            state.resolver = ast.resolver();
+        }
+
+        let mods: Imports = Default::default();
+
+        self.load_fn_resolution_cache(&mut state, &mods, ast);
+
+        let bytecode = Bytecode::compile(ast);
+        let vm = Vm::new(&bytecode, true);
+
+        let continuation = Continuation {
+            token: Dynamic::UNIT,
+            pos: Position::NONE,
+            bytecode,
+            vm,
+            state,
+            mods,
+        };
+
+        self.run_continuation(scope, Box::new(continuation), None)
+    }
+    /// Resume an evaluation suspended by [`eval_ast_resumable`][Engine::eval_ast_resumable].
+    ///
+    /// The function call that suspended the evaluation returns `value`.
+    ///
+    /// The same [`Scope`] that the evaluation was started with must be passed.
+    #[inline(always)]
+    pub fn resume(
+        &self,
+        scope: &mut Scope,
+        continuation: Continuation,
+        value: Dynamic,
+    ) -> Result<Execution, Box<EvalAltResult>> {
+        self.run_continuation(scope, Box::new(continuation), Some(value))
+    }
+    /// Evaluate compiled [`Bytecode`].
+    ///
+    /// The result is identical to evaluating the [`AST`] it is compiled from.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Bytecode, Engine};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let bytecode = Bytecode::compile(&engine.compile("40 + 2")?);
+    ///
+    /// assert_eq!(engine.eval_bytecode::<i64>(&bytecode)?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline(always)]
+    pub fn eval_bytecode<T: Variant + Clone>(
+        &self,
+        bytecode: &Bytecode,
+    ) -> Result<T, Box<EvalAltResult>> {
+        self.eval_bytecode_with_scope(&mut Default::default(), bytecode)
+    }
+    /// Evaluate compiled [`Bytecode`] with own scope.
+    ///
+    /// The result is identical to evaluating the [`AST`] it is compiled from.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Bytecode, Engine, Scope};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// // Create initialized scope
+    /// let mut scope = Scope::new();
+    /// scope.push("x", 40_i64);
+    ///
+    /// let bytecode = Bytecode::compile(&engine.compile("while x < 42 { x += 1; } x")?);
+    ///
+    /// assert_eq!(engine.eval_bytecode_with_scope::<i64>(&mut scope, &bytecode)?, 42);
+    ///
+    /// // The variable in the scope is modified
+    /// assert_eq!(scope.get_value::<i64>("x").expect("variable x should exist"), 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn eval_bytecode_with_scope<T: Variant + Clone>(
+        &self,
+        scope: &mut Scope,
+        bytecode: &Bytecode,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let ast = bytecode.ast();
+
+        self.record_coverage(|c| c.register_ast(ast));
+
+        let mut state: State = Default::default();
+        state.source = ast.clone_source();
+        #[cfg(not(feature = "no_module"))]
+        {
+            state.resolver = ast.resolver();
+        }
+
+        let mods = &mut Default::default();
+
+        self.load_fn_resolution_cache(&mut state, mods, ast);
+        let result = self.run_bytecode(scope, mods, &mut state, bytecode);
+        self.keep_fn_resolution_cache(&mut state, mods, ast);
+
+        let result = result?;
+        let typ = self.map_type_name(result.type_name());
+
+        result.try_cast::<T>().ok_or_else(|| {
+            EvalAltResult::ErrorMismatchOutputType(
+                self.map_type_name(type_name::<T>()).into(),
+                typ.into(),
+                Position::NONE,
+            )
+            .into()
+        })
+    }
+    /// Evaluate a string asynchronously, awaiting all calls to asynchronous native functions
+    /// registered via [`register_async_fn`][crate::RegisterAsyncFn::register_async_fn].
+    ///
+    /// The returned future is executor-agnostic.
+    ///
+    /// See [`eval_ast_with_scope_async`][Engine::eval_ast_with_scope_async] for details.
+    #[inline(always)]
+    pub async fn eval_async<T: Variant + Clone>(
+        &self,
+        script: &str,
+    ) -> Result<T, Box<EvalAltResult>> {
+        self.eval_with_scope_async(&mut Default::default(), script)
+            .await
+    }
+    /// Evaluate a string with own scope asynchronously, awaiting all calls to asynchronous
+    /// native functions registered via [`register_async_fn`][crate::RegisterAsyncFn::register_async_fn].
+    ///
+    /// The returned future is executor-agnostic.
+    ///
+    /// See [`eval_ast_with_scope_async`][Engine::eval_ast_with_scope_async] for details.
+    #[inline(always)]
+    pub async fn eval_with_scope_async<T: Variant + Clone>(
+        &self,
+        scope: &mut Scope<'_>,
+        script: &str,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let ast = self.compile_with_scope_and_optimization_level(
+            scope,
+            &[script],
+            self.optimization_level,
+        )?;
+        self.eval_ast_with_scope_async(scope, &ast).await
+    }
+    /// Evaluate an [`AST`] with own scope asynchronously, awaiting all calls to asynchronous
+    /// native functions registered via [`register_async_fn`][crate::RegisterAsyncFn::register_async_fn].
+    ///
+    /// The returned future is executor-agnostic.
+    ///
+    /// Evaluation is suspended at each call to an asynchronous native function and resumed right
+    /// after the call when its future completes, via the same mechanism as
+    /// [`eval_ast_resumable`][Engine::eval_ast_resumable], and with the same restrictions.
+    /// Each statement, and each asynchronous native function call, is evaluated only once.
+    ///
+    /// Other suspensions (i.e. not due to asynchronous native functions) are returned as
+    /// [`EvalAltResult::ErrorSuspended`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterAsyncFn, Scope};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_async_fn("fetch", |key: String| async move { Ok(key.len() as i64) });
+    ///
+    /// let ast = engine.compile(r#"let x = fetch("hello"); x + fetch("world!")"#)?;
+    ///
+    /// let mut scope = Scope::new();
+    ///
+    /// let result = futures::executor::block_on(
+    ///     engine.eval_ast_with_scope_async::<i64>(&mut scope, &ast)
+    /// )?;
+    ///
+    /// assert_eq!(result, 11);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn eval_ast_with_scope_async<T: Variant + Clone>(
+        &self,
+        scope: &mut Scope<'_>,
+        ast: &AST,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let execution = self.eval_ast_resumable(scope, ast)?;
+        let result = self.complete_async(scope, execution).await?;
+
+        let typ = self.map_type_name(result.type_name());
+
+        result.try_cast::<T>().ok_or_else(|| {
+            EvalAltResult::ErrorMismatchOutputType(
+                self.map_type_name(type_name::<T>()).into(),
+                typ.into(),
+                Position::NONE,
+            )
+            .into()
+        })
+    }
+    /// Run a resumable evaluation to completion, awaiting each call to an asynchronous native
+    /// function that suspends it.
+    async fn complete_async(&self, scope: &mut Scope<'_>, mut execution: Execution) -> RhaiResult {
+        loop {
+            match execution {
+                Execution::Completed(result) => return Ok(result),
+                Execution::Suspended(mut continuation) => {
+                    let token = crate::stdlib::mem::take(&mut continuation.token);
+                    let value = self.await_async_call(token, continuation.pos).await?;
+                    execution = self.resume(scope, *continuation, value)?;
+                }
+            }
+        }
+    }
+    /// Await a pending call to an asynchronous native function, given the suspension token.
+    ///
+    /// Other suspension tokens are returned as [`EvalAltResult::ErrorSuspended`].
+    async fn await_async_call(&self, token: Dynamic, pos: Position) -> RhaiResult {
+        if !token.is::<AsyncCall>() {
+            return EvalAltResult::ErrorSuspended(token, pos).into();
+        }
+
+        token
+            .cast::<AsyncCall>()
+            .run()
+            .await
+            .map_err(|err| err.fill_position(pos))
     }
     /// Evaluate a file, but throw away the result and only return error (if any).
     /// Useful for when you don't need the result, but still need to keep track of possible errors.
@@ -1604,15 +1940,7 @@ impl Engine {
         ast: &AST,
     ) -> Result<(), Box<EvalAltResult>> {
         let mods = &mut Default::default();
-        let mut state: State = Default::default();
-        state.source = ast.clone_source();
-        #[cfg(not(feature = "no_module"))]
-        {
-            state.resolver = ast.resolver();
-        }
-        let statements = ast.statements();
-        let lib = &[ast.lib()];
-        self.eval_global_statements(scope, mods, &mut state, statements, lib, 0)?;
+        self.eval_ast_with_scope_raw(scope, mods, ast, 0)?;
         Ok(())
     }
     /// Call a script function defined in an [`AST`] with multiple arguments.
@@ -1770,10 +2098,14 @@ impl Engine {
         this_ptr: &mut Option<&mut Dynamic>,
         args: &mut FnCallArgs,
     ) -> RhaiResult {
+        self.record_coverage(|c| c.register_ast(ast));
+
         let state = &mut Default::default();
         let mods = &mut Default::default();
         let lib = &[ast.lib()];
 
+        self.load_fn_resolution_cache(state, mods, ast);
+
         if eval_ast {
             self.eval_global_statements(scope, mods, state, ast.statements(), lib, 0)?;
         }
@@ -1788,7 +2120,7 @@ impl Engine {
             crate::fn_call::ensure_no_data_race(name, args, false)?;
         }
 
-        self.call_script_fn(
+        let result = self.call_script_fn(
             scope,
             mods,
             state,
@@ -1798,7 +2130,115 @@ impl Engine {
             args,
             Position::NONE,
             0,
-        )
+        );
+
+        self.keep_fn_resolution_cache(state, mods, ast);
+        result
+    }
+    /// Call a script function defined in an [`AST`] with multiple arguments asynchronously,
+    /// awaiting all calls to asynchronous native functions registered via
+    /// [`register_async_fn`][crate::RegisterAsyncFn::register_async_fn].
+    /// Arguments are passed as a tuple.
+    ///
+    /// The returned future is executor-agnostic.
+    ///
+    /// The [`AST`] is _not_ evaluated before calling the function.
+    ///
+    /// The function call is suspended at each call to an asynchronous native function and resumed
+    /// right after the call when its future completes.
+    /// See [`eval_ast_resumable`][Engine::eval_ast_resumable] for the restrictions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "no_function"))]
+    /// # {
+    /// use rhai::{Engine, RegisterAsyncFn, Scope};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_async_fn("double", |x: i64| async move { Ok(x * 2) });
+    ///
+    /// let ast = engine.compile("fn add(x, y) { double(x) + double(y) }")?;
+    ///
+    /// let mut scope = Scope::new();
+    ///
+    /// let result = futures::executor::block_on(
+    ///     engine.call_fn_async::<i64>(&mut scope, &ast, "add", (20_i64, 1_i64))
+    /// )?;
+    ///
+    /// assert_eq!(result, 42);
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_function"))]
+    pub async fn call_fn_async<T: Variant + Clone>(
+        &self,
+        scope: &mut Scope<'_>,
+        ast: &AST,
+        name: &str,
+        args: impl crate::fn_args::FuncArgs,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut arg_values: StaticVec<_> = Default::default();
+        args.parse(&mut arg_values);
+
+        self.record_coverage(|c| c.register_ast(ast));
+
+        let bytecode = Bytecode::compile(ast);
+
+        let fn_def = bytecode
+            .ast()
+            .lib()
+            .get_script_fn(name, arg_values.len(), false)
+            .ok_or_else(|| EvalAltResult::ErrorFunctionNotFound(name.into(), Position::NONE))?
+            .clone();
+
+        let mut args: StaticVec<_> = arg_values.iter_mut().collect();
+
+        // Check for data race.
+        if cfg!(not(feature = "no_closure")) {
+            crate::fn_call::ensure_no_data_race(name, args.as_mut(), false)?;
+        }
+
+        let mut state: State = Default::default();
+        let mut mods: Imports = Default::default();
+
+        let execution = match self.start_fn_call(
+            scope,
+            &mut mods,
+            &mut state,
+            &bytecode,
+            &fn_def,
+            args.as_mut(),
+        )? {
+            Ok(vm) => {
+                let continuation = Continuation {
+                    token: Dynamic::UNIT,
+                    pos: Position::NONE,
+                    bytecode,
+                    vm,
+                    state,
+                    mods,
+                };
+                self.run_continuation(scope, Box::new(continuation), None)?
+            }
+            Err(value) => Execution::Completed(value),
+        };
+
+        let result = self.complete_async(scope, execution).await?;
+
+        let typ = self.map_type_name(result.type_name());
+
+        result.try_cast().ok_or_else(|| {
+            EvalAltResult::ErrorMismatchOutputType(
+                self.map_type_name(type_name::<T>()).into(),
+                typ.into(),
+                Position::NONE,
+            )
+            .into()
+        })
     }
     /// Optimize the [`AST`] with constants defined in an external Scope.
     /// An optimized copy of the [`AST`] is returned while the original [`AST`] is consumed.
@@ -1944,6 +2384,45 @@ impl Engine {
         self.progress = Some(Box::new(callback));
         self
     }
+    /// Get a cloneable [`TerminationHandle`][crate::TerminationHandle] which can be used,
+    /// possibly from another thread, to terminate scripts running in this [`Engine`].
+    ///
+    /// All handles obtained from the same [`Engine`] are equivalent.
+    #[inline(always)]
+    pub fn termination_handle(&self) -> crate::TerminationHandle {
+        self.termination.clone()
+    }
+    /// Override the clock used by the `now` function to read the current date and time
+    /// (the system clock in UTC by default).
+    ///
+    /// On WASM targets there is no default clock, and `now` fails unless a clock is set.
+    ///
+    /// Not available under `no_std`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{DateTime, Engine};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // Always return 2021-03-04T05:06:07Z
+    /// engine.set_clock(|| DateTime::new(2021, 3, 4, 5, 6, 7).unwrap());
+    ///
+    /// assert_eq!(engine.eval::<String>("to_string(now())")?, "2021-03-04T05:06:07Z");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    #[inline(always)]
+    pub fn set_clock(
+        &mut self,
+        callback: impl Fn() -> crate::DateTime + SendSync + 'static,
+    ) -> &mut Self {
+        self.clock = Some(Box::new(callback));
+        self
+    }
     /// Override default action of `print` (print to stdout using [`println!`])
     ///
     /// # Example
@@ -1,9 +1,10 @@
 //! Configuration settings for [`Engine`].
 
 use crate::engine::Precedence;
+use crate::fn_native::Locked;
 use crate::stdlib::{format, string::String};
 use crate::token::Token;
-use crate::Engine;
+use crate::{Coverage, Engine};
 
 #[cfg(not(feature = "unchecked"))]
 use crate::stdlib::num::{NonZeroU64, NonZeroUsize};
@@ -39,6 +40,70 @@ impl Engine {
         self.disable_doc_comments = !enable;
         self
     }
+    /// Enable/disable recording of code coverage information.
+    ///
+    /// When enabled, every executed statement and every taken branch of `if` and `switch`
+    /// statements is recorded. Disabling it discards all coverage information recorded so far.
+    #[inline(always)]
+    pub fn enable_coverage(&mut self, enable: bool) -> &mut Self {
+        self.coverage = if enable {
+            Some(Locked::new(Default::default()))
+        } else {
+            None
+        };
+        self
+    }
+    /// Get a snapshot of the code coverage information recorded so far,
+    /// or [`None`] if coverage recording is not enabled.
+    #[inline(always)]
+    pub fn coverage(&self) -> Option<Coverage> {
+        let mut coverage = None;
+        self.record_coverage(|c| coverage = Some(c.clone()));
+        coverage
+    }
+    /// Clear all code coverage information recorded so far.
+    #[inline(always)]
+    pub fn clear_coverage(&self) -> &Self {
+        self.record_coverage(Coverage::clear);
+        self
+    }
+    /// Set the maximum number of results of calls to each script-defined function that are cached
+    /// for memoization (0 to disable memoization, which is the default).
+    ///
+    /// Results are cached by the values of the arguments, which must be hashable,
+    /// and only for pure functions.  A script-defined function is pure if it does not use `this`,
+    /// import modules, capture variables, access modules, use custom syntax, create or call
+    /// function pointers, or call `print`, `debug`, `eval` or `is_def_var`, and all the functions
+    /// it calls (including operators, property getters/setters and indexers) are pure.
+    ///
+    /// Native Rust functions are not pure unless registered via
+    /// [`register_pure_fn`][crate::RegisterFn::register_pure_fn] or
+    /// [`register_pure_result_fn`][crate::RegisterResultFn::register_pure_result_fn],
+    /// or marked via [`Module::update_fn_purity`][crate::Module::update_fn_purity].
+    /// Functions in the built-in packages are pure, except those reading the clock
+    /// (`timestamp`, `elapsed` and `now`).
+    /// Functions of the built-in packages that call back into registered functions (e.g. to format
+    /// values of custom types) assume those functions to be pure.
+    ///
+    /// The cache is discarded whenever functions or modules are registered into the [`Engine`].
+    /// Use [`AST::clear_fn_memo_cache`][crate::AST::clear_fn_memo_cache] to discard it manually.
+    ///
+    /// Not available under `no_function`.
+    #[cfg(not(feature = "no_function"))]
+    #[inline(always)]
+    pub fn set_max_fn_memo_size(&mut self, size: usize) -> &mut Self {
+        self.max_fn_memo_size = size;
+        self
+    }
+    /// The maximum number of results of calls to each script-defined function that are cached
+    /// for memoization (0 if memoization is disabled).
+    ///
+    /// Not available under `no_function`.
+    #[cfg(not(feature = "no_function"))]
+    #[inline(always)]
+    pub fn max_fn_memo_size(&self) -> usize {
+        self.max_fn_memo_size
+    }
     /// Set the maximum levels of function calls allowed for a script in order to avoid
     /// infinite recursion and stack overflows.
     ///
@@ -77,6 +142,61 @@ impl Engine {
     pub fn max_operations(&self) -> u64 {
         self.limits.max_operations.map_or(0, NonZeroU64::get)
     }
+    /// Set the maximum amount of wall-clock time allowed for a script to run (zero for unlimited).
+    ///
+    /// A script running beyond this limit is terminated with
+    /// [`EvalAltResult::ErrorTerminated`][crate::EvalAltResult::ErrorTerminated] with
+    /// [`TerminationReason::TimedOut`][crate::TerminationReason::TimedOut] as the token.
+    ///
+    /// Time spent inside a native Rust function is only checked after the function returns.
+    ///
+    /// Not available under `unchecked` or `no_std`.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    #[inline(always)]
+    pub fn set_max_execution_time(&mut self, duration: crate::stdlib::time::Duration) -> &mut Self {
+        self.limits.max_execution_time = if duration.as_nanos() == 0 {
+            None
+        } else {
+            Some(duration)
+        };
+        self
+    }
+    /// The maximum amount of wall-clock time allowed for a script to run (zero for unlimited).
+    ///
+    /// Not available under `unchecked` or `no_std`.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    #[inline(always)]
+    pub fn max_execution_time(&self) -> crate::stdlib::time::Duration {
+        self.limits.max_execution_time.unwrap_or_default()
+    }
+    /// Set the maximum approximate amount of memory, in bytes, that a script can hold in all its
+    /// variables and temporary values (0 for unlimited).
+    ///
+    /// Memory usage is checked after each statement, counting the variables of all function calls
+    /// in progress. It is tracked incrementally: a variable is measured when it is declared, and
+    /// measured again only after a statement that may modify it.
+    /// Exceeding the limit raises [`EvalAltResult::ErrorDataTooLarge`][crate::EvalAltResult::ErrorDataTooLarge].
+    ///
+    /// Use [`Scope::memory_usage`][crate::Scope::memory_usage] to query the current memory usage
+    /// of all variables in a [`Scope`][crate::Scope].
+    ///
+    /// Not available under `unchecked`.
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    pub fn set_max_memory(&mut self, max_memory: usize) -> &mut Self {
+        self.limits.max_memory = NonZeroUsize::new(max_memory);
+        self
+    }
+    /// The maximum approximate amount of memory, in bytes, that a script can hold (0 for unlimited).
+    ///
+    /// Not available under `unchecked`.
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    pub fn max_memory(&self) -> usize {
+        self.limits.max_memory.map_or(0, NonZeroUsize::get)
+    }
     /// Set the maximum number of imported [modules][crate::Module] allowed for a script.
     ///
     /// Not available under `unchecked` or `no_module`.
@@ -134,6 +254,8 @@ impl Engine {
     }
     /// Set the maximum length of [strings][crate::ImmutableString] (0 for unlimited).
     ///
+    /// Under `bigint`, this also limits the number of decimal digits of big integers.
+    ///
     /// Not available under `unchecked`.
     #[cfg(not(feature = "unchecked"))]
     #[inline(always)]
@@ -187,6 +309,44 @@ impl Engine {
     pub fn max_map_size(&self) -> usize {
         self.limits.max_map_size.map_or(0, NonZeroUsize::get)
     }
+    /// Set the maximum number of instructions in a compiled [regular expression][crate::Regex]
+    /// (0 for unlimited).
+    ///
+    /// Not available under `unchecked`.
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    pub fn set_max_regex_size(&mut self, max_size: usize) -> &mut Self {
+        self.limits.max_regex_size = NonZeroUsize::new(max_size);
+        self
+    }
+    /// The maximum number of instructions in a compiled [regular expression][crate::Regex]
+    /// (0 for unlimited).
+    ///
+    /// Not available under `unchecked`.
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    pub fn max_regex_size(&self) -> usize {
+        self.limits.max_regex_size.map_or(0, NonZeroUsize::get)
+    }
+    /// Set the maximum number of steps taken by a single [regular expression][crate::Regex]
+    /// search, to guard against catastrophic backtracking (0 for unlimited).
+    ///
+    /// Not available under `unchecked`.
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    pub fn set_max_regex_steps(&mut self, steps: u64) -> &mut Self {
+        self.limits.max_regex_steps = NonZeroU64::new(steps);
+        self
+    }
+    /// The maximum number of steps taken by a single [regular expression][crate::Regex]
+    /// search (0 for unlimited).
+    ///
+    /// Not available under `unchecked`.
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    pub fn max_regex_steps(&self) -> u64 {
+        self.limits.max_regex_steps.map_or(0, NonZeroU64::get)
+    }
     /// Set the module resolution service used by the [`Engine`].
     ///
     /// Not available under `no_module`.
@@ -11,6 +11,9 @@ use crate::FLOAT;
 #[cfg(feature = "decimal")]
 use rust_decimal::Decimal;
 
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
 #[cfg(feature = "no_std")]
 #[cfg(not(feature = "no_float"))]
 use num_traits::float::Float;
@@ -36,6 +39,9 @@ fn is_numeric(type_id: TypeId) -> bool {
     #[cfg(feature = "decimal")]
     let result = result || type_id == TypeId::of::<rust_decimal::Decimal>();
 
+    #[cfg(feature = "bigint")]
+    let result = result || type_id == TypeId::of::<num_bigint::BigInt>();
+
     result
 }
 
@@ -126,6 +132,13 @@ pub fn get_builtin_binary_op_fn(
                 Ok(x.$func(y).into())
             })
         };
+        (from $base:ty => $func:ident ( ctx, $xx:ident, $yy:ident )) => {
+            return Some(|ctx, args| {
+                let x = <$base>::from(args[0].$xx().unwrap());
+                let y = <$base>::from(args[1].$yy().unwrap());
+                $func(ctx, x, y)
+            })
+        };
         (from $base:ty => $func:ident ( $xx:ident, $yy:ident )) => {
             return Some(|_, args| {
                 let x = <$base>::from(args[0].$xx().unwrap());
@@ -205,6 +218,40 @@ pub fn get_builtin_binary_op_fn(
     impl_decimal!(Decimal, as_decimal, INT, as_int);
     impl_decimal!(INT, as_int, Decimal, as_decimal);
 
+    macro_rules! impl_bigint {
+        ($x:ty, $xx:ident, $y:ty, $yy:ident) => {
+            #[cfg(feature = "bigint")]
+            if types_pair == (TypeId::of::<$x>(), TypeId::of::<$y>()) {
+                use crate::packages::arithmetic::bigint_functions::*;
+
+                match op {
+                    "+" => impl_op!(from BigInt => $xx + $yy),
+                    "-" => impl_op!(from BigInt => $xx - $yy),
+                    "*" => impl_op!(from BigInt => $xx * $yy),
+                    "/" => impl_op!(from BigInt => divide($xx, $yy)),
+                    "%" => impl_op!(from BigInt => modulo($xx, $yy)),
+                    "**" => impl_op!(from BigInt => power(ctx, $xx, $yy)),
+                    "<<" => impl_op!(from BigInt => shift_left(ctx, $xx, $yy)),
+                    ">>" => impl_op!(from BigInt => shift_right($xx, $yy)),
+                    "&" => impl_op!(from BigInt => $xx & $yy),
+                    "|" => impl_op!(from BigInt => $xx | $yy),
+                    "^" => impl_op!(from BigInt => $xx ^ $yy),
+                    "==" => impl_op!(from BigInt => $xx == $yy),
+                    "!=" => impl_op!(from BigInt => $xx != $yy),
+                    ">" => impl_op!(from BigInt => $xx > $yy),
+                    ">=" => impl_op!(from BigInt => $xx >= $yy),
+                    "<" => impl_op!(from BigInt => $xx < $yy),
+                    "<=" => impl_op!(from BigInt => $xx <= $yy),
+                    _ => return None,
+                }
+            }
+        };
+    }
+
+    impl_bigint!(BigInt, as_bigint, BigInt, as_bigint);
+    impl_bigint!(BigInt, as_bigint, INT, as_int);
+    impl_bigint!(INT, as_int, BigInt, as_bigint);
+
     // char op string
     if types_pair == (TypeId::of::<char>(), TypeId::of::<ImmutableString>()) {
         #[inline(always)]
@@ -281,10 +328,16 @@ pub fn get_builtin_binary_op_fn(
     // map op string
     #[cfg(not(feature = "no_object"))]
     if types_pair == (TypeId::of::<crate::Map>(), TypeId::of::<ImmutableString>()) {
-        use crate::Map;
+        use crate::{Map, MapKey};
 
         match op {
-            OP_CONTAINS => impl_op!(Map.contains_key(ImmutableString)),
+            OP_CONTAINS => {
+                return Some(|_, args| {
+                    let m = &*args[0].read_lock::<Map>().unwrap();
+                    let s = args[1].read_lock::<ImmutableString>().unwrap().clone();
+                    Ok(m.contains_key(&MapKey::Str(s)).into())
+                })
+            }
             _ => return None,
         }
     }
@@ -467,6 +520,13 @@ pub fn get_builtin_op_assignment_fn(
                 Ok((*args[0].write_lock::<$x>().unwrap() = x.$func(y)).into())
             })
         };
+        (from $x:ty => $func:ident ( ctx, $xx:ident, $yy:ident )) => {
+            return Some(|ctx, args| {
+                let x = args[0].$xx().unwrap();
+                let y = <$x>::from(args[1].$yy().unwrap());
+                Ok((*args[0].write_lock().unwrap() = $func(ctx, x, y)?).into())
+            })
+        };
         (from $x:ty => $func:ident ( $xx:ident, $yy:ident )) => {
             return Some(|_, args| {
                 let x = args[0].$xx().unwrap();
@@ -528,6 +588,33 @@ pub fn get_builtin_op_assignment_fn(
     impl_decimal!(Decimal, as_decimal, Decimal, as_decimal);
     impl_decimal!(Decimal, as_decimal, INT, as_int);
 
+    macro_rules! impl_bigint {
+        ($x:ident, $xx:ident, $y:ty, $yy:ident) => {
+            #[cfg(feature = "bigint")]
+            if types_pair == (TypeId::of::<$x>(), TypeId::of::<$y>()) {
+                use crate::packages::arithmetic::bigint_functions::*;
+
+                match op {
+                    "+=" => impl_op!(from $x += $yy),
+                    "-=" => impl_op!(from $x -= $yy),
+                    "*=" => impl_op!(from $x *= $yy),
+                    "/=" => impl_op!(from $x => divide($xx, $yy)),
+                    "%=" => impl_op!(from $x => modulo($xx, $yy)),
+                    "**=" => impl_op!(from $x => power(ctx, $xx, $yy)),
+                    "<<=" => impl_op!(from $x => shift_left(ctx, $xx, $yy)),
+                    ">>=" => impl_op!(from $x => shift_right($xx, $yy)),
+                    "&=" => impl_op!(from $x &= $yy),
+                    "|=" => impl_op!(from $x |= $yy),
+                    "^=" => impl_op!(from $x ^= $yy),
+                    _ => return None,
+                }
+            }
+        };
+    }
+
+    impl_bigint!(BigInt, as_bigint, BigInt, as_bigint);
+    impl_bigint!(BigInt, as_bigint, INT, as_int);
+
     // string op= char
     if types_pair == (TypeId::of::<ImmutableString>(), TypeId::of::<char>()) {
         match op {
@@ -13,6 +13,7 @@ use crate::optimize::OptimizationLevel;
 use crate::stdlib::{
     any::{type_name, TypeId},
     boxed::Box,
+    cell::Cell,
     convert::TryFrom,
     format,
     iter::{empty, once},
@@ -27,9 +28,12 @@ use crate::{
 };
 use crate::{
     calc_fn_hash, calc_fn_params_hash, combine_hashes, Dynamic, Engine, EvalAltResult, FnPtr,
-    ImmutableString, Module, ParseErrorType, Position, Scope, StaticVec,
+    ImmutableString, Module, NativeCallContext, ParseErrorType, Position, Scope, StaticVec,
 };
 
+#[cfg(not(feature = "no_function"))]
+use crate::engine::EvalEnv;
+
 #[cfg(not(feature = "no_object"))]
 use crate::Map;
 
@@ -144,9 +148,40 @@ pub fn ensure_no_data_race(
     Ok(())
 }
 
+/// State saved when a call to a script-defined function starts, restored when it ends.
+#[cfg(not(feature = "no_function"))]
+#[derive(Debug, Clone)]
+pub(crate) struct ScriptFnFrame {
+    /// Level of the scope of the caller.
+    scope_level: usize,
+    /// Number of variables in the scope before the arguments are put into it.
+    pub scope_len: usize,
+    /// Number of imported modules of the caller.
+    mods_len: usize,
+    /// Address of the script-defined function being evaluated by the caller, if any.
+    fn_def: Option<usize>,
+    /// Hash and values of the arguments to cache the result with, if it can be cached.
+    memo: Option<(u64, StaticVec<Dynamic>)>,
+}
+
+/// Put the argument values of a call to a script-defined function into a [`Scope`] as variables.
+#[cfg(not(feature = "no_function"))]
+#[inline(always)]
+pub(crate) fn push_params<'s>(
+    scope: &mut Scope<'s>,
+    fn_def: &crate::ast::ScriptFnDef,
+    values: impl Iterator<Item = Dynamic>,
+) {
+    scope.extend(fn_def.params.iter().zip(values).map(|(name, value)| {
+        let var_name: crate::stdlib::borrow::Cow<'s, str> =
+            crate::r#unsafe::unsafe_cast_var_name_to_lifetime(name).into();
+        (var_name, value)
+    }));
+}
+
 impl Engine {
     /// Generate the signature for a function call.
-    fn gen_call_signature(
+    pub(crate) fn gen_call_signature(
         &self,
         namespace: Option<&NamespaceRef>,
         fn_name: &str,
@@ -176,7 +211,7 @@ impl Engine {
     /// 4) Imported modules - functions marked with global namespace
     /// 5) Global sub-modules - functions marked with global namespace
     #[inline(always)]
-    fn resolve_function<'s>(
+    pub(crate) fn resolve_function<'s>(
         &self,
         mods: &Imports,
         state: &'s mut State,
@@ -339,6 +374,10 @@ impl Engine {
 
         let state_source = state.source.clone();
 
+        // Operations performed by the function count towards the total
+        let operations = Cell::new(state.operations);
+        let generation = self.generation(state);
+
         // Check if function access already in the cache
         let func = self.resolve_function(
             mods,
@@ -366,11 +405,12 @@ impl Engine {
                 .as_ref()
                 .or_else(|| state_source.as_ref())
                 .map(|s| s.as_str());
+            let context = NativeCallContext::from((self, fn_name, source, mods, lib))
+                .with_operations(&operations, generation);
             let result = if func.is_plugin_fn() {
-                func.get_plugin_fn()
-                    .call((self, fn_name, source, mods, lib).into(), args)
+                func.get_plugin_fn().call(context, args)
             } else {
-                func.get_native_fn()((self, fn_name, source, mods, lib).into(), args)
+                func.get_native_fn()(context, args)
             };
 
             // Restore the original reference
@@ -378,6 +418,10 @@ impl Engine {
                 backup.restore_first_arg(args);
             }
 
+            let is_method = func.is_method();
+
+            state.operations = operations.get();
+
             let result = result.map_err(|err| err.fill_position(pos))?;
 
             // See if the function match print/debug (which requires special processing)
@@ -403,7 +447,7 @@ impl Engine {
                     let source = state.source.as_ref().map(|s| s.as_str());
                     ((self.debug)(&text, source, pos).into(), false)
                 }
-                _ => (result, func.is_method()),
+                _ => (result, is_method),
             });
         }
 
@@ -492,72 +536,129 @@ impl Engine {
         pos: Position,
         level: usize,
     ) -> RhaiResult {
-        #[inline(always)]
-        fn make_error(
-            name: crate::stdlib::string::String,
-            fn_def: &crate::ast::ScriptFnDef,
-            state: &State,
-            err: Box<EvalAltResult>,
-            pos: Position,
-        ) -> RhaiResult {
-            Err(Box::new(EvalAltResult::ErrorInFunctionCall(
-                name,
-                fn_def
-                    .lib
-                    .as_ref()
-                    .and_then(|m| m.id())
-                    .unwrap_or_else(|| state.source.as_ref().map_or_else(|| "", |s| s.as_str()))
-                    .to_string(),
-                err,
-                pos,
-            )))
-        }
+        let env = &mut EvalEnv {
+            scope,
+            mods,
+            state,
+            lib,
+        };
+
+        let frame = match self.enter_script_fn(env, this_ptr.is_some(), fn_def, args, pos, level)? {
+            Ok(frame) => frame,
+            Err(value) => return Ok(value),
+        };
+
+        // Merge in encapsulated environment, if any
+        let lib_merged;
+
+        let unified_lib = if let Some(ref env_lib) = fn_def.lib {
+            lib_merged = once(env_lib.as_ref())
+                .chain(lib.iter().cloned())
+                .collect::<StaticVec<_>>();
+            lib_merged.as_ref()
+        } else {
+            lib
+        };
+
+        // Evaluate the function
+        let body = &fn_def.body.statements;
+
+        let result = loop {
+            let result =
+                self.eval_stmt_block(scope, mods, state, unified_lib, this_ptr, body, true, level);
+            let result = self.map_script_fn_result(state, fn_def, result, pos);
+
+            match state.tail_call_args.take() {
+                // Tail call - evaluate the function again with the new arguments
+                // instead of recursing
+                Some(values) if result.is_ok() => {
+                    self.rewind_scope(scope, state, frame.scope_len);
+                    push_params(scope, fn_def, values.into_iter());
+                }
+                _ => break result,
+            }
+        };
+
+        self.leave_script_fn(scope, mods, state, fn_def, frame, result)
+    }
+
+    /// Start a call to a script-defined function, putting the arguments into the [`Scope`].
+    ///
+    /// Returns the result instead, as `Ok(Err(value))`, if the function need not be evaluated
+    /// (e.g. because the result of a previous call with the same arguments is kept).
+    ///
+    /// # WARNING
+    ///
+    /// All function call arguments are _consumed_.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn enter_script_fn(
+        &self,
+        env: &mut EvalEnv,
+        is_method: bool,
+        fn_def: &crate::ast::ScriptFnDef,
+        args: &mut FnCallArgs,
+        pos: Position,
+        level: usize,
+    ) -> Result<Result<ScriptFnFrame, Dynamic>, Box<EvalAltResult>> {
+        let EvalEnv {
+            scope,
+            mods,
+            state,
+            lib,
+        } = env;
 
         self.inc_operations(state, pos)?;
 
         if fn_def.body.is_empty() {
-            return Ok(Dynamic::UNIT);
+            return Ok(Err(Dynamic::UNIT));
         }
 
+        // Look up the result of a previous call with the same arguments
+        let memo_hash = if self.max_fn_memo_size > 0 && !is_method {
+            self.calc_fn_memo_hash(mods, fn_def, lib, args)
+        } else {
+            None
+        };
+
+        if let Some(value) = memo_hash.and_then(|hash| fn_def.memo.get(hash, args)) {
+            return Ok(Err(value));
+        }
+
+        // Keep the argument values to cache the result with
+        let memo =
+            memo_hash.map(|hash| (hash, args.iter().map(|arg| arg.flatten_clone()).collect()));
+
         // Check for stack overflow
-        #[cfg(not(feature = "no_function"))]
         #[cfg(not(feature = "unchecked"))]
         if level > self.max_call_levels() {
             return Err(Box::new(EvalAltResult::ErrorStackOverflow(pos)));
         }
 
-        let orig_scope_level = state.scope_level;
+        let frame = ScriptFnFrame {
+            scope_level: state.scope_level,
+            scope_len: scope.len(),
+            mods_len: mods.len(),
+            fn_def: state.fn_def.replace(fn_def as *const _ as usize),
+            memo,
+        };
+
         state.scope_level += 1;
 
-        let prev_scope_len = scope.len();
-        let prev_mods_len = mods.len();
+        #[cfg(not(feature = "unchecked"))]
+        if self.limits.max_memory.is_some() {
+            state.memory.push_frame();
+        }
+
+        state.push_var_lookup_cache();
 
         // Put arguments into scope as variables
         // Actually consume the arguments instead of cloning them
-        scope.extend(
-            fn_def
-                .params
-                .iter()
-                .zip(args.iter_mut().map(|v| mem::take(*v)))
-                .map(|(name, value)| {
-                    let var_name: crate::stdlib::borrow::Cow<'_, str> =
-                        crate::r#unsafe::unsafe_cast_var_name_to_lifetime(name).into();
-                    (var_name, value)
-                }),
-        );
+        push_params(scope, fn_def, args.iter_mut().map(|v| mem::take(*v)));
 
-        // Merge in encapsulated environment, if any
-        let lib_merged;
-
-        let (unified_lib, unified) = if let Some(ref env_lib) = fn_def.lib {
+        // Functions resolve differently within an encapsulated environment
+        if fn_def.lib.is_some() {
             state.push_fn_resolution_cache();
-            lib_merged = once(env_lib.as_ref())
-                .chain(lib.iter().cloned())
-                .collect::<StaticVec<_>>();
-            (lib_merged.as_ref(), true)
-        } else {
-            (lib, false)
-        };
+        }
 
         #[cfg(not(feature = "no_module"))]
         if !fn_def.mods.is_empty() {
@@ -567,47 +668,170 @@ impl Engine {
                 .for_each(|(n, m)| mods.push(n.clone(), m.clone()));
         }
 
-        // Evaluate the function
-        let body = &fn_def.body.statements;
+        Ok(Ok(frame))
+    }
 
-        let result = self
-            .eval_stmt_block(scope, mods, state, unified_lib, this_ptr, body, true, level)
-            .or_else(|err| match *err {
-                // Convert return statement to return value
-                EvalAltResult::Return(x, _) => Ok(x),
-                // Error in sub function call
-                EvalAltResult::ErrorInFunctionCall(name, src, err, _) => {
-                    let fn_name = if src.is_empty() {
-                        format!("{} < {}", name, fn_def.name)
-                    } else {
-                        format!("{} @ '{}' < {}", name, src, fn_def.name)
-                    };
+    /// Map the result of evaluating the body of a script-defined function into the result of
+    /// the function call.
+    #[cfg(not(feature = "no_function"))]
+    #[inline(always)]
+    pub(crate) fn map_script_fn_result(
+        &self,
+        state: &State,
+        fn_def: &crate::ast::ScriptFnDef,
+        result: RhaiResult,
+        pos: Position,
+    ) -> RhaiResult {
+        let lib_id = fn_def.lib.as_ref().and_then(|m| m.id());
+        self.map_fn_body_result(state, &fn_def.name, lib_id, result, pos)
+    }
 
-                    make_error(fn_name, fn_def, state, err, pos)
-                }
-                // System errors are passed straight-through
-                mut err if err.is_system_exception() => Err(Box::new({
-                    err.set_position(pos);
-                    err
-                })),
-                // Other errors are wrapped in `ErrorInFunctionCall`
-                _ => make_error(fn_def.name.to_string(), fn_def, state, err, pos),
-            });
+    /// Map the result of evaluating the body of a script-defined function (with the ID of its
+    /// encapsulated environment, if any) into the result of the function call.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn map_fn_body_result(
+        &self,
+        state: &State,
+        fn_name: &str,
+        lib_id: Option<&str>,
+        result: RhaiResult,
+        pos: Position,
+    ) -> RhaiResult {
+        let make_error = |name: crate::stdlib::string::String, err: Box<EvalAltResult>| {
+            Err(Box::new(EvalAltResult::ErrorInFunctionCall(
+                name,
+                lib_id
+                    .unwrap_or_else(|| state.source.as_ref().map_or_else(|| "", |s| s.as_str()))
+                    .to_string(),
+                err,
+                pos,
+            )))
+        };
 
+        result.or_else(|err| match *err {
+            // Convert return statement to return value
+            EvalAltResult::Return(x, _) => Ok(x),
+            // Error in sub function call
+            EvalAltResult::ErrorInFunctionCall(name, src, err, _) => {
+                let fn_name = if src.is_empty() {
+                    format!("{} < {}", name, fn_name)
+                } else {
+                    format!("{} @ '{}' < {}", name, src, fn_name)
+                };
+
+                make_error(fn_name, err)
+            }
+            // System errors are passed straight-through
+            mut err if err.is_system_exception() => Err(Box::new({
+                err.set_position(pos);
+                err
+            })),
+            // Other errors are wrapped in `ErrorInFunctionCall`
+            _ => make_error(fn_name.to_string(), err),
+        })
+    }
+
+    /// End a call to a script-defined function, restoring the state saved when it started.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn leave_script_fn(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        fn_def: &crate::ast::ScriptFnDef,
+        frame: ScriptFnFrame,
+        result: RhaiResult,
+    ) -> RhaiResult {
         // Remove all local variables
-        scope.rewind(prev_scope_len);
-        mods.truncate(prev_mods_len);
-        state.scope_level = orig_scope_level;
+        scope.rewind(frame.scope_len);
+        mods.truncate(frame.mods_len);
 
-        if unified {
+        #[cfg(not(feature = "unchecked"))]
+        if self.limits.max_memory.is_some() {
+            state.memory.pop_frame();
+        }
+        state.scope_level = frame.scope_level;
+        state.fn_def = frame.fn_def;
+        state.pop_var_lookup_cache();
+
+        if fn_def.lib.is_some() {
             state.pop_fn_resolution_cache();
         }
 
+        // Cache the result for memoization
+        match (frame.memo, &result) {
+            (Some((hash, memo_args)), Ok(value)) if !value.is_shared() => {
+                fn_def
+                    .memo
+                    .put(hash, memo_args, value.clone(), self.max_fn_memo_size)
+            }
+            _ => (),
+        }
+
         result
     }
 
-    // Does a scripted function exist?
+    /// Calculate the hash of the argument values of a call to a script-defined function,
+    /// for memoization of its result.
+    ///
+    /// Returns [`None`] if the result cannot be cached.
     #[cfg(not(feature = "no_function"))]
+    fn calc_fn_memo_hash(
+        &self,
+        mods: &Imports,
+        fn_def: &crate::ast::ScriptFnDef,
+        lib: &[&Module],
+        args: &FnCallArgs,
+    ) -> Option<u64> {
+        use crate::stdlib::hash::{Hash, Hasher};
+
+        // Functions in imported modules may be called without a namespace,
+        // and they may not be pure
+        if mods.contains_indexed_global_functions() {
+            return None;
+        }
+        #[cfg(not(feature = "no_module"))]
+        if fn_def.mods.contains_indexed_global_functions() {
+            return None;
+        }
+
+        let is_memoizable = if let Some(ref env_lib) = fn_def.lib {
+            let lib = once(env_lib.as_ref())
+                .chain(lib.iter().cloned())
+                .collect::<StaticVec<_>>();
+            fn_def.memo.is_memoizable(self, fn_def, &lib)
+        } else {
+            fn_def.memo.is_memoizable(self, fn_def, lib)
+        };
+
+        if !is_memoizable || !args.iter().all(|arg| arg.is_hashable()) {
+            return None;
+        }
+
+        let hasher = &mut crate::utils::get_hasher();
+        args.iter().for_each(|arg| {
+            arg.type_id().hash(hasher);
+            arg.hash(hasher);
+        });
+        Some(hasher.finish())
+    }
+
+    /// Are all native Rust functions with a particular name, which a script may call without
+    /// a namespace, pure?
+    pub(crate) fn is_pure_native_fn(&self, lib: &[&Module], name: &str) -> bool {
+        lib.iter().all(|m| m.is_pure_native_fn(name))
+            && self.global_namespace.is_pure_native_fn(name)
+            && self
+                .global_modules
+                .iter()
+                .all(|m| m.is_pure_native_fn(name))
+            && self
+                .global_sub_modules
+                .values()
+                .all(|m| m.is_pure_native_fn(name))
+    }
+
+    // Does a scripted function exist?
     #[inline(always)]
     pub(crate) fn has_script_fn(
         &self,
@@ -640,41 +864,22 @@ impl Engine {
         result
     }
 
-    /// Perform an actual function call, native Rust or scripted, taking care of special functions.
-    ///
-    /// # WARNING
-    ///
-    /// Function call arguments may be _consumed_ when the function requires them to be passed by value.
-    /// All function arguments not in the first position are always passed by value and thus consumed.
-    /// **DO NOT** reuse the argument values unless for the first `&mut` argument - all others are silently replaced by `()`!
-    pub(crate) fn exec_fn_call(
+    /// Call a function with a keyword name (e.g. `type_of`), which may be redirected from a method
+    /// style call, returning [`None`] if it is not such a function.
+    pub(crate) fn exec_keyword_fn_call(
         &self,
-        mods: &mut Imports,
+        mods: &Imports,
         state: &mut State,
         lib: &[&Module],
         fn_name: &str,
-        hash: FnHash,
-        args: &mut FnCallArgs,
-        is_ref: bool,
-        _is_method: bool,
+        args: &FnCallArgs,
         pos: Position,
-        _capture_scope: Option<Scope>,
-        _level: usize,
-    ) -> Result<(Dynamic, bool), Box<EvalAltResult>> {
-        // Check for data race.
-        if cfg!(not(feature = "no_closure")) {
-            ensure_no_data_race(fn_name, args, is_ref)?;
-        }
-
-        // These may be redirected from method style calls.
+    ) -> Result<Option<Dynamic>, Box<EvalAltResult>> {
         match fn_name {
             // Handle type_of()
-            KEYWORD_TYPE_OF if args.len() == 1 => {
-                return Ok((
-                    self.map_type_name(args[0].type_name()).to_string().into(),
-                    false,
-                ));
-            }
+            KEYWORD_TYPE_OF if args.len() == 1 => Ok(Some(
+                self.map_type_name(args[0].type_name()).to_string().into(),
+            )),
 
             // Handle is_def_fn()
             #[cfg(not(feature = "no_function"))]
@@ -684,22 +889,19 @@ impl Engine {
                 let fn_name = args[0].read_lock::<ImmutableString>().unwrap();
                 let num_params = args[1].as_int().unwrap();
 
-                return Ok((
-                    if num_params < 0 {
-                        Dynamic::FALSE
-                    } else {
-                        let hash_script = calc_fn_hash(empty(), &fn_name, num_params as usize);
-                        self.has_script_fn(Some(mods), state, lib, hash_script)
-                            .into()
-                    },
-                    false,
-                ));
+                Ok(Some(if num_params < 0 {
+                    Dynamic::FALSE
+                } else {
+                    let hash_script = calc_fn_hash(empty(), &fn_name, num_params as usize);
+                    self.has_script_fn(Some(mods), state, lib, hash_script)
+                        .into()
+                }))
             }
 
             // Handle is_shared()
             #[cfg(not(feature = "no_closure"))]
             crate::engine::KEYWORD_IS_SHARED if args.len() == 1 => {
-                return Err(Box::new(EvalAltResult::ErrorRuntime(
+                Err(Box::new(EvalAltResult::ErrorRuntime(
                     format!(
                         "'{}' should not be called this way. Try {}(...);",
                         fn_name, fn_name
@@ -710,7 +912,7 @@ impl Engine {
             }
 
             KEYWORD_FN_PTR | KEYWORD_EVAL | KEYWORD_IS_DEF_VAR if args.len() == 1 => {
-                return Err(Box::new(EvalAltResult::ErrorRuntime(
+                Err(Box::new(EvalAltResult::ErrorRuntime(
                     format!(
                         "'{}' should not be called this way. Try {}(...);",
                         fn_name, fn_name
@@ -721,7 +923,7 @@ impl Engine {
             }
 
             KEYWORD_FN_PTR_CALL | KEYWORD_FN_PTR_CURRY if !args.is_empty() => {
-                return Err(Box::new(EvalAltResult::ErrorRuntime(
+                Err(Box::new(EvalAltResult::ErrorRuntime(
                     format!(
                         "'{}' should not be called this way. Try {}(...);",
                         fn_name, fn_name
@@ -731,7 +933,39 @@ impl Engine {
                 )))
             }
 
-            _ => (),
+            _ => Ok(None),
+        }
+    }
+
+    /// Perform an actual function call, native Rust or scripted, taking care of special functions.
+    ///
+    /// # WARNING
+    ///
+    /// Function call arguments may be _consumed_ when the function requires them to be passed by value.
+    /// All function arguments not in the first position are always passed by value and thus consumed.
+    /// **DO NOT** reuse the argument values unless for the first `&mut` argument - all others are silently replaced by `()`!
+    pub(crate) fn exec_fn_call(
+        &self,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        fn_name: &str,
+        hash: FnHash,
+        args: &mut FnCallArgs,
+        is_ref: bool,
+        _is_method: bool,
+        pos: Position,
+        _capture_scope: Option<Scope>,
+        _level: usize,
+    ) -> Result<(Dynamic, bool), Box<EvalAltResult>> {
+        // Check for data race.
+        if cfg!(not(feature = "no_closure")) {
+            ensure_no_data_race(fn_name, args, is_ref)?;
+        }
+
+        // These may be redirected from method style calls.
+        if let Some(value) = self.exec_keyword_fn_call(mods, state, lib, fn_name, args, pos)? {
+            return Ok((value, false));
         }
 
         // Scripted function call?
@@ -866,7 +1100,7 @@ impl Engine {
 
     /// Evaluate a text script in place - used primarily for 'eval'.
     #[inline]
-    fn eval_script_expr_in_place(
+    pub(crate) fn eval_script_expr_in_place(
         &self,
         scope: &mut Scope,
         mods: &mut Imports,
@@ -900,6 +1134,7 @@ impl Engine {
         let mut new_state: State = Default::default();
         new_state.source = state.source.clone();
         new_state.operations = state.operations;
+        new_state.generation = state.generation;
 
         let result =
             self.eval_global_statements(scope, mods, &mut new_state, ast.statements(), lib, level);
@@ -1032,7 +1267,7 @@ impl Engine {
                 // Check if it is a map method call in OOP style
                 #[cfg(not(feature = "no_object"))]
                 if let Some(map) = obj.read_lock::<Map>() {
-                    if let Some(val) = map.get(fn_name) {
+                    if let Some(val) = map.get(&crate::MapKey::from(fn_name)) {
                         if let Some(fn_ptr) = val.read_lock::<FnPtr>() {
                             // Remap the function name
                             _redirected = fn_ptr.get_fn_name().clone();
@@ -1286,8 +1521,14 @@ impl Engine {
                     .map(|expr| self.eval_expr(scope, mods, state, lib, this_ptr, expr, level))
                     .collect::<Result<_, _>>()?;
 
-                let (mut target, pos) =
-                    self.search_namespace(scope, mods, state, lib, this_ptr, &args_expr[0])?;
+                let (mut target, pos) = self.search_namespace(
+                    scope,
+                    mods,
+                    state,
+                    lib,
+                    this_ptr,
+                    args_expr[0].as_variable(),
+                )?;
 
                 if target.as_ref().is_read_only() {
                     target = target.into_owned();
@@ -1316,12 +1557,111 @@ impl Engine {
             }
         }
 
-        let args = args.as_mut();
+        let (result, modified) = self.exec_fn_call(
+            mods,
+            state,
+            lib,
+            name,
+            hash,
+            args.as_mut(),
+            is_ref,
+            false,
+            pos,
+            capture,
+            level,
+        )?;
+
+        // Release the reference to the first argument before measuring it again
+        drop(args);
+
+        if is_ref && modified {
+            self.set_var_modified(scope, state, args_expr[0].as_variable());
+        }
+
+        Ok(result)
+    }
 
-        self.exec_fn_call(
-            mods, state, lib, name, hash, args, is_ref, false, pos, capture, level,
-        )
-        .map(|(v, _)| v)
+    /// Call the script-defined function being evaluated, from a function call in tail position.
+    ///
+    /// Instead of calling the function recursively, the argument values are kept in the [`State`]
+    /// for [`call_script_fn`][Engine::call_script_fn] to evaluate the function again with.
+    /// If the call does not resolve to the function being evaluated, it is made normally.
+    ///
+    /// An error raised within the function is therefore wrapped in a single
+    /// [`ErrorInFunctionCall`][EvalAltResult::ErrorInFunctionCall] for the outermost call,
+    /// instead of once for each tail call.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn make_tail_call(
+        &self,
+        env: &mut EvalEnv,
+        this_ptr: &mut Option<&mut Dynamic>,
+        x: &crate::ast::FnCallExpr,
+        pos: Position,
+        level: usize,
+    ) -> RhaiResult {
+        let EvalEnv {
+            scope,
+            mods,
+            state,
+            lib,
+        } = env;
+
+        let crate::ast::FnCallExpr {
+            name,
+            hash,
+            args: args_expr,
+            capture,
+            ..
+        } = x;
+
+        let fn_def = state.fn_def;
+
+        let is_same_fn = match self.resolve_function(
+            mods,
+            state,
+            lib,
+            name,
+            hash.script_hash(),
+            None,
+            false,
+            false,
+        ) {
+            Some(FnResolutionCacheEntry { func, .. }) if func.is_script() => {
+                fn_def == Some(func.get_fn_def().as_ref() as *const _ as usize)
+            }
+            _ => false,
+        };
+
+        if !is_same_fn {
+            return self.make_function_call(
+                scope, mods, state, lib, this_ptr, name, args_expr, *hash, pos, *capture, level,
+            );
+        }
+
+        let mut values = args_expr
+            .iter()
+            .map(|expr| self.eval_expr(scope, mods, state, lib, this_ptr, expr, level))
+            .collect::<Result<StaticVec<_>, _>>()?;
+
+        // A variable as the first argument is passed as a copy, the same as a normal function call
+        if !args_expr.is_empty() && args_expr[0].get_variable_access(false).is_some() {
+            values[0] = mem::take(&mut values[0]).flatten();
+        }
+
+        // Check for data race.
+        if cfg!(not(feature = "no_closure")) {
+            ensure_no_data_race(
+                name,
+                values.iter_mut().collect::<StaticVec<_>>().as_mut(),
+                false,
+            )?;
+        }
+
+        self.inc_operations(state, pos)?;
+
+        state.tail_call_args = Some(values);
+
+        Ok(Dynamic::UNIT)
     }
 
     /// Call a namespace-qualified function in normal function-call style.
@@ -1369,8 +1709,14 @@ impl Engine {
                     .collect::<Result<_, _>>()?;
 
                 // Get target reference to first argument
-                let (target, pos) =
-                    self.search_scope_only(scope, mods, state, lib, this_ptr, &args_expr[0])?;
+                let (target, pos) = self.search_scope_only(
+                    scope,
+                    mods,
+                    state,
+                    lib,
+                    this_ptr,
+                    args_expr[0].as_variable(),
+                )?;
 
                 self.inc_operations(state, pos)?;
 
@@ -1413,6 +1759,8 @@ impl Engine {
             r => r,
         };
 
+        let is_ref = first_arg_value.is_some();
+
         // Clone first argument if the function is not a method after-all
         if let Some(first) = first_arg_value {
             if !func.map(|f| f.is_method()).unwrap_or(true) {
@@ -1422,7 +1770,9 @@ impl Engine {
             }
         }
 
-        match func {
+        let is_method = matches!(func, Some(f) if f.is_method());
+
+        let result = match func {
             #[cfg(not(feature = "no_function"))]
             Some(f) if f.is_script() => {
                 let fn_def = f.get_fn_def();
@@ -1448,20 +1798,19 @@ impl Engine {
                 }
             }
 
-            Some(f) if f.is_plugin_fn() => f
-                .get_plugin_fn()
-                .clone()
-                .call(
-                    (self, fn_name, module.id(), &*mods, lib).into(),
-                    args.as_mut(),
-                )
-                .map_err(|err| err.fill_position(pos)),
-
-            Some(f) if f.is_native() => f.get_native_fn()(
-                (self, fn_name, module.id(), &*mods, lib).into(),
-                args.as_mut(),
-            )
-            .map_err(|err| err.fill_position(pos)),
+            Some(f) if f.is_plugin_fn() || f.is_native() => {
+                let operations = Cell::new(state.operations);
+                let generation = self.generation(state);
+                let context = NativeCallContext::from((self, fn_name, module.id(), &*mods, lib))
+                    .with_operations(&operations, generation);
+                let result = if f.is_plugin_fn() {
+                    f.get_plugin_fn().clone().call(context, args.as_mut())
+                } else {
+                    f.get_native_fn()(context, args.as_mut())
+                };
+                state.operations = operations.get();
+                result.map_err(|err| err.fill_position(pos))
+            }
 
             Some(f) => unreachable!("unknown function type: {:?}", f),
 
@@ -1470,6 +1819,15 @@ impl Engine {
                 pos,
             )
             .into(),
+        };
+
+        // Release the reference to the first argument before measuring it again
+        drop(args);
+
+        if is_ref && is_method {
+            self.set_var_modified(scope, state, args_expr[0].as_variable());
         }
+
+        result
     }
 }
@@ -5,19 +5,25 @@ use crate::engine::Imports;
 use crate::plugin::PluginFunction;
 use crate::stdlib::{
     boxed::Box,
+    cell::Cell,
     convert::{TryFrom, TryInto},
     fmt,
+    future::Future,
     iter::empty,
     mem,
+    pin::Pin,
     string::String,
     vec::Vec,
 };
 use crate::token::is_valid_identifier;
 use crate::{
     calc_fn_hash, Dynamic, Engine, EvalAltResult, EvalContext, ImmutableString, Module, Position,
-    RhaiResult,
+    RhaiResult, StaticVec,
 };
 
+#[cfg(not(feature = "no_index"))]
+use crate::engine::State;
+
 /// Trait that maps to `Send + Sync` only under the `sync` feature.
 #[cfg(feature = "sync")]
 pub trait SendSync: Send + Sync {}
@@ -40,11 +46,9 @@ pub use crate::stdlib::rc::Rc as Shared;
 pub use crate::stdlib::sync::Arc as Shared;
 
 /// Synchronized shared object.
-#[cfg(not(feature = "no_closure"))]
 #[cfg(not(feature = "sync"))]
 pub use crate::stdlib::cell::RefCell as Locked;
 /// Synchronized shared object.
-#[cfg(not(feature = "no_closure"))]
 #[cfg(feature = "sync")]
 pub use crate::stdlib::sync::RwLock as Locked;
 
@@ -56,6 +60,7 @@ pub struct NativeCallContext<'a> {
     source: Option<&'a str>,
     mods: Option<&'a Imports>,
     lib: &'a [&'a Module],
+    operations: Option<(&'a Cell<u64>, u64)>,
 }
 
 impl<'a, M: AsRef<[&'a Module]> + ?Sized>
@@ -69,6 +74,7 @@ impl<'a, M: AsRef<[&'a Module]> + ?Sized>
             source: value.2,
             mods: Some(value.3),
             lib: value.4.as_ref(),
+            operations: None,
         }
     }
 }
@@ -84,6 +90,7 @@ impl<'a, M: AsRef<[&'a Module]> + ?Sized> From<(&'a Engine, &'a str, &'a M)>
             source: None,
             mods: None,
             lib: value.2.as_ref(),
+            operations: None,
         }
     }
 }
@@ -98,6 +105,7 @@ impl<'a> NativeCallContext<'a> {
             source: None,
             mods: None,
             lib,
+            operations: None,
         }
     }
     /// _(INTERNALS)_ Create a new [`NativeCallContext`].
@@ -118,8 +126,17 @@ impl<'a> NativeCallContext<'a> {
             source: source.clone(),
             mods: Some(imports),
             lib,
+            operations: None,
         }
     }
+    /// Count the operations of calls made within this context towards a running total,
+    /// normally that of the calling script, and terminate them together with the script
+    /// (identified by its generation for the [`TerminationHandle`][crate::TerminationHandle]).
+    #[inline(always)]
+    pub(crate) fn with_operations(mut self, operations: &'a Cell<u64>, generation: u64) -> Self {
+        self.operations = Some((operations, generation));
+        self
+    }
     /// The current [`Engine`].
     #[inline(always)]
     pub fn engine(&self) -> &Engine {
@@ -170,6 +187,21 @@ impl<'a> NativeCallContext<'a> {
     pub fn namespaces(&self) -> &[&Module] {
         self.lib
     }
+    /// Count a number of operations performed by the native function, e.g. one for each item
+    /// processed in a loop, towards the limit on the number of operations of the calling script.
+    ///
+    /// Does nothing if the function is not called from a script.
+    #[inline]
+    pub fn inc_operations(&self, num: u64) -> Result<(), Box<EvalAltResult>> {
+        match self.operations {
+            Some((operations, generation)) => {
+                operations.set(operations.get() + num);
+                self.engine
+                    .check_operations(operations.get(), generation, Position::NONE)
+            }
+            None => Ok(()),
+        }
+    }
     /// Call a function inside the call context.
     ///
     /// # WARNING
@@ -198,10 +230,18 @@ impl<'a> NativeCallContext<'a> {
             FnHash::from_script(calc_fn_hash(empty(), fn_name, args.len()))
         };
 
-        self.engine()
+        // Operations count towards those of the caller
+        let mut state: crate::engine::State = Default::default();
+        if let Some((operations, generation)) = self.operations {
+            state.operations = operations.get();
+            state.generation = Some(generation);
+        }
+
+        let result = self
+            .engine()
             .exec_fn_call(
                 &mut self.mods.cloned().unwrap_or_default(),
-                &mut Default::default(),
+                &mut state,
                 self.lib,
                 fn_name,
                 hash,
@@ -213,6 +253,24 @@ impl<'a> NativeCallContext<'a> {
                 0,
             )
             .map(|(r, _)| r)
+            .map_err(|err| match *err {
+                // The evaluation cannot be resumed from within the native function
+                EvalAltResult::ErrorSuspended(_, _) => {
+                    Box::new(EvalAltResult::ErrorInFunctionCall(
+                        fn_name.to_string(),
+                        Default::default(),
+                        err,
+                        Position::NONE,
+                    ))
+                }
+                _ => err,
+            });
+
+        if let Some((operations, _)) = self.operations {
+            operations.set(state.operations);
+        }
+
+        result
     }
 }
 
@@ -345,6 +403,149 @@ impl FnPtr {
     }
 }
 
+/// A [function pointer][FnPtr] prepared for repeated calls from within a native Rust function,
+/// e.g. a callback applied to every item in an array.
+///
+/// The number of arguments to pass is picked once, up front, and resolution of the function is
+/// cached across calls.
+#[cfg(not(feature = "no_index"))]
+pub(crate) struct FnPtrCallback<'a> {
+    /// The native call context.
+    ctx: NativeCallContext<'a>,
+    /// The function pointer to call.
+    fn_ptr: &'a FnPtr,
+    /// Imported modules, cloned once for all calls.
+    mods: Imports,
+    /// Evaluation state, keeping the function resolution cache across calls.
+    state: State,
+    /// Numbers of arguments (excluding curried arguments) still to try, in order of preference.
+    num_args: &'a [usize],
+    /// Is the number of arguments settled?
+    settled: bool,
+    /// Hash of the function call.
+    hash: FnHash,
+    /// Buffer holding the argument values of a call.
+    args: Vec<Dynamic>,
+}
+
+#[cfg(not(feature = "no_index"))]
+impl<'a> FnPtrCallback<'a> {
+    /// Prepare a [function pointer][FnPtr] for repeated calls.
+    ///
+    /// `num_args` lists the numbers of arguments the function may take, in order of preference.
+    /// It must not be empty.
+    pub fn new(ctx: NativeCallContext<'a>, fn_ptr: &'a FnPtr, num_args: &'a [usize]) -> Self {
+        let mods = ctx.mods.cloned().unwrap_or_default();
+        let mut state: State = Default::default();
+        let num_curry = fn_ptr.curry().len();
+
+        // Pick the first number of arguments matching a function with the same name
+        let found = num_args.iter().position(|&n| {
+            let hash_script = calc_fn_hash(empty(), fn_ptr.fn_name(), num_curry + n);
+            ctx.engine
+                .has_script_fn(Some(&mods), &mut state, ctx.lib, hash_script)
+        });
+
+        let (num_args, settled) = match found {
+            Some(index) => (&num_args[index..=index], true),
+            None => (num_args, false),
+        };
+
+        let hash = FnHash::from_script(calc_fn_hash(
+            empty(),
+            fn_ptr.fn_name(),
+            num_curry + num_args[0],
+        ));
+
+        Self {
+            ctx,
+            fn_ptr,
+            mods,
+            state,
+            num_args,
+            settled,
+            hash,
+            args: Vec::with_capacity(num_curry + num_args[num_args.len() - 1]),
+        }
+    }
+    /// Call the function pointer with curried arguments (if any), followed by as many leading
+    /// values in `arg_values` as the number of arguments picked.
+    ///
+    /// Until a call succeeds, the next number of arguments is tried if no function is found
+    /// for the current one.
+    ///
+    /// # WARNING
+    ///
+    /// Argument values may be _consumed_, meaning that they're
+    /// replaced by `()`.
+    pub fn call(&mut self, arg_values: &mut [Dynamic]) -> RhaiResult {
+        let fn_name = self.fn_ptr.fn_name();
+
+        loop {
+            let num_args = self.num_args[0];
+
+            self.args.clear();
+            self.args.extend(self.fn_ptr.curry().iter().cloned());
+
+            if self.settled {
+                self.args
+                    .extend(arg_values[..num_args].iter_mut().map(mem::take));
+            } else {
+                // Keep the argument values in case another number of arguments must be tried
+                self.args.extend(arg_values[..num_args].iter().cloned());
+            }
+
+            // Operations count towards those of the caller
+            self.state.operations = self.ctx.operations.map_or(0, |(ops, _)| ops.get());
+            self.state.generation = self.ctx.operations.map(|(_, generation)| generation);
+
+            let mut args: StaticVec<_> = self.args.iter_mut().collect();
+
+            let result = self
+                .ctx
+                .engine
+                .exec_fn_call(
+                    &mut self.mods,
+                    &mut self.state,
+                    self.ctx.lib,
+                    fn_name,
+                    self.hash,
+                    args.as_mut(),
+                    false,
+                    false,
+                    Position::NONE,
+                    None,
+                    0,
+                )
+                .map(|(r, _)| r);
+
+            if let Some((operations, _)) = self.ctx.operations {
+                operations.set(self.state.operations);
+            }
+
+            match result {
+                Err(err) if !self.settled && self.num_args.len() > 1 => match *err {
+                    EvalAltResult::ErrorFunctionNotFound(ref sig, _)
+                        if sig.starts_with(fn_name) =>
+                    {
+                        self.num_args = &self.num_args[1..];
+                        self.hash = FnHash::from_script(calc_fn_hash(
+                            empty(),
+                            fn_name,
+                            self.fn_ptr.curry().len() + self.num_args[0],
+                        ));
+                    }
+                    _ => return Err(err),
+                },
+                result => {
+                    self.settled = self.settled || result.is_ok();
+                    return result;
+                }
+            }
+        }
+    }
+}
+
 impl fmt::Display for FnPtr {
     #[inline(always)]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -392,8 +593,63 @@ pub type FnAny = dyn Fn(NativeCallContext, &mut FnCallArgs) -> RhaiResult;
 #[cfg(feature = "sync")]
 pub type FnAny = dyn Fn(NativeCallContext, &mut FnCallArgs) -> RhaiResult + Send + Sync;
 
+/// A future returned by an asynchronous native function.
+#[cfg(not(feature = "sync"))]
+pub type FnAsyncFuture = Pin<Box<dyn Future<Output = RhaiResult>>>;
+/// A future returned by an asynchronous native function.
+#[cfg(feature = "sync")]
+pub type FnAsyncFuture = Pin<Box<dyn Future<Output = RhaiResult> + Send>>;
+
+/// An asynchronous native function trait object.
+#[cfg(not(feature = "sync"))]
+pub type FnAsync = dyn Fn(&mut FnCallArgs) -> FnAsyncFuture;
+/// An asynchronous native function trait object.
+#[cfg(feature = "sync")]
+pub type FnAsync = dyn Fn(&mut FnCallArgs) -> FnAsyncFuture + Send + Sync;
+
+/// A pending call to an asynchronous native function.
+///
+/// Calling an asynchronous native function suspends the evaluation with an [`AsyncCall`] as the
+/// suspension token. The future is created and awaited only when the evaluation is driven by one
+/// of the asynchronous evaluation API's (e.g. [`Engine::eval_async`]).
+#[derive(Clone)]
+pub(crate) struct AsyncCall {
+    /// The asynchronous native function.
+    func: Shared<FnAsync>,
+    /// Argument values.
+    args: StaticVec<Dynamic>,
+}
+
+impl AsyncCall {
+    /// Suspend the evaluation with a pending call to an asynchronous native function.
+    #[inline(always)]
+    pub(crate) fn suspend(func: &Shared<FnAsync>, args: &FnCallArgs) -> RhaiResult {
+        let call = Self {
+            func: func.clone(),
+            args: args.iter().map(|value| value.flatten_clone()).collect(),
+        };
+        EvalAltResult::ErrorSuspended(Dynamic::from(call), Position::NONE).into()
+    }
+    /// Create the future of the function call and await it.
+    pub(crate) async fn run(mut self) -> RhaiResult {
+        let future = {
+            let mut args: StaticVec<_> = self.args.iter_mut().collect();
+            (self.func)(args.as_mut())
+        };
+        // The argument values must outlive the future, as string arguments are passed by reference.
+        future.await
+    }
+}
+
+/// An iterator over the values of a type.
+#[cfg(not(feature = "sync"))]
+pub type DynamicIterator = Box<dyn Iterator<Item = Dynamic>>;
+/// An iterator over the values of a type.
+#[cfg(feature = "sync")]
+pub type DynamicIterator = Box<dyn Iterator<Item = Dynamic> + Send + Sync>;
+
 /// A standard function that gets an iterator from a type.
-pub type IteratorFn = fn(Dynamic) -> Box<dyn Iterator<Item = Dynamic>>;
+pub type IteratorFn = fn(Dynamic) -> DynamicIterator;
 
 #[cfg(not(feature = "sync"))]
 pub type FnPlugin = dyn PluginFunction;
@@ -414,6 +670,15 @@ pub type OnPrintCallback = Box<dyn Fn(&str) + 'static>;
 #[cfg(feature = "sync")]
 pub type OnPrintCallback = Box<dyn Fn(&str) + Send + Sync + 'static>;
 
+/// A standard callback function for reading the current date and time.
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(feature = "sync"))]
+pub type OnClockCallback = Box<dyn Fn() -> crate::DateTime + 'static>;
+/// A standard callback function for reading the current date and time.
+#[cfg(not(feature = "no_std"))]
+#[cfg(feature = "sync")]
+pub type OnClockCallback = Box<dyn Fn() -> crate::DateTime + Send + Sync + 'static>;
+
 /// A standard callback function for debugging.
 #[cfg(not(feature = "sync"))]
 pub type OnDebugCallback = Box<dyn Fn(&str, Option<&str>, Position) + 'static>;
@@ -3,10 +3,27 @@
 #![allow(non_snake_case)]
 
 use crate::dynamic::{DynamicWriteLock, Variant};
-use crate::fn_native::{CallableFunction, FnAny, FnCallArgs, SendSync};
+use crate::engine::next_global_fn_id;
+use crate::fn_native::{
+    AsyncCall, CallableFunction, FnAny, FnAsync, FnAsyncFuture, FnCallArgs, SendSync, Shared,
+};
 use crate::r#unsafe::unsafe_cast_box;
-use crate::stdlib::{any::TypeId, boxed::Box, mem, string::String};
-use crate::{Dynamic, Engine, FnAccess, FnNamespace, NativeCallContext, RhaiResult};
+use crate::stdlib::{any::TypeId, boxed::Box, future::Future, mem, string::String};
+use crate::{Dynamic, Engine, EvalAltResult, FnAccess, FnNamespace, NativeCallContext, RhaiResult};
+
+/// Trait that maps to `Send` only under the `sync` feature.
+#[cfg(feature = "sync")]
+pub trait AsyncSend: Send {}
+/// Trait that maps to `Send` only under the `sync` feature.
+#[cfg(feature = "sync")]
+impl<T: Send> AsyncSend for T {}
+
+/// Trait that maps to `Send` only under the `sync` feature.
+#[cfg(not(feature = "sync"))]
+pub trait AsyncSend {}
+/// Trait that maps to `Send` only under the `sync` feature.
+#[cfg(not(feature = "sync"))]
+impl<T> AsyncSend for T {}
 
 /// Trait to register custom functions with the [`Engine`].
 pub trait RegisterFn<FN, ARGS, RET> {
@@ -38,6 +55,12 @@ pub trait RegisterFn<FN, ARGS, RET> {
     /// # }
     /// ```
     fn register_fn(&mut self, name: &str, f: FN) -> &mut Self;
+    /// Register a custom function with the [`Engine`], marking it as pure, i.e. free of side
+    /// effects and with its result depending only on its arguments.
+    ///
+    /// Only script-defined functions calling pure functions are memoized.
+    /// See [`Engine::set_max_fn_memo_size`].
+    fn register_pure_fn(&mut self, name: &str, f: FN) -> &mut Self;
 }
 
 /// Trait to register fallible custom functions returning [`Result`]`<`[`Dynamic`]`, `[`Box`]`<`[`EvalAltResult`][crate::EvalAltResult]`>>` with the [`Engine`].
@@ -68,6 +91,48 @@ pub trait RegisterResultFn<FN, ARGS> {
     ///         .expect_err("expecting division by zero error!");
     /// ```
     fn register_result_fn(&mut self, name: &str, f: FN) -> &mut Self;
+    /// Register a custom fallible function with the [`Engine`], marking it as pure, i.e. free of
+    /// side effects and with its result depending only on its arguments.
+    ///
+    /// Only script-defined functions calling pure functions are memoized.
+    /// See [`Engine::set_max_fn_memo_size`].
+    fn register_pure_result_fn(&mut self, name: &str, f: FN) -> &mut Self;
+}
+
+/// Trait to register asynchronous custom functions with the [`Engine`].
+pub trait RegisterAsyncFn<FN, ARGS, RET> {
+    /// Register a custom asynchronous function with the [`Engine`].
+    ///
+    /// The function returns a [`Future`] resolving to
+    /// [`Result`]`<RET, `[`Box`]`<`[`EvalAltResult`][crate::EvalAltResult]`>>`.
+    /// Under the `sync` feature, the [`Future`] must also be [`Send`].
+    ///
+    /// All parameters are passed by value. Use owned types (e.g. [`String`] instead of `&str`)
+    /// because the [`Future`] cannot borrow from the arguments. Asynchronous functions can only be called
+    /// when the script is evaluated via one of the asynchronous evaluation API's
+    /// (e.g. [`Engine::eval_async`]).
+    /// Otherwise, calling an asynchronous function returns
+    /// [`EvalAltResult::ErrorSuspended`][crate::EvalAltResult::ErrorSuspended].
+    /// The same happens when it is called where evaluation cannot be suspended (e.g. as a method).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterAsyncFn};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // You must use the trait rhai::RegisterAsyncFn to get this method.
+    /// engine.register_async_fn("add", |x: i64, y: i64| async move { Ok(x + y) });
+    ///
+    /// let result = futures::executor::block_on(engine.eval_async::<i64>("add(40, 2)"))?;
+    ///
+    /// assert_eq!(result, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn register_async_fn(&mut self, name: &str, f: FN) -> &mut Self;
 }
 
 // These types are used to build a unique _marker_ tuple type for each combination
@@ -175,6 +240,17 @@ macro_rules! def_register {
                     &[$(TypeId::of::<$par>()),*],
                     CallableFunction::$abi(make_func!(f : map_dynamic ; $($par => $let => $clone => $arg),*))
                 );
+                self.global_fn_id = next_global_fn_id();
+                self
+            }
+            #[inline(always)]
+            fn register_pure_fn(&mut self, name: &str, f: FN) -> &mut Self {
+                let hash = self.global_namespace.set_fn(name, FnNamespace::Global, FnAccess::Public, None,
+                    &[$(TypeId::of::<$par>()),*],
+                    CallableFunction::$abi(make_func!(f : map_dynamic ; $($par => $let => $clone => $arg),*))
+                );
+                self.global_namespace.update_fn_purity(hash, true);
+                self.global_fn_id = next_global_fn_id();
                 self
             }
         }
@@ -190,6 +266,17 @@ macro_rules! def_register {
                     &[$(TypeId::of::<$par>()),*],
                     CallableFunction::$abi(make_func!(f : map_result ; $($par => $let => $clone => $arg),*))
                 );
+                self.global_fn_id = next_global_fn_id();
+                self
+            }
+            #[inline(always)]
+            fn register_pure_result_fn(&mut self, name: &str, f: FN) -> &mut Self {
+                let hash = self.global_namespace.set_fn(name, FnNamespace::Global, FnAccess::Public, None,
+                    &[$(TypeId::of::<$par>()),*],
+                    CallableFunction::$abi(make_func!(f : map_result ; $($par => $let => $clone => $arg),*))
+                );
+                self.global_namespace.update_fn_purity(hash, true);
+                self.global_fn_id = next_global_fn_id();
                 self
             }
         }
@@ -212,3 +299,48 @@ macro_rules! def_register {
 }
 
 def_register!(A, B, C, D, E, F, G, H, J, K, L, M, N, P, Q, R, S, T, U, V);
+
+macro_rules! def_register_async {
+    () => {
+        def_register_async!(imp);
+    };
+    (imp $($par:ident),*) => {
+    //   ^ function parameter generic type name (A, B, C etc.)
+        impl<
+            $($par: Variant + Clone,)*
+            FN: Fn($($par),*) -> FUT + SendSync + 'static,
+            FUT: Future<Output = Result<RET, Box<EvalAltResult>>> + AsyncSend + 'static,
+            RET: Variant + Clone
+        > RegisterAsyncFn<FN, ($($par,)*), RET> for Engine
+        {
+            #[inline(always)]
+            fn register_async_fn(&mut self, name: &str, f: FN) -> &mut Self {
+                let func: Shared<FnAsync> = Shared::new(move |_args: &mut FnCallArgs| {
+                    // The arguments are assumed to be of the correct number and types!
+
+                    let mut _drain = _args.iter_mut();
+                    $(let $par = by_value::<$par>(_drain.next().unwrap()); )*
+
+                    let future = f($($par),*);
+
+                    Box::pin(async move { future.await.map(Variant::into_dynamic) }) as FnAsyncFuture
+                });
+
+                self.global_namespace.set_fn(name, FnNamespace::Global, FnAccess::Public, None,
+                    &[$(TypeId::of::<$par>()),*],
+                    CallableFunction::from_pure(Box::new(move |_: NativeCallContext, args: &mut FnCallArgs| {
+                        AsyncCall::suspend(&func, args)
+                    }))
+                );
+                self.global_fn_id = next_global_fn_id();
+                self
+            }
+        }
+    };
+    ($p0:ident $(, $p:ident)*) => {
+        def_register_async!(imp $p0 $(, $p)*);
+        def_register_async!($($p),*);
+    };
+}
+
+def_register_async!(A, B, C, D, E, F, G, H, J, K, L, M, N, P, Q, R, S, T, U, V);
@@ -64,6 +64,11 @@ extern crate alloc;
 // Internal modules
 
 mod ast;
+mod ast_binary;
+mod bytecode;
+mod continuation;
+mod coverage;
+mod datetime;
 mod dynamic;
 mod engine;
 mod engine_api;
@@ -80,8 +85,10 @@ pub mod packages;
 mod parse_error;
 mod parser;
 pub mod plugin;
+mod regex;
 mod result;
 mod scope;
+mod set;
 mod stdlib;
 mod syntax;
 mod token;
@@ -123,10 +130,15 @@ pub type FLOAT = f64;
 pub type FLOAT = f32;
 
 pub use ast::{FnAccess, AST};
+pub use bytecode::Bytecode;
+pub use continuation::{Continuation, Execution};
+pub use coverage::Coverage;
 pub use dynamic::Dynamic;
-pub use engine::{Engine, EvalContext, OP_CONTAINS, OP_EQUALS};
+pub use engine::{
+    Engine, EvalContext, TerminationHandle, TerminationReason, OP_CONTAINS, OP_EQUALS,
+};
 pub use fn_native::{FnPtr, NativeCallContext};
-pub use fn_register::{RegisterFn, RegisterResultFn};
+pub use fn_register::{RegisterAsyncFn, RegisterFn, RegisterResultFn};
 pub use module::{FnNamespace, Module};
 pub use parse_error::{LexError, ParseError, ParseErrorType};
 pub use result::EvalAltResult;
@@ -160,11 +172,36 @@ pub use ast::ScriptFnMetadata;
 #[cfg(not(feature = "no_index"))]
 pub type Array = stdlib::vec::Vec<Dynamic>;
 
-/// Hash map of [`Dynamic`] values with [`ImmutableString`] keys.
+/// Variable-sized array of [`u8`] values (byte array).
+///
+/// Not available under `no_index`.
+#[cfg(not(feature = "no_index"))]
+pub type Blob = stdlib::vec::Vec<u8>;
+
+#[cfg(not(feature = "no_index"))]
+pub use set::Set;
+
+#[cfg(not(feature = "no_std"))]
+pub use datetime::{DateTime, TimeSpan};
+
+pub use regex::Regex;
+
+/// Hash map of [`Dynamic`] values with [`MapKey`] keys.
 ///
 /// Not available under `no_object`.
 #[cfg(not(feature = "no_object"))]
-pub type Map = stdlib::collections::HashMap<ImmutableString, Dynamic>;
+#[cfg(not(feature = "ordered_map"))]
+pub type Map = stdlib::collections::HashMap<MapKey, Dynamic>;
+
+/// Hash map of [`Dynamic`] values with [`MapKey`] keys, which preserves the insertion order of keys.
+///
+/// Not available under `no_object`.
+#[cfg(not(feature = "no_object"))]
+#[cfg(feature = "ordered_map")]
+pub type Map = indexmap::IndexMap<MapKey, Dynamic>;
+
+#[cfg(not(feature = "no_object"))]
+pub use dynamic::MapKey;
 
 #[cfg(not(feature = "no_module"))]
 pub use module::ModuleResolver;
@@ -199,6 +236,11 @@ pub use ast::{
 #[deprecated = "this type is volatile and may change"]
 pub use engine::{Imports, State as EvalState};
 
+#[cfg(feature = "internals")]
+#[cfg(not(feature = "no_function"))]
+#[deprecated = "this type is volatile and may change"]
+pub use engine::FnMemoCache;
+
 #[cfg(feature = "internals")]
 #[cfg(not(feature = "unchecked"))]
 pub use engine::Limits;
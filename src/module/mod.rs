@@ -7,7 +7,7 @@ use crate::fn_register::by_value as cast_arg;
 use crate::stdlib::{
     any::TypeId,
     boxed::Box,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt, format,
     iter::empty,
     num::NonZeroUsize,
@@ -62,6 +62,10 @@ pub struct FuncInfo {
     pub param_types: StaticVec<TypeId>,
     /// Parameter names (if available).
     pub param_names: StaticVec<ImmutableString>,
+    /// Is the function free of side effects, with its result depending only on its arguments?
+    ///
+    /// Only native Rust functions can be marked pure.
+    pub pure: bool,
 }
 
 impl FuncInfo {
@@ -497,6 +501,7 @@ impl Module {
                 params: num_params,
                 param_types: Default::default(),
                 param_names,
+                pure: false,
                 func: fn_def.into(),
             }),
         );
@@ -665,6 +670,47 @@ impl Module {
         self
     }
 
+    /// Update whether a registered native Rust function is pure, i.e. free of side effects and
+    /// with its result depending only on its arguments.
+    ///
+    /// Script-defined functions calling only pure functions can be memoized.
+    /// See [`Engine::set_max_fn_memo_size`][crate::Engine::set_max_fn_memo_size].
+    ///
+    /// The [`u64`] hash is returned by the `set_fn_XXX` calls.
+    #[inline(always)]
+    pub fn update_fn_purity(&mut self, hash_fn: u64, pure: bool) -> &mut Self {
+        if let Some(f) = self.functions.get_mut(&hash_fn) {
+            if !f.func.is_script() {
+                f.pure = pure;
+            }
+        }
+        self
+    }
+
+    /// Mark all native Rust functions registered by `register` as pure.
+    #[inline]
+    pub(crate) fn with_pure_fns(&mut self, register: impl FnOnce(&mut Self)) -> &mut Self {
+        let existing: HashSet<u64> = self.functions.keys().cloned().collect();
+
+        register(self);
+
+        self.functions
+            .iter_mut()
+            .filter(|(hash, f)| !existing.contains(hash) && !f.func.is_script())
+            .for_each(|(_, f)| f.pure = true);
+        self
+    }
+
+    /// Are all native Rust functions with a particular name in the [`Module`] and its
+    /// sub-modules pure?
+    pub(crate) fn is_pure_native_fn(&self, name: &str) -> bool {
+        self.functions
+            .values()
+            .filter(|f| f.name == name && !f.func.is_script())
+            .all(|f| f.pure)
+            && self.modules.values().all(|m| m.is_pure_native_fn(name))
+    }
+
     /// Set a Rust function into the [`Module`], returning a hash key.
     ///
     /// If there is an existing Rust function of the same hash, it is replaced.
@@ -722,6 +768,7 @@ impl Module {
                 } else {
                     Default::default()
                 },
+                pure: false,
                 func: func.into(),
             }),
         );
@@ -1945,6 +1992,7 @@ impl Module {
     where
         T: Variant + Clone + IntoIterator,
         <T as IntoIterator>::Item: Variant + Clone,
+        <T as IntoIterator>::IntoIter: SendSync + 'static,
     {
         self.set_iter(TypeId::of::<T>(), |obj: Dynamic| {
             Box::new(obj.cast::<T>().into_iter().map(Dynamic::from))
@@ -1,26 +1,35 @@
 //! Module implementing the [`AST`] optimizer.
 
-use crate::ast::{Expr, Ident, Stmt, StmtBlock};
+use crate::ast::{ASTNode, Expr, Ident, Stmt, StmtBlock};
 use crate::dynamic::AccessMode;
-use crate::engine::{KEYWORD_DEBUG, KEYWORD_EVAL, KEYWORD_PRINT, KEYWORD_TYPE_OF};
+use crate::engine::{
+    KEYWORD_DEBUG, KEYWORD_EVAL, KEYWORD_FN_PTR, KEYWORD_FN_PTR_CALL, KEYWORD_FN_PTR_CURRY,
+    KEYWORD_IS_DEF_VAR, KEYWORD_PRINT, KEYWORD_THIS, KEYWORD_TYPE_OF,
+};
 use crate::fn_builtin::get_builtin_binary_op_fn;
 use crate::parser::map_dynamic_to_expr;
 use crate::stdlib::{
     any::TypeId,
     boxed::Box,
+    format,
     hash::{Hash, Hasher},
     iter::empty,
     mem,
+    num::NonZeroUsize,
     string::{String, ToString},
     vec,
     vec::Vec,
 };
+use crate::token::is_valid_identifier;
 use crate::utils::get_hasher;
 use crate::{
-    calc_fn_hash, calc_fn_params_hash, combine_hashes, Dynamic, Engine, Module, Position, Scope,
-    StaticVec, AST,
+    calc_fn_hash, calc_fn_params_hash, combine_hashes, Dynamic, Engine, ImmutableString, Module,
+    Position, Scope, StaticVec, AST,
 };
 
+#[cfg(not(feature = "no_function"))]
+use crate::engine::KEYWORD_IS_DEF_FN;
+
 /// Level of optimization performed.
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
 pub enum OptimizationLevel {
@@ -28,7 +37,9 @@ pub enum OptimizationLevel {
     None,
     /// Only perform simple optimizations without evaluating functions.
     Simple,
-    /// Full optimizations performed, including evaluating functions.
+    /// Full optimizations performed, including evaluating functions, inlining small
+    /// script-defined functions, hoisting loop-invariant expressions out of loops and
+    /// eliminating common sub-expressions.
     /// Take care that this may cause side effects as it essentially assumes that all functions are pure.
     Full,
 }
@@ -69,6 +80,10 @@ struct State<'a> {
     lib: &'a [&'a Module],
     /// Optimization level.
     optimization_level: OptimizationLevel,
+    /// Names of variables captured by closures, which may change whenever a function is called.
+    captured: Vec<ImmutableString>,
+    /// Number of temporary variables created for hoisted expressions.
+    hoisted_vars: usize,
 }
 
 impl<'a> State<'a> {
@@ -86,6 +101,8 @@ impl<'a> State<'a> {
             engine,
             lib,
             optimization_level,
+            captured: Default::default(),
+            hoisted_vars: 0,
         }
     }
     /// Set the [`AST`] state to be dirty (i.e. changed).
@@ -113,6 +130,31 @@ impl<'a> State<'a> {
     pub fn push_var(&mut self, name: &str, access: AccessMode, value: Expr) {
         self.variables.push((name.into(), access, value))
     }
+    /// Record the variables captured by closures within a block of [statements][Stmt].
+    #[cfg(not(feature = "no_closure"))]
+    pub fn capture_vars(&mut self, statements: &[Stmt]) {
+        let captured = &mut self.captured;
+
+        statements.iter().for_each(|stmt| {
+            stmt.walk(&mut Default::default(), &mut |path| {
+                if let ASTNode::Stmt(Stmt::Share(x)) = path.last().unwrap() {
+                    captured.push(x.name.clone());
+                }
+                true
+            });
+        });
+    }
+    /// Create a new temporary variable for a hoisted expression.
+    ///
+    /// Its name is not a valid identifier, so it never clashes with variables in scripts.
+    pub fn new_hoisted_var(&mut self, pos: Position) -> Ident {
+        self.hoisted_vars += 1;
+
+        Ident {
+            name: format!("$hoisted${}", self.hoisted_vars).into(),
+            pos,
+        }
+    }
     /// Look up a constant from the list.
     #[inline]
     pub fn find_constant(&self, name: &str) -> Option<&Expr> {
@@ -120,16 +162,15 @@ impl<'a> State<'a> {
             return None;
         }
 
-        self.variables.iter().rev().find_map(|(n, access, expr)| {
-            if n == name {
-                match access {
-                    AccessMode::ReadWrite => None,
-                    AccessMode::ReadOnly => Some(expr),
-                }
-            } else {
-                None
-            }
-        })
+        // The innermost variable with the name shadows all others
+        self.variables
+            .iter()
+            .rev()
+            .find(|(n, _, _)| n == name)
+            .and_then(|(_, access, expr)| match access {
+                AccessMode::ReadWrite => None,
+                AccessMode::ReadOnly => Some(expr),
+            })
     }
 }
 
@@ -169,6 +210,841 @@ fn call_fn_with_constant_arguments(
         .map(|(v, _)| v)
 }
 
+/// Maximum number of nodes in the body of a script-defined function for it to be inlined.
+#[cfg(not(feature = "no_function"))]
+const MAX_INLINE_FN_SIZE: usize = 32;
+
+/// Functions which behave differently within the body of a script-defined function than in place
+/// of a call to it, so that neither the function nor the arguments to the call may use them.
+#[cfg(not(feature = "no_function"))]
+const DONT_INLINE_FUNCTIONS: &[&str] = &[
+    KEYWORD_EVAL,         // arbitrary scripts
+    KEYWORD_FN_PTR,       // depends on script-defined functions
+    KEYWORD_FN_PTR_CALL,  // arbitrary functions
+    KEYWORD_FN_PTR_CURRY, // creates a new function pointer
+    KEYWORD_IS_DEF_VAR,   // depends on the scope
+    KEYWORD_IS_DEF_FN,    // depends on script-defined functions
+];
+
+/// Get a script-defined function that can be inlined in place of a function call.
+///
+/// The function must be small, must not call any script-defined function (which also rules out
+/// recursion), must not be a closure, must not have an encapsulated environment, must not use
+/// `this`, variables not declared within it, `return`, `throw`, custom syntax or any of the
+/// [`DONT_INLINE_FUNCTIONS`], and must not import modules.
+///
+/// The arguments must not contain statement blocks or custom syntax (which may declare
+/// variables), nor refer to variables with the same names as preceding parameters, nor use any of
+/// the [`DONT_INLINE_FUNCTIONS`].
+#[cfg(not(feature = "no_function"))]
+fn get_inlinable_fn<'a>(
+    state: &State<'a>,
+    x: &crate::ast::FnCallExpr,
+) -> Option<&'a crate::ast::ScriptFnDef> {
+    use crate::ast::ASTNode;
+
+    if x.namespace.is_some() || x.capture {
+        return None;
+    }
+
+    let lib = state.lib;
+    let fn_def = lib
+        .iter()
+        .find_map(|m| m.get_script_fn(x.name.as_ref(), x.args.len(), false))?;
+
+    #[cfg(not(feature = "no_closure"))]
+    if !fn_def.externals.is_empty() {
+        return None;
+    }
+
+    if fn_def.lib.is_some() {
+        return None;
+    }
+
+    let is_script_fn = |name: &str| {
+        lib.iter()
+            .flat_map(|m| m.iter_script_fn())
+            .any(|(_, _, fn_name, _, _)| fn_name == name)
+    };
+
+    let mut size = 0;
+    let path = &mut Default::default();
+
+    let can_inline = fn_def.body.statements.iter().all(|stmt| {
+        stmt.walk(path, &mut |path| {
+            size += 1;
+
+            size <= MAX_INLINE_FN_SIZE
+                && match path.last().unwrap() {
+                    ASTNode::Stmt(Stmt::Return(_, _, _)) => false,
+                    #[cfg(not(feature = "no_module"))]
+                    ASTNode::Stmt(Stmt::Import(_, _, _)) | ASTNode::Stmt(Stmt::Export(_, _)) => {
+                        false
+                    }
+                    #[cfg(not(feature = "no_closure"))]
+                    ASTNode::Stmt(Stmt::Share(_)) => false,
+                    ASTNode::Expr(Expr::Variable(v)) => v.0.is_some() && v.1.is_none(),
+                    ASTNode::Expr(Expr::Custom(_, _))
+                    | ASTNode::Expr(Expr::InlinedFnCall(_, _)) => false,
+                    ASTNode::Expr(Expr::FnCall(f, _)) => {
+                        !f.capture
+                            && !DONT_INLINE_FUNCTIONS.contains(&f.name.as_ref())
+                            && !is_script_fn(f.name.as_ref())
+                    }
+                    _ => true,
+                }
+        })
+    });
+
+    if !can_inline {
+        return None;
+    }
+
+    let can_inline_args = x.args.iter().enumerate().all(|(i, arg)| {
+        let path = &mut Default::default();
+
+        arg.walk(path, &mut |path| match path.last().unwrap() {
+            ASTNode::Expr(Expr::Stmt(_)) | ASTNode::Expr(Expr::Custom(_, _)) => false,
+            ASTNode::Expr(Expr::InlinedFnCall(_, _)) => false,
+            ASTNode::Expr(Expr::FnCall(f, _)) => {
+                !f.capture && !DONT_INLINE_FUNCTIONS.contains(&f.name.as_ref())
+            }
+            ASTNode::Expr(Expr::Variable(v)) => !fn_def.params[..i].contains(&v.2.name),
+            _ => true,
+        })
+    });
+
+    if can_inline_args {
+        Some(fn_def)
+    } else {
+        None
+    }
+}
+
+/// Shift the positions in the scope of all variables in an [expression][Expr].
+fn shift_var_indices(expr: &mut Expr, delta: isize) {
+    match expr {
+        Expr::Variable(x) => {
+            if let Some(index) = x.0 {
+                x.0 = NonZeroUsize::new((index.get() as isize + delta) as usize);
+            }
+        }
+        Expr::Array(x, _) => x.iter_mut().for_each(|e| shift_var_indices(e, delta)),
+        Expr::Map(x, _) => x.iter_mut().for_each(|(_, e)| shift_var_indices(e, delta)),
+        Expr::FnCall(x, _) => x.args.iter_mut().for_each(|e| shift_var_indices(e, delta)),
+        Expr::Dot(x, _) | Expr::Index(x, _) | Expr::And(x, _) | Expr::Or(x, _) => {
+            shift_var_indices(&mut x.lhs, delta);
+            shift_var_indices(&mut x.rhs, delta);
+        }
+        _ => (),
+    }
+}
+
+/// Inline a script-defined function in place of a call to it.
+///
+/// `f(a, b)` becomes `{ let p1 = a; let p2 = b; body }`, with the body kept in an
+/// [`Expr::InlinedFnCall`] so that errors raised within it are reported as for the function call.
+/// Positions of variables are kept as offsets from the top of the scope, so variables in the
+/// function body need no change.
+/// Each argument is evaluated after the parameters preceding it are declared, so the positions
+/// of variables in the arguments are shifted accordingly.
+#[cfg(not(feature = "no_function"))]
+fn inline_fn_call(fn_def: &crate::ast::ScriptFnDef, args: StaticVec<Expr>, pos: Position) -> Expr {
+    let mut statements: Vec<_> = fn_def
+        .params
+        .iter()
+        .zip(args)
+        .enumerate()
+        .map(|(i, (param, mut arg))| {
+            shift_var_indices(&mut arg, i as isize);
+            let var_pos = arg.position();
+            let var = Ident {
+                name: param.clone(),
+                pos: var_pos,
+            };
+            Stmt::Let(arg, var, false, var_pos)
+        })
+        .collect();
+
+    let body = (fn_def.name.clone(), fn_def.body.clone());
+    statements.push(Stmt::Expr(Expr::InlinedFnCall(Box::new(body), pos)));
+
+    Expr::Stmt(Box::new(StmtBlock {
+        statements: statements.into(),
+        pos,
+    }))
+}
+
+/// Functions which are never hoisted out of loops or treated as common sub-expressions.
+const DONT_HOIST_FUNCTIONS: &[&str] = &[
+    KEYWORD_PRINT,        // side effects
+    KEYWORD_DEBUG,        // side effects
+    KEYWORD_EVAL,         // arbitrary scripts
+    KEYWORD_FN_PTR,       // depends on script-defined functions
+    KEYWORD_FN_PTR_CALL,  // arbitrary functions
+    KEYWORD_FN_PTR_CURRY, // creates a new function pointer
+    KEYWORD_IS_DEF_VAR,   // depends on the scope
+];
+
+/// Is there a script-defined function with a particular name?
+#[cfg(not(feature = "no_function"))]
+fn has_script_fn_named(state: &State, name: &str) -> bool {
+    state
+        .lib
+        .iter()
+        .flat_map(|m| m.iter_script_fn())
+        .any(|(_, _, fn_name, _, _)| fn_name == name)
+}
+
+/// Is there a script-defined function with a particular name?
+#[cfg(feature = "no_function")]
+#[inline(always)]
+fn has_script_fn_named(_state: &State, _name: &str) -> bool {
+    false
+}
+
+/// Get the name of the variable at the root of a dot/index chain.
+fn root_var_name(expr: &Expr) -> Option<&ImmutableString> {
+    match expr {
+        Expr::Variable(x) => Some(&x.2.name),
+        Expr::Dot(x, _) | Expr::Index(x, _) => root_var_name(&x.lhs),
+        _ => None,
+    }
+}
+
+/// Does the right-hand side of a dot/index chain contain a method call?
+fn chain_has_method_call(expr: &Expr, is_index: bool) -> bool {
+    let next_is_index = matches!(expr, Expr::Index(_, _));
+
+    match expr {
+        Expr::Dot(x, _) | Expr::Index(x, _) => {
+            chain_has_method_call(&x.lhs, is_index) || chain_has_method_call(&x.rhs, next_is_index)
+        }
+        Expr::FnCall(_, _) => !is_index,
+        _ => false,
+    }
+}
+
+/// Record the name of a variable that may be changed by the last node in a path.
+///
+/// Functions are called with their first argument passed by reference, so a variable passed as
+/// the first argument to a function or used as the object of a method call may be changed.
+///
+/// Returns `false` if changes cannot be tracked, i.e. for `eval`, custom syntax, closures
+/// capturing the current scope and calls to function pointers.
+fn track_changed_var(path: &[ASTNode], changed: &mut Vec<ImmutableString>) -> bool {
+    match path.last().unwrap() {
+        ASTNode::Stmt(Stmt::Let(_, x, _, _)) | ASTNode::Stmt(Stmt::Const(_, x, _, _)) => {
+            changed.push(x.name.clone())
+        }
+        ASTNode::Stmt(Stmt::For(_, x, _)) => changed.push(x.0.as_str().into()),
+        ASTNode::Stmt(Stmt::TryCatch(x, _, _)) => {
+            changed.extend(x.1.iter().map(|v| v.name.clone()))
+        }
+        ASTNode::Stmt(Stmt::Assignment(x, _)) => changed.extend(root_var_name(&x.0).cloned()),
+        #[cfg(not(feature = "no_closure"))]
+        ASTNode::Stmt(Stmt::Share(x)) => changed.push(x.name.clone()),
+        ASTNode::Expr(Expr::Dot(x, _)) if chain_has_method_call(&x.rhs, false) => {
+            changed.extend(root_var_name(&x.lhs).cloned())
+        }
+        ASTNode::Expr(Expr::Index(x, _)) if chain_has_method_call(&x.rhs, true) => {
+            changed.extend(root_var_name(&x.lhs).cloned())
+        }
+        ASTNode::Expr(Expr::FnCall(x, _))
+            if x.capture || x.name == KEYWORD_EVAL || x.name == KEYWORD_FN_PTR_CALL =>
+        {
+            return false
+        }
+        ASTNode::Expr(Expr::FnCall(x, _)) if is_valid_identifier(x.name.chars()) => {
+            if let Some(Expr::Variable(v)) = x.args.first() {
+                changed.push(v.2.name.clone());
+            }
+        }
+        ASTNode::Expr(Expr::Custom(_, _)) => return false,
+        _ => (),
+    }
+
+    true
+}
+
+/// Is an [expression][Expr] invariant within a region of code, i.e. without side effects and
+/// always evaluating to the same value?
+///
+/// `locals` is the number of variables declared within the region at the position of the
+/// expression.  Variables declared within the region, variables that may be changed within it
+/// and variables captured by closures are never invariant.
+///
+/// Function calls are only invariant if all native Rust functions with the same name are pure,
+/// and no script-defined function or special keyword has the same name.  Properties and indexing
+/// are only invariant if all getters or indexers registered are pure.
+fn is_invariant(expr: &Expr, state: &State, locals: usize, changed: &[ImmutableString]) -> bool {
+    match expr {
+        Expr::Variable(x) => {
+            x.1.is_none()
+                && x.0.is_none_or(|index| index.get() > locals)
+                && x.2.name != KEYWORD_THIS
+                && !changed.contains(&x.2.name)
+                && !state.captured.contains(&x.2.name)
+        }
+        Expr::Array(x, _) => x.iter().all(|e| is_invariant(e, state, locals, changed)),
+        Expr::Map(x, _) => x
+            .iter()
+            .all(|(_, e)| is_invariant(e, state, locals, changed)),
+        Expr::And(x, _) | Expr::Or(x, _) => {
+            is_invariant(&x.lhs, state, locals, changed)
+                && is_invariant(&x.rhs, state, locals, changed)
+        }
+        Expr::Dot(x, _) => {
+            is_invariant(&x.lhs, state, locals, changed)
+                && is_invariant_chain(&x.rhs, false, state, locals, changed)
+        }
+        Expr::Index(x, _) => {
+            is_invariant(&x.lhs, state, locals, changed)
+                && is_invariant_chain(&x.rhs, true, state, locals, changed)
+        }
+        Expr::FnCall(x, _) => {
+            x.namespace.is_none()
+                && !x.capture
+                && !x.args.is_empty()
+                && !DONT_HOIST_FUNCTIONS.contains(&x.name.as_ref())
+                && !has_script_fn_named(state, x.name.as_ref())
+                && state.engine.is_pure_native_fn(state.lib, x.name.as_ref())
+                && x.args
+                    .iter()
+                    .all(|e| is_invariant(e, state, locals, changed))
+        }
+        // Constants are already as cheap as they can be, while statement blocks and custom
+        // syntax may declare variables
+        Expr::DynamicConstant(_, _) | Expr::Stmt(_) | Expr::Custom(_, _) | Expr::Property(_) => {
+            false
+        }
+        #[cfg(not(feature = "no_function"))]
+        Expr::InlinedFnCall(_, _) => false,
+        _ => expr.is_pure(),
+    }
+}
+
+/// Is the right-hand side of a dot/index chain invariant within a region of code?
+fn is_invariant_chain(
+    expr: &Expr,
+    is_index: bool,
+    state: &State,
+    locals: usize,
+    changed: &[ImmutableString],
+) -> bool {
+    let next_is_index = matches!(expr, Expr::Index(_, _));
+
+    match expr {
+        Expr::Dot(x, _) | Expr::Index(x, _) => {
+            is_invariant_chain(&x.lhs, is_index, state, locals, changed)
+                && is_invariant_chain(&x.rhs, next_is_index, state, locals, changed)
+        }
+        _ if is_index => {
+            #[cfg(not(feature = "no_index"))]
+            if !state
+                .engine
+                .is_pure_native_fn(state.lib, crate::engine::FN_IDX_GET)
+            {
+                return false;
+            }
+
+            is_invariant(expr, state, locals, changed)
+        }
+        Expr::Property(x) => state.engine.is_pure_native_fn(state.lib, &(x.0).0),
+        _ => false,
+    }
+}
+
+/// Collect the invariant sub-expressions of an [expression][Expr] that are evaluated whenever
+/// the expression is, i.e. not on the right-hand side of `&&` or `||`.
+///
+/// Only property access, indexing and function calls are collected, with the number of variables
+/// declared within the region at their positions.  If `maximal` is `true`, sub-expressions of
+/// collected expressions are skipped.
+fn collect_invariants<'e>(
+    expr: &'e Expr,
+    state: &State,
+    locals: usize,
+    changed: &[ImmutableString],
+    maximal: bool,
+    list: &mut Vec<(&'e Expr, usize)>,
+) {
+    match expr {
+        Expr::Dot(_, _) | Expr::Index(_, _) | Expr::FnCall(_, _)
+            if is_invariant(expr, state, locals, changed) =>
+        {
+            list.push((expr, locals));
+
+            if maximal {
+                return;
+            }
+        }
+        _ => (),
+    }
+
+    match expr {
+        Expr::Array(x, _) => x
+            .iter()
+            .for_each(|e| collect_invariants(e, state, locals, changed, maximal, list)),
+        Expr::Map(x, _) => x
+            .iter()
+            .for_each(|(_, e)| collect_invariants(e, state, locals, changed, maximal, list)),
+        Expr::And(x, _) | Expr::Or(x, _) => {
+            collect_invariants(&x.lhs, state, locals, changed, maximal, list)
+        }
+        Expr::FnCall(x, _) => x
+            .args
+            .iter()
+            .for_each(|e| collect_invariants(e, state, locals, changed, maximal, list)),
+        Expr::Dot(x, _) | Expr::Index(x, _) => {
+            let is_index = matches!(expr, Expr::Index(_, _));
+            collect_invariants(&x.lhs, state, locals, changed, maximal, list);
+            collect_chain_invariants(&x.rhs, is_index, state, locals, changed, maximal, list);
+        }
+        _ => (),
+    }
+}
+
+/// Collect the invariant sub-expressions of the right-hand side of a dot/index chain.
+fn collect_chain_invariants<'e>(
+    expr: &'e Expr,
+    is_index: bool,
+    state: &State,
+    locals: usize,
+    changed: &[ImmutableString],
+    maximal: bool,
+    list: &mut Vec<(&'e Expr, usize)>,
+) {
+    let next_is_index = matches!(expr, Expr::Index(_, _));
+
+    match expr {
+        Expr::Dot(x, _) | Expr::Index(x, _) => {
+            collect_chain_invariants(&x.lhs, is_index, state, locals, changed, maximal, list);
+            collect_chain_invariants(&x.rhs, next_is_index, state, locals, changed, maximal, list);
+        }
+        _ if is_index => collect_invariants(expr, state, locals, changed, maximal, list),
+        Expr::FnCall(x, _) => x
+            .args
+            .iter()
+            .for_each(|e| collect_invariants(e, state, locals, changed, maximal, list)),
+        _ => (),
+    }
+}
+
+/// Are two [expressions][Expr] accepted by [`is_invariant`] the same?
+///
+/// Positions of variables are compared relative to the number of variables declared within the
+/// region at the position of each expression.
+fn is_same_expr(a: &Expr, a_locals: usize, b: &Expr, b_locals: usize) -> bool {
+    let same = |x: &Expr, y: &Expr| is_same_expr(x, a_locals, y, b_locals);
+
+    match (a, b) {
+        (Expr::Variable(x), Expr::Variable(y)) => {
+            x.1.is_none()
+                && y.1.is_none()
+                && x.2.name == y.2.name
+                && match (x.0, y.0) {
+                    (Some(i), Some(j)) => i.get() + b_locals == j.get() + a_locals,
+                    (None, None) => true,
+                    _ => false,
+                }
+        }
+        (Expr::Property(x), Expr::Property(y)) => x.2.name == y.2.name,
+        (Expr::BoolConstant(x, _), Expr::BoolConstant(y, _)) => x == y,
+        (Expr::IntegerConstant(x, _), Expr::IntegerConstant(y, _)) => x == y,
+        #[cfg(not(feature = "no_float"))]
+        (Expr::FloatConstant(x, _), Expr::FloatConstant(y, _)) => x.to_bits() == y.to_bits(),
+        (Expr::CharConstant(x, _), Expr::CharConstant(y, _)) => x == y,
+        (Expr::StringConstant(x, _), Expr::StringConstant(y, _))
+        | (Expr::FnPointer(x, _), Expr::FnPointer(y, _)) => x == y,
+        (Expr::Unit(_), Expr::Unit(_)) => true,
+        (Expr::Array(x, _), Expr::Array(y, _)) => {
+            x.len() == y.len() && x.iter().zip(y.iter()).all(|(x, y)| same(x, y))
+        }
+        (Expr::Map(x, _), Expr::Map(y, _)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .zip(y.iter())
+                    .all(|((xk, xv), (yk, yv))| xk == yk && same(xv, yv))
+        }
+        (Expr::FnCall(x, _), Expr::FnCall(y, _)) => {
+            x.name == y.name
+                && x.hash == y.hash
+                && x.namespace.is_none()
+                && y.namespace.is_none()
+                && !x.capture
+                && !y.capture
+                && x.args.len() == y.args.len()
+                && x.args.iter().zip(y.args.iter()).all(|(x, y)| same(x, y))
+        }
+        (Expr::Dot(x, _), Expr::Dot(y, _))
+        | (Expr::Index(x, _), Expr::Index(y, _))
+        | (Expr::And(x, _), Expr::And(y, _))
+        | (Expr::Or(x, _), Expr::Or(y, _)) => same(&x.lhs, &y.lhs) && same(&x.rhs, &y.rhs),
+        _ => false,
+    }
+}
+
+/// An [expression][Expr] hoisted into a temporary variable declared just before a region of code.
+///
+/// The region becomes `{ let tmp = (); let done = false; ... }`, and each occurrence of the
+/// expression becomes `{ if done {} else { tmp = expr; done = true; } tmp }`.
+///
+/// The expression is therefore evaluated, at most once, only where it is first evaluated in the
+/// original region, so that errors (e.g. division by zero or overflow) are raised at the same
+/// point, and never if the region does not evaluate the expression at all (e.g. a loop body that
+/// never runs).
+struct HoistedExpr<'a> {
+    /// The hoisted expression.
+    expr: &'a Expr,
+    /// Number of variables declared within the region at the position of the hoisted expression.
+    locals: usize,
+    /// The temporary variable.
+    var: &'a Ident,
+    /// The variable recording whether the expression has been evaluated.
+    done: &'a Ident,
+}
+
+impl HoistedExpr<'_> {
+    /// Number of variables declared before the region.
+    const NUM_VARS: usize = 2;
+
+    /// Wrap a region of code with the declarations of the temporary variables.
+    fn declare(&self, region: Stmt) -> Stmt {
+        let pos = self.var.pos;
+        let block_pos = region.position();
+
+        Stmt::Block(
+            vec![
+                Stmt::Let(Expr::Unit(pos), self.var.clone(), false, pos),
+                Stmt::Let(
+                    Expr::BoolConstant(false, pos),
+                    self.done.clone(),
+                    false,
+                    pos,
+                ),
+                region,
+            ],
+            block_pos,
+        )
+    }
+    /// Make an occurrence of the hoisted expression evaluate it only if not already evaluated.
+    ///
+    /// `locals` is the number of variables declared within the region at the position of the
+    /// occurrence.
+    fn make_lazy(&self, expr: &Expr, locals: usize) -> Expr {
+        let pos = expr.position();
+        let var = |ident: &Ident, index: usize| {
+            Expr::Variable(Box::new((
+                NonZeroUsize::new(index),
+                None,
+                ident.clone(),
+                None,
+            )))
+        };
+        let done = var(self.done, locals + 1);
+        let tmp = var(self.var, locals + 2);
+
+        // The expression only contains variables declared outside the region
+        let mut value = expr.clone();
+        shift_var_indices(&mut value, Self::NUM_VARS as isize);
+
+        let evaluate = vec![
+            Stmt::Assignment(Box::new((tmp.clone(), value, None)), pos),
+            Stmt::Assignment(
+                Box::new((done.clone(), Expr::BoolConstant(true, pos), None)),
+                pos,
+            ),
+        ];
+
+        Expr::Stmt(Box::new(StmtBlock {
+            statements: vec![
+                Stmt::If(
+                    done,
+                    Box::new((
+                        StmtBlock {
+                            statements: Default::default(),
+                            pos,
+                        },
+                        StmtBlock {
+                            statements: evaluate.into(),
+                            pos,
+                        },
+                    )),
+                    pos,
+                ),
+                Stmt::Expr(tmp),
+            ]
+            .into(),
+            pos,
+        }))
+    }
+    /// Replace all occurrences of the hoisted expression in a [statement][Stmt] with the
+    /// temporary variable, and shift the positions of all variables declared outside the region
+    /// to make room for the temporary variables.
+    ///
+    /// `locals` is the number of variables declared within the region before the statement.
+    fn replace_in_stmt(&self, stmt: &mut Stmt, locals: &mut usize) {
+        match stmt {
+            Stmt::Let(e, _, _, _) | Stmt::Const(e, _, _, _) => {
+                self.replace_in_expr(e, *locals);
+                *locals += 1;
+            }
+            Stmt::Expr(e) | Stmt::Return(_, Some(e), _) => self.replace_in_expr(e, *locals),
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(e, _, _) => self.replace_in_expr(e, *locals),
+            Stmt::Assignment(x, _) => {
+                self.replace_in_expr(&mut x.0, *locals);
+                self.replace_in_expr(&mut x.1, *locals);
+            }
+            Stmt::If(e, x, _) => {
+                self.replace_in_expr(e, *locals);
+                self.replace_in_block(&mut x.0.statements, *locals);
+                self.replace_in_block(&mut x.1.statements, *locals);
+            }
+            Stmt::Switch(e, x, _) => {
+                self.replace_in_expr(e, *locals);
+                x.0.values_mut()
+                    .for_each(|block| self.replace_in_block(&mut block.statements, *locals));
+                self.replace_in_block(&mut x.1.statements, *locals);
+            }
+            Stmt::While(e, body, _) | Stmt::Do(body, e, _, _) => {
+                self.replace_in_expr(e, *locals);
+                self.replace_in_block(&mut body.statements, *locals);
+            }
+            Stmt::For(e, x, _) => {
+                self.replace_in_expr(e, *locals);
+                self.replace_in_block(&mut x.1.statements, *locals + 1);
+            }
+            Stmt::Block(statements, _) => self.replace_in_block(statements, *locals),
+            Stmt::TryCatch(x, _, _) => {
+                self.replace_in_block(&mut x.0.statements, *locals);
+                let catch_locals = *locals + if x.1.is_some() { 1 } else { 0 };
+                self.replace_in_block(&mut x.2.statements, catch_locals);
+            }
+            _ => (),
+        }
+    }
+    /// Replace all occurrences of the hoisted expression in a block of [statements][Stmt].
+    fn replace_in_block(&self, statements: &mut [Stmt], mut locals: usize) {
+        statements
+            .iter_mut()
+            .for_each(|stmt| self.replace_in_stmt(stmt, &mut locals));
+    }
+    /// Replace all occurrences of the hoisted expression in an [expression][Expr].
+    fn replace_in_expr(&self, expr: &mut Expr, locals: usize) {
+        if is_same_expr(expr, locals, self.expr, self.locals) {
+            *expr = self.make_lazy(expr, locals);
+            return;
+        }
+
+        let is_index = matches!(expr, Expr::Index(_, _));
+
+        match expr {
+            Expr::Variable(x) => match x.0 {
+                Some(index) if index.get() > locals => {
+                    x.0 = NonZeroUsize::new(index.get() + Self::NUM_VARS)
+                }
+                _ => (),
+            },
+            Expr::Stmt(x) => self.replace_in_block(&mut x.statements, locals),
+            #[cfg(not(feature = "no_function"))]
+            Expr::InlinedFnCall(x, _) => self.replace_in_block(&mut x.1.statements, locals),
+            Expr::Array(x, _) => x.iter_mut().for_each(|e| self.replace_in_expr(e, locals)),
+            Expr::Map(x, _) => x
+                .iter_mut()
+                .for_each(|(_, e)| self.replace_in_expr(e, locals)),
+            Expr::FnCall(x, _) => x
+                .args
+                .iter_mut()
+                .for_each(|e| self.replace_in_expr(e, locals)),
+            Expr::Dot(x, _) | Expr::Index(x, _) => {
+                self.replace_in_expr(&mut x.lhs, locals);
+                self.replace_in_chain(&mut x.rhs, is_index, locals);
+            }
+            Expr::And(x, _) | Expr::Or(x, _) => {
+                self.replace_in_expr(&mut x.lhs, locals);
+                self.replace_in_expr(&mut x.rhs, locals);
+            }
+            _ => (),
+        }
+    }
+    /// Replace all occurrences of the hoisted expression in the right-hand side of a dot/index
+    /// chain.
+    fn replace_in_chain(&self, expr: &mut Expr, is_index: bool, locals: usize) {
+        let next_is_index = matches!(expr, Expr::Index(_, _));
+
+        match expr {
+            Expr::Dot(x, _) | Expr::Index(x, _) => {
+                self.replace_in_chain(&mut x.lhs, is_index, locals);
+                self.replace_in_chain(&mut x.rhs, next_is_index, locals);
+            }
+            _ if is_index => self.replace_in_expr(expr, locals),
+            Expr::FnCall(x, _) => x
+                .args
+                .iter_mut()
+                .for_each(|e| self.replace_in_expr(e, locals)),
+            _ => (),
+        }
+    }
+}
+
+/// Hoist a loop-invariant [expression][Expr] out of a loop.
+///
+/// `loop { ... expr ... }` becomes `{ let tmp = (); let done = false; loop { ... tmp ... } }`,
+/// with the expression evaluated lazily where it is first used (see [`HoistedExpr`]).
+///
+/// Only expressions evaluated in every iteration, before anything that may break out of the
+/// loop, are hoisted.
+fn hoist_loop_invariant(stmt: &mut Stmt, state: &mut State) {
+    let mut changed = Default::default();
+
+    if !stmt.walk(&mut Default::default(), &mut |path| {
+        track_changed_var(path, &mut changed)
+    }) {
+        return;
+    }
+
+    let mut list = Default::default();
+
+    match stmt {
+        Stmt::While(condition, body, _) => {
+            collect_invariants(condition, state, 0, &changed, true, &mut list);
+            collect_body_invariants(&body.statements, state, 0, &changed, &mut list);
+        }
+        Stmt::Do(body, condition, _, _) => {
+            if collect_body_invariants(&body.statements, state, 0, &changed, &mut list) {
+                collect_invariants(condition, state, 0, &changed, true, &mut list);
+            }
+        }
+        Stmt::For(_, x, _) => {
+            collect_body_invariants(&x.1.statements, state, 1, &changed, &mut list);
+        }
+        _ => return,
+    }
+
+    let (expr, locals) = match list.first() {
+        Some(&(expr, locals)) => (expr.clone(), locals),
+        None => return,
+    };
+
+    let pos = expr.position();
+    let var = state.new_hoisted_var(pos);
+    let done = state.new_hoisted_var(pos);
+
+    let hoisted = HoistedExpr {
+        expr: &expr,
+        locals,
+        var: &var,
+        done: &done,
+    };
+    hoisted.replace_in_stmt(stmt, &mut 0);
+
+    state.set_dirty();
+    *stmt = hoisted.declare(mem::take(stmt));
+}
+
+/// Collect the invariant sub-expressions evaluated unconditionally by the statements in the
+/// body of a loop, stopping at the first statement that may break out of the loop.
+///
+/// Returns `false` if the body may break out of the loop.
+fn collect_body_invariants<'e>(
+    statements: &'e [Stmt],
+    state: &State,
+    mut locals: usize,
+    changed: &[ImmutableString],
+    list: &mut Vec<(&'e Expr, usize)>,
+) -> bool {
+    for stmt in statements {
+        let breaks = !stmt.walk(&mut Default::default(), &mut |path| {
+            !matches!(
+                path.last().unwrap(),
+                ASTNode::Stmt(Stmt::Break(_))
+                    | ASTNode::Stmt(Stmt::Continue(_))
+                    | ASTNode::Stmt(Stmt::Return(_, _, _))
+            )
+        });
+
+        match stmt {
+            Stmt::If(e, _, _) | Stmt::Switch(e, _, _) => {
+                collect_invariants(e, state, locals, changed, true, list)
+            }
+            _ if breaks => (),
+            Stmt::Let(e, _, _, _) | Stmt::Const(e, _, _, _) => {
+                collect_invariants(e, state, locals, changed, true, list);
+                locals += 1;
+            }
+            Stmt::Expr(e) => collect_invariants(e, state, locals, changed, true, list),
+            Stmt::Assignment(x, _) => collect_invariants(&x.1, state, locals, changed, true, list),
+            _ => (),
+        }
+
+        if breaks {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Eliminate a common sub-expression within an [expression][Expr].
+///
+/// `... expr ... expr ...` becomes `{ let tmp = (); let done = false; ... tmp ... tmp ... }`,
+/// with the expression evaluated lazily where it is first used (see [`HoistedExpr`]).
+fn eliminate_common_sub_expr(expr: &mut Expr, state: &mut State) {
+    let mut changed = Default::default();
+
+    if !expr.walk(&mut Default::default(), &mut |path| {
+        match path.last().unwrap() {
+            // Statement blocks may declare variables
+            ASTNode::Expr(Expr::Stmt(_)) => false,
+            #[cfg(not(feature = "no_function"))]
+            ASTNode::Expr(Expr::InlinedFnCall(_, _)) => false,
+            _ => track_changed_var(path, &mut changed),
+        }
+    }) {
+        return;
+    }
+
+    let mut list = Default::default();
+    collect_invariants(expr, state, 0, &changed, false, &mut list);
+
+    let common = list.iter().enumerate().find_map(|(i, &(e, _))| {
+        if list[i + 1..].iter().any(|&(x, _)| is_same_expr(e, 0, x, 0)) {
+            Some(e.clone())
+        } else {
+            None
+        }
+    });
+
+    let common = match common {
+        Some(common) => common,
+        None => return,
+    };
+
+    let pos = common.position();
+    let var = state.new_hoisted_var(pos);
+    let done = state.new_hoisted_var(pos);
+
+    let hoisted = HoistedExpr {
+        expr: &common,
+        locals: 0,
+        var: &var,
+        done: &done,
+    };
+    hoisted.replace_in_expr(expr, 0);
+
+    state.set_dirty();
+    let block_pos = expr.position();
+    *expr = Expr::Stmt(Box::new(StmtBlock {
+        statements: match hoisted.declare(Stmt::Expr(mem::take(expr))) {
+            Stmt::Block(statements, _) => statements.into(),
+            _ => unreachable!(),
+        },
+        pos: block_pos,
+    }));
+}
+
 /// Optimize a block of [statements][Stmt].
 fn optimize_stmt_block(
     mut statements: Vec<Stmt>,
@@ -217,6 +1093,10 @@ fn optimize_stmt_block(
                 Stmt::Const(value_expr, Ident { name, .. }, _, _) => {
                     optimize_expr(value_expr, state);
 
+                    if state.optimization_level == OptimizationLevel::Full {
+                        eliminate_common_sub_expr(value_expr, state);
+                    }
+
                     if value_expr.is_constant() {
                         state.push_var(name, AccessMode::ReadOnly, value_expr.clone());
                     }
@@ -224,6 +1104,10 @@ fn optimize_stmt_block(
                 // Add variables into the state
                 Stmt::Let(value_expr, Ident { name, pos, .. }, _, _) => {
                     optimize_expr(value_expr, state);
+
+                    if state.optimization_level == OptimizationLevel::Full {
+                        eliminate_common_sub_expr(value_expr, state);
+                    }
                     state.push_var(name, AccessMode::ReadWrite, Expr::Unit(*pos));
                 }
                 // Optimize the statement
@@ -595,6 +1479,21 @@ fn optimize_stmt(stmt: &mut Stmt, state: &mut State, preserve_result: bool) {
         // All other statements - skip
         _ => (),
     }
+
+    if state.optimization_level == OptimizationLevel::Full {
+        match stmt {
+            // Hoist loop-invariant expressions
+            Stmt::While(_, _, _) | Stmt::Do(_, _, _, _) | Stmt::For(_, _, _) => {
+                hoist_loop_invariant(stmt, state)
+            }
+            // Eliminate common sub-expressions
+            Stmt::Expr(expr) | Stmt::Return(_, Some(expr), _) => {
+                eliminate_common_sub_expr(expr, state)
+            }
+            Stmt::Assignment(x, _) => eliminate_common_sub_expr(&mut x.1, state),
+            _ => (),
+        }
+    }
 }
 
 /// Optimize an [expression][Expr].
@@ -611,6 +1510,25 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
         Expr::Stmt(x) if x.statements.is_empty() => { state.set_dirty(); *expr = Expr::Unit(x.pos) }
         // { stmt; ... } - do not count promotion as dirty because it gets turned back into an array
         Expr::Stmt(x) => x.statements = optimize_stmt_block(mem::take(&mut x.statements).into_vec(), state, true, true, false).into(),
+        // Inlined function call
+        #[cfg(not(feature = "no_function"))]
+        Expr::InlinedFnCall(x, pos) => {
+            let body = &mut x.1;
+            body.statements = optimize_stmt_block(mem::take(&mut body.statements).into_vec(), state, true, true, false).into();
+
+            // A body that is empty or constant cannot raise errors
+            let value = match body.statements.as_mut() {
+                [] => Some(Expr::Unit(*pos)),
+                [Stmt::Expr(value)] if value.is_constant() => Some(mem::take(value)),
+                _ => None,
+            };
+
+            if let Some(mut value) = value {
+                state.set_dirty();
+                value.set_position(*pos);
+                *expr = value;
+            }
+        }
         // lhs.rhs
         #[cfg(not(feature = "no_object"))]
         Expr::Dot(x, _) => match (&mut x.lhs, &mut x.rhs) {
@@ -620,12 +1538,16 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
                 // Map literal where everything is pure - promote the indexed item.
                 // All other items can be thrown away.
                 state.set_dirty();
-                *expr = mem::take(m).into_iter().find(|(x, _)| &x.name == prop)
+                *expr = mem::take(m).into_iter().find(|(x, _)| x.as_str() == Some(prop.as_str()))
                             .map(|(_, mut expr)| { expr.set_position(*pos); expr })
                             .unwrap_or_else(|| Expr::Unit(*pos));
             }
+            // var.method(args ..) -> optimize method call arguments only
+            (Expr::Variable(_), Expr::FnCall(x, _)) => x.args.iter_mut().for_each(|a| optimize_expr(a, state)),
             // var.rhs
             (Expr::Variable(_), rhs) => optimize_expr(rhs, state),
+            // lhs.method(args ..) -> optimize method call arguments only
+            (lhs, Expr::FnCall(x, _)) => { optimize_expr(lhs, state); x.args.iter_mut().for_each(|a| optimize_expr(a, state)); }
             // lhs.rhs
             (lhs, rhs) => { optimize_expr(lhs, state); optimize_expr(rhs, state); }
         }
@@ -644,12 +1566,16 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
                 result.set_position(*pos);
                 *expr = result;
             }
-            // map[string]
-            (Expr::Map(m, pos), Expr::StringConstant(s, _)) if m.iter().all(|(_, x)| x.is_pure()) => {
+            // map[key]
+            (Expr::Map(m, pos), k @ Expr::StringConstant(_, _))
+            | (Expr::Map(m, pos), k @ Expr::IntegerConstant(_, _))
+            | (Expr::Map(m, pos), k @ Expr::CharConstant(_, _))
+            | (Expr::Map(m, pos), k @ Expr::BoolConstant(_, _)) if m.iter().all(|(_, x)| x.is_pure()) => {
+                let key = k.get_constant_value().unwrap().as_map_key().unwrap();
                 // Map literal where everything is pure - promote the indexed item.
                 // All other items can be thrown away.
                 state.set_dirty();
-                *expr = mem::take(m).into_iter().find(|(x, _)| x.name == *s)
+                *expr = mem::take(m).into_iter().find(|(x, _)| *x == key)
                             .map(|(_, mut expr)| { expr.set_position(*pos); expr })
                             .unwrap_or_else(|| Expr::Unit(*pos));
             }
@@ -766,6 +1692,18 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
             x.args.iter_mut().for_each(|a| optimize_expr(a, state));
         }
 
+        // Inline small script-defined functions
+        #[cfg(not(feature = "no_function"))]
+        Expr::FnCall(x, pos)
+                if state.optimization_level == OptimizationLevel::Full // full optimizations
+                && get_inlinable_fn(state, x).is_some()
+        => {
+            let fn_def = get_inlinable_fn(state, x).unwrap();
+            state.set_dirty();
+            *expr = inline_fn_call(fn_def, mem::take(&mut x.args), *pos);
+            optimize_expr(expr, state);
+        }
+
         // Eagerly call functions
         Expr::FnCall(x, pos)
                 if x.namespace.is_none() // Non-qualified
@@ -851,6 +1789,8 @@ fn optimize_top_level(
 
     // Set up the state
     let mut state = State::new(engine, lib, optimization_level);
+    #[cfg(not(feature = "no_closure"))]
+    state.capture_vars(&statements);
 
     // Add constants and variables from the scope
     scope.iter().for_each(|(name, constant, value)| {
@@ -886,11 +1826,20 @@ pub fn optimize_into_ast(
         let mut module = Module::new();
 
         if !level.is_none() {
-            // We only need the script library's signatures for optimization purposes
+            // We only need the script library's signatures for optimization purposes,
+            // except for full optimizations which may inline functions
             let mut lib2 = Module::new();
 
             _functions
                 .iter()
+                .filter(|_| level.is_full())
+                .for_each(|fn_def| {
+                    lib2.set_script_fn(fn_def.clone());
+                });
+
+            _functions
+                .iter()
+                .filter(|_| !level.is_full())
                 .map(|fn_def| crate::ast::ScriptFnDef {
                     name: fn_def.name.clone(),
                     access: fn_def.access,
@@ -902,6 +1851,7 @@ pub fn optimize_into_ast(
                     #[cfg(not(feature = "no_module"))]
                     mods: Default::default(),
                     comments: Default::default(),
+                    memo: Default::default(),
                 })
                 .for_each(|fn_def| {
                     lib2.set_script_fn(fn_def);
@@ -920,6 +1870,11 @@ pub fn optimize_into_ast(
 
                     // Optimize the function body
                     let state = &mut State::new(engine, lib2, level);
+                    #[cfg(not(feature = "no_closure"))]
+                    {
+                        state.capture_vars(&body);
+                        state.captured.extend(fn_def.externals.iter().cloned());
+                    }
 
                     body = optimize_stmt_block(body, state, true, true, true);
 
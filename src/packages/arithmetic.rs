@@ -176,31 +176,37 @@ macro_rules! reg_functions {
 }
 
 def_package!(crate:ArithmeticPackage:"Basic arithmetic", lib, {
-    reg_functions!(lib += signed_basic; INT);
+    lib.with_pure_fns(|lib| {
+        reg_functions!(lib += signed_basic; INT);
 
-    #[cfg(not(feature = "only_i32"))]
-    #[cfg(not(feature = "only_i64"))]
-    {
-        reg_functions!(lib += arith_numbers; i8, u8, i16, u16, i32, u32, u64);
-        reg_functions!(lib += signed_numbers; i8, i16, i32);
+        #[cfg(not(feature = "only_i32"))]
+        #[cfg(not(feature = "only_i64"))]
+        {
+            reg_functions!(lib += arith_numbers; i8, u8, i16, u16, i32, u32, u64);
+            reg_functions!(lib += signed_numbers; i8, i16, i32);
+
+            #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+            {
+                reg_functions!(lib += arith_num_128; i128, u128);
+                reg_functions!(lib += signed_num_128; i128);
+            }
+        }
 
-        #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+        // Basic arithmetic for floating-point
+        #[cfg(not(feature = "no_float"))]
         {
-            reg_functions!(lib += arith_num_128; i128, u128);
-            reg_functions!(lib += signed_num_128; i128);
+            combine_with_exported_module!(lib, "f32", f32_functions);
+            combine_with_exported_module!(lib, "f64", f64_functions);
         }
-    }
 
-    // Basic arithmetic for floating-point
-    #[cfg(not(feature = "no_float"))]
-    {
-        combine_with_exported_module!(lib, "f32", f32_functions);
-        combine_with_exported_module!(lib, "f64", f64_functions);
-    }
+        // Decimal functions
+        #[cfg(feature = "decimal")]
+        combine_with_exported_module!(lib, "decimal", decimal_functions);
 
-    // Decimal functions
-    #[cfg(feature = "decimal")]
-    combine_with_exported_module!(lib, "decimal", decimal_functions);
+        // BigInt functions
+        #[cfg(feature = "bigint")]
+        combine_with_exported_module!(lib, "bigint", bigint_functions);
+    });
 });
 
 gen_arithmetic_functions!(arith_basic => INT);
@@ -521,3 +527,139 @@ pub mod decimal_functions {
         }
     }
 }
+
+/// Make sure that a big integer with a particular number of bits, when written in decimal,
+/// is within the maximum string size.
+///
+/// A number of bits of [`None`] means that the number overflows a `u64`.
+#[cfg(feature = "bigint")]
+fn check_bigint_bits(
+    _ctx: &NativeCallContext,
+    bits: Option<u64>,
+) -> Result<(), Box<EvalAltResult>> {
+    let too_large = || -> Box<EvalAltResult> {
+        EvalAltResult::ErrorDataTooLarge("Size of number".into(), Position::NONE).into()
+    };
+
+    let _bits = bits.ok_or_else(too_large)?;
+
+    #[cfg(not(feature = "unchecked"))]
+    if _ctx.engine().max_string_size() > 0
+        && bigint_digits(_bits) > _ctx.engine().max_string_size() as u64
+    {
+        return Err(too_large());
+    }
+
+    Ok(())
+}
+
+/// Lower bound of the number of decimal digits of a big integer with a particular number of bits.
+#[cfg(feature = "bigint")]
+#[inline(always)]
+pub fn bigint_digits(bits: u64) -> u64 {
+    // log10(2) = 0.30102999..., rounded down
+    bits.saturating_sub(1).saturating_mul(30_102) / 100_000 + 1
+}
+
+#[cfg(feature = "bigint")]
+#[export_module]
+pub mod bigint_functions {
+    use num_bigint::{BigInt, Sign};
+    use num_traits::{Signed, ToPrimitive, Zero};
+
+    #[rhai_fn(skip, return_raw)]
+    pub fn divide(x: BigInt, y: BigInt) -> Result<Dynamic, Box<EvalAltResult>> {
+        // Detect division by zero
+        if y.is_zero() {
+            Err(make_err(format!("Division by zero: {} / {}", x, y)))
+        } else {
+            Ok(Dynamic::from(x / y))
+        }
+    }
+    #[rhai_fn(skip, return_raw)]
+    pub fn modulo(x: BigInt, y: BigInt) -> Result<Dynamic, Box<EvalAltResult>> {
+        if y.is_zero() {
+            Err(make_err(format!("Modulo division by zero: {} % {}", x, y)))
+        } else {
+            Ok(Dynamic::from(x % y))
+        }
+    }
+    #[rhai_fn(skip, return_raw)]
+    pub fn power(
+        ctx: NativeCallContext,
+        x: BigInt,
+        y: BigInt,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        if y.is_negative() {
+            Err(make_err(format!(
+                "Integer raised to a negative index: {} ~ {}",
+                x, y
+            )))
+        } else if let Some(n) = y.to_u32() {
+            // The result has at least this many bits
+            let bits = (x.bits().saturating_sub(1))
+                .checked_mul(n as u64)
+                .and_then(|b| b.checked_add(1));
+            check_bigint_bits(&ctx, bits)?;
+            Ok(Dynamic::from(x.pow(n)))
+        } else {
+            Err(make_err(format!(
+                "Integer raised to too large an index: {} ~ {}",
+                x, y
+            )))
+        }
+    }
+    #[rhai_fn(skip, return_raw)]
+    pub fn shift_left(
+        ctx: NativeCallContext,
+        x: BigInt,
+        y: BigInt,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        if y.is_negative() {
+            Err(make_err(format!(
+                "Left-shift by a negative number: {} << {}",
+                x, y
+            )))
+        } else if let Some(n) = y.to_usize() {
+            if !x.is_zero() {
+                check_bigint_bits(&ctx, x.bits().checked_add(n as u64))?;
+            }
+            Ok(Dynamic::from(x << n))
+        } else {
+            Err(make_err(format!(
+                "Left-shift by too many bits: {} << {}",
+                x, y
+            )))
+        }
+    }
+    #[rhai_fn(skip, return_raw)]
+    pub fn shift_right(x: BigInt, y: BigInt) -> Result<Dynamic, Box<EvalAltResult>> {
+        if y.is_negative() {
+            Err(make_err(format!(
+                "Right-shift by a negative number: {} >> {}",
+                x, y
+            )))
+        } else {
+            // Shifting by more bits than the number has always gives 0 or -1
+            Ok(Dynamic::from(x >> y.to_usize().unwrap_or(usize::MAX)))
+        }
+    }
+    #[rhai_fn(name = "-")]
+    pub fn neg(x: BigInt) -> BigInt {
+        -x
+    }
+    #[rhai_fn(name = "+")]
+    pub fn plus(x: BigInt) -> BigInt {
+        x
+    }
+    pub fn abs(x: BigInt) -> BigInt {
+        x.abs()
+    }
+    pub fn sign(x: BigInt) -> INT {
+        match x.sign() {
+            Sign::Minus => -1,
+            Sign::NoSign => 0,
+            Sign::Plus => 1,
+        }
+    }
+}
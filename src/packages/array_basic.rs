@@ -2,17 +2,123 @@
 #![allow(non_snake_case)]
 
 use crate::engine::{OP_EQUALS, TYPICAL_ARRAY_SIZE};
+use crate::fn_native::FnPtrCallback;
 use crate::plugin::*;
-use crate::stdlib::{any::TypeId, boxed::Box, cmp::max, cmp::Ordering, mem, string::ToString};
-use crate::{def_package, Array, Dynamic, EvalAltResult, FnPtr, NativeCallContext, Position, INT};
+use crate::stdlib::{
+    any::TypeId,
+    boxed::Box,
+    cmp::max,
+    cmp::Ordering,
+    mem,
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::{
+    def_package, Array, Dynamic, EvalAltResult, FnPtr, ImmutableString, NativeCallContext,
+    Position, INT,
+};
 
 def_package!(crate:BasicArrayPackage:"Basic array utilities.", lib, {
-    combine_with_exported_module!(lib, "array", array_functions);
+    lib.with_pure_fns(|lib| {
+        combine_with_exported_module!(lib, "array", array_functions);
 
-    // Register array iterator
-    lib.set_iterable::<Array>();
+        // Register array iterator
+        lib.set_iterable::<Array>();
+    });
 });
 
+/// Make sure that an array of a particular size is within the maximum array size.
+#[inline(always)]
+fn check_array_size(_ctx: &NativeCallContext, _len: usize) -> Result<(), Box<EvalAltResult>> {
+    #[cfg(not(feature = "unchecked"))]
+    if _ctx.engine().max_array_size() > 0 && _len > _ctx.engine().max_array_size() {
+        return EvalAltResult::ErrorDataTooLarge("Size of array".to_string(), Position::NONE)
+            .into();
+    }
+
+    Ok(())
+}
+
+/// Are two values equal, calling the `==` operator?
+///
+/// Values of different types without an `==` operator between them are not equal.
+fn values_equal(
+    ctx: &NativeCallContext,
+    x: &mut Dynamic,
+    y: &mut Dynamic,
+) -> Result<bool, Box<EvalAltResult>> {
+    ctx.call_fn_dynamic_raw(OP_EQUALS, true, &mut [x, y])
+        .or_else(|err| match *err {
+            EvalAltResult::ErrorFunctionNotFound(ref fn_sig, _)
+                if fn_sig.starts_with(OP_EQUALS) =>
+            {
+                if x.type_id() == y.type_id() {
+                    // No default when comparing same type
+                    Err(err)
+                } else {
+                    Ok(Dynamic::FALSE)
+                }
+            }
+            _ => Err(err),
+        })
+        .map(|r| r.as_bool().unwrap_or(false))
+}
+
+/// Compare two values, calling the `<` operator.
+fn compare_values(
+    ctx: &NativeCallContext,
+    x: &Dynamic,
+    y: &Dynamic,
+) -> Result<Ordering, Box<EvalAltResult>> {
+    if let (Ok(x), Ok(y)) = (x.as_int(), y.as_int()) {
+        ctx.inc_operations(1)?;
+        return Ok(x.cmp(&y));
+    }
+
+    let less_than = |a: &Dynamic, b: &Dynamic| {
+        ctx.call_fn_dynamic_raw("<", true, &mut [&mut a.clone(), &mut b.clone()])
+            .map(|r| r.as_bool().unwrap_or(false))
+    };
+
+    Ok(if less_than(x, y)? {
+        Ordering::Less
+    } else if less_than(y, x)? {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    })
+}
+
+/// Sort an array by keys, one for each item, stopping at the first failed comparison.
+fn sort_by_keys(
+    ctx: &NativeCallContext,
+    array: &mut Array,
+    keys: &[Dynamic],
+) -> Result<(), Box<EvalAltResult>> {
+    let mut error = None;
+    let mut indices: Vec<usize> = (0..array.len()).collect();
+
+    // The sort is stable, so items with equal keys keep their order
+    indices.sort_by(|&i, &j| {
+        if error.is_some() {
+            return Ordering::Equal;
+        }
+        compare_values(ctx, &keys[i], &keys[j]).unwrap_or_else(|err| {
+            error = Some(err);
+            Ordering::Equal
+        })
+    });
+
+    if let Some(err) = error {
+        return Err(err);
+    }
+
+    let mut items: Vec<_> = array.drain(..).map(Some).collect();
+    array.extend(indices.into_iter().map(|i| items[i].take().unwrap()));
+
+    Ok(())
+}
+
 #[export_module]
 mod array_functions {
     #[rhai_fn(name = "len", get = "len", pure)]
@@ -172,18 +278,12 @@ mod array_functions {
     ) -> Result<Dynamic, Box<EvalAltResult>> {
         let mut ar = Array::with_capacity(max(TYPICAL_ARRAY_SIZE, array.len()));
 
+        let mut callback = FnPtrCallback::new(ctx, &mapper, &[1, 2]);
+
         for (i, item) in array.iter().enumerate() {
             ar.push(
-                mapper
-                    .call_dynamic(ctx, None, [item.clone()])
-                    .or_else(|err| match *err {
-                        EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                            if fn_sig.starts_with(mapper.fn_name()) =>
-                        {
-                            mapper.call_dynamic(ctx, None, [item.clone(), (i as INT).into()])
-                        }
-                        _ => Err(err),
-                    })
+                callback
+                    .call(&mut [item.clone(), (i as INT).into()])
                     .map_err(|err| {
                         Box::new(EvalAltResult::ErrorInFunctionCall(
                             "map".to_string(),
@@ -205,17 +305,11 @@ mod array_functions {
     ) -> Result<Dynamic, Box<EvalAltResult>> {
         let mut ar = Array::with_capacity(max(TYPICAL_ARRAY_SIZE, array.len()));
 
+        let mut callback = FnPtrCallback::new(ctx, &filter, &[1, 2]);
+
         for (i, item) in array.iter().enumerate() {
-            if filter
-                .call_dynamic(ctx, None, [item.clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(filter.fn_name()) =>
-                    {
-                        filter.call_dynamic(ctx, None, [item.clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            if callback
+                .call(&mut [item.clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "filter".to_string(),
@@ -301,17 +395,11 @@ mod array_functions {
         array: &mut Array,
         filter: FnPtr,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut callback = FnPtrCallback::new(ctx, &filter, &[1, 2]);
+
         for (i, item) in array.iter().enumerate() {
-            if filter
-                .call_dynamic(ctx, None, [item.clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(filter.fn_name()) =>
-                    {
-                        filter.call_dynamic(ctx, None, [item.clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            if callback
+                .call(&mut [item.clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "index_of".to_string(),
@@ -335,17 +423,11 @@ mod array_functions {
         array: &mut Array,
         filter: FnPtr,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut callback = FnPtrCallback::new(ctx, &filter, &[1, 2]);
+
         for (i, item) in array.iter().enumerate() {
-            if filter
-                .call_dynamic(ctx, None, [item.clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(filter.fn_name()) =>
-                    {
-                        filter.call_dynamic(ctx, None, [item.clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            if callback
+                .call(&mut [item.clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "some".to_string(),
@@ -369,17 +451,11 @@ mod array_functions {
         array: &mut Array,
         filter: FnPtr,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut callback = FnPtrCallback::new(ctx, &filter, &[1, 2]);
+
         for (i, item) in array.iter().enumerate() {
-            if !filter
-                .call_dynamic(ctx, None, [item.clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(filter.fn_name()) =>
-                    {
-                        filter.call_dynamic(ctx, None, [item.clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            if !callback
+                .call(&mut [item.clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "all".to_string(),
@@ -405,17 +481,11 @@ mod array_functions {
     ) -> Result<Dynamic, Box<EvalAltResult>> {
         let mut result: Dynamic = Dynamic::UNIT;
 
+        let mut callback = FnPtrCallback::new(ctx, &reducer, &[2, 3]);
+
         for (i, item) in array.iter().enumerate() {
-            result = reducer
-                .call_dynamic(ctx, None, [result.clone(), item.clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(reducer.fn_name()) =>
-                    {
-                        reducer.call_dynamic(ctx, None, [result, item.clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            result = callback
+                .call(&mut [mem::take(&mut result), item.clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "reduce".to_string(),
@@ -444,17 +514,11 @@ mod array_functions {
             ))
         })?;
 
+        let mut callback = FnPtrCallback::new(ctx, &reducer, &[2, 3]);
+
         for (i, item) in array.iter().enumerate() {
-            result = reducer
-                .call_dynamic(ctx, None, [result.clone(), item.clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(reducer.fn_name()) =>
-                    {
-                        reducer.call_dynamic(ctx, None, [result, item.clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            result = callback
+                .call(&mut [mem::take(&mut result), item.clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "reduce".to_string(),
@@ -475,17 +539,11 @@ mod array_functions {
     ) -> Result<Dynamic, Box<EvalAltResult>> {
         let mut result: Dynamic = Dynamic::UNIT;
 
+        let mut callback = FnPtrCallback::new(ctx, &reducer, &[2, 3]);
+
         for (i, item) in array.iter().enumerate().rev() {
-            result = reducer
-                .call_dynamic(ctx, None, [result.clone(), item.clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(reducer.fn_name()) =>
-                    {
-                        reducer.call_dynamic(ctx, None, [result, item.clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            result = callback
+                .call(&mut [mem::take(&mut result), item.clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "reduce_rev".to_string(),
@@ -514,17 +572,11 @@ mod array_functions {
             ))
         })?;
 
+        let mut callback = FnPtrCallback::new(ctx, &reducer, &[2, 3]);
+
         for (i, item) in array.iter().enumerate().rev() {
-            result = reducer
-                .call_dynamic(ctx, None, [result.clone(), item.clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(reducer.fn_name()) =>
-                    {
-                        reducer.call_dynamic(ctx, None, [result, item.clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            result = callback
+                .call(&mut [mem::take(&mut result), item.clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "reduce_rev".to_string(),
@@ -543,9 +595,22 @@ mod array_functions {
         array: &mut Array,
         comparer: FnPtr,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut callback = FnPtrCallback::new(ctx, &comparer, &[2]);
+        let mut error = None;
+
         array.sort_by(|x, y| {
-            comparer
-                .call_dynamic(ctx, None, [x.clone(), y.clone()])
+            if error.is_some() {
+                return Ordering::Equal;
+            }
+
+            callback
+                .call(&mut [x.clone(), y.clone()])
+                .map_err(|err| {
+                    // Stop at errors such as exceeding the limit on the number of operations
+                    if !err.is_catchable() {
+                        error = Some(err);
+                    }
+                })
                 .ok()
                 .and_then(|v| v.as_int().ok())
                 .map(|v| {
@@ -571,7 +636,10 @@ mod array_functions {
                 })
         });
 
-        Ok(Dynamic::UNIT)
+        match error {
+            Some(err) => Err(err),
+            None => Ok(Dynamic::UNIT),
+        }
     }
     #[rhai_fn(return_raw)]
     pub fn drain(
@@ -581,21 +649,15 @@ mod array_functions {
     ) -> Result<Dynamic, Box<EvalAltResult>> {
         let mut drained = Array::with_capacity(max(TYPICAL_ARRAY_SIZE, array.len()));
 
+        let mut callback = FnPtrCallback::new(ctx, &filter, &[1, 2]);
+
         let mut i = array.len();
 
         while i > 0 {
             i -= 1;
 
-            if filter
-                .call_dynamic(ctx, None, [array[i].clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(filter.fn_name()) =>
-                    {
-                        filter.call_dynamic(ctx, None, [array[i].clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            if callback
+                .call(&mut [array[i].clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "drain".to_string(),
@@ -641,21 +703,15 @@ mod array_functions {
     ) -> Result<Dynamic, Box<EvalAltResult>> {
         let mut drained = Array::with_capacity(max(TYPICAL_ARRAY_SIZE, array.len()));
 
+        let mut callback = FnPtrCallback::new(ctx, &filter, &[1, 2]);
+
         let mut i = array.len();
 
         while i > 0 {
             i -= 1;
 
-            if !filter
-                .call_dynamic(ctx, None, [array[i].clone()])
-                .or_else(|err| match *err {
-                    EvalAltResult::ErrorFunctionNotFound(fn_sig, _)
-                        if fn_sig.starts_with(filter.fn_name()) =>
-                    {
-                        filter.call_dynamic(ctx, None, [array[i].clone(), (i as INT).into()])
-                    }
-                    _ => Err(err),
-                })
+            if !callback
+                .call(&mut [array[i].clone(), (i as INT).into()])
                 .map_err(|err| {
                     Box::new(EvalAltResult::ErrorInFunctionCall(
                         "retain".to_string(),
@@ -696,6 +752,389 @@ mod array_functions {
 
         drained
     }
+    #[rhai_fn(name = "sort", return_raw)]
+    pub fn sort_natural(
+        ctx: NativeCallContext,
+        array: &mut Array,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let keys = array.clone();
+        sort_by_keys(&ctx, array, &keys)?;
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw)]
+    pub fn sort_by(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        key: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut keys = Array::with_capacity(array.len());
+
+        let mut callback = FnPtrCallback::new(ctx, &key, &[1, 2]);
+
+        for (i, item) in array.iter().enumerate() {
+            keys.push(
+                callback
+                    .call(&mut [item.clone(), (i as INT).into()])
+                    .map_err(|err| {
+                        Box::new(EvalAltResult::ErrorInFunctionCall(
+                            "sort_by".to_string(),
+                            ctx.source().unwrap_or("").to_string(),
+                            err,
+                            Position::NONE,
+                        ))
+                    })?,
+            );
+        }
+
+        sort_by_keys(&ctx, array, &keys)?;
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw)]
+    pub fn dedup(ctx: NativeCallContext, array: &mut Array) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut result = Array::with_capacity(array.len());
+
+        for mut item in array.drain(..) {
+            if let Some(last) = result.last_mut() {
+                if values_equal(&ctx, last, &mut item)? {
+                    continue;
+                }
+            }
+            result.push(item);
+        }
+
+        *array = result;
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(name = "dedup", return_raw)]
+    pub fn dedup_by(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        comparer: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut result = Array::with_capacity(array.len());
+
+        let mut callback = FnPtrCallback::new(ctx, &comparer, &[2]);
+
+        for item in array.drain(..) {
+            if let Some(last) = result.last() {
+                if callback
+                    .call(&mut [last.clone(), item.clone()])
+                    .map_err(|err| {
+                        Box::new(EvalAltResult::ErrorInFunctionCall(
+                            "dedup".to_string(),
+                            ctx.source().unwrap_or("").to_string(),
+                            err,
+                            Position::NONE,
+                        ))
+                    })?
+                    .as_bool()
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+            }
+            result.push(item);
+        }
+
+        *array = result;
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn zip(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        array2: Array,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut ar = Array::with_capacity(array.len().min(array2.len()));
+
+        for (x, y) in array.iter().zip(array2) {
+            ctx.inc_operations(1)?;
+            ar.push(Dynamic::from(vec![x.clone(), y]));
+        }
+
+        Ok(ar.into())
+    }
+    #[rhai_fn(name = "zip", return_raw, pure)]
+    pub fn zip_with(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        array2: Array,
+        mapper: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut ar = Array::with_capacity(array.len().min(array2.len()));
+
+        let mut callback = FnPtrCallback::new(ctx, &mapper, &[2, 3]);
+
+        for (i, (x, y)) in array.iter().zip(array2).enumerate() {
+            ar.push(
+                callback
+                    .call(&mut [x.clone(), y, (i as INT).into()])
+                    .map_err(|err| {
+                        Box::new(EvalAltResult::ErrorInFunctionCall(
+                            "zip".to_string(),
+                            ctx.source().unwrap_or("").to_string(),
+                            err,
+                            Position::NONE,
+                        ))
+                    })?,
+            );
+        }
+
+        Ok(ar.into())
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn chunks(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        size: INT,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let size = if size < 1 { 1 } else { size as usize };
+        let mut ar = Array::with_capacity(array.len() / size + 1);
+
+        for chunk in array.chunks(size) {
+            ctx.inc_operations(chunk.len() as u64)?;
+            ar.push(Dynamic::from(chunk.to_vec()));
+        }
+
+        Ok(ar.into())
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn windows(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        size: INT,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let size = if size < 1 { 1 } else { size as usize };
+
+        // Check if the windows will be over max size limit in total
+        let total = array.len().saturating_sub(size - 1).saturating_mul(size);
+        check_array_size(&ctx, total)?;
+
+        let mut ar = Array::with_capacity(array.len().saturating_sub(size - 1));
+
+        for window in array.windows(size) {
+            ctx.inc_operations(size as u64)?;
+            ar.push(Dynamic::from(window.to_vec()));
+        }
+
+        Ok(ar.into())
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn flatten(
+        ctx: NativeCallContext,
+        array: &mut Array,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut ar = Array::with_capacity(max(TYPICAL_ARRAY_SIZE, array.len()));
+
+        for item in array.iter() {
+            if item.is::<Array>() {
+                let items = item.clone().cast::<Array>();
+                ctx.inc_operations(items.len() as u64)?;
+                ar.extend(items);
+            } else {
+                ctx.inc_operations(1)?;
+                ar.push(item.clone());
+            }
+            check_array_size(&ctx, ar.len())?;
+        }
+
+        Ok(ar.into())
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn flat_map(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        mapper: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut ar = Array::with_capacity(max(TYPICAL_ARRAY_SIZE, array.len()));
+
+        let mut callback = FnPtrCallback::new(ctx, &mapper, &[1, 2]);
+
+        for (i, item) in array.iter().enumerate() {
+            let value = callback
+                .call(&mut [item.clone(), (i as INT).into()])
+                .map_err(|err| {
+                    Box::new(EvalAltResult::ErrorInFunctionCall(
+                        "flat_map".to_string(),
+                        ctx.source().unwrap_or("").to_string(),
+                        err,
+                        Position::NONE,
+                    ))
+                })?;
+
+            if value.is::<Array>() {
+                ar.extend(value.cast::<Array>());
+            } else {
+                ar.push(value);
+            }
+            check_array_size(&ctx, ar.len())?;
+        }
+
+        Ok(ar.into())
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn find(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        filter: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut callback = FnPtrCallback::new(ctx, &filter, &[1, 2]);
+
+        for (i, item) in array.iter().enumerate() {
+            if callback
+                .call(&mut [item.clone(), (i as INT).into()])
+                .map_err(|err| {
+                    Box::new(EvalAltResult::ErrorInFunctionCall(
+                        "find".to_string(),
+                        ctx.source().unwrap_or("").to_string(),
+                        err,
+                        Position::NONE,
+                    ))
+                })?
+                .as_bool()
+                .unwrap_or(false)
+            {
+                return Ok(item.clone());
+            }
+        }
+
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn find_map(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        mapper: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut callback = FnPtrCallback::new(ctx, &mapper, &[1, 2]);
+
+        for (i, item) in array.iter().enumerate() {
+            let value = callback
+                .call(&mut [item.clone(), (i as INT).into()])
+                .map_err(|err| {
+                    Box::new(EvalAltResult::ErrorInFunctionCall(
+                        "find_map".to_string(),
+                        ctx.source().unwrap_or("").to_string(),
+                        err,
+                        Position::NONE,
+                    ))
+                })?;
+
+            if !value.is::<()>() {
+                return Ok(value);
+            }
+        }
+
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn for_each(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        action: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut callback = FnPtrCallback::new(ctx, &action, &[1, 2]);
+
+        for (i, item) in array.iter().enumerate() {
+            callback
+                .call(&mut [item.clone(), (i as INT).into()])
+                .map_err(|err| {
+                    Box::new(EvalAltResult::ErrorInFunctionCall(
+                        "for_each".to_string(),
+                        ctx.source().unwrap_or("").to_string(),
+                        err,
+                        Position::NONE,
+                    ))
+                })?;
+        }
+
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn sum(ctx: NativeCallContext, array: &mut Array) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut items = array.iter();
+
+        let mut result = match items.next() {
+            Some(item) => item.clone(),
+            None => return Ok((0 as INT).into()),
+        };
+
+        for item in items {
+            result = ctx.call_fn_dynamic_raw("+", true, &mut [&mut result, &mut item.clone()])?;
+        }
+
+        Ok(result)
+    }
+    #[rhai_fn(name = "min", return_raw, pure)]
+    pub fn min_item(
+        ctx: NativeCallContext,
+        array: &mut Array,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut result: Option<&Dynamic> = None;
+
+        for item in array.iter() {
+            match result {
+                Some(min) if compare_values(&ctx, item, min)? != Ordering::Less => (),
+                _ => result = Some(item),
+            }
+        }
+
+        Ok(result.cloned().unwrap_or(Dynamic::UNIT))
+    }
+    #[rhai_fn(name = "max", return_raw, pure)]
+    pub fn max_item(
+        ctx: NativeCallContext,
+        array: &mut Array,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut result: Option<&Dynamic> = None;
+
+        for item in array.iter() {
+            match result {
+                Some(max) if compare_values(&ctx, item, max)? != Ordering::Greater => (),
+                _ => result = Some(item),
+            }
+        }
+
+        Ok(result.cloned().unwrap_or(Dynamic::UNIT))
+    }
+    #[rhai_fn(return_raw, pure)]
+    pub fn join(
+        ctx: NativeCallContext,
+        array: &mut Array,
+        separator: &str,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut result = String::new();
+
+        for (i, item) in array.iter_mut().enumerate() {
+            ctx.inc_operations(1)?;
+
+            if i > 0 {
+                result.push_str(separator);
+            }
+
+            if item.is::<ImmutableString>() {
+                result.push_str(item.read_lock::<ImmutableString>().unwrap().as_str());
+            } else {
+                match ctx.call_fn_dynamic_raw("to_string", true, &mut [item]) {
+                    Ok(s) if s.is::<ImmutableString>() => {
+                        result.push_str(s.take_immutable_string().unwrap().as_str())
+                    }
+                    _ => result.push_str(&item.to_string()),
+                }
+            }
+
+            #[cfg(not(feature = "unchecked"))]
+            if ctx.engine().max_string_size() > 0 && result.len() > ctx.engine().max_string_size() {
+                return EvalAltResult::ErrorDataTooLarge(
+                    "Length of string".to_string(),
+                    Position::NONE,
+                )
+                .into();
+            }
+        }
+
+        Ok(result.into())
+    }
     #[rhai_fn(name = "==", return_raw, pure)]
     pub fn equals(
         ctx: NativeCallContext,
@@ -0,0 +1,362 @@
+#![cfg(not(feature = "no_index"))]
+#![allow(non_snake_case)]
+
+use crate::plugin::*;
+use crate::stdlib::{any::TypeId, boxed::Box, format, mem, string::String, string::ToString};
+use crate::{def_package, Blob, Dynamic, EvalAltResult, NativeCallContext, Position, INT};
+
+/// Number of bytes in an integer.
+const INT_BYTES: usize = mem::size_of::<INT>();
+
+/// Characters used in base-64 encoding.
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+def_package!(crate:BasicBlobPackage:"Basic BLOB utilities.", lib, {
+    lib.with_pure_fns(|lib| {
+        combine_with_exported_module!(lib, "blob", blob_functions);
+
+        // Register BLOB iterator
+        lib.set_iter(
+            TypeId::of::<Blob>(),
+            |blob| Box::new(blob.cast::<Blob>().into_iter().map(|byte| (byte as INT).into()))
+        );
+    });
+});
+
+/// Make sure that a BLOB of a particular length is within the maximum array size.
+#[inline(always)]
+fn check_blob_size(_ctx: &NativeCallContext, _len: usize) -> Result<(), Box<EvalAltResult>> {
+    #[cfg(not(feature = "unchecked"))]
+    if _ctx.engine().max_array_size() > 0 && _len > _ctx.engine().max_array_size() {
+        return EvalAltResult::ErrorDataTooLarge("Size of BLOB".to_string(), Position::NONE).into();
+    }
+
+    Ok(())
+}
+
+/// Calculate the range of bytes covered by a starting position and a length,
+/// clamped to the bounds of a BLOB.
+#[inline(always)]
+fn calc_range(blob: &Blob, start: INT, len: INT) -> (usize, usize) {
+    let start = if start < 0 {
+        0
+    } else if start as usize > blob.len() {
+        blob.len()
+    } else {
+        start as usize
+    };
+
+    let len = if len < 0 {
+        0
+    } else if len as usize > blob.len() - start {
+        blob.len() - start
+    } else {
+        len as usize
+    };
+
+    (start, start + len)
+}
+
+/// Get the value of a hex digit.
+#[inline(always)]
+fn hex_digit(ch: u8) -> Option<u8> {
+    match ch {
+        b'0'..=b'9' => Some(ch - b'0'),
+        b'a'..=b'f' => Some(ch - b'a' + 10),
+        b'A'..=b'F' => Some(ch - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[export_module]
+mod blob_functions {
+    #[rhai_fn(name = "blob")]
+    pub fn new_blob() -> Blob {
+        Blob::new()
+    }
+    #[rhai_fn(name = "blob", return_raw)]
+    pub fn new_blob_with_len(
+        ctx: NativeCallContext,
+        len: INT,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        new_blob_with_value(ctx, len, 0)
+    }
+    #[rhai_fn(name = "blob", return_raw)]
+    pub fn new_blob_with_value(
+        ctx: NativeCallContext,
+        len: INT,
+        value: INT,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let len = if len < 0 { 0 } else { len as usize };
+
+        check_blob_size(&ctx, len)?;
+
+        let mut blob = Blob::new();
+        blob.resize(len, (value & 0xff) as u8);
+        Ok(Dynamic::from(blob))
+    }
+    #[rhai_fn(name = "len", get = "len", pure)]
+    pub fn len(blob: &mut Blob) -> INT {
+        blob.len() as INT
+    }
+    #[rhai_fn(name = "push", name = "+=", return_raw)]
+    pub fn push(
+        ctx: NativeCallContext,
+        blob: &mut Blob,
+        value: INT,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        check_blob_size(&ctx, blob.len() + 1)?;
+        blob.push((value & 0xff) as u8);
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(name = "append", name = "+=", return_raw)]
+    pub fn append(
+        ctx: NativeCallContext,
+        blob: &mut Blob,
+        y: Blob,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        check_blob_size(&ctx, blob.len() + y.len())?;
+        blob.extend(y);
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(name = "+")]
+    pub fn concat(mut blob: Blob, y: Blob) -> Blob {
+        blob.extend(y);
+        blob
+    }
+    #[rhai_fn(return_raw)]
+    pub fn insert(
+        ctx: NativeCallContext,
+        blob: &mut Blob,
+        position: INT,
+        value: INT,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        check_blob_size(&ctx, blob.len() + 1)?;
+
+        let value = (value & 0xff) as u8;
+
+        if position <= 0 {
+            blob.insert(0, value);
+        } else if (position as usize) >= blob.len() {
+            blob.push(value);
+        } else {
+            blob.insert(position as usize, value);
+        }
+
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw)]
+    pub fn pad(
+        ctx: NativeCallContext,
+        blob: &mut Blob,
+        len: INT,
+        value: INT,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        if len > 0 && len as usize > blob.len() {
+            check_blob_size(&ctx, len as usize)?;
+            blob.resize(len as usize, (value & 0xff) as u8);
+        }
+
+        Ok(Dynamic::UNIT)
+    }
+    pub fn pop(blob: &mut Blob) -> Dynamic {
+        blob.pop()
+            .map_or(Dynamic::UNIT, |byte| (byte as INT).into())
+    }
+    pub fn shift(blob: &mut Blob) -> Dynamic {
+        if blob.is_empty() {
+            Dynamic::UNIT
+        } else {
+            (blob.remove(0) as INT).into()
+        }
+    }
+    pub fn remove(blob: &mut Blob, index: INT) -> Dynamic {
+        if index < 0 || (index as usize) >= blob.len() {
+            Dynamic::UNIT
+        } else {
+            (blob.remove(index as usize) as INT).into()
+        }
+    }
+    pub fn clear(blob: &mut Blob) {
+        blob.clear();
+    }
+    pub fn truncate(blob: &mut Blob, len: INT) {
+        if len >= 0 {
+            blob.truncate(len as usize);
+        } else {
+            blob.clear();
+        }
+    }
+    pub fn chop(blob: &mut Blob, len: INT) {
+        if len < 0 {
+            blob.clear();
+        } else if (len as usize) < blob.len() {
+            blob.drain(0..blob.len() - len as usize);
+        }
+    }
+    pub fn reverse(blob: &mut Blob) {
+        blob.reverse();
+    }
+    #[rhai_fn(return_raw)]
+    pub fn splice(
+        ctx: NativeCallContext,
+        blob: &mut Blob,
+        start: INT,
+        len: INT,
+        replace: Blob,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let (start, end) = calc_range(blob, start, len);
+
+        check_blob_size(&ctx, blob.len() - (end - start) + replace.len())?;
+
+        blob.splice(start..end, replace);
+        Ok(Dynamic::UNIT)
+    }
+    pub fn extract(blob: &mut Blob, start: INT, len: INT) -> Blob {
+        let (start, end) = calc_range(blob, start, len);
+        blob[start..end].to_vec()
+    }
+    #[rhai_fn(name = "extract")]
+    pub fn extract_tail(blob: &mut Blob, start: INT) -> Blob {
+        let (start, end) = calc_range(blob, start, blob.len() as INT);
+        blob[start..end].to_vec()
+    }
+    #[rhai_fn(name = "split")]
+    pub fn split_at(blob: &mut Blob, start: INT) -> Blob {
+        let (start, _) = calc_range(blob, start, 0);
+        blob.split_off(start)
+    }
+    #[rhai_fn(name = "==", pure)]
+    pub fn equals(blob: &mut Blob, blob2: Blob) -> bool {
+        *blob == blob2
+    }
+    #[rhai_fn(name = "!=", pure)]
+    pub fn not_equals(blob: &mut Blob, blob2: Blob) -> bool {
+        *blob != blob2
+    }
+
+    #[rhai_fn(pure)]
+    pub fn parse_le_int(blob: &mut Blob, start: INT, len: INT) -> INT {
+        let (start, end) = calc_range(blob, start, len.min(INT_BYTES as INT));
+        let mut buf = [0_u8; INT_BYTES];
+        buf[..end - start].copy_from_slice(&blob[start..end]);
+        INT::from_le_bytes(buf)
+    }
+    #[rhai_fn(pure)]
+    pub fn parse_be_int(blob: &mut Blob, start: INT, len: INT) -> INT {
+        let (start, end) = calc_range(blob, start, len.min(INT_BYTES as INT));
+        let mut buf = [0_u8; INT_BYTES];
+        buf[INT_BYTES - (end - start)..].copy_from_slice(&blob[start..end]);
+        INT::from_be_bytes(buf)
+    }
+    pub fn write_le_int(blob: &mut Blob, start: INT, len: INT, value: INT) {
+        let (start, end) = calc_range(blob, start, len.min(INT_BYTES as INT));
+        blob[start..end].copy_from_slice(&value.to_le_bytes()[..end - start]);
+    }
+    pub fn write_be_int(blob: &mut Blob, start: INT, len: INT, value: INT) {
+        let (start, end) = calc_range(blob, start, len.min(INT_BYTES as INT));
+        let len = end - start;
+        blob[start..end].copy_from_slice(&value.to_be_bytes()[INT_BYTES - len..]);
+    }
+
+    #[rhai_fn(pure)]
+    pub fn as_string(blob: &mut Blob) -> String {
+        String::from_utf8_lossy(blob).into_owned()
+    }
+    pub fn to_blob(string: &str) -> Blob {
+        string.as_bytes().to_vec()
+    }
+    #[rhai_fn(pure)]
+    pub fn to_hex(blob: &mut Blob) -> String {
+        const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+
+        let mut result = String::with_capacity(blob.len() * 2);
+        blob.iter().for_each(|&byte| {
+            result.push(HEX_CHARS[(byte >> 4) as usize] as char);
+            result.push(HEX_CHARS[(byte & 0x0f) as usize] as char);
+        });
+        result
+    }
+    #[rhai_fn(return_raw)]
+    pub fn parse_hex(ctx: NativeCallContext, s: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let digits = s.trim().as_bytes();
+
+        let make_err = || {
+            EvalAltResult::ErrorArithmetic(
+                format!("Error parsing hex string '{}'", s),
+                Position::NONE,
+            )
+            .into()
+        };
+
+        if digits.len() & 1 == 1 {
+            return Err(make_err());
+        }
+
+        check_blob_size(&ctx, digits.len() / 2)?;
+
+        digits
+            .chunks(2)
+            .map(|pair| Some((hex_digit(pair[0])? << 4) | hex_digit(pair[1])?))
+            .collect::<Option<Blob>>()
+            .map(Dynamic::from)
+            .ok_or_else(make_err)
+    }
+    #[rhai_fn(pure)]
+    pub fn to_base64(blob: &mut Blob) -> String {
+        let mut result = String::with_capacity(blob.len().div_ceil(3) * 4);
+
+        blob.chunks(3).for_each(|chunk| {
+            let bits = chunk.iter().enumerate().fold(0_u32, |bits, (i, &byte)| {
+                bits | ((byte as u32) << (16 - i * 8))
+            });
+
+            (0..4).for_each(|i| {
+                if i <= chunk.len() {
+                    let index = (bits >> (18 - i * 6)) & 0x3f;
+                    result.push(BASE64_CHARS[index as usize] as char);
+                } else {
+                    result.push('=');
+                }
+            });
+        });
+
+        result
+    }
+    #[rhai_fn(return_raw)]
+    pub fn parse_base64(ctx: NativeCallContext, s: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let chars = s.trim().trim_end_matches('=').as_bytes();
+
+        let make_err = || {
+            EvalAltResult::ErrorArithmetic(
+                format!("Error parsing base-64 string '{}'", s),
+                Position::NONE,
+            )
+            .into()
+        };
+
+        if chars.len() % 4 == 1 {
+            return Err(make_err());
+        }
+
+        check_blob_size(&ctx, chars.len() * 3 / 4)?;
+
+        let mut blob = Blob::with_capacity(chars.len() * 3 / 4);
+
+        for chunk in chars.chunks(4) {
+            let mut bits = 0_u32;
+
+            for (i, ch) in chunk.iter().enumerate() {
+                let value = BASE64_CHARS
+                    .iter()
+                    .position(|c| c == ch)
+                    .ok_or_else(make_err)?;
+                bits |= (value as u32) << (18 - i * 6);
+            }
+
+            (0..chunk.len() - 1).for_each(|i| blob.push((bits >> (16 - i * 8)) as u8));
+        }
+
+        Ok(Dynamic::from(blob))
+    }
+}
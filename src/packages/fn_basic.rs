@@ -2,7 +2,9 @@ use crate::plugin::*;
 use crate::{def_package, FnPtr, ImmutableString, NativeCallContext};
 
 def_package!(crate:BasicFnPackage:"Basic Fn functions.", lib, {
-    combine_with_exported_module!(lib, "FnPtr", fn_ptr_functions);
+    lib.with_pure_fns(|lib| {
+        combine_with_exported_module!(lib, "FnPtr", fn_ptr_functions);
+    });
 });
 
 #[export_module]
@@ -45,11 +47,11 @@ fn collect_fn_metadata(ctx: NativeCallContext) -> crate::Array {
         let mut map = Map::with_capacity(6);
 
         if let Some(ns) = namespace {
-            map.insert(dict["namespace"].clone(), ns.into());
+            map.insert(dict["namespace"].clone().into(), ns.into());
         }
-        map.insert(dict["name"].clone(), f.name.clone().into());
+        map.insert(dict["name"].clone().into(), f.name.clone().into());
         map.insert(
-            dict["access"].clone(),
+            dict["access"].clone().into(),
             match f.access {
                 FnAccess::Public => dict["public"].clone(),
                 FnAccess::Private => dict["private"].clone(),
@@ -57,11 +59,11 @@ fn collect_fn_metadata(ctx: NativeCallContext) -> crate::Array {
             .into(),
         );
         map.insert(
-            dict["is_anonymous"].clone(),
+            dict["is_anonymous"].clone().into(),
             f.name.starts_with(crate::engine::FN_ANONYMOUS).into(),
         );
         map.insert(
-            dict["params"].clone(),
+            dict["params"].clone().into(),
             f.params
                 .iter()
                 .cloned()
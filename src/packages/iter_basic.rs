@@ -164,94 +164,96 @@ macro_rules! reg_range {
 }
 
 def_package!(crate:BasicIteratorPackage:"Basic range iterators.", lib, {
-    reg_range!(lib | "range" => INT);
+    lib.with_pure_fns(|lib| {
+        reg_range!(lib | "range" => INT);
 
-    #[cfg(not(feature = "only_i32"))]
-    #[cfg(not(feature = "only_i64"))]
-    {
-        reg_range!(lib | "range" => i8, u8, i16, u16, i32, u32, i64, u64);
+        #[cfg(not(feature = "only_i32"))]
+        #[cfg(not(feature = "only_i64"))]
+        {
+            reg_range!(lib | "range" => i8, u8, i16, u16, i32, u32, i64, u64);
 
-        if cfg!(not(target_arch = "wasm32")) {
-            reg_range!(lib | "range" => i128, u128);
+            if cfg!(not(target_arch = "wasm32")) {
+                reg_range!(lib | "range" => i128, u128);
+            }
         }
-    }
-
-    reg_range!(lib | step "range" => INT);
 
-    #[cfg(not(feature = "only_i32"))]
-    #[cfg(not(feature = "only_i64"))]
-    {
-        reg_range!(lib | step "range" => i8, u8, i16, u16, i32, u32, i64, u64);
-
-        if cfg!(not(target_arch = "wasm32")) {
-            reg_range!(lib | step "range" => i128, u128);
-        }
-    }
+        reg_range!(lib | step "range" => INT);
 
-    #[cfg(feature = "decimal")]
-    {
-        use rust_decimal::{
-            prelude::{One, Zero},
-            Decimal,
-        };
-
-        #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
-        struct StepDecimalRange(Decimal, Decimal, Decimal);
-
-        impl StepDecimalRange {
-            pub fn new(from: Decimal, to: Decimal, step: Decimal) -> Result<Self, Box<EvalAltResult>> {
-                #[cfg(not(feature = "unchecked"))]
-                if step.is_zero() {
-                    use crate::stdlib::string::ToString;
-
-                    return Err(Box::new(EvalAltResult::ErrorInFunctionCall("range".to_string(), "".to_string(),
-                        Box::new(EvalAltResult::ErrorArithmetic("step value cannot be zero".to_string(), crate::Position::NONE)),
-                        crate::Position::NONE,
-                    )));
-                }
+        #[cfg(not(feature = "only_i32"))]
+        #[cfg(not(feature = "only_i64"))]
+        {
+            reg_range!(lib | step "range" => i8, u8, i16, u16, i32, u32, i64, u64);
 
-                Ok(Self(from, to, step))
+            if cfg!(not(target_arch = "wasm32")) {
+                reg_range!(lib | step "range" => i128, u128);
             }
         }
 
-        impl Iterator for StepDecimalRange {
-            type Item = Decimal;
-
-            fn next(&mut self) -> Option<Decimal> {
-                if self.0 == self.1 {
-                    None
-                } else if self.0 < self.1 {
-                    #[cfg(not(feature = "unchecked"))]
-                    if self.2.is_sign_negative() {
-                        return None;
-                    }
+        #[cfg(feature = "decimal")]
+        {
+            use rust_decimal::{
+                prelude::{One, Zero},
+                Decimal,
+            };
 
-                    let v = self.0;
-                    let n = self.0 + self.2;
+            #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+            struct StepDecimalRange(Decimal, Decimal, Decimal);
 
-                    self.0 = if n >= self.1 { self.1 } else { n };
-                    Some(v)
-                } else {
+            impl StepDecimalRange {
+                pub fn new(from: Decimal, to: Decimal, step: Decimal) -> Result<Self, Box<EvalAltResult>> {
                     #[cfg(not(feature = "unchecked"))]
-                    if self.2.is_sign_positive() {
-                        return None;
+                    if step.is_zero() {
+                        use crate::stdlib::string::ToString;
+
+                        return Err(Box::new(EvalAltResult::ErrorInFunctionCall("range".to_string(), "".to_string(),
+                            Box::new(EvalAltResult::ErrorArithmetic("step value cannot be zero".to_string(), crate::Position::NONE)),
+                            crate::Position::NONE,
+                        )));
                     }
 
-                    let v = self.0;
-                    let n = self.0 + self.2;
+                    Ok(Self(from, to, step))
+                }
+            }
 
-                    self.0 = if n <= self.1 { self.1 } else { n };
-                    Some(v)
+            impl Iterator for StepDecimalRange {
+                type Item = Decimal;
+
+                fn next(&mut self) -> Option<Decimal> {
+                    if self.0 == self.1 {
+                        None
+                    } else if self.0 < self.1 {
+                        #[cfg(not(feature = "unchecked"))]
+                        if self.2.is_sign_negative() {
+                            return None;
+                        }
+
+                        let v = self.0;
+                        let n = self.0 + self.2;
+
+                        self.0 = if n >= self.1 { self.1 } else { n };
+                        Some(v)
+                    } else {
+                        #[cfg(not(feature = "unchecked"))]
+                        if self.2.is_sign_positive() {
+                            return None;
+                        }
+
+                        let v = self.0;
+                        let n = self.0 + self.2;
+
+                        self.0 = if n <= self.1 { self.1 } else { n };
+                        Some(v)
+                    }
                 }
             }
-        }
 
-        lib.set_iterator::<StepDecimalRange>();
+            lib.set_iterator::<StepDecimalRange>();
 
-        let hash = lib.set_fn_2("range", |from, to| StepDecimalRange::new(from, to, Decimal::one()));
-        lib.update_fn_metadata(hash, &["from: Decimal", "to: Decimal", "Iterator<Item=Decimal>"]);
+            let hash = lib.set_fn_2("range", |from, to| StepDecimalRange::new(from, to, Decimal::one()));
+            lib.update_fn_metadata(hash, &["from: Decimal", "to: Decimal", "Iterator<Item=Decimal>"]);
 
-        let hash = lib.set_fn_3("range", |from, to, step| StepDecimalRange::new(from, to, step));
-        lib.update_fn_metadata(hash, &["from: Decimal", "to: Decimal", "step: Decimal", "Iterator<Item=Decimal>"]);
-    }
+            let hash = lib.set_fn_3("range", |from, to, step| StepDecimalRange::new(from, to, step));
+            lib.update_fn_metadata(hash, &["from: Decimal", "to: Decimal", "step: Decimal", "Iterator<Item=Decimal>"]);
+        }
+    });
 });
@@ -2,20 +2,25 @@
 
 use crate::engine::OP_EQUALS;
 use crate::plugin::*;
-use crate::{def_package, Dynamic, ImmutableString, Map, INT};
+use crate::{def_package, Dynamic, Map, INT};
 
 #[cfg(not(feature = "no_index"))]
 use crate::Array;
 
 def_package!(crate:BasicMapPackage:"Basic object map utilities.", lib, {
-    combine_with_exported_module!(lib, "map", map_functions);
+    lib.with_pure_fns(|lib| {
+        combine_with_exported_module!(lib, "map", map_functions);
+    });
 });
 
 #[export_module]
 mod map_functions {
-    #[rhai_fn(name = "has", pure)]
-    pub fn contains(map: &mut Map, prop: ImmutableString) -> bool {
-        map.contains_key(&prop)
+    #[rhai_fn(name = "has", name = "contains", pure)]
+    pub fn contains(map: &mut Map, key: Dynamic) -> bool {
+        match key.as_map_key() {
+            Ok(key) => map.contains_key(&key),
+            Err(_) => false,
+        }
     }
     #[rhai_fn(pure)]
     pub fn len(map: &mut Map) -> INT {
@@ -24,8 +29,18 @@ mod map_functions {
     pub fn clear(map: &mut Map) {
         map.clear();
     }
-    pub fn remove(map: &mut Map, name: ImmutableString) -> Dynamic {
-        map.remove(&name).unwrap_or_else(|| ().into())
+    pub fn remove(map: &mut Map, key: Dynamic) -> Dynamic {
+        let key = match key.as_map_key() {
+            Ok(key) => key,
+            Err(_) => return ().into(),
+        };
+
+        #[cfg(not(feature = "ordered_map"))]
+        let value = map.remove(&key);
+        #[cfg(feature = "ordered_map")]
+        let value = map.shift_remove(&key);
+
+        value.unwrap_or_else(|| ().into())
     }
     #[rhai_fn(name = "mixin", name = "+=")]
     pub fn mixin(map: &mut Map, map2: Map) {
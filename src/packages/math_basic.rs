@@ -19,7 +19,10 @@ use crate::stdlib::format;
 #[cfg(feature = "decimal")]
 use rust_decimal::Decimal;
 
-#[cfg(feature = "decimal")]
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
+#[cfg(any(feature = "decimal", feature = "bigint"))]
 use super::arithmetic::make_err;
 
 #[allow(dead_code)]
@@ -42,7 +45,7 @@ macro_rules! gen_conversion_as_functions {
     }
 }
 
-#[cfg(feature = "decimal")]
+#[cfg(any(feature = "decimal", feature = "bigint"))]
 macro_rules! gen_conversion_into_functions {
     ($root:ident => $func_name:ident ( $($arg_type:ident),+ ) -> $result_type:ty) => {
         pub mod $root { $(pub mod $arg_type {
@@ -63,58 +66,77 @@ macro_rules! reg_functions {
 }
 
 def_package!(crate:BasicMathPackage:"Basic mathematic functions.", lib, {
-    // Integer functions
-    combine_with_exported_module!(lib, "int", int_functions);
+    lib.with_pure_fns(|lib| {
+        // Integer functions
+        combine_with_exported_module!(lib, "int", int_functions);
 
-    reg_functions!(lib += basic_to_int::to_int(char));
+        reg_functions!(lib += basic_to_int::to_int(char));
 
-    #[cfg(not(feature = "only_i32"))]
-    #[cfg(not(feature = "only_i64"))]
-    {
-        reg_functions!(lib += numbers_to_int::to_int(i8, u8, i16, u16, i32, u32, i64, u64));
+        #[cfg(not(feature = "only_i32"))]
+        #[cfg(not(feature = "only_i64"))]
+        {
+            reg_functions!(lib += numbers_to_int::to_int(i8, u8, i16, u16, i32, u32, i64, u64));
 
-        #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-        reg_functions!(lib += num_128_to_int::to_int(i128, u128));
-    }
+            #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+            reg_functions!(lib += num_128_to_int::to_int(i128, u128));
+        }
 
-    #[cfg(not(feature = "no_float"))]
-    {
-        // Floating point functions
-        combine_with_exported_module!(lib, "float", float_functions);
+        #[cfg(not(feature = "no_float"))]
+        {
+            // Floating point functions
+            combine_with_exported_module!(lib, "float", float_functions);
 
-        // Trig functions
-        combine_with_exported_module!(lib, "trig", trig_functions);
+            // Trig functions
+            combine_with_exported_module!(lib, "trig", trig_functions);
 
-        reg_functions!(lib += basic_to_float::to_float(INT));
+            reg_functions!(lib += basic_to_float::to_float(INT));
 
-        #[cfg(not(feature = "only_i32"))]
-        #[cfg(not(feature = "only_i64"))]
+            #[cfg(not(feature = "only_i32"))]
+            #[cfg(not(feature = "only_i64"))]
+            {
+                reg_functions!(lib += numbers_to_float::to_float(i8, u8, i16, u16, i32, u32, i64, u32));
+
+                #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+                reg_functions!(lib += num_128_to_float::to_float(i128, u128));
+            }
+        }
+
+        // Decimal functions
+        #[cfg(feature = "decimal")]
         {
-            reg_functions!(lib += numbers_to_float::to_float(i8, u8, i16, u16, i32, u32, i64, u32));
+            combine_with_exported_module!(lib, "decimal", decimal_functions);
 
-            #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-            reg_functions!(lib += num_128_to_float::to_float(i128, u128));
+            reg_functions!(lib += basic_to_decimal::to_decimal(INT));
+
+            #[cfg(not(feature = "only_i32"))]
+            #[cfg(not(feature = "only_i64"))]
+            reg_functions!(lib += numbers_to_decimal::to_decimal(i8, u8, i16, u16, i32, u32, i64, u64));
         }
-    }
 
-    // Decimal functions
-    #[cfg(feature = "decimal")]
-    {
-        combine_with_exported_module!(lib, "decimal", decimal_functions);
+        // BigInt functions
+        #[cfg(feature = "bigint")]
+        {
+            combine_with_exported_module!(lib, "bigint", bigint_functions);
 
-        reg_functions!(lib += basic_to_decimal::to_decimal(INT));
+            reg_functions!(lib += basic_to_bigint::to_bigint(INT));
 
-        #[cfg(not(feature = "only_i32"))]
-        #[cfg(not(feature = "only_i64"))]
-        reg_functions!(lib += numbers_to_decimal::to_decimal(i8, u8, i16, u16, i32, u32, i64, u64));
-    }
+            #[cfg(not(feature = "only_i32"))]
+            #[cfg(not(feature = "only_i64"))]
+            {
+                reg_functions!(lib += numbers_to_bigint::to_bigint(i8, u8, i16, u16, i32, u32, i64, u64));
+
+                #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+                reg_functions!(lib += num_128_to_bigint::to_bigint(i128, u128));
+            }
+        }
+    });
 });
 
 #[export_module]
 mod int_functions {
     #[rhai_fn(name = "parse_int", return_raw)]
     pub fn parse_int_radix(s: &str, radix: INT) -> Result<Dynamic, Box<EvalAltResult>> {
-        if radix < 2 || radix > 36 {
+        if !(2..=36).contains(&radix) {
             return EvalAltResult::ErrorArithmetic(
                 format!("Invalid radix: '{}'", radix),
                 Position::NONE,
@@ -441,6 +463,95 @@ mod decimal_functions {
     }
 }
 
+#[cfg(feature = "bigint")]
+#[export_module]
+mod bigint_functions {
+    use num_traits::ToPrimitive;
+
+    #[rhai_fn(name = "to_int", return_raw)]
+    pub fn to_int(x: BigInt) -> Result<Dynamic, Box<EvalAltResult>> {
+        #[cfg(not(feature = "only_i32"))]
+        let n = x.to_i64();
+        #[cfg(feature = "only_i32")]
+        let n = x.to_i32();
+
+        n.map(Into::<Dynamic>::into)
+            .ok_or_else(|| make_err(format!("Integer overflow: {} is too large", x)))
+    }
+    #[cfg(not(feature = "no_float"))]
+    pub mod float_functions {
+        use num_traits::FromPrimitive;
+
+        #[rhai_fn(name = "to_float")]
+        pub fn to_float(x: BigInt) -> FLOAT {
+            x.to_f64().map_or(FLOAT::NAN, |n| n as FLOAT)
+        }
+        #[rhai_fn(name = "to_bigint", return_raw)]
+        pub fn float_to_bigint(x: FLOAT) -> Result<Dynamic, Box<EvalAltResult>> {
+            BigInt::from_f64(x as f64)
+                .map(Into::<Dynamic>::into)
+                .ok_or_else(|| make_err(format!("Cannot convert {} into a big integer", x)))
+        }
+    }
+    #[cfg(feature = "decimal")]
+    pub mod decimal_functions {
+        use num_traits::FromPrimitive;
+
+        #[rhai_fn(name = "to_decimal", return_raw)]
+        pub fn to_decimal(x: BigInt) -> Result<Dynamic, Box<EvalAltResult>> {
+            x.to_i128()
+                .and_then(Decimal::from_i128)
+                .map(Into::<Dynamic>::into)
+                .ok_or_else(|| make_err(format!("Decimal overflow: {} is too large", x)))
+        }
+        #[rhai_fn(name = "to_bigint")]
+        pub fn decimal_to_bigint(x: Decimal) -> BigInt {
+            // A decimal number always fits into an i128 after truncation
+            BigInt::from(x.trunc().to_i128().unwrap())
+        }
+    }
+    #[rhai_fn(name = "to_bigint", return_raw)]
+    pub fn parse_bigint(s: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        parse_bigint_radix(s, 10)
+    }
+    #[rhai_fn(name = "parse_bigint", return_raw)]
+    pub fn parse_bigint_str(s: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        parse_bigint_radix(s, 10)
+    }
+    #[rhai_fn(name = "parse_bigint", return_raw)]
+    pub fn parse_bigint_radix(s: &str, radix: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        if !(2..=36).contains(&radix) {
+            return EvalAltResult::ErrorArithmetic(
+                format!("Invalid radix: '{}'", radix),
+                Position::NONE,
+            )
+            .into();
+        }
+
+        BigInt::parse_bytes(s.trim().as_bytes(), radix as u32)
+            .map(Into::<Dynamic>::into)
+            .ok_or_else(|| {
+                EvalAltResult::ErrorArithmetic(
+                    format!("Error parsing big integer number '{}'", s),
+                    Position::NONE,
+                )
+                .into()
+            })
+    }
+    #[rhai_fn(name = "to_string", return_raw)]
+    pub fn to_string_radix(x: BigInt, radix: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        if !(2..=36).contains(&radix) {
+            return EvalAltResult::ErrorArithmetic(
+                format!("Invalid radix: '{}'", radix),
+                Position::NONE,
+            )
+            .into();
+        }
+
+        Ok(x.to_str_radix(radix as u32).into())
+    }
+}
+
 #[cfg(not(feature = "no_float"))]
 gen_conversion_as_functions!(basic_to_float => to_float (INT) -> FLOAT);
 
@@ -473,3 +584,17 @@ gen_conversion_into_functions!(basic_to_decimal => to_decimal (INT) -> Decimal);
 #[cfg(not(feature = "only_i32"))]
 #[cfg(not(feature = "only_i64"))]
 gen_conversion_into_functions!(numbers_to_decimal => to_decimal (i8, u8, i16, u16, i32, u32, i64, u64) -> Decimal);
+
+#[cfg(feature = "bigint")]
+gen_conversion_into_functions!(basic_to_bigint => to_bigint (INT) -> BigInt);
+
+#[cfg(feature = "bigint")]
+#[cfg(not(feature = "only_i32"))]
+#[cfg(not(feature = "only_i64"))]
+gen_conversion_into_functions!(numbers_to_bigint => to_bigint (i8, u8, i16, u16, i32, u32, i64, u64) -> BigInt);
+
+#[cfg(feature = "bigint")]
+#[cfg(not(feature = "only_i32"))]
+#[cfg(not(feature = "only_i64"))]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+gen_conversion_into_functions!(num_128_to_bigint => to_bigint (i128, u128) -> BigInt);
@@ -4,6 +4,7 @@ use crate::{Module, Shared};
 
 pub(crate) mod arithmetic;
 mod array_basic;
+mod blob_basic;
 mod fn_basic;
 mod iter_basic;
 mod logic;
@@ -11,6 +12,8 @@ mod map_basic;
 mod math_basic;
 mod pkg_core;
 mod pkg_std;
+mod regex_basic;
+mod set_basic;
 mod string_basic;
 mod string_more;
 mod time_basic;
@@ -18,6 +21,8 @@ mod time_basic;
 pub use arithmetic::ArithmeticPackage;
 #[cfg(not(feature = "no_index"))]
 pub use array_basic::BasicArrayPackage;
+#[cfg(not(feature = "no_index"))]
+pub use blob_basic::BasicBlobPackage;
 pub use fn_basic::BasicFnPackage;
 pub use iter_basic::BasicIteratorPackage;
 pub use logic::LogicPackage;
@@ -26,6 +31,9 @@ pub use map_basic::BasicMapPackage;
 pub use math_basic::BasicMathPackage;
 pub use pkg_core::CorePackage;
 pub use pkg_std::StandardPackage;
+pub use regex_basic::BasicRegexPackage;
+#[cfg(not(feature = "no_index"))]
+pub use set_basic::BasicSetPackage;
 pub use string_basic::BasicStringPackage;
 pub use string_more::MoreStringPackage;
 #[cfg(not(feature = "no_std"))]
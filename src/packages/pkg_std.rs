@@ -1,9 +1,14 @@
 #[cfg(not(feature = "no_index"))]
 use super::array_basic::BasicArrayPackage;
+#[cfg(not(feature = "no_index"))]
+use super::blob_basic::BasicBlobPackage;
 #[cfg(not(feature = "no_object"))]
 use super::map_basic::BasicMapPackage;
 use super::math_basic::BasicMathPackage;
 use super::pkg_core::CorePackage;
+use super::regex_basic::BasicRegexPackage;
+#[cfg(not(feature = "no_index"))]
+use super::set_basic::BasicSetPackage;
 use super::string_more::MoreStringPackage;
 #[cfg(not(feature = "no_std"))]
 use super::time_basic::BasicTimePackage;
@@ -15,9 +20,14 @@ def_package!(crate:StandardPackage:"_Standard_ package containing all built-in f
     BasicMathPackage::init(lib);
     #[cfg(not(feature = "no_index"))]
     BasicArrayPackage::init(lib);
+    #[cfg(not(feature = "no_index"))]
+    BasicBlobPackage::init(lib);
+    #[cfg(not(feature = "no_index"))]
+    BasicSetPackage::init(lib);
     #[cfg(not(feature = "no_object"))]
     BasicMapPackage::init(lib);
     #[cfg(not(feature = "no_std"))]
     BasicTimePackage::init(lib);
     MoreStringPackage::init(lib);
+    BasicRegexPackage::init(lib);
 });
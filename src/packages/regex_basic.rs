@@ -0,0 +1,310 @@
+#![allow(non_snake_case)]
+
+use super::arithmetic::make_err as make_arithmetic_err;
+use crate::plugin::*;
+use crate::regex::{Budget, Captures, RegexError};
+use crate::stdlib::{boxed::Box, format, string::String, string::ToString};
+use crate::{def_package, Dynamic, EvalAltResult, ImmutableString, Position, Regex};
+
+#[cfg(not(feature = "no_index"))]
+use crate::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::{Map, INT};
+
+def_package!(crate:BasicRegexPackage:"Basic regular expression utilities.", lib, {
+    lib.with_pure_fns(|lib| {
+        combine_with_exported_module!(lib, "regex", regex_functions);
+    });
+});
+
+/// Convert a [`RegexError`] into an error result.
+fn make_err(err: RegexError, pattern: &str) -> Box<EvalAltResult> {
+    match err {
+        RegexError::Syntax(msg) => {
+            make_arithmetic_err(format!("Invalid regular expression '{}': {}", pattern, msg))
+        }
+        RegexError::TooLarge => EvalAltResult::ErrorDataTooLarge(
+            "Size of regular expression".to_string(),
+            Position::NONE,
+        )
+        .into(),
+        RegexError::TooManySteps => EvalAltResult::ErrorDataTooLarge(
+            "Number of steps in regular expression".to_string(),
+            Position::NONE,
+        )
+        .into(),
+    }
+}
+
+/// Maximum size of a compiled regular expression.
+#[inline(always)]
+fn max_size(_ctx: &NativeCallContext) -> usize {
+    #[cfg(not(feature = "unchecked"))]
+    if _ctx.engine().max_regex_size() > 0 {
+        return _ctx.engine().max_regex_size();
+    }
+
+    usize::MAX
+}
+
+/// Budget of steps for a regular expression search.
+#[inline(always)]
+fn budget(_ctx: &NativeCallContext) -> Budget {
+    #[cfg(not(feature = "unchecked"))]
+    return Budget::new(_ctx.engine().max_regex_steps());
+
+    #[cfg(feature = "unchecked")]
+    return Budget::new(0);
+}
+
+/// Compile a pattern, using the cache of the [`Engine`][crate::Engine].
+#[inline(always)]
+fn compile(ctx: &NativeCallContext, pattern: &str) -> Result<Regex, Box<EvalAltResult>> {
+    ctx.engine()
+        .regex_cache
+        .get(pattern, max_size(ctx))
+        .map_err(|err| make_err(err, pattern))
+}
+
+/// Make sure that a regular expression is within the maximum size.
+#[inline(always)]
+fn check_size(ctx: &NativeCallContext, regex: &Regex) -> Result<(), Box<EvalAltResult>> {
+    if regex.size() > max_size(ctx) {
+        Err(make_err(RegexError::TooLarge, regex.as_str()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Find the first match of a regular expression in a string.
+fn find_first(
+    ctx: &NativeCallContext,
+    string: &str,
+    regex: &Regex,
+) -> Result<Option<Captures>, Box<EvalAltResult>> {
+    check_size(ctx, regex)?;
+
+    regex
+        .captures_at(string, 0, &mut budget(ctx))
+        .map_err(|err| make_err(err, regex.as_str()))
+}
+
+/// Get the text matched by a group.
+#[inline(always)]
+fn group_text(string: &str, caps: &Captures, index: usize) -> Dynamic {
+    match (caps[index * 2], caps[index * 2 + 1]) {
+        (Some(from), Some(to)) => string[from..to].into(),
+        _ => Dynamic::UNIT,
+    }
+}
+
+/// Maximum length of an array.
+#[cfg(not(feature = "no_index"))]
+#[inline(always)]
+fn max_array_size(_ctx: &NativeCallContext) -> usize {
+    #[cfg(not(feature = "unchecked"))]
+    if _ctx.engine().max_array_size() > 0 {
+        return _ctx.engine().max_array_size();
+    }
+
+    usize::MAX
+}
+/// Make sure that an array is within the maximum array size.
+#[cfg(not(feature = "no_index"))]
+#[inline(always)]
+fn check_array_size(ctx: &NativeCallContext, list: Array) -> Result<Dynamic, Box<EvalAltResult>> {
+    if list.len() > max_array_size(ctx) {
+        EvalAltResult::ErrorDataTooLarge("Size of array".to_string(), Position::NONE).into()
+    } else {
+        Ok(list.into())
+    }
+}
+
+#[export_module]
+mod regex_functions {
+    #[rhai_fn(return_raw)]
+    pub fn regex(ctx: NativeCallContext, pattern: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        compile(&ctx, pattern).map(Dynamic::from)
+    }
+
+    #[rhai_fn(name = "pattern", get = "pattern")]
+    pub fn pattern(regex: &mut Regex) -> ImmutableString {
+        regex.as_str().into()
+    }
+
+    #[rhai_fn(name = "print", name = "to_string")]
+    pub fn to_string(regex: &mut Regex) -> ImmutableString {
+        regex.as_str().into()
+    }
+    #[rhai_fn(name = "debug", name = "to_debug")]
+    pub fn to_debug(regex: &mut Regex) -> ImmutableString {
+        format!("regex({:?})", regex.as_str()).into()
+    }
+
+    #[rhai_fn(name = "matches", return_raw)]
+    pub fn matches(
+        ctx: NativeCallContext,
+        string: &str,
+        regex: Regex,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        find_first(&ctx, string, &regex).map(|caps| caps.is_some().into())
+    }
+    #[rhai_fn(name = "matches", return_raw)]
+    pub fn matches_pattern(
+        ctx: NativeCallContext,
+        string: &str,
+        pattern: &str,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        matches(ctx, string, compile(&ctx, pattern)?)
+    }
+
+    #[rhai_fn(name = "find", return_raw)]
+    pub fn find(
+        ctx: NativeCallContext,
+        string: &str,
+        regex: Regex,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        find_first(&ctx, string, &regex)
+            .map(|caps| caps.map_or(Dynamic::UNIT, |caps| group_text(string, &caps, 0)))
+    }
+    #[rhai_fn(name = "find", return_raw)]
+    pub fn find_pattern(
+        ctx: NativeCallContext,
+        string: &str,
+        pattern: &str,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        find(ctx, string, compile(&ctx, pattern)?)
+    }
+
+    #[rhai_fn(name = "replace", return_raw)]
+    pub fn replace(
+        ctx: NativeCallContext,
+        string: &mut ImmutableString,
+        regex: Regex,
+        replacement: &str,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        check_size(&ctx, &regex)?;
+
+        let text = string.as_str();
+        let mut output = String::with_capacity(text.len());
+        let mut last = 0;
+
+        regex
+            .for_each_match(text, &mut budget(&ctx), |caps| {
+                let (from, to) = (caps[0].unwrap(), caps[1].unwrap());
+                output.push_str(&text[last..from]);
+                regex.expand(text, caps, replacement, &mut output);
+                last = to;
+                true
+            })
+            .map_err(|err| make_err(err, regex.as_str()))?;
+
+        output.push_str(&text[last..]);
+
+        // Check if string will be over max size limit
+        #[cfg(not(feature = "unchecked"))]
+        if ctx.engine().max_string_size() > 0 && output.len() > ctx.engine().max_string_size() {
+            return EvalAltResult::ErrorDataTooLarge(
+                "Length of string".to_string(),
+                Position::NONE,
+            )
+            .into();
+        }
+
+        *string = output.into();
+        Ok(Dynamic::UNIT)
+    }
+
+    #[cfg(not(feature = "no_index"))]
+    pub mod array_functions {
+        #[rhai_fn(name = "find_all", return_raw)]
+        pub fn find_all(
+            ctx: NativeCallContext,
+            string: &str,
+            regex: Regex,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            check_size(&ctx, &regex)?;
+
+            let max = max_array_size(&ctx);
+            let mut list = Array::new();
+
+            regex
+                .for_each_match(string, &mut budget(&ctx), |caps| {
+                    list.push(group_text(string, caps, 0));
+                    list.len() <= max
+                })
+                .map_err(|err| make_err(err, regex.as_str()))?;
+
+            check_array_size(&ctx, list)
+        }
+        #[rhai_fn(name = "find_all", return_raw)]
+        pub fn find_all_pattern(
+            ctx: NativeCallContext,
+            string: &str,
+            pattern: &str,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            find_all(ctx, string, compile(&ctx, pattern)?)
+        }
+
+        #[rhai_fn(name = "split", return_raw)]
+        pub fn split(
+            ctx: NativeCallContext,
+            string: &str,
+            regex: Regex,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            check_size(&ctx, &regex)?;
+
+            let max = max_array_size(&ctx);
+            let mut list = Array::new();
+            let mut last = 0;
+
+            regex
+                .for_each_match(string, &mut budget(&ctx), |caps| {
+                    let (from, to) = (caps[0].unwrap(), caps[1].unwrap());
+                    list.push(string[last..from].into());
+                    last = to;
+                    list.len() < max
+                })
+                .map_err(|err| make_err(err, regex.as_str()))?;
+
+            list.push(string[last..].into());
+
+            check_array_size(&ctx, list)
+        }
+    }
+
+    #[cfg(not(feature = "no_object"))]
+    pub mod map_functions {
+        #[rhai_fn(name = "captures", return_raw)]
+        pub fn captures(
+            ctx: NativeCallContext,
+            string: &str,
+            regex: Regex,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            let caps = match find_first(&ctx, string, &regex)? {
+                Some(caps) => caps,
+                None => return Ok(Dynamic::UNIT),
+            };
+
+            let mut map = Map::new();
+
+            for index in 0..regex.num_groups() {
+                map.insert((index as INT).into(), group_text(string, &caps, index));
+            }
+            for (name, index) in regex.group_names() {
+                map.insert(name.as_str().into(), group_text(string, &caps, *index));
+            }
+
+            Ok(map.into())
+        }
+        #[rhai_fn(name = "captures", return_raw)]
+        pub fn captures_pattern(
+            ctx: NativeCallContext,
+            string: &str,
+            pattern: &str,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            captures(ctx, string, compile(&ctx, pattern)?)
+        }
+    }
+}
@@ -0,0 +1,146 @@
+#![cfg(not(feature = "no_index"))]
+#![allow(non_snake_case)]
+
+use crate::plugin::*;
+use crate::stdlib::{any::TypeId, boxed::Box, string::ToString};
+use crate::{def_package, Array, Dynamic, EvalAltResult, NativeCallContext, Position, Set, INT};
+
+def_package!(crate:BasicSetPackage:"Basic set utilities.", lib, {
+    lib.with_pure_fns(|lib| {
+        combine_with_exported_module!(lib, "set", set_functions);
+
+        // Register set iterator
+        lib.set_iter(TypeId::of::<Set>(), |set| Box::new(set.cast::<Set>().into_iter()));
+    });
+});
+
+/// Make sure that a set of a particular size is within the maximum array size.
+#[inline(always)]
+fn check_set_size(_ctx: &NativeCallContext, _len: usize) -> Result<(), Box<EvalAltResult>> {
+    #[cfg(not(feature = "unchecked"))]
+    if _ctx.engine().max_array_size() > 0 && _len > _ctx.engine().max_array_size() {
+        return EvalAltResult::ErrorDataTooLarge("Size of set".to_string(), Position::NONE).into();
+    }
+
+    Ok(())
+}
+
+/// Add a value to a set, failing if the value is not hashable.
+#[inline(always)]
+fn insert_item(set: &mut Set, item: Dynamic) -> Result<bool, Box<EvalAltResult>> {
+    set.insert(item).map_err(|typ| {
+        EvalAltResult::ErrorMismatchDataType(
+            "hashable value".to_string(),
+            typ.to_string(),
+            Position::NONE,
+        )
+        .into()
+    })
+}
+
+#[export_module]
+mod set_functions {
+    #[rhai_fn(name = "set")]
+    pub fn new_set() -> Set {
+        Set::new()
+    }
+    #[rhai_fn(name = "set", return_raw)]
+    pub fn from_array(ctx: NativeCallContext, array: Array) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut set = Set::new();
+        for item in array {
+            insert_item(&mut set, item)?;
+        }
+        check_set_size(&ctx, set.len())?;
+        Ok(Dynamic::from(set))
+    }
+    #[rhai_fn(name = "len", get = "len", pure)]
+    pub fn len(set: &mut Set) -> INT {
+        set.len() as INT
+    }
+    #[rhai_fn(name = "is_empty", get = "is_empty", pure)]
+    pub fn is_empty(set: &mut Set) -> bool {
+        set.is_empty()
+    }
+    pub fn clear(set: &mut Set) {
+        set.clear();
+    }
+    #[rhai_fn(pure)]
+    pub fn contains(set: &mut Set, item: Dynamic) -> bool {
+        set.contains(&item)
+    }
+    #[rhai_fn(return_raw)]
+    pub fn insert(
+        ctx: NativeCallContext,
+        set: &mut Set,
+        item: Dynamic,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let inserted = insert_item(set, item)?;
+        check_set_size(&ctx, set.len())?;
+        Ok(inserted.into())
+    }
+    #[rhai_fn(name = "+=", return_raw)]
+    pub fn add_item(
+        ctx: NativeCallContext,
+        set: &mut Set,
+        item: Dynamic,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        insert_item(set, item)?;
+        check_set_size(&ctx, set.len())?;
+        Ok(Dynamic::UNIT)
+    }
+    pub fn remove(set: &mut Set, item: Dynamic) -> bool {
+        set.remove(&item)
+    }
+    #[rhai_fn(name = "-=")]
+    pub fn remove_item(set: &mut Set, item: Dynamic) {
+        set.remove(&item);
+    }
+    #[rhai_fn(name = "union", name = "+", name = "|", return_raw)]
+    pub fn union(
+        ctx: NativeCallContext,
+        set: Set,
+        other: Set,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let set = set.union(&other);
+        check_set_size(&ctx, set.len())?;
+        Ok(Dynamic::from(set))
+    }
+    #[rhai_fn(name = "+=", return_raw)]
+    pub fn union_with(
+        ctx: NativeCallContext,
+        set: &mut Set,
+        other: Set,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        for item in other {
+            insert_item(set, item)?;
+        }
+        check_set_size(&ctx, set.len())?;
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(name = "intersection", name = "&")]
+    pub fn intersection(set: Set, other: Set) -> Set {
+        set.intersection(&other)
+    }
+    #[rhai_fn(name = "difference", name = "-")]
+    pub fn difference(set: Set, other: Set) -> Set {
+        set.difference(&other)
+    }
+    #[rhai_fn(name = "-=")]
+    pub fn difference_with(set: &mut Set, other: Set) {
+        other.iter().for_each(|item| {
+            set.remove(item);
+        });
+    }
+    #[rhai_fn(pure)]
+    pub fn to_array(set: &mut Set) -> Array {
+        set.iter().cloned().collect()
+    }
+    #[rhai_fn(name = "==", pure)]
+    pub fn equals(set: &mut Set, other: Set) -> bool {
+        *set == other
+    }
+    #[rhai_fn(name = "!=", pure)]
+    pub fn not_equals(set: &mut Set, other: Set) -> bool {
+        *set != other
+    }
+}
@@ -1,8 +1,18 @@
 #![allow(non_snake_case)]
 
 use crate::plugin::*;
-use crate::stdlib::{format, string::ToString};
-use crate::{def_package, FnPtr, ImmutableString};
+use crate::stdlib::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+};
+use crate::{def_package, EvalAltResult, FnPtr, ImmutableString, Position, INT};
+
+#[cfg(not(feature = "no_float"))]
+use crate::FLOAT;
+
+#[cfg(feature = "decimal")]
+use rust_decimal::Decimal;
 
 #[cfg(not(feature = "no_index"))]
 use crate::Array;
@@ -10,15 +20,18 @@ use crate::Array;
 #[cfg(not(feature = "no_object"))]
 use crate::Map;
 
-const FUNC_TO_DEBUG: &'static str = "to_debug";
+const FUNC_TO_STRING: &str = "to_string";
+const FUNC_TO_DEBUG: &str = "to_debug";
 
 def_package!(crate:BasicStringPackage:"Basic string utilities, including printing.", lib, {
-    combine_with_exported_module!(lib, "print_debug", print_debug_functions);
+    lib.with_pure_fns(|lib| {
+        combine_with_exported_module!(lib, "print_debug", print_debug_functions);
+        combine_with_exported_module!(lib, "format", format_functions);
+    });
 });
 
 // Register print and debug
 
-#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
 #[inline(always)]
 fn print_with_func(fn_name: &str, ctx: &NativeCallContext, value: &mut Dynamic) -> ImmutableString {
     match ctx.call_fn_dynamic_raw(fn_name, true, &mut [value]) {
@@ -116,6 +129,49 @@ mod print_debug_functions {
             result.into()
         }
     }
+    #[cfg(not(feature = "no_index"))]
+    pub mod blob_functions {
+        use crate::Blob;
+
+        #[rhai_fn(
+            name = "print",
+            name = "to_string",
+            name = "debug",
+            name = "to_debug",
+            pure
+        )]
+        pub fn format_blob(blob: &mut Blob) -> ImmutableString {
+            Dynamic::from(blob.clone()).to_string().into()
+        }
+    }
+    #[cfg(not(feature = "no_index"))]
+    pub mod set_functions {
+        use super::*;
+        use crate::Set;
+
+        #[rhai_fn(
+            name = "print",
+            name = "to_string",
+            name = "debug",
+            name = "to_debug",
+            pure
+        )]
+        pub fn format_set(ctx: NativeCallContext, set: &mut Set) -> ImmutableString {
+            let len = set.len();
+            let mut result = crate::stdlib::string::String::with_capacity(len * 5 + 2);
+            result.push('{');
+
+            set.iter().enumerate().for_each(|(i, x)| {
+                result.push_str(&print_with_func(FUNC_TO_DEBUG, &ctx, &mut x.clone()));
+                if i < len - 1 {
+                    result.push_str(", ");
+                }
+            });
+
+            result.push('}');
+            result.into()
+        }
+    }
     #[cfg(not(feature = "no_object"))]
     pub mod map_functions {
         use super::*;
@@ -146,3 +202,462 @@ mod print_debug_functions {
         }
     }
 }
+
+/// Alignment of a formatted value within its width.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// A parsed format specification, i.e. the part after `:` in `{:>8.2}`.
+#[derive(Debug, Clone)]
+struct FormatSpec {
+    fill: char,
+    align: Option<Align>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+/// Error for an invalid format string.
+#[inline(always)]
+fn format_err(msg: impl AsRef<str>) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorRuntime(
+        format!("Invalid format string: {}", msg.as_ref()).into(),
+        Position::NONE,
+    )
+    .into()
+}
+
+/// Make an error for a string exceeding the maximum string size.
+fn too_large_err() -> Box<EvalAltResult> {
+    EvalAltResult::ErrorDataTooLarge("Length of string".to_string(), Position::NONE).into()
+}
+
+/// Make sure that a string of a particular length is within the maximum string size.
+///
+/// A length of [`None`] means that the length overflows a `usize`.
+#[inline(always)]
+fn check_string_size(
+    _ctx: &NativeCallContext,
+    len: Option<usize>,
+) -> Result<(), Box<EvalAltResult>> {
+    let _len = len.ok_or_else(too_large_err)?;
+
+    #[cfg(not(feature = "unchecked"))]
+    if _ctx.engine().max_string_size() > 0 && _len > _ctx.engine().max_string_size() {
+        return Err(too_large_err());
+    }
+
+    Ok(())
+}
+
+/// Maximum precision when formatting a number.
+const MAX_NUMBER_PRECISION: usize = u16::MAX as usize;
+
+/// Find a format argument by position (empty for the next one) or by name.
+///
+/// Named arguments are looked up in the [object maps][crate::Map] among the arguments.
+fn find_arg(name: &str, args: &[Dynamic], next: &mut usize) -> Result<Dynamic, Box<EvalAltResult>> {
+    let index = if name.is_empty() {
+        *next += 1;
+        *next - 1
+    } else if let Ok(index) = name.parse::<usize>() {
+        index
+    } else {
+        #[cfg(not(feature = "no_object"))]
+        if let Some(value) = args
+            .iter()
+            .filter_map(|arg| arg.read_lock::<crate::Map>())
+            .find_map(|map| map.get(&crate::MapKey::from(name)).cloned())
+        {
+            return Ok(value);
+        }
+
+        return Err(format_err(format!("there is no argument named '{}'", name)));
+    };
+
+    args.get(index)
+        .cloned()
+        .ok_or_else(|| format_err(format!("argument {} is missing", index)))
+}
+
+/// Parse a width or precision, which is a number or an argument followed by `$`.
+fn parse_count(
+    spec: &[char],
+    pos: &mut usize,
+    args: &[Dynamic],
+) -> Result<Option<usize>, Box<EvalAltResult>> {
+    let start = *pos;
+    let mut end = start;
+
+    while end < spec.len() && (spec[end].is_alphanumeric() || spec[end] == '_') {
+        end += 1;
+    }
+
+    let token: String = spec[start..end].iter().collect();
+
+    if spec.get(end) == Some(&'$') {
+        *pos = end + 1;
+        let value = find_arg(&token, args, &mut 0)?;
+
+        return match value.as_int() {
+            Ok(count) if count >= 0 => Ok(Some(count as usize)),
+            _ => Err(format_err(format!(
+                "count argument '{}' is not valid",
+                token
+            ))),
+        };
+    }
+
+    let digits = token.chars().take_while(char::is_ascii_digit).count();
+
+    if digits == 0 {
+        return Ok(None);
+    }
+
+    *pos = start + digits;
+    token[..digits]
+        .parse::<usize>()
+        .map(Some)
+        .map_err(|_| format_err(format!("count '{}' is too large", &token[..digits])))
+}
+
+/// Parse a format specification such as `>8.2`.
+fn parse_spec(spec: &str, args: &[Dynamic]) -> Result<FormatSpec, Box<EvalAltResult>> {
+    let align_of = |c: Option<&char>| match c {
+        Some('<') => Some(Align::Left),
+        Some('^') => Some(Align::Center),
+        Some('>') => Some(Align::Right),
+        _ => None,
+    };
+
+    let chars: crate::stdlib::vec::Vec<char> = spec.chars().collect();
+    let mut pos = 0;
+    let mut result = FormatSpec {
+        fill: ' ',
+        align: None,
+        plus: false,
+        alternate: false,
+        zero: false,
+        width: None,
+        precision: None,
+        kind: None,
+    };
+
+    if let Some(align) = align_of(chars.get(1)) {
+        result.fill = chars[0];
+        result.align = Some(align);
+        pos = 2;
+    } else if let Some(align) = align_of(chars.first()) {
+        result.align = Some(align);
+        pos = 1;
+    }
+    if chars.get(pos) == Some(&'+') {
+        result.plus = true;
+        pos += 1;
+    }
+    if chars.get(pos) == Some(&'#') {
+        result.alternate = true;
+        pos += 1;
+    }
+    if chars.get(pos) == Some(&'0') && chars.get(pos + 1) != Some(&'$') {
+        result.zero = true;
+        pos += 1;
+    }
+
+    result.width = parse_count(&chars, &mut pos, args)?;
+
+    if chars.get(pos) == Some(&'.') {
+        pos += 1;
+        result.precision = parse_count(&chars, &mut pos, args)?;
+
+        if result.precision.is_none() {
+            return Err(format_err(format!("missing precision in '{{:{}}}'", spec)));
+        }
+    }
+
+    match &chars[pos..] {
+        [] => (),
+        &[kind] if "?xXobeE".contains(kind) => result.kind = Some(kind),
+        _ => {
+            return Err(format_err(format!(
+                "unknown format specification '{{:{}}}'",
+                spec
+            )))
+        }
+    }
+
+    Ok(result)
+}
+
+/// Format a value according to a format specification, appending it to an output string.
+fn format_value(
+    ctx: &NativeCallContext,
+    mut value: Dynamic,
+    spec: &FormatSpec,
+    output: &mut String,
+) -> Result<(), Box<EvalAltResult>> {
+    let mismatch = |value: &Dynamic, expected: &str| -> Box<EvalAltResult> {
+        EvalAltResult::ErrorMismatchDataType(
+            expected.into(),
+            ctx.engine().map_type_name(value.type_name()).into(),
+            Position::NONE,
+        )
+        .into()
+    };
+
+    let is_int = value.is::<INT>();
+    #[cfg(not(feature = "no_float"))]
+    let is_float = value.is::<FLOAT>();
+    #[cfg(feature = "no_float")]
+    let is_float = false;
+    #[cfg(feature = "decimal")]
+    let is_decimal = value.is::<Decimal>();
+    #[cfg(not(feature = "decimal"))]
+    let is_decimal = false;
+
+    let is_number = is_int || is_float || is_decimal;
+    let mut prefix = "";
+
+    // The width and precision are bounded by the maximum string size, and a numeric precision
+    // is further bounded by what the standard formatting machinery supports
+    if let Some(width) = spec.width {
+        check_string_size(ctx, Some(width))?;
+    }
+    if let Some(precision) = spec.precision {
+        check_string_size(ctx, Some(precision))?;
+
+        if is_number && precision > MAX_NUMBER_PRECISION {
+            return Err(format_err(format!(
+                "precision {} is larger than the maximum of {}",
+                precision, MAX_NUMBER_PRECISION
+            )));
+        }
+    }
+
+    let mut body: String = match spec.kind {
+        Some('x') | Some('X') | Some('o') | Some('b') if is_int => {
+            let n = value.as_int().unwrap();
+            let (body, radix_prefix) = match spec.kind {
+                Some('x') => (format!("{:x}", n), "0x"),
+                Some('X') => (format!("{:X}", n), "0x"),
+                Some('o') => (format!("{:o}", n), "0o"),
+                _ => (format!("{:b}", n), "0b"),
+            };
+            if spec.alternate {
+                prefix = radix_prefix;
+            }
+            body
+        }
+        Some('x') | Some('X') | Some('o') | Some('b') => return Err(mismatch(&value, "integer")),
+
+        Some('e') | Some('E') if is_int => {
+            let body = format!("{:e}", value.as_int().unwrap());
+            if spec.kind == Some('E') {
+                body.to_uppercase()
+            } else {
+                body
+            }
+        }
+        #[cfg(not(feature = "no_float"))]
+        Some('e') | Some('E') if is_float => {
+            let f = value.as_float().unwrap();
+            let body = match spec.precision {
+                Some(precision) => format!("{:.*e}", precision, f),
+                None => format!("{:e}", f),
+            };
+            if spec.kind == Some('E') {
+                body.to_uppercase()
+            } else {
+                body
+            }
+        }
+        Some('e') | Some('E') => return Err(mismatch(&value, "number")),
+
+        #[cfg(not(feature = "no_float"))]
+        _ if is_float && spec.precision.is_some() => {
+            let f = value.as_float().unwrap();
+            match spec.kind {
+                Some('?') => format!("{:.*?}", spec.precision.unwrap(), f),
+                _ => format!("{:.*}", spec.precision.unwrap(), f),
+            }
+        }
+        #[cfg(feature = "decimal")]
+        _ if is_decimal && spec.precision.is_some() => {
+            format!(
+                "{:.*}",
+                spec.precision.unwrap(),
+                value.as_decimal().unwrap()
+            )
+        }
+
+        // Strings and characters are formatted directly
+        None if value.is::<ImmutableString>() => value.take_immutable_string().unwrap().into(),
+        None if value.is::<char>() => value.as_char().unwrap().to_string(),
+
+        // Other values are formatted via `to_string` or `to_debug`
+        Some('?') => print_with_func(FUNC_TO_DEBUG, ctx, &mut value).into(),
+        _ => print_with_func(FUNC_TO_STRING, ctx, &mut value).into(),
+    };
+
+    // Precision truncates non-numeric values
+    if let Some(precision) = spec.precision.filter(|_| !is_number) {
+        if let Some((index, _)) = body.char_indices().nth(precision) {
+            body.truncate(index);
+        }
+    }
+
+    let sign = if is_number && body.starts_with('-') {
+        body.remove(0);
+        "-"
+    } else if is_number && spec.plus {
+        "+"
+    } else {
+        ""
+    };
+
+    let len = sign.len() + prefix.len() + body.chars().count();
+    let pad = spec.width.map_or(0, |width| width.saturating_sub(len));
+
+    let size = pad
+        .checked_mul(spec.fill.len_utf8())
+        .and_then(|n| n.checked_add(output.len() + sign.len() + prefix.len() + body.len()));
+
+    check_string_size(ctx, size)?;
+
+    // Without a maximum string size, a huge width may still be impossible to allocate
+    output
+        .try_reserve(size.unwrap() - output.len())
+        .map_err(|_| too_large_err())?;
+
+    if spec.zero && is_number {
+        output.push_str(sign);
+        output.push_str(prefix);
+        (0..pad).for_each(|_| output.push('0'));
+        output.push_str(&body);
+    } else {
+        let (left, right) = match spec.align {
+            Some(Align::Left) => (0, pad),
+            Some(Align::Center) => (pad / 2, pad - pad / 2),
+            Some(Align::Right) => (pad, 0),
+            None if is_number => (pad, 0),
+            None => (0, pad),
+        };
+        (0..left).for_each(|_| output.push(spec.fill));
+        output.push_str(sign);
+        output.push_str(prefix);
+        output.push_str(&body);
+        (0..right).for_each(|_| output.push(spec.fill));
+    }
+
+    Ok(())
+}
+
+/// Format arguments according to a format string with Rust-like `{}` placeholders.
+fn format_with(
+    ctx: &NativeCallContext,
+    fmt: &str,
+    args: &[Dynamic],
+) -> Result<Dynamic, Box<EvalAltResult>> {
+    let mut output = String::with_capacity(fmt.len());
+    let mut chars = fmt.chars();
+    let mut next = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                output.push('}');
+            }
+            '}' => return Err(format_err("unmatched '}'")),
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest.find('}').ok_or_else(|| format_err("unmatched '{'"))?;
+                let placeholder = &rest[..end];
+                chars = rest[end + 1..].chars();
+
+                let (name, spec) = match placeholder.find(':') {
+                    Some(index) => (&placeholder[..index], &placeholder[index + 1..]),
+                    None => (placeholder, ""),
+                };
+                let spec = parse_spec(spec, args)?;
+                let value = find_arg(name.trim(), args, &mut next)?;
+
+                format_value(ctx, value, &spec, &mut output)?;
+            }
+            c => output.push(c),
+        }
+    }
+
+    check_string_size(ctx, Some(output.len()))?;
+
+    Ok(output.into())
+}
+
+#[export_module]
+mod format_functions {
+    #[rhai_fn(name = "format", return_raw)]
+    pub fn format0(ctx: NativeCallContext, fmt: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        format_with(&ctx, fmt, &[])
+    }
+    #[rhai_fn(name = "format", return_raw)]
+    pub fn format1(
+        ctx: NativeCallContext,
+        fmt: &str,
+        a: Dynamic,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        format_with(&ctx, fmt, &[a])
+    }
+    #[rhai_fn(name = "format", return_raw)]
+    pub fn format2(
+        ctx: NativeCallContext,
+        fmt: &str,
+        a: Dynamic,
+        b: Dynamic,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        format_with(&ctx, fmt, &[a, b])
+    }
+    #[rhai_fn(name = "format", return_raw)]
+    pub fn format3(
+        ctx: NativeCallContext,
+        fmt: &str,
+        a: Dynamic,
+        b: Dynamic,
+        c: Dynamic,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        format_with(&ctx, fmt, &[a, b, c])
+    }
+    #[rhai_fn(name = "format", return_raw)]
+    pub fn format4(
+        ctx: NativeCallContext,
+        fmt: &str,
+        a: Dynamic,
+        b: Dynamic,
+        c: Dynamic,
+        d: Dynamic,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        format_with(&ctx, fmt, &[a, b, c, d])
+    }
+    #[rhai_fn(name = "format", return_raw)]
+    pub fn format5(
+        ctx: NativeCallContext,
+        fmt: &str,
+        a: Dynamic,
+        b: Dynamic,
+        c: Dynamic,
+        d: Dynamic,
+        e: Dynamic,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        format_with(&ctx, fmt, &[a, b, c, d, e])
+    }
+}
@@ -4,18 +4,32 @@ use crate::plugin::*;
 use crate::stdlib::{
     any::TypeId, boxed::Box, format, mem, string::String, string::ToString, vec::Vec,
 };
-use crate::{def_package, Dynamic, ImmutableString, StaticVec, INT};
+use crate::{def_package, Dynamic, EvalAltResult, ImmutableString, Position, StaticVec, INT};
 
 def_package!(crate:MoreStringPackage:"Additional string utilities, including string building.", lib, {
-    combine_with_exported_module!(lib, "string", string_functions);
-
-    // Register string iterator
-    lib.set_iter(
-        TypeId::of::<ImmutableString>(),
-        |string| Box::new(string.cast::<ImmutableString>().chars().collect::<Vec<_>>().into_iter().map(Into::into))
-    );
+    lib.with_pure_fns(|lib| {
+        combine_with_exported_module!(lib, "string", string_functions);
+
+        // Register string iterator
+        lib.set_iter(
+            TypeId::of::<ImmutableString>(),
+            |string| Box::new(string.cast::<ImmutableString>().chars().collect::<Vec<_>>().into_iter().map(Into::into))
+        );
+    });
 });
 
+/// Make sure that a string of a particular length is within the maximum string size.
+#[inline(always)]
+fn check_string_size(_ctx: &NativeCallContext, _len: usize) -> Result<(), Box<EvalAltResult>> {
+    #[cfg(not(feature = "unchecked"))]
+    if _ctx.engine().max_string_size() > 0 && _len > _ctx.engine().max_string_size() {
+        return EvalAltResult::ErrorDataTooLarge("Length of string".to_string(), Position::NONE)
+            .into();
+    }
+
+    Ok(())
+}
+
 #[export_module]
 mod string_functions {
     use crate::ImmutableString;
@@ -73,6 +87,133 @@ mod string_functions {
             *string = trimmed.to_string().into();
         }
     }
+    pub fn trim_start(string: &mut ImmutableString) {
+        let trimmed = string.trim_start();
+
+        if trimmed.len() < string.len() {
+            *string = trimmed.to_string().into();
+        }
+    }
+    pub fn trim_end(string: &mut ImmutableString) {
+        let trimmed = string.trim_end();
+
+        if trimmed.len() < string.len() {
+            *string = trimmed.to_string().into();
+        }
+    }
+    pub fn reverse(string: &mut ImmutableString) {
+        *string = string.chars().rev().collect::<String>().into();
+    }
+
+    #[rhai_fn(return_raw)]
+    pub fn to_upper(ctx: NativeCallContext, string: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let result = string.to_uppercase();
+        check_string_size(&ctx, result.len())?;
+        Ok(result.into())
+    }
+    #[rhai_fn(return_raw)]
+    pub fn to_lower(ctx: NativeCallContext, string: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let result = string.to_lowercase();
+        check_string_size(&ctx, result.len())?;
+        Ok(result.into())
+    }
+    #[rhai_fn(name = "to_upper")]
+    pub fn to_upper_char(character: char) -> char {
+        let mut upper = character.to_uppercase();
+        match (upper.next(), upper.next()) {
+            (Some(ch), None) => ch,
+            _ => character,
+        }
+    }
+    #[rhai_fn(name = "to_lower")]
+    pub fn to_lower_char(character: char) -> char {
+        let mut lower = character.to_lowercase();
+        match (lower.next(), lower.next()) {
+            (Some(ch), None) => ch,
+            _ => character,
+        }
+    }
+    #[rhai_fn(name = "is_upper")]
+    pub fn is_upper_char(character: char) -> bool {
+        character.is_uppercase()
+    }
+    #[rhai_fn(name = "is_lower")]
+    pub fn is_lower_char(character: char) -> bool {
+        character.is_lowercase()
+    }
+
+    pub fn starts_with(string: &str, prefix: &str) -> bool {
+        string.starts_with(prefix)
+    }
+    #[rhai_fn(name = "starts_with")]
+    pub fn starts_with_char(string: &str, character: char) -> bool {
+        string.starts_with(character)
+    }
+    pub fn ends_with(string: &str, suffix: &str) -> bool {
+        string.ends_with(suffix)
+    }
+    #[rhai_fn(name = "ends_with")]
+    pub fn ends_with_char(string: &str, character: char) -> bool {
+        string.ends_with(character)
+    }
+    pub fn contains(string: &str, sub_string: &str) -> bool {
+        string.contains(sub_string)
+    }
+    #[rhai_fn(name = "contains")]
+    pub fn contains_char(string: &str, character: char) -> bool {
+        string.contains(character)
+    }
+
+    pub fn eq_ignore_case(string: &str, other: &str) -> bool {
+        string
+            .chars()
+            .flat_map(char::to_lowercase)
+            .eq(other.chars().flat_map(char::to_lowercase))
+    }
+    pub fn compare_ignore_case(string: &str, other: &str) -> INT {
+        match string
+            .chars()
+            .flat_map(char::to_lowercase)
+            .cmp(other.chars().flat_map(char::to_lowercase))
+        {
+            crate::stdlib::cmp::Ordering::Less => -1,
+            crate::stdlib::cmp::Ordering::Equal => 0,
+            crate::stdlib::cmp::Ordering::Greater => 1,
+        }
+    }
+
+    pub fn char_at(string: &str, index: INT) -> Dynamic {
+        if index < 0 {
+            return Dynamic::UNIT;
+        }
+
+        string
+            .chars()
+            .nth(index as usize)
+            .map_or(Dynamic::UNIT, Into::into)
+    }
+
+    #[rhai_fn(return_raw)]
+    pub fn repeat(
+        ctx: NativeCallContext,
+        string: &str,
+        count: INT,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        if count <= 0 || string.is_empty() {
+            return Ok(ImmutableString::default().into());
+        }
+
+        // Check if string will be over max size limit
+        let len = string.len().checked_mul(count as usize).ok_or_else(|| {
+            Box::new(EvalAltResult::ErrorDataTooLarge(
+                "Length of string".to_string(),
+                Position::NONE,
+            ))
+        })?;
+        check_string_size(&ctx, len)?;
+
+        Ok(string.repeat(count as usize).into())
+    }
 
     #[rhai_fn(name = "index_of")]
     pub fn index_of_char_starting_from(string: &str, character: char, start: INT) -> INT {
@@ -340,6 +481,9 @@ mod string_functions {
                 vec![prefix.into(), string[prefix_len..].into()]
             }
         }
+        pub fn lines(string: &str) -> Array {
+            string.lines().map(Into::<Dynamic>::into).collect()
+        }
         pub fn split(string: &str, delimiter: &str) -> Array {
             string.split(delimiter).map(Into::<Dynamic>::into).collect()
         }
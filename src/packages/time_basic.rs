@@ -1,9 +1,10 @@
 #![cfg(not(feature = "no_std"))]
 
 use super::{arithmetic::make_err as make_arithmetic_err, math_basic::MAX_INT};
+use crate::datetime::parse_offset;
 use crate::plugin::*;
-use crate::stdlib::boxed::Box;
-use crate::{def_package, Dynamic, EvalAltResult, INT};
+use crate::stdlib::{boxed::Box, convert::TryFrom, format, string::ToString};
+use crate::{def_package, DateTime, Dynamic, EvalAltResult, ImmutableString, TimeSpan, INT};
 
 #[cfg(not(feature = "no_float"))]
 use crate::FLOAT;
@@ -16,11 +17,66 @@ use instant::{Duration, Instant};
 
 def_package!(crate:BasicTimePackage:"Basic timing utilities.", lib, {
     // Register date/time functions
-    combine_with_exported_module!(lib, "time", time_functions);
+    lib.with_pure_fns(|lib| {
+        combine_with_exported_module!(lib, "time", time_functions);
+        combine_with_exported_module!(lib, "datetime", datetime_functions);
+        combine_with_exported_module!(lib, "duration", duration_functions);
+    });
+
+    // Functions reading the clock are not pure
+    combine_with_exported_module!(lib, "clock", clock_functions);
 });
 
+/// Convert a 64-bit integer into an [`INT`], failing on overflow.
+#[inline(always)]
+fn make_int(value: i64) -> Result<INT, Box<EvalAltResult>> {
+    #[cfg(not(feature = "only_i32"))]
+    return Ok(value);
+
+    #[cfg(feature = "only_i32")]
+    if value > (MAX_INT as i64) || value < (INT::MIN as i64) {
+        Err(make_arithmetic_err(format!("Integer overflow: {}", value)))
+    } else {
+        Ok(value as INT)
+    }
+}
+
+/// Error for a date/time calculation that is out of range.
+#[inline(always)]
+fn out_of_range(op: &str) -> Box<EvalAltResult> {
+    make_arithmetic_err(format!("Date/time out of range for {}", op))
+}
+
+/// Convert an integer into an [`i64`].
+#[cfg(not(feature = "only_i32"))]
+#[inline(always)]
+fn to_i64(value: INT) -> i64 {
+    value
+}
+/// Convert an integer into an [`i64`].
+#[cfg(feature = "only_i32")]
+#[inline(always)]
+fn to_i64(value: INT) -> i64 {
+    value.into()
+}
+
+/// Convert a floating-point number into an [`f64`].
+#[cfg(not(feature = "no_float"))]
+#[cfg(not(feature = "f32_float"))]
+#[inline(always)]
+fn to_f64(value: FLOAT) -> f64 {
+    value
+}
+/// Convert a floating-point number into an [`f64`].
+#[cfg(not(feature = "no_float"))]
+#[cfg(feature = "f32_float")]
+#[inline(always)]
+fn to_f64(value: FLOAT) -> f64 {
+    value.into()
+}
+
 #[export_module]
-mod time_functions {
+mod clock_functions {
     pub fn timestamp() -> Instant {
         Instant::now()
     }
@@ -51,6 +107,24 @@ mod time_functions {
         }
     }
 
+    #[rhai_fn(return_raw)]
+    pub fn now(ctx: NativeCallContext) -> Result<Dynamic, Box<EvalAltResult>> {
+        match ctx.engine().clock {
+            Some(ref clock) => Ok(Dynamic::from(clock())),
+            #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+            None => Ok(Dynamic::from(DateTime::now())),
+            #[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
+            None => EvalAltResult::ErrorRuntime(
+                "No clock is available to read the current date and time".into(),
+                crate::Position::NONE,
+            )
+            .into(),
+        }
+    }
+}
+
+#[export_module]
+mod time_functions {
     #[rhai_fn(return_raw, name = "-")]
     pub fn time_diff(
         timestamp: Instant,
@@ -251,3 +325,361 @@ mod time_functions {
         timestamp >= timestamp2
     }
 }
+
+#[export_module]
+mod datetime_functions {
+    #[rhai_fn(name = "datetime", return_raw)]
+    pub fn from_date(year: INT, month: INT, day: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        from_date_time(year, month, day, 0, 0, 0)
+    }
+    #[rhai_fn(name = "datetime", return_raw)]
+    pub fn from_date_time(
+        year: INT,
+        month: INT,
+        day: INT,
+        hour: INT,
+        minute: INT,
+        second: INT,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let component = |value: INT| if value < 0 { u32::MAX } else { value as u32 };
+
+        DateTime::new(
+            to_i64(year),
+            component(month),
+            component(day),
+            component(hour),
+            component(minute),
+            component(second),
+        )
+        .map(Dynamic::from)
+        .ok_or_else(|| {
+            make_arithmetic_err(format!(
+                "Invalid date/time: {}-{}-{} {}:{}:{}",
+                year, month, day, hour, minute, second
+            ))
+        })
+    }
+    #[rhai_fn(return_raw)]
+    pub fn from_timestamp(seconds: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        DateTime::from_timestamp(to_i64(seconds), 0)
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("from_timestamp"))
+    }
+    #[rhai_fn(return_raw)]
+    pub fn parse_datetime(s: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        s.parse::<DateTime>()
+            .map(Dynamic::from)
+            .map_err(|err| make_arithmetic_err(format!("Error parsing date/time '{}': {}", s, err)))
+    }
+
+    #[rhai_fn(name = "year", get = "year", return_raw)]
+    pub fn year(dt: DateTime) -> Result<Dynamic, Box<EvalAltResult>> {
+        make_int(dt.year()).map(Dynamic::from)
+    }
+    #[rhai_fn(name = "month", get = "month")]
+    pub fn month(dt: DateTime) -> INT {
+        dt.month() as INT
+    }
+    #[rhai_fn(name = "day", get = "day")]
+    pub fn day(dt: DateTime) -> INT {
+        dt.day() as INT
+    }
+    #[rhai_fn(name = "hour", get = "hour")]
+    pub fn hour(dt: DateTime) -> INT {
+        dt.hour() as INT
+    }
+    #[rhai_fn(name = "minute", get = "minute")]
+    pub fn minute(dt: DateTime) -> INT {
+        dt.minute() as INT
+    }
+    #[rhai_fn(name = "second", get = "second")]
+    pub fn second(dt: DateTime) -> INT {
+        dt.second() as INT
+    }
+    #[rhai_fn(name = "nanosecond", get = "nanosecond")]
+    pub fn nanosecond(dt: DateTime) -> INT {
+        dt.nanosecond() as INT
+    }
+    #[rhai_fn(name = "weekday", get = "weekday")]
+    pub fn weekday(dt: DateTime) -> INT {
+        dt.weekday() as INT
+    }
+    #[rhai_fn(name = "day_of_year", get = "day_of_year")]
+    pub fn day_of_year(dt: DateTime) -> INT {
+        dt.day_of_year() as INT
+    }
+    #[rhai_fn(name = "offset", get = "offset")]
+    pub fn offset(dt: DateTime) -> INT {
+        dt.offset() as INT
+    }
+    #[rhai_fn(name = "timestamp", get = "timestamp", return_raw)]
+    pub fn timestamp(dt: DateTime) -> Result<Dynamic, Box<EvalAltResult>> {
+        make_int(dt.timestamp()).map(Dynamic::from)
+    }
+
+    pub fn to_utc(dt: DateTime) -> DateTime {
+        dt.with_offset(0).expect("UTC is always in range")
+    }
+    #[rhai_fn(return_raw)]
+    pub fn with_offset(dt: DateTime, seconds: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        let seconds = i32::try_from(seconds).map_err(|_| out_of_range("with_offset"))?;
+
+        dt.with_offset(seconds)
+            .map(Dynamic::from)
+            .ok_or_else(|| make_arithmetic_err(format!("Invalid offset: {}", seconds)))
+    }
+    #[rhai_fn(name = "with_offset", return_raw)]
+    pub fn with_offset_str(dt: DateTime, offset: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let seconds = parse_offset(offset).map_err(|err| {
+            make_arithmetic_err(format!("Error parsing offset '{}': {}", offset, err))
+        })?;
+
+        dt.with_offset(seconds)
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("with_offset"))
+    }
+    #[rhai_fn(return_raw)]
+    pub fn format(dt: DateTime, format: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        dt.format(format)
+            .map(Dynamic::from)
+            .map_err(make_arithmetic_err)
+    }
+    #[rhai_fn(name = "print", name = "to_string", name = "debug", name = "to_debug")]
+    pub fn to_string(dt: DateTime) -> ImmutableString {
+        dt.to_string().into()
+    }
+
+    #[rhai_fn(return_raw)]
+    pub fn add_days(dt: DateTime, days: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        dt.add_days(to_i64(days))
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("add_days"))
+    }
+    #[rhai_fn(return_raw)]
+    pub fn add_months(dt: DateTime, months: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        dt.add_months(to_i64(months))
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("add_months"))
+    }
+    #[rhai_fn(return_raw)]
+    pub fn add_years(dt: DateTime, years: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        to_i64(years)
+            .checked_mul(12)
+            .and_then(|months| dt.add_months(months))
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("add_years"))
+    }
+
+    #[rhai_fn(return_raw, name = "+")]
+    pub fn add(dt: DateTime, span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        dt.checked_add(&span)
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("+"))
+    }
+    #[rhai_fn(return_raw, name = "+=")]
+    pub fn add_assign(dt: &mut DateTime, span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        *dt = dt.checked_add(&span).ok_or_else(|| out_of_range("+="))?;
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw, name = "-")]
+    pub fn subtract(dt: DateTime, span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        dt.checked_sub(&span)
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("-"))
+    }
+    #[rhai_fn(return_raw, name = "-=")]
+    pub fn subtract_assign(
+        dt: &mut DateTime,
+        span: TimeSpan,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        *dt = dt.checked_sub(&span).ok_or_else(|| out_of_range("-="))?;
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(name = "-")]
+    pub fn diff(dt: DateTime, dt2: DateTime) -> TimeSpan {
+        dt.since(&dt2)
+    }
+
+    #[rhai_fn(name = "==")]
+    pub fn eq(dt: DateTime, dt2: DateTime) -> bool {
+        dt == dt2
+    }
+    #[rhai_fn(name = "!=")]
+    pub fn ne(dt: DateTime, dt2: DateTime) -> bool {
+        dt != dt2
+    }
+    #[rhai_fn(name = "<")]
+    pub fn lt(dt: DateTime, dt2: DateTime) -> bool {
+        dt < dt2
+    }
+    #[rhai_fn(name = "<=")]
+    pub fn lte(dt: DateTime, dt2: DateTime) -> bool {
+        dt <= dt2
+    }
+    #[rhai_fn(name = ">")]
+    pub fn gt(dt: DateTime, dt2: DateTime) -> bool {
+        dt > dt2
+    }
+    #[rhai_fn(name = ">=")]
+    pub fn gte(dt: DateTime, dt2: DateTime) -> bool {
+        dt >= dt2
+    }
+}
+
+#[export_module]
+mod duration_functions {
+    #[rhai_fn(name = "days", return_raw)]
+    pub fn from_days(days: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        to_i64(days)
+            .checked_mul(86_400)
+            .map(|secs| Dynamic::from(TimeSpan::from_secs(secs)))
+            .ok_or_else(|| out_of_range("days"))
+    }
+    #[rhai_fn(name = "hours", return_raw)]
+    pub fn from_hours(hours: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        to_i64(hours)
+            .checked_mul(3600)
+            .map(|secs| Dynamic::from(TimeSpan::from_secs(secs)))
+            .ok_or_else(|| out_of_range("hours"))
+    }
+    #[rhai_fn(name = "minutes", return_raw)]
+    pub fn from_minutes(minutes: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        to_i64(minutes)
+            .checked_mul(60)
+            .map(|secs| Dynamic::from(TimeSpan::from_secs(secs)))
+            .ok_or_else(|| out_of_range("minutes"))
+    }
+    #[rhai_fn(name = "seconds")]
+    pub fn from_seconds(seconds: INT) -> TimeSpan {
+        TimeSpan::from_secs(to_i64(seconds))
+    }
+    #[rhai_fn(name = "milliseconds")]
+    pub fn from_milliseconds(milliseconds: INT) -> TimeSpan {
+        TimeSpan::from_millis(to_i64(milliseconds))
+    }
+
+    #[rhai_fn(name = "days", get = "days")]
+    pub fn days(span: TimeSpan) -> INT {
+        (span.whole_secs() / 86_400) as INT
+    }
+    #[rhai_fn(name = "hours", get = "hours", return_raw)]
+    pub fn hours(span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        make_int(span.whole_secs() / 3600).map(Dynamic::from)
+    }
+    #[rhai_fn(name = "minutes", get = "minutes", return_raw)]
+    pub fn minutes(span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        make_int(span.whole_secs() / 60).map(Dynamic::from)
+    }
+    #[rhai_fn(name = "seconds", get = "seconds", return_raw)]
+    pub fn seconds(span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        make_int(span.whole_secs()).map(Dynamic::from)
+    }
+    #[rhai_fn(name = "milliseconds", get = "milliseconds", return_raw)]
+    pub fn milliseconds(span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        span.whole_millis()
+            .ok_or_else(|| out_of_range("milliseconds"))
+            .and_then(make_int)
+            .map(Dynamic::from)
+    }
+    #[rhai_fn(name = "print", name = "to_string", name = "debug", name = "to_debug")]
+    pub fn to_string(span: TimeSpan) -> ImmutableString {
+        span.to_string().into()
+    }
+
+    #[cfg(not(feature = "no_float"))]
+    pub mod float_functions {
+        #[rhai_fn(name = "seconds", return_raw)]
+        pub fn from_float_seconds(seconds: FLOAT) -> Result<Dynamic, Box<EvalAltResult>> {
+            TimeSpan::from_secs_f64(to_f64(seconds))
+                .map(Dynamic::from)
+                .ok_or_else(|| out_of_range("seconds"))
+        }
+        #[rhai_fn(name = "to_float")]
+        pub fn to_float(span: TimeSpan) -> FLOAT {
+            span.as_secs_f64() as FLOAT
+        }
+    }
+
+    #[rhai_fn(return_raw, name = "+")]
+    pub fn add(span: TimeSpan, span2: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        span.checked_add(&span2)
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("+"))
+    }
+    #[rhai_fn(return_raw, name = "+=")]
+    pub fn add_assign(span: &mut TimeSpan, span2: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        *span = span.checked_add(&span2).ok_or_else(|| out_of_range("+="))?;
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw, name = "-")]
+    pub fn subtract(span: TimeSpan, span2: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        span.checked_sub(&span2)
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("-"))
+    }
+    #[rhai_fn(return_raw, name = "-=")]
+    pub fn subtract_assign(
+        span: &mut TimeSpan,
+        span2: TimeSpan,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        *span = span.checked_sub(&span2).ok_or_else(|| out_of_range("-="))?;
+        Ok(Dynamic::UNIT)
+    }
+    #[rhai_fn(return_raw, name = "-")]
+    pub fn neg(span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        span.checked_neg()
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("-"))
+    }
+    #[rhai_fn(return_raw)]
+    pub fn abs(span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        span.checked_abs()
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("abs"))
+    }
+    #[rhai_fn(return_raw, name = "*")]
+    pub fn multiply(span: TimeSpan, factor: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        span.checked_mul(to_i64(factor))
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("*"))
+    }
+    #[rhai_fn(return_raw, name = "*")]
+    pub fn multiply_rev(factor: INT, span: TimeSpan) -> Result<Dynamic, Box<EvalAltResult>> {
+        multiply(span, factor)
+    }
+    #[rhai_fn(return_raw, name = "/")]
+    pub fn divide(span: TimeSpan, divisor: INT) -> Result<Dynamic, Box<EvalAltResult>> {
+        if divisor == 0 {
+            return Err(make_arithmetic_err("Division by zero"));
+        }
+
+        span.checked_div(to_i64(divisor))
+            .map(Dynamic::from)
+            .ok_or_else(|| out_of_range("/"))
+    }
+
+    #[rhai_fn(name = "==")]
+    pub fn eq(span: TimeSpan, span2: TimeSpan) -> bool {
+        span == span2
+    }
+    #[rhai_fn(name = "!=")]
+    pub fn ne(span: TimeSpan, span2: TimeSpan) -> bool {
+        span != span2
+    }
+    #[rhai_fn(name = "<")]
+    pub fn lt(span: TimeSpan, span2: TimeSpan) -> bool {
+        span < span2
+    }
+    #[rhai_fn(name = "<=")]
+    pub fn lte(span: TimeSpan, span2: TimeSpan) -> bool {
+        span <= span2
+    }
+    #[rhai_fn(name = ">")]
+    pub fn gt(span: TimeSpan, span2: TimeSpan) -> bool {
+        span > span2
+    }
+    #[rhai_fn(name = ">=")]
+    pub fn gte(span: TimeSpan, span2: TimeSpan) -> bool {
+        span >= span2
+    }
+}
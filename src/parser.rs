@@ -35,6 +35,9 @@ use crate::FLOAT;
 #[cfg(not(feature = "no_function"))]
 use crate::FnAccess;
 
+#[cfg(not(feature = "no_object"))]
+use crate::MapKey;
+
 type PERR = ParseErrorType;
 
 type FunctionsLib = HashMap<u64, Shared<ScriptFnDef>, StraightHasherBuilder>;
@@ -104,12 +107,15 @@ impl<'e> ParseState<'e> {
     ///
     /// If the variable is not present in the scope adds it to the list of external variables
     ///
-    /// The return value is the offset to be deducted from `Stack::len`,
+    /// The first return value is the offset to be deducted from `Stack::len`,
     /// i.e. the top element of the [`ParseState`] is offset 1.
+    /// It is `None` when the variable name is not found in the `stack`.
     ///
-    /// Return `None` when the variable name is not found in the `stack`.
+    /// The second return value is the hash for caching the lookup of a variable that is not
+    /// declared within the script.  It is `None` when the variable is found in the `stack`,
+    /// or when the search is stopped by a barrier (in which case nothing is known about it).
     #[inline(always)]
-    fn access_var(&mut self, name: &str, _pos: Position) -> Option<NonZeroUsize> {
+    fn access_var(&mut self, name: &str, _pos: Position) -> (Option<NonZeroUsize>, Option<u64>) {
         let mut barrier = false;
 
         let index = self
@@ -137,10 +143,10 @@ impl<'e> ParseState<'e> {
             self.allow_capture = true
         }
 
-        if barrier {
-            None
-        } else {
-            index
+        match index {
+            _ if barrier => (None, None),
+            Some(_) => (index, None),
+            None => (None, Some(calc_fn_hash(empty(), name, 0))),
         }
     }
 
@@ -449,26 +455,21 @@ fn parse_index_chain(
 
     let idx_expr = parse_expr(input, state, lib, settings.level_up())?;
 
-    // Check type of indexing - must be integer or string
+    // Check type of indexing - must be integer or string, or a key of an object map
     match &idx_expr {
         // lhs[int]
-        Expr::IntegerConstant(x, pos) if *x < 0 => {
-            return Err(PERR::MalformedIndexExpr(format!(
-                "Array access expects non-negative index: {} < 0",
-                *x
-            ))
-            .into_err(*pos))
-        }
-        Expr::IntegerConstant(_, pos) => match lhs {
-            Expr::Array(_, _) | Expr::StringConstant(_, _) => (),
-
-            Expr::Map(_, _) => {
-                return Err(PERR::MalformedIndexExpr(
-                    "Object map access expects string index, not a number".into(),
-                )
+        Expr::IntegerConstant(x, pos) if *x < 0 => match lhs {
+            Expr::Array(_, _) | Expr::StringConstant(_, _) => {
+                return Err(PERR::MalformedIndexExpr(format!(
+                    "Array access expects non-negative index: {} < 0",
+                    *x
+                ))
                 .into_err(*pos))
             }
 
+            _ => (),
+        },
+        Expr::IntegerConstant(_, _) => match lhs {
             #[cfg(not(feature = "no_float"))]
             Expr::FloatConstant(_, _) => {
                 return Err(PERR::MalformedIndexExpr(
@@ -532,13 +533,6 @@ fn parse_index_chain(
             )
             .into_err(x.position()))
         }
-        // lhs[char]
-        x @ Expr::CharConstant(_, _) => {
-            return Err(PERR::MalformedIndexExpr(
-                "Array access expects integer index, not a character".into(),
-            )
-            .into_err(x.position()))
-        }
         // lhs[()]
         x @ Expr::Unit(_) => {
             return Err(PERR::MalformedIndexExpr(
@@ -546,20 +540,28 @@ fn parse_index_chain(
             )
             .into_err(x.position()))
         }
-        // lhs[??? && ???], lhs[??? || ???]
-        x @ Expr::And(_, _) | x @ Expr::Or(_, _) => {
-            return Err(PERR::MalformedIndexExpr(
-                "Array access expects integer index, not a boolean".into(),
-            )
-            .into_err(x.position()))
-        }
-        // lhs[true], lhs[false]
-        x @ Expr::BoolConstant(_, _) => {
-            return Err(PERR::MalformedIndexExpr(
-                "Array access expects integer index, not a boolean".into(),
-            )
-            .into_err(x.position()))
-        }
+        // lhs[char] - only object maps can be indexed by a character
+        x @ Expr::CharConstant(_, _) => match lhs {
+            Expr::Array(_, _) | Expr::StringConstant(_, _) => {
+                return Err(PERR::MalformedIndexExpr(
+                    "Array access expects integer index, not a character".into(),
+                )
+                .into_err(x.position()))
+            }
+
+            _ => (),
+        },
+        // lhs[true], lhs[false], lhs[??? && ???], lhs[??? || ???] - only object maps can be indexed by a boolean
+        x @ Expr::BoolConstant(_, _) | x @ Expr::And(_, _) | x @ Expr::Or(_, _) => match lhs {
+            Expr::Array(_, _) | Expr::StringConstant(_, _) => {
+                return Err(PERR::MalformedIndexExpr(
+                    "Array access expects integer index, not a boolean".into(),
+                )
+                .into_err(x.position()))
+            }
+
+            _ => (),
+        },
         // All other expressions
         _ => (),
     }
@@ -684,7 +686,7 @@ fn parse_map_literal(
     // #{ ...
     settings.pos = eat_token(input, Token::MapStart);
 
-    let mut map: StaticVec<(Ident, Expr)> = Default::default();
+    let mut map: StaticVec<(MapKey, Expr)> = Default::default();
 
     loop {
         const MISSING_RBRACE: &str = "to end this object map literal";
@@ -703,13 +705,19 @@ fn parse_map_literal(
             _ => (),
         }
 
-        let (name, pos) = match input.next().unwrap() {
+        let (key, pos) = match input.next().unwrap() {
             (Token::Identifier(s), pos) | (Token::StringConstant(s), pos) => {
-                if map.iter().any(|(p, _)| p.name == &s) {
-                    return Err(PERR::DuplicatedProperty(s).into_err(pos));
-                }
-                (s, pos)
+                (MapKey::Str(state.get_interned_string(s)), pos)
             }
+            (Token::IntegerConstant(n), pos) => (MapKey::Int(n), pos),
+            (Token::UnaryMinus, pos) => match input.next().unwrap() {
+                (Token::IntegerConstant(n), _) => (MapKey::Int(-n), pos),
+                (Token::LexError(err), pos) => return Err(err.into_err(pos)),
+                (_, pos) => return Err(PERR::PropertyExpected.into_err(pos)),
+            },
+            (Token::CharConstant(ch), pos) => (MapKey::Char(ch), pos),
+            (Token::True, pos) => (MapKey::Bool(true), pos),
+            (Token::False, pos) => (MapKey::Bool(false), pos),
             (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
                 return Err(PERR::Reserved(s).into_err(pos));
             }
@@ -729,6 +737,10 @@ fn parse_map_literal(
             (_, pos) => return Err(PERR::PropertyExpected.into_err(pos)),
         };
 
+        if map.iter().any(|(k, _)| *k == key) {
+            return Err(PERR::DuplicatedProperty(key.to_string()).into_err(pos));
+        }
+
         match input.next().unwrap() {
             (Token::Colon, _) => (),
             (Token::LexError(err), pos) => return Err(err.into_err(pos)),
@@ -737,7 +749,7 @@ fn parse_map_literal(
                     Token::Colon.into(),
                     format!(
                         "to follow the property '{}' in this object map literal",
-                        name
+                        key
                     ),
                 )
                 .into_err(pos))
@@ -754,8 +766,7 @@ fn parse_map_literal(
         }
 
         let expr = parse_expr(input, state, lib, settings.level_up())?;
-        let name = state.get_interned_string(name);
-        map.push((Ident { name, pos }, expr));
+        map.push((key, expr));
 
         match input.peek().unwrap() {
             (Token::Comma, _) => {
@@ -955,6 +966,13 @@ fn parse_primary(
             input.next().unwrap();
             Expr::DynamicConstant(Box::new(x), settings.pos)
         }
+        #[cfg(feature = "bigint")]
+        Token::BigIntConstant(_) => match input.next().unwrap() {
+            (Token::BigIntConstant(x), _) => {
+                Expr::DynamicConstant(Box::new(x.into()), settings.pos)
+            }
+            _ => unreachable!(),
+        },
 
         // { - block statement as expression
         Token::LeftBrace if settings.allow_stmt_expr => {
@@ -998,12 +1016,10 @@ fn parse_primary(
                 pos: settings.pos,
             };
 
-            let (expr, func) = parse_anon_fn(input, &mut new_state, lib, settings)?;
+            let (expr, func, _externals) = parse_anon_fn(input, &mut new_state, lib, settings)?;
 
             #[cfg(not(feature = "no_closure"))]
-            new_state.externals.iter().for_each(|(closure, pos)| {
-                state.access_var(closure, *pos);
-            });
+            let expr = make_curry_from_externals(state, expr, _externals, settings.pos);
 
             let hash_script = calc_fn_hash(empty(), &func.name, func.params.len());
             lib.insert(hash_script, func.into());
@@ -1038,7 +1054,7 @@ fn parse_primary(
                         name: state.get_interned_string(s),
                         pos: settings.pos,
                     };
-                    Expr::Variable(Box::new((None, None, var_name_def)))
+                    Expr::Variable(Box::new((None, None, var_name_def, None)))
                 }
                 // Namespace qualification
                 #[cfg(not(feature = "no_module"))]
@@ -1052,16 +1068,16 @@ fn parse_primary(
                         name: state.get_interned_string(s),
                         pos: settings.pos,
                     };
-                    Expr::Variable(Box::new((None, None, var_name_def)))
+                    Expr::Variable(Box::new((None, None, var_name_def, None)))
                 }
                 // Normal variable access
                 _ => {
-                    let index = state.access_var(&s, settings.pos);
+                    let (index, hash) = state.access_var(&s, settings.pos);
                     let var_name_def = Ident {
                         name: state.get_interned_string(s),
                         pos: settings.pos,
                     };
-                    Expr::Variable(Box::new((index, None, var_name_def)))
+                    Expr::Variable(Box::new((index, None, var_name_def, hash)))
                 }
             }
         }
@@ -1080,7 +1096,7 @@ fn parse_primary(
                         name: state.get_interned_string(s),
                         pos: settings.pos,
                     };
-                    Expr::Variable(Box::new((None, None, var_name_def)))
+                    Expr::Variable(Box::new((None, None, var_name_def, None)))
                 }
                 // Access to `this` as a variable is OK within a function scope
                 _ if s == KEYWORD_THIS && settings.is_function_scope => {
@@ -1088,7 +1104,7 @@ fn parse_primary(
                         name: state.get_interned_string(s),
                         pos: settings.pos,
                     };
-                    Expr::Variable(Box::new((None, None, var_name_def)))
+                    Expr::Variable(Box::new((None, None, var_name_def, None)))
                 }
                 // Cannot access to `this` as a variable not in a function scope
                 _ if s == KEYWORD_THIS => {
@@ -1151,14 +1167,14 @@ fn parse_primary(
                     .into_err(pos));
                 }
 
-                let (_, namespace, Ident { name, pos, .. }) = *x;
+                let (_, namespace, Ident { name, pos, .. }, _) = *x;
                 settings.pos = pos;
                 let ns = namespace.map(|(_, ns)| ns);
                 parse_fn_call(input, state, lib, name, true, ns, settings.level_up())?
             }
             // Function call
             (Expr::Variable(x), Token::LeftParen) => {
-                let (_, namespace, Ident { name, pos, .. }) = *x;
+                let (_, namespace, Ident { name, pos, .. }, _) = *x;
                 settings.pos = pos;
                 let ns = namespace.map(|(_, ns)| ns);
                 parse_fn_call(input, state, lib, name, false, ns, settings.level_up())?
@@ -1166,7 +1182,7 @@ fn parse_primary(
             // module access
             (Expr::Variable(x), Token::DoubleColon) => match input.next().unwrap() {
                 (Token::Identifier(id2), pos2) => {
-                    let (index, mut namespace, var_name_def) = *x;
+                    let (index, mut namespace, var_name_def, _) = *x;
 
                     if let Some((_, ref mut namespace)) = namespace {
                         namespace.push(var_name_def);
@@ -1181,7 +1197,7 @@ fn parse_primary(
                         name: state.get_interned_string(id2),
                         pos: pos2,
                     };
-                    Expr::Variable(Box::new((index, namespace, var_name_def)))
+                    Expr::Variable(Box::new((index, namespace, var_name_def, None)))
                 }
                 (Token::Reserved(id2), pos2) if is_valid_identifier(id2.chars()) => {
                     return Err(PERR::Reserved(id2).into_err(pos2));
@@ -1232,7 +1248,7 @@ fn parse_primary(
         _ => None,
     }
     .map(|x| match x.as_mut() {
-        (_, Some((ref mut hash, ref mut namespace)), Ident { name, .. }) => {
+        (_, Some((ref mut hash, ref mut namespace)), Ident { name, .. }, _) => {
             *hash = calc_fn_hash(namespace.iter().map(|v| v.name.as_str()), name, 0);
 
             #[cfg(not(feature = "no_module"))]
@@ -1399,7 +1415,7 @@ fn make_assignment_stmt<'a>(
         }
         // var (indexed) = rhs
         Expr::Variable(x) => {
-            let (index, _, Ident { name, pos, .. }) = x.as_ref();
+            let (index, _, Ident { name, pos, .. }, _) = x.as_ref();
             match state.stack[(state.stack.len() - index.unwrap().get())].1 {
                 AccessMode::ReadWrite => {
                     Ok(Stmt::Assignment(Box::new((lhs, rhs, op_info)), op_pos))
@@ -1420,7 +1436,7 @@ fn make_assignment_stmt<'a>(
                     }
                     // var[???] (indexed) = rhs, var.??? (indexed) = rhs
                     Expr::Variable(x) => {
-                        let (index, _, Ident { name, pos, .. }) = x.as_ref();
+                        let (index, _, Ident { name, pos, .. }, _) = x.as_ref();
                         match state.stack[(state.stack.len() - index.unwrap().get())].1 {
                             AccessMode::ReadWrite => {
                                 Ok(Stmt::Assignment(Box::new((lhs, rhs, op_info)), op_pos))
@@ -1839,7 +1855,7 @@ fn parse_custom_syntax(
                     segments.push(name.clone());
                     tokens.push(state.get_interned_string(MARKER_IDENT));
                     let var_name_def = Ident { name, pos };
-                    keywords.push(Expr::Variable(Box::new((None, None, var_name_def))));
+                    keywords.push(Expr::Variable(Box::new((None, None, var_name_def, None))));
                 }
                 (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
                     return Err(PERR::Reserved(s).into_err(pos));
@@ -1907,7 +1923,7 @@ fn parse_expr(
 
         match token {
             Token::Custom(key) | Token::Reserved(key) | Token::Identifier(key) => {
-                match state.engine.custom_syntax.get_key_value(key) {
+                match state.engine.custom_syntax.get_key_value(key.as_str()) {
                     Some((key, syntax)) => {
                         input.next().unwrap();
                         return parse_custom_syntax(
@@ -2735,6 +2751,9 @@ fn parse_fn(
 
     let params: StaticVec<_> = params.into_iter().map(|(p, _)| p).collect();
 
+    let mut body: StmtBlock = body;
+    mark_tail_calls(&mut body.statements, &name, params.len(), true);
+
     #[cfg(not(feature = "no_closure"))]
     let externals = state
         .externals
@@ -2755,13 +2774,19 @@ fn parse_fn(
         #[cfg(not(feature = "no_module"))]
         mods: Default::default(),
         comments,
+        memo: Default::default(),
     })
 }
 
 /// Creates a curried expression from a list of external variables
 #[cfg(not(feature = "no_function"))]
 #[cfg(not(feature = "no_closure"))]
-fn make_curry_from_externals(fn_expr: Expr, externals: StaticVec<Ident>, pos: Position) -> Expr {
+fn make_curry_from_externals(
+    state: &mut ParseState,
+    fn_expr: Expr,
+    externals: StaticVec<Ident>,
+    pos: Position,
+) -> Expr {
     // If there are no captured variables, no need to curry
     if externals.is_empty() {
         return fn_expr;
@@ -2773,7 +2798,9 @@ fn make_curry_from_externals(fn_expr: Expr, externals: StaticVec<Ident>, pos: Po
     args.push(fn_expr);
 
     externals.iter().for_each(|x| {
-        args.push(Expr::Variable(Box::new((None, None, x.clone()))));
+        // Captured variables are accessed in the enclosing scope
+        let (index, hash) = state.access_var(&x.name, x.pos);
+        args.push(Expr::Variable(Box::new((index, None, x.clone(), hash))));
     });
 
     let curry_func = crate::engine::KEYWORD_FN_PTR_CURRY;
@@ -2796,6 +2823,55 @@ fn make_curry_from_externals(fn_expr: Expr, externals: StaticVec<Ident>, pos: Po
     Expr::Stmt(Box::new(StmtBlock { statements, pos }))
 }
 
+/// Mark calls to a script-defined function, within its own body, that are in tail position
+/// (i.e. the results of which are directly returned).
+#[cfg(not(feature = "no_function"))]
+fn mark_tail_calls(statements: &mut [Stmt], name: &str, num_params: usize, is_tail: bool) {
+    fn mark_expr(expr: &mut Expr, name: &str, num_params: usize) {
+        match expr {
+            Expr::FnCall(x, _)
+                if x.namespace.is_none()
+                    && !x.capture
+                    && x.name == name
+                    && x.args.len() == num_params =>
+            {
+                x.tail_call = true
+            }
+            Expr::Stmt(x) => mark_tail_calls(&mut x.statements, name, num_params, true),
+            _ => (),
+        }
+    }
+
+    let last = statements.len().saturating_sub(1);
+
+    statements
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, stmt)| match stmt {
+            Stmt::Return(ReturnType::Return, Some(expr), _) => mark_expr(expr, name, num_params),
+            Stmt::Expr(expr) if is_tail && i == last => mark_expr(expr, name, num_params),
+            Stmt::If(_, x, _) => {
+                let is_tail = is_tail && i == last;
+                mark_tail_calls(&mut x.0.statements, name, num_params, is_tail);
+                mark_tail_calls(&mut x.1.statements, name, num_params, is_tail);
+            }
+            Stmt::Switch(_, x, _) => {
+                let is_tail = is_tail && i == last;
+                x.0.values_mut().for_each(|block| {
+                    mark_tail_calls(&mut block.statements, name, num_params, is_tail)
+                });
+                mark_tail_calls(&mut x.1.statements, name, num_params, is_tail);
+            }
+            Stmt::Block(block, _) => mark_tail_calls(block, name, num_params, is_tail && i == last),
+            Stmt::While(_, body, _) | Stmt::Do(body, _, _, _) => {
+                mark_tail_calls(&mut body.statements, name, num_params, false)
+            }
+            Stmt::For(_, x, _) => mark_tail_calls(&mut x.1.statements, name, num_params, false),
+            // Errors in a try block must be caught, so nothing in it is in tail position
+            _ => (),
+        });
+}
+
 /// Parse an anonymous function definition.
 #[cfg(not(feature = "no_function"))]
 fn parse_anon_fn(
@@ -2803,7 +2879,7 @@ fn parse_anon_fn(
     state: &mut ParseState,
     lib: &mut FunctionsLib,
     mut settings: ParseSettings,
-) -> Result<(Expr, ScriptFnDef), ParseError> {
+) -> Result<(Expr, ScriptFnDef, StaticVec<Ident>), ParseError> {
     #[cfg(not(feature = "unchecked"))]
     settings.ensure_level_within_max_limit(state.max_expr_depth)?;
 
@@ -2900,14 +2976,12 @@ fn parse_anon_fn(
         #[cfg(not(feature = "no_module"))]
         mods: Default::default(),
         comments: Default::default(),
+        memo: Default::default(),
     };
 
     let expr = Expr::FnPointer(fn_name, settings.pos);
 
-    #[cfg(not(feature = "no_closure"))]
-    let expr = make_curry_from_externals(expr, externals, settings.pos);
-
-    Ok((expr, script))
+    Ok((expr, script, externals))
 }
 
 impl Engine {
@@ -3062,6 +3136,12 @@ pub fn map_dynamic_to_expr(value: Dynamic, pos: Position) -> Option<Expr> {
         #[cfg(not(feature = "no_index"))]
         Union::Array(array, _) => Some(Expr::DynamicConstant(Box::new((*array).into()), pos)),
 
+        #[cfg(not(feature = "no_index"))]
+        Union::Blob(blob, _) => Some(Expr::DynamicConstant(Box::new(Dynamic::from(*blob)), pos)),
+
+        #[cfg(not(feature = "no_index"))]
+        Union::Set(set, _) => Some(Expr::DynamicConstant(Box::new(Dynamic::from(*set)), pos)),
+
         #[cfg(not(feature = "no_object"))]
         Union::Map(map, _) => Some(Expr::DynamicConstant(Box::new((*map).into()), pos)),
 
@@ -0,0 +1,1065 @@
+//! Module implementing the [`Regex`] type with a bounded backtracking matcher.
+
+use crate::fn_native::{Locked, Shared};
+use crate::stdlib::{
+    boxed::Box,
+    collections::HashMap,
+    fmt, format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// Maximum nesting depth of groups in a regular expression.
+const MAX_NESTING: usize = 128;
+
+/// Maximum count in a counted repetition such as `a{1,1000}`.
+const MAX_REPEAT: u32 = 1000;
+
+/// Maximum number of instructions in a compiled regular expression, regardless of limits.
+const MAX_PROGRAM_SIZE: usize = 1 << 20;
+
+/// Maximum number of compiled regular expressions cached by an [`Engine`][crate::Engine].
+const MAX_CACHED_REGEX: usize = 128;
+
+/// Error when compiling or running a regular expression.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum RegexError {
+    /// The pattern is not a valid regular expression.
+    Syntax(String),
+    /// The compiled program exceeds the maximum size.
+    TooLarge,
+    /// Matching exceeds the maximum number of steps.
+    TooManySteps,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(s) => f.write_str(s),
+            Self::TooLarge => f.write_str("regular expression is too large"),
+            Self::TooManySteps => f.write_str("too many steps in matching regular expression"),
+        }
+    }
+}
+
+/// Flags in effect while parsing part of a pattern.
+#[derive(Debug, Clone, Copy, Default)]
+struct Flags {
+    /// `i`: case-insensitive matching.
+    case_insensitive: bool,
+    /// `m`: `^` and `$` match at the start and end of lines.
+    multi_line: bool,
+    /// `s`: `.` matches new-lines.
+    dot_all: bool,
+}
+
+/// A zero-width assertion.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Assertion {
+    TextStart,
+    TextEnd,
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+/// An item in a character class.
+#[derive(Debug, Clone, Copy)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+/// A character class such as `[a-z_\d]`.
+#[derive(Debug, Clone)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+    case_insensitive: bool,
+}
+
+/// Node of a parsed regular expression.
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char, bool),
+    Any(bool),
+    Class(Box<Class>),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+/// Instruction of a compiled regular expression.
+#[derive(Debug, Clone)]
+enum Inst {
+    /// Match a character, case-insensitively (with the character in lower case) if the flag is set.
+    Char(char, bool),
+    /// Match any character, including new-lines if the flag is set.
+    Any(bool),
+    /// Match a character class.
+    Class(Box<Class>),
+    /// Check a zero-width assertion.
+    Assert(Assertion),
+    /// Try the first branch, backtracking to the second.
+    Split(usize, usize),
+    /// Jump to an instruction.
+    Jmp(usize),
+    /// Save the current position into a slot.
+    Save(usize),
+    /// Fail if the current position equals the one in a slot (an iteration that matched nothing).
+    Progress(usize),
+    /// Successful match.
+    Match,
+}
+
+/// Is a character a word character for `\w` and `\b`?
+#[inline(always)]
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Simple lower-case mapping of a character, for case-insensitive matching.
+#[inline]
+fn to_lower(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(ch), None) => ch,
+        _ => c,
+    }
+}
+
+/// Simple upper-case mapping of a character, for case-insensitive matching.
+#[inline]
+fn to_upper(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(ch), None) => ch,
+        _ => c,
+    }
+}
+
+impl ClassItem {
+    #[inline]
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            Self::Range(from, to) => from <= c && c <= to,
+            Self::Digit(negated) => c.is_ascii_digit() != negated,
+            Self::Word(negated) => is_word_char(c) != negated,
+            Self::Space(negated) => c.is_whitespace() != negated,
+        }
+    }
+}
+
+impl Class {
+    #[inline]
+    fn matches(&self, c: char) -> bool {
+        let found = |c| self.items.iter().any(|item| item.matches(c));
+        let found =
+            found(c) || (self.case_insensitive && (found(to_lower(c)) || found(to_upper(c))));
+        found != self.negated
+    }
+}
+
+impl Assertion {
+    #[inline]
+    fn holds(self, text: &str, pos: usize) -> bool {
+        let prev = || text[..pos].chars().next_back();
+        let next = || text[pos..].chars().next();
+
+        match self {
+            Self::TextStart => pos == 0,
+            Self::TextEnd => pos == text.len(),
+            Self::LineStart => pos == 0 || prev() == Some('\n'),
+            Self::LineEnd => pos == text.len() || next() == Some('\n'),
+            Self::WordBoundary | Self::NotWordBoundary => {
+                let before = prev().is_some_and(is_word_char);
+                let after = next().is_some_and(is_word_char);
+                (before != after) == (self == Self::WordBoundary)
+            }
+        }
+    }
+}
+
+/// Parser of regular expression patterns.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    num_groups: usize,
+    names: Vec<(String, usize)>,
+}
+
+impl Parser {
+    #[inline(always)]
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+    #[inline(always)]
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    #[inline(always)]
+    fn error<T>(&self, msg: &str) -> Result<T, RegexError> {
+        Err(RegexError::Syntax(format!(
+            "{} at position {}",
+            msg, self.pos
+        )))
+    }
+    /// Parse alternatives separated by `|`.
+    fn parse_alt(&mut self, flags: &mut Flags, depth: usize) -> Result<Node, RegexError> {
+        if depth > MAX_NESTING {
+            return self.error("too many nested groups");
+        }
+
+        let mut alts = vec![self.parse_concat(flags, depth)?];
+
+        while self.eat('|') {
+            alts.push(self.parse_concat(flags, depth)?);
+        }
+
+        Ok(if alts.len() == 1 {
+            alts.pop().unwrap()
+        } else {
+            Node::Alt(alts)
+        })
+    }
+    /// Parse a sequence of repeated atoms.
+    fn parse_concat(&mut self, flags: &mut Flags, depth: usize) -> Result<Node, RegexError> {
+        let mut nodes = Vec::new();
+
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+
+            let node = match self.parse_atom(flags, depth)? {
+                Some(node) => node,
+                None => continue,
+            };
+            nodes.push(self.parse_repeat(node)?);
+        }
+
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Concat(nodes),
+        })
+    }
+    /// Parse an optional repetition operator after an atom.
+    fn parse_repeat(&mut self, node: Node) -> Result<Node, RegexError> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.parse_count()?;
+                let max = if self.eat(',') {
+                    if self.peek() == Some('}') {
+                        None
+                    } else {
+                        Some(self.parse_count()?)
+                    }
+                } else {
+                    Some(min)
+                };
+                if self.peek() != Some('}') {
+                    return self.error("invalid repetition");
+                }
+                if max.is_some_and(|max| max < min) {
+                    return self.error("invalid repetition range");
+                }
+                (min, max)
+            }
+            _ => return Ok(node),
+        };
+        self.pos += 1;
+
+        let greedy = !self.eat('?');
+
+        if let Some('*') | Some('+') | Some('?') | Some('{') = self.peek() {
+            return self.error("nested repetition operator");
+        }
+
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            greedy,
+        })
+    }
+    /// Parse the count in a counted repetition.
+    fn parse_count(&mut self) -> Result<u32, RegexError> {
+        let start = self.pos;
+        let mut count: u32 = 0;
+
+        while let Some(d) = self.peek().and_then(|c| c.to_digit(10)) {
+            count = count.saturating_mul(10).saturating_add(d);
+            self.pos += 1;
+        }
+
+        if self.pos == start {
+            self.error("invalid repetition count")
+        } else if count > MAX_REPEAT {
+            self.error("repetition count is too large")
+        } else {
+            Ok(count)
+        }
+    }
+    /// Parse an atom, returning [`None`] for a flags group such as `(?i)`.
+    fn parse_atom(&mut self, flags: &mut Flags, depth: usize) -> Result<Option<Node>, RegexError> {
+        let c = self.peek().unwrap();
+        self.pos += 1;
+
+        Ok(Some(match c {
+            '(' => return self.parse_group(flags, depth),
+            '[' => Node::Class(Box::new(self.parse_class(flags)?)),
+            '.' => Node::Any(flags.dot_all),
+            '^' if flags.multi_line => Node::Assert(Assertion::LineStart),
+            '^' => Node::Assert(Assertion::TextStart),
+            '$' if flags.multi_line => Node::Assert(Assertion::LineEnd),
+            '$' => Node::Assert(Assertion::TextEnd),
+            '*' | '+' | '?' | '{' => {
+                self.pos -= 1;
+                return self.error("repetition operator missing expression");
+            }
+            '\\' => match self.peek() {
+                Some('b') => {
+                    self.pos += 1;
+                    Node::Assert(Assertion::WordBoundary)
+                }
+                Some('B') => {
+                    self.pos += 1;
+                    Node::Assert(Assertion::NotWordBoundary)
+                }
+                Some('A') => {
+                    self.pos += 1;
+                    Node::Assert(Assertion::TextStart)
+                }
+                Some('z') => {
+                    self.pos += 1;
+                    Node::Assert(Assertion::TextEnd)
+                }
+                _ => match self.parse_escape()? {
+                    Ok(ch) => Node::Char(ch, flags.case_insensitive),
+                    Err(item) => Node::Class(Box::new(Class {
+                        items: vec![item],
+                        negated: false,
+                        case_insensitive: false,
+                    })),
+                },
+            },
+            c if flags.case_insensitive => Node::Char(to_lower(c), true),
+            c => Node::Char(c, false),
+        }))
+    }
+    /// Parse a group after the opening `(`.
+    fn parse_group(&mut self, flags: &mut Flags, depth: usize) -> Result<Option<Node>, RegexError> {
+        let mut index = None;
+        let mut inner = *flags;
+
+        if self.eat('?') {
+            if self.eat('P') && self.peek() != Some('<') {
+                return self.error("invalid group");
+            }
+            if self.eat('<') {
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if c == '>' {
+                        break;
+                    }
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        return self.error("invalid character in group name");
+                    }
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                if !self.eat('>') {
+                    return self.error("unterminated group name");
+                }
+                if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+                    return self.error("invalid group name");
+                }
+                if self.names.iter().any(|(n, _)| *n == name) {
+                    return self.error("duplicate group name");
+                }
+                self.num_groups += 1;
+                self.names.push((name, self.num_groups));
+                index = Some(self.num_groups);
+            } else {
+                // Flags such as `(?im-s)` or `(?i:...)`
+                let mut negate = false;
+                loop {
+                    match self.peek() {
+                        Some('i') => inner.case_insensitive = !negate,
+                        Some('m') => inner.multi_line = !negate,
+                        Some('s') => inner.dot_all = !negate,
+                        Some('-') if !negate => negate = true,
+                        Some(':') => {
+                            self.pos += 1;
+                            break;
+                        }
+                        Some(')') => {
+                            // Flags apply to the rest of the enclosing group
+                            self.pos += 1;
+                            *flags = inner;
+                            return Ok(None);
+                        }
+                        _ => return self.error("invalid group flags"),
+                    }
+                    self.pos += 1;
+                }
+            }
+        } else {
+            self.num_groups += 1;
+            index = Some(self.num_groups);
+        }
+
+        let node = self.parse_alt(&mut inner, depth + 1)?;
+
+        if !self.eat(')') {
+            return self.error("unclosed group");
+        }
+
+        Ok(Some(Node::Group(Box::new(node), index)))
+    }
+    /// Parse an escape sequence after `\`, returning either a character or a class item.
+    fn parse_escape(&mut self) -> Result<Result<char, ClassItem>, RegexError> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return self.error("incomplete escape sequence"),
+        };
+        self.pos += 1;
+
+        Ok(Ok(match c {
+            'd' => return Ok(Err(ClassItem::Digit(false))),
+            'D' => return Ok(Err(ClassItem::Digit(true))),
+            'w' => return Ok(Err(ClassItem::Word(false))),
+            'W' => return Ok(Err(ClassItem::Word(true))),
+            's' => return Ok(Err(ClassItem::Space(false))),
+            'S' => return Ok(Err(ClassItem::Space(true))),
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'f' => '\x0c',
+            'v' => '\x0b',
+            'x' => {
+                let braced = self.eat('{');
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_hexdigit())
+                    && (braced || self.pos - start < 2)
+                {
+                    self.pos += 1;
+                }
+                let digits: String = self.chars[start..self.pos].iter().collect();
+                if (braced && !self.eat('}')) || digits.is_empty() || (!braced && digits.len() != 2)
+                {
+                    return self.error("invalid hex escape");
+                }
+                match u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(crate::stdlib::char::from_u32)
+                {
+                    Some(ch) => ch,
+                    None => return self.error("invalid hex escape"),
+                }
+            }
+            '0'..='9' => {
+                self.pos -= 1;
+                return self.error("back-references are not supported");
+            }
+            c if c.is_ascii_punctuation() || c == ' ' => c,
+            _ => {
+                self.pos -= 1;
+                return self.error("unrecognized escape sequence");
+            }
+        }))
+    }
+    /// Parse a character class after the opening `[`.
+    fn parse_class(&mut self, flags: &Flags) -> Result<Class, RegexError> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+
+        loop {
+            let c = match self.peek() {
+                Some(']') if !first => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(c) => c,
+                None => return self.error("unclosed character class"),
+            };
+            self.pos += 1;
+            first = false;
+
+            let from = if c == '\\' {
+                match self.parse_escape()? {
+                    Ok(ch) => ch,
+                    Err(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                }
+            } else {
+                c
+            };
+
+            // Range such as `a-z`
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&c| c != ']') {
+                self.pos += 1;
+                let c = self.peek().unwrap();
+                self.pos += 1;
+                let to = if c == '\\' {
+                    match self.parse_escape()? {
+                        Ok(ch) => ch,
+                        Err(_) => return self.error("invalid character class range"),
+                    }
+                } else {
+                    c
+                };
+                if to < from {
+                    return self.error("invalid character class range");
+                }
+                items.push(ClassItem::Range(from, to));
+            } else {
+                items.push(ClassItem::Range(from, from));
+            }
+        }
+
+        Ok(Class {
+            items,
+            negated,
+            case_insensitive: flags.case_insensitive,
+        })
+    }
+}
+
+/// Can a node match an empty string?
+fn can_be_empty(node: &Node) -> bool {
+    match node {
+        Node::Empty | Node::Assert(_) => true,
+        Node::Char(_, _) | Node::Any(_) | Node::Class(_) => false,
+        Node::Group(node, _) => can_be_empty(node),
+        Node::Concat(nodes) => nodes.iter().all(can_be_empty),
+        Node::Alt(nodes) => nodes.iter().any(can_be_empty),
+        Node::Repeat { node, min, .. } => *min == 0 || can_be_empty(node),
+    }
+}
+
+/// Compiler of parsed regular expressions into programs.
+struct Compiler {
+    prog: Vec<Inst>,
+    max_size: usize,
+    num_slots: usize,
+}
+
+impl Compiler {
+    #[inline]
+    fn emit(&mut self, inst: Inst) -> Result<usize, RegexError> {
+        if self.prog.len() >= self.max_size {
+            return Err(RegexError::TooLarge);
+        }
+        self.prog.push(inst);
+        Ok(self.prog.len() - 1)
+    }
+    fn compile(&mut self, node: &Node) -> Result<(), RegexError> {
+        match node {
+            Node::Empty => (),
+            Node::Char(c, case_insensitive) => {
+                self.emit(Inst::Char(*c, *case_insensitive))?;
+            }
+            Node::Any(dot_all) => {
+                self.emit(Inst::Any(*dot_all))?;
+            }
+            Node::Class(class) => {
+                self.emit(Inst::Class(class.clone()))?;
+            }
+            Node::Assert(assertion) => {
+                self.emit(Inst::Assert(*assertion))?;
+            }
+            Node::Group(node, None) => self.compile(node)?,
+            Node::Group(node, Some(index)) => {
+                self.emit(Inst::Save(index * 2))?;
+                self.compile(node)?;
+                self.emit(Inst::Save(index * 2 + 1))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            }
+            Node::Alt(nodes) => {
+                let mut jumps = Vec::with_capacity(nodes.len());
+
+                for (i, node) in nodes.iter().enumerate() {
+                    if i == nodes.len() - 1 {
+                        self.compile(node)?;
+                    } else {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.compile(node)?;
+                        jumps.push(self.emit(Inst::Jmp(0))?);
+                        self.prog[split] = Inst::Split(split + 1, self.prog.len());
+                    }
+                }
+
+                let end = self.prog.len();
+                jumps
+                    .into_iter()
+                    .for_each(|j| self.prog[j] = Inst::Jmp(end));
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.compile(node)?;
+                }
+
+                let branch = |body, exit| {
+                    if *greedy {
+                        Inst::Split(body, exit)
+                    } else {
+                        Inst::Split(exit, body)
+                    }
+                };
+
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0))?;
+
+                        // Stop looping when an iteration matches nothing
+                        let slot = if can_be_empty(node) {
+                            self.num_slots += 1;
+                            self.emit(Inst::Save(self.num_slots - 1))?;
+                            Some(self.num_slots - 1)
+                        } else {
+                            None
+                        };
+                        self.compile(node)?;
+                        if let Some(slot) = slot {
+                            self.emit(Inst::Progress(slot))?;
+                        }
+                        self.emit(Inst::Jmp(split))?;
+                        self.prog[split] = branch(split + 1, self.prog.len());
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0))?);
+                            self.compile(node)?;
+                        }
+
+                        let end = self.prog.len();
+                        splits
+                            .into_iter()
+                            .for_each(|s| self.prog[s] = branch(s + 1, end));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Budget of steps for matching a regular expression.
+pub(crate) struct Budget {
+    steps: u64,
+    max_steps: u64,
+}
+
+impl Budget {
+    /// Create a new [`Budget`] with a maximum number of steps (zero for unlimited).
+    #[inline(always)]
+    pub fn new(max_steps: u64) -> Self {
+        Self {
+            steps: 0,
+            max_steps: if max_steps == 0 { u64::MAX } else { max_steps },
+        }
+    }
+    #[inline(always)]
+    fn step(&mut self) -> Result<(), RegexError> {
+        self.steps += 1;
+
+        if self.steps > self.max_steps {
+            Err(RegexError::TooManySteps)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Backtracking frame.
+enum Frame {
+    /// Resume at an instruction and position.
+    Branch(usize, usize),
+    /// Restore the previous value of a slot.
+    Restore(usize, Option<usize>),
+}
+
+/// Positions of the groups in a match, as start and end positions in pairs.
+pub(crate) type Captures = Vec<Option<usize>>;
+
+/// A compiled regular expression.
+struct Program {
+    pattern: String,
+    prog: Vec<Inst>,
+    num_groups: usize,
+    num_slots: usize,
+    names: Vec<(String, usize)>,
+    anchored: bool,
+}
+
+/// A compiled regular expression, shown as `regex` by `type_of`.
+///
+/// Matching is by backtracking, with leftmost-first semantics like Perl.
+///
+/// The supported syntax includes literals, `.`, character classes (e.g. `[^a-z\d]`),
+/// `\d` (ASCII digits), `\w` (Unicode word characters), `\s` (Unicode white-space) and their
+/// negations, anchors (`^`, `$`, `\A`, `\z`, `\b`, `\B`), alternation, greedy and lazy
+/// repetitions (`*`, `+`, `?`, `{n,m}`), capturing, non-capturing (`(?:...)`) and named
+/// (`(?P<name>...)` or `(?<name>...)`) groups, and the flags `i`, `m` and `s` (e.g. `(?i)`).
+///
+/// Cloning a [`Regex`] is cheap.
+#[derive(Clone)]
+pub struct Regex(Shared<Program>);
+
+impl Regex {
+    /// Compile a regular expression.
+    ///
+    /// Returns a message describing the error if the pattern is not valid.
+    #[inline(always)]
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        Self::compile(pattern, usize::MAX).map_err(|err| err.to_string())
+    }
+    /// Compile a regular expression into a program of at most a number of instructions.
+    pub(crate) fn compile(pattern: &str, max_size: usize) -> Result<Self, RegexError> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            num_groups: 0,
+            names: Vec::new(),
+        };
+        let node = parser.parse_alt(&mut Default::default(), 0)?;
+
+        if parser.pos < parser.chars.len() {
+            return parser.error("unmatched ')'");
+        }
+
+        let num_groups = parser.num_groups + 1;
+        let mut compiler = Compiler {
+            prog: Vec::new(),
+            max_size: max_size.min(MAX_PROGRAM_SIZE),
+            num_slots: num_groups * 2,
+        };
+        compiler.emit(Inst::Save(0))?;
+        compiler.compile(&node)?;
+        compiler.emit(Inst::Save(1))?;
+        compiler.emit(Inst::Match)?;
+
+        let anchored = matches!(compiler.prog[1], Inst::Assert(Assertion::TextStart));
+
+        Ok(Self(
+            Program {
+                pattern: pattern.into(),
+                prog: compiler.prog,
+                num_groups,
+                num_slots: compiler.num_slots,
+                names: parser.names,
+                anchored,
+            }
+            .into(),
+        ))
+    }
+    /// The pattern of the regular expression.
+    #[inline(always)]
+    pub fn as_str(&self) -> &str {
+        &self.0.pattern
+    }
+    /// Does the regular expression match anywhere in a string?
+    #[inline(always)]
+    pub fn is_match(&self, text: &str) -> bool {
+        matches!(self.captures_at(text, 0, &mut Budget::new(0)), Ok(Some(_)))
+    }
+    /// Number of instructions in the compiled program.
+    #[inline(always)]
+    pub(crate) fn size(&self) -> usize {
+        self.0.prog.len()
+    }
+    /// Number of groups, including the implicit group 0 for the whole match.
+    #[inline(always)]
+    pub(crate) fn num_groups(&self) -> usize {
+        self.0.num_groups
+    }
+    /// Names and indices of named groups.
+    #[inline(always)]
+    pub(crate) fn group_names(&self) -> &[(String, usize)] {
+        &self.0.names
+    }
+    /// Find the first match starting at or after a position.
+    pub(crate) fn captures_at(
+        &self,
+        text: &str,
+        start: usize,
+        budget: &mut Budget,
+    ) -> Result<Option<Captures>, RegexError> {
+        let mut slots = vec![None; self.0.num_slots];
+        let mut pos = start;
+
+        loop {
+            slots.iter_mut().for_each(|s| *s = None);
+
+            if self.run(text, pos, &mut slots, budget)? {
+                slots.truncate(self.0.num_groups * 2);
+                return Ok(Some(slots));
+            }
+
+            match text[pos..].chars().next() {
+                Some(c) if !self.0.anchored => pos += c.len_utf8(),
+                _ => return Ok(None),
+            }
+        }
+    }
+    /// Call a function for each successive non-overlapping match, stopping when it returns `false`.
+    ///
+    /// An empty match immediately after the previous match is skipped.
+    pub(crate) fn for_each_match(
+        &self,
+        text: &str,
+        budget: &mut Budget,
+        mut f: impl FnMut(&Captures) -> bool,
+    ) -> Result<(), RegexError> {
+        let mut start = 0;
+        let mut last_end = None;
+
+        while start <= text.len() {
+            let caps = match self.captures_at(text, start, budget)? {
+                Some(caps) => caps,
+                None => break,
+            };
+            let (from, to) = (caps[0].unwrap(), caps[1].unwrap());
+
+            if from == to && last_end == Some(to) {
+                // Skip an empty match right after the previous match
+                match text[from..].chars().next() {
+                    Some(c) => start = from + c.len_utf8(),
+                    None => break,
+                }
+                continue;
+            }
+
+            if !f(&caps) {
+                break;
+            }
+            last_end = Some(to);
+
+            start = if from == to {
+                match text[to..].chars().next() {
+                    Some(c) => to + c.len_utf8(),
+                    None => break,
+                }
+            } else {
+                to
+            };
+        }
+
+        Ok(())
+    }
+    /// Run the program at a position.
+    fn run(
+        &self,
+        text: &str,
+        start: usize,
+        slots: &mut [Option<usize>],
+        budget: &mut Budget,
+    ) -> Result<bool, RegexError> {
+        let prog = &self.0.prog;
+        let mut stack = vec![Frame::Branch(0, start)];
+
+        while let Some(frame) = stack.pop() {
+            let (mut pc, mut pos) = match frame {
+                Frame::Branch(pc, pos) => (pc, pos),
+                Frame::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+
+            loop {
+                budget.step()?;
+
+                match prog[pc] {
+                    Inst::Match => return Ok(true),
+                    Inst::Char(c, case_insensitive) => match text[pos..].chars().next() {
+                        Some(ch) if ch == c || (case_insensitive && to_lower(ch) == c) => {
+                            pos += ch.len_utf8();
+                            pc += 1;
+                        }
+                        _ => break,
+                    },
+                    Inst::Any(dot_all) => match text[pos..].chars().next() {
+                        Some(ch) if dot_all || ch != '\n' => {
+                            pos += ch.len_utf8();
+                            pc += 1;
+                        }
+                        _ => break,
+                    },
+                    Inst::Class(ref class) => match text[pos..].chars().next() {
+                        Some(ch) if class.matches(ch) => {
+                            pos += ch.len_utf8();
+                            pc += 1;
+                        }
+                        _ => break,
+                    },
+                    Inst::Assert(assertion) if assertion.holds(text, pos) => pc += 1,
+                    Inst::Assert(_) => break,
+                    Inst::Split(first, second) => {
+                        stack.push(Frame::Branch(second, pos));
+                        pc = first;
+                    }
+                    Inst::Jmp(target) => pc = target,
+                    Inst::Save(slot) => {
+                        stack.push(Frame::Restore(slot, slots[slot]));
+                        slots[slot] = Some(pos);
+                        pc += 1;
+                    }
+                    Inst::Progress(slot) if slots[slot] == Some(pos) => break,
+                    Inst::Progress(_) => pc += 1,
+                }
+            }
+        }
+
+        Ok(false)
+    }
+    /// Append a replacement string to an output, expanding references to groups.
+    ///
+    /// `$n` and `${n}` refer to a group by index, `$name` and `${name}` to a group by name,
+    /// and `$$` is a literal `$`. References to groups that do not exist or did not match
+    /// are replaced by nothing.
+    pub(crate) fn expand(
+        &self,
+        text: &str,
+        caps: &Captures,
+        replacement: &str,
+        output: &mut String,
+    ) {
+        let mut rest = replacement;
+
+        while let Some(i) = rest.find('$') {
+            output.push_str(&rest[..i]);
+            rest = &rest[i + 1..];
+
+            let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+            let name = if rest.starts_with('$') {
+                output.push('$');
+                rest = &rest[1..];
+                continue;
+            } else if rest.starts_with('{') {
+                match rest.find('}') {
+                    Some(end) => {
+                        let name = &rest[1..end];
+                        rest = &rest[end + 1..];
+                        name
+                    }
+                    None => {
+                        output.push('$');
+                        continue;
+                    }
+                }
+            } else {
+                let end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+                if end == 0 {
+                    output.push('$');
+                    continue;
+                }
+                let name = &rest[..end];
+                rest = &rest[end..];
+                name
+            };
+
+            let index = name.parse::<usize>().ok().or_else(|| {
+                self.group_names()
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map(|&(_, index)| index)
+            });
+
+            if let Some(index) = index.filter(|&index| index < self.num_groups()) {
+                if let (Some(from), Some(to)) = (caps[index * 2], caps[index * 2 + 1]) {
+                    output.push_str(&text[from..to]);
+                }
+            }
+        }
+
+        output.push_str(rest);
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.as_str()).finish()
+    }
+}
+
+impl fmt::Display for Regex {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Cache of compiled regular expressions, keyed by pattern.
+///
+/// The cache is emptied when it is full.
+#[derive(Debug, Default)]
+pub(crate) struct RegexCache(Locked<HashMap<String, Regex>>);
+
+impl RegexCache {
+    /// Get the compiled regular expression for a pattern, compiling and caching it if necessary.
+    ///
+    /// A cached regular expression larger than the maximum size is an error.
+    pub fn get(&self, pattern: &str, max_size: usize) -> Result<Regex, RegexError> {
+        #[cfg(not(feature = "sync"))]
+        let mut cache = self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        let mut cache = self.0.write().unwrap();
+
+        if let Some(regex) = cache.get(pattern) {
+            return if regex.size() > max_size {
+                Err(RegexError::TooLarge)
+            } else {
+                Ok(regex.clone())
+            };
+        }
+
+        let regex = Regex::compile(pattern, max_size)?;
+
+        if cache.len() >= MAX_CACHED_REGEX {
+            cache.clear();
+        }
+        cache.insert(pattern.into(), regex.clone());
+
+        Ok(regex)
+    }
+}
@@ -78,6 +78,11 @@ pub enum EvalAltResult {
     ErrorDataTooLarge(String, Position),
     /// The script is prematurely terminated. Wrapped value is the termination token.
     ErrorTerminated(Dynamic, Position),
+    /// Evaluation is suspended by a native function, waiting to be resumed.
+    /// Wrapped value is the suspension token.
+    ///
+    /// See [`Engine::eval_ast_resumable`][crate::Engine::eval_ast_resumable] for details.
+    ErrorSuspended(Dynamic, Position),
     /// Run-time error encountered. Wrapped value is the error token.
     ErrorRuntime(Dynamic, Position),
 
@@ -127,6 +132,7 @@ impl EvalAltResult {
             Self::ErrorStackOverflow(_) => "Stack overflow",
             Self::ErrorDataTooLarge(_, _) => "Data size exceeds maximum limit",
             Self::ErrorTerminated(_,_) => "Script terminated.",
+            Self::ErrorSuspended(_,_) => "Script suspended",
             Self::ErrorRuntime(_, _) => "Runtime error",
             Self::LoopBreak(true, _) => "Break statement not inside a loop",
             Self::LoopBreak(false, _) => "Continue statement not inside a loop",
@@ -183,7 +189,8 @@ impl fmt::Display for EvalAltResult {
             | Self::ErrorTooManyOperations(_)
             | Self::ErrorTooManyModules(_)
             | Self::ErrorStackOverflow(_)
-            | Self::ErrorTerminated(_, _) => f.write_str(desc)?,
+            | Self::ErrorTerminated(_, _)
+            | Self::ErrorSuspended(_, _) => f.write_str(desc)?,
 
             Self::ErrorRuntime(d, _) if d.is::<ImmutableString>() => {
                 let s = &*d.read_lock::<ImmutableString>().unwrap();
@@ -309,7 +316,8 @@ impl EvalAltResult {
             | Self::ErrorTooManyModules(_)
             | Self::ErrorStackOverflow(_)
             | Self::ErrorDataTooLarge(_, _)
-            | Self::ErrorTerminated(_, _) => false,
+            | Self::ErrorTerminated(_, _)
+            | Self::ErrorSuspended(_, _) => false,
 
             Self::LoopBreak(_, _) => panic!("EvalAltResult::LoopBreak should not occur naturally"),
             Self::Return(_, _) => panic!("EvalAltResult::Return should not occur naturally"),
@@ -330,7 +338,7 @@ impl EvalAltResult {
             | Self::ErrorStackOverflow(_)
             | Self::ErrorDataTooLarge(_, _) => true,
 
-            Self::ErrorTerminated(_, _) => true,
+            Self::ErrorTerminated(_, _) | Self::ErrorSuspended(_, _) => true,
 
             Self::LoopBreak(_, _) => panic!("EvalAltResult::LoopBreak should not occur naturally"),
             Self::Return(_, _) => panic!("EvalAltResult::Return should not occur naturally"),
@@ -399,7 +407,7 @@ impl EvalAltResult {
             Self::ErrorDataTooLarge(t, _) => {
                 map.insert("type".into(), t.into());
             }
-            Self::ErrorTerminated(t, _) => {
+            Self::ErrorTerminated(t, _) | Self::ErrorSuspended(t, _) => {
                 map.insert("token".into(), t.clone());
             }
         };
@@ -431,6 +439,7 @@ impl EvalAltResult {
             | Self::ErrorStackOverflow(pos)
             | Self::ErrorDataTooLarge(_, pos)
             | Self::ErrorTerminated(_, pos)
+            | Self::ErrorSuspended(_, pos)
             | Self::ErrorRuntime(_, pos)
             | Self::LoopBreak(_, pos)
             | Self::Return(_, pos) => *pos,
@@ -471,6 +480,7 @@ impl EvalAltResult {
             | Self::ErrorStackOverflow(pos)
             | Self::ErrorDataTooLarge(_, pos)
             | Self::ErrorTerminated(_, pos)
+            | Self::ErrorSuspended(_, pos)
             | Self::ErrorRuntime(_, pos)
             | Self::LoopBreak(_, pos)
             | Self::Return(_, pos) => *pos = new_position,
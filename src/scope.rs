@@ -1,7 +1,7 @@
 //! Module that defines the [`Scope`] type representing a function call-stack scope.
 
 use crate::dynamic::{AccessMode, Variant};
-use crate::stdlib::{borrow::Cow, boxed::Box, iter, vec::Vec};
+use crate::stdlib::{borrow::Cow, boxed::Box, iter, mem::size_of, vec::Vec};
 use crate::{Dynamic, ImmutableString, StaticVec};
 
 /// Keep a number of entries inline (since [`Dynamic`] is usually small enough).
@@ -157,6 +157,35 @@ impl<'a> Scope<'a> {
     pub fn is_empty(&self) -> bool {
         self.values.len() == 0
     }
+    /// Get the approximate amount of memory, in bytes, used by all the entries inside the [`Scope`].
+    ///
+    /// This is only an estimate. See [`Dynamic::memory_usage`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::Scope;
+    ///
+    /// let mut my_scope = Scope::new();
+    /// assert_eq!(my_scope.memory_usage(), 0);
+    ///
+    /// my_scope.push("x", "hello, world!");
+    /// assert!(my_scope.memory_usage() > 13);
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        (0..self.len())
+            .map(|index| self.memory_usage_by_index(index))
+            .sum()
+    }
+    /// Get the approximate amount of memory, in bytes, used by an entry inside the [`Scope`].
+    #[inline(always)]
+    pub(crate) fn memory_usage_by_index(&self, index: usize) -> usize {
+        let name_size = match self.names[index].0 {
+            Cow::Owned(ref name) => name.len(),
+            Cow::Borrowed(_) => 0,
+        };
+        size_of::<Dynamic>() + name_size + self.values[index].memory_usage()
+    }
     /// Add (push) a new entry to the [`Scope`].
     ///
     /// # Example
@@ -402,6 +431,11 @@ impl<'a> Scope<'a> {
                 AccessMode::ReadOnly => None,
             })
     }
+    /// Get the name of an entry in the [`Scope`] based on the index.
+    #[inline(always)]
+    pub(crate) fn get_name_by_index(&self, index: usize) -> Option<&str> {
+        self.names.get(index).map(|(key, _)| key.as_ref())
+    }
     /// Get a mutable reference to an entry in the [`Scope`] based on the index.
     #[inline(always)]
     pub(crate) fn get_mut_by_index(&mut self, index: usize) -> &mut Dynamic {
@@ -424,6 +458,18 @@ impl<'a> Scope<'a> {
         }
         self
     }
+    /// Convert the [`Scope`] into one that owns all its variable names.
+    #[inline(always)]
+    pub(crate) fn into_owned(self) -> Scope<'static> {
+        Scope {
+            values: self.values,
+            names: self
+                .names
+                .into_iter()
+                .map(|(name, alias)| (Cow::Owned(name.into_owned()), alias))
+                .collect(),
+        }
+    }
     /// Clone the [`Scope`], keeping only the last instances of each variable name.
     /// Shadowed variables are omitted in the copy.
     #[inline(always)]
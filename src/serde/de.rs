@@ -1,6 +1,5 @@
 //! Implement deserialization support of [`Dynamic`][crate::Dynamic] for [`serde`].
 
-use super::str::ImmutableStringDeserializer;
 use crate::dynamic::Union;
 use crate::stdlib::{any::type_name, boxed::Box, fmt, string::ToString};
 use crate::{Dynamic, EvalAltResult, ImmutableString, LexError, Position};
@@ -8,10 +7,17 @@ use serde::de::{DeserializeSeed, Error, IntoDeserializer, MapAccess, SeqAccess,
 use serde::{Deserialize, Deserializer};
 
 #[cfg(not(feature = "no_index"))]
-use crate::Array;
+use crate::{Array, Blob, Set};
+
+#[cfg(feature = "bigint")]
+use crate::stdlib::convert::TryFrom;
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
 
 #[cfg(not(feature = "no_object"))]
-use crate::Map;
+use super::str::ImmutableStringDeserializer;
+#[cfg(not(feature = "no_object"))]
+use crate::{Map, MapKey};
 
 /// Deserializer for [`Dynamic`][crate::Dynamic] which is kept as a reference.
 ///
@@ -29,6 +35,33 @@ impl<'de> DynamicDeserializer<'de> {
     pub fn from_dynamic(value: &'de Dynamic) -> Self {
         Self { value }
     }
+    /// Convert a big integer into a primitive integer type, if it fits.
+    #[cfg(feature = "bigint")]
+    fn bigint_value<T: for<'b> TryFrom<&'b BigInt>>(&self) -> Option<T> {
+        self.value
+            .downcast_ref::<BigInt>()
+            .and_then(|x| T::try_from(x).ok())
+    }
+    /// Deserialize a big integer as the smallest primitive integer type that holds it,
+    /// or as a string if it is too large.
+    #[cfg(feature = "bigint")]
+    fn deserialize_bigint<V: Visitor<'de>>(
+        &mut self,
+        x: &BigInt,
+        visitor: V,
+    ) -> Result<V::Value, Box<EvalAltResult>> {
+        if let Ok(v) = i64::try_from(x) {
+            visitor.visit_i64(v)
+        } else if let Ok(v) = u64::try_from(x) {
+            visitor.visit_u64(v)
+        } else if let Ok(v) = i128::try_from(x) {
+            visitor.visit_i128(v)
+        } else if let Ok(v) = u128::try_from(x) {
+            visitor.visit_u128(v)
+        } else {
+            visitor.visit_str(&x.to_string())
+        }
+    }
     /// Shortcut for a type conversion error.
     fn type_error<T>(&self) -> Result<T, Box<EvalAltResult>> {
         self.type_error_str(type_name::<T>())
@@ -148,8 +181,15 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
             #[cfg(feature = "f32_float")]
             Union::Decimal(_, _) => self.deserialize_f32(visitor),
 
+            #[cfg(feature = "bigint")]
+            Union::BigInt(x, _) => self.deserialize_bigint(x, visitor),
+
             #[cfg(not(feature = "no_index"))]
             Union::Array(_, _) => self.deserialize_seq(visitor),
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(_, _) => self.deserialize_bytes(visitor),
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(_, _) => self.deserialize_seq(visitor),
             #[cfg(not(feature = "no_object"))]
             Union::Map(_, _) => self.deserialize_map(visitor),
             Union::FnPtr(_, _) => self.type_error(),
@@ -179,6 +219,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<i8>() {
+            return visitor.visit_i8(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else {
@@ -189,6 +234,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<i16>() {
+            return visitor.visit_i16(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else {
@@ -199,6 +249,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<i32>() {
+            return visitor.visit_i32(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else if cfg!(feature = "only_i32") {
@@ -211,6 +266,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<i64>() {
+            return visitor.visit_i64(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else if cfg!(not(feature = "only_i32")) {
@@ -223,6 +283,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<i128>() {
+            return visitor.visit_i128(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else if cfg!(not(feature = "only_i32")) {
@@ -235,6 +300,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<u8>() {
+            return visitor.visit_u8(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else {
@@ -245,6 +315,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<u16>() {
+            return visitor.visit_u16(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else {
@@ -255,6 +330,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<u32>() {
+            return visitor.visit_u32(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else {
@@ -265,6 +345,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<u64>() {
+            return visitor.visit_u64(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else {
@@ -275,6 +360,11 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(feature = "bigint")]
+        if let Some(v) = self.bigint_value::<u128>() {
+            return visitor.visit_u128(v);
+        }
+
         if let Ok(v) = self.value.as_int() {
             self.deserialize_int(v, visitor)
         } else {
@@ -352,12 +442,25 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
         self.deserialize_str(visitor)
     }
 
-    fn deserialize_bytes<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Box<EvalAltResult>> {
-        self.type_error()
+    fn deserialize_bytes<V: Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(not(feature = "no_index"))]
+        return self.value.downcast_ref::<Blob>().map_or_else(
+            || self.type_error(),
+            |x| _visitor.visit_borrowed_bytes(x.as_slice()),
+        );
+
+        #[cfg(feature = "no_index")]
+        return self.type_error();
     }
 
-    fn deserialize_byte_buf<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Box<EvalAltResult>> {
-        self.type_error()
+    fn deserialize_byte_buf<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Box<EvalAltResult>> {
+        self.deserialize_bytes(visitor)
     }
 
     fn deserialize_option<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Box<EvalAltResult>> {
@@ -387,6 +490,17 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
     }
 
     fn deserialize_seq<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Box<EvalAltResult>> {
+        #[cfg(not(feature = "no_index"))]
+        if let Some(blob) = self.value.downcast_ref::<Blob>() {
+            return _visitor
+                .visit_seq(serde::de::value::SeqDeserializer::new(blob.iter().copied()));
+        }
+
+        #[cfg(not(feature = "no_index"))]
+        if let Some(set) = self.value.downcast_ref::<Set>() {
+            return _visitor.visit_seq(IterateArray::new(set.iter()));
+        }
+
         #[cfg(not(feature = "no_index"))]
         return self.value.downcast_ref::<Array>().map_or_else(
             || self.type_error(),
@@ -448,9 +562,9 @@ impl<'de> Deserializer<'de> for &mut DynamicDeserializer<'de> {
                 let mut iter = map.iter();
                 let first = iter.next();
                 let second = iter.next();
-                if let (Some((key, value)), None) = (first, second) {
+                if let (Some((MapKey::Str(key), value)), None) = (first, second) {
                     visitor.visit_enum(EnumDeserializer {
-                        tag: &key,
+                        tag: key,
                         content: DynamicDeserializer::from_dynamic(value),
                     })
                 } else {
@@ -510,9 +624,10 @@ impl<'a: 'de, 'de, ITER: Iterator<Item = &'a Dynamic>> SeqAccess<'de> for Iterat
 }
 
 /// `MapAccess` implementation for maps.
+#[cfg(not(feature = "no_object"))]
 struct IterateMap<'a, KEYS, VALUES>
 where
-    KEYS: Iterator<Item = &'a ImmutableString>,
+    KEYS: Iterator<Item = &'a MapKey>,
     VALUES: Iterator<Item = &'a Dynamic>,
 {
     // Iterator for a stream of [`Dynamic`][crate::Dynamic] keys.
@@ -524,7 +639,7 @@ where
 #[cfg(not(feature = "no_object"))]
 impl<'a, KEYS, VALUES> IterateMap<'a, KEYS, VALUES>
 where
-    KEYS: Iterator<Item = &'a ImmutableString>,
+    KEYS: Iterator<Item = &'a MapKey>,
     VALUES: Iterator<Item = &'a Dynamic>,
 {
     pub fn new(keys: KEYS, values: VALUES) -> Self {
@@ -532,9 +647,10 @@ where
     }
 }
 
+#[cfg(not(feature = "no_object"))]
 impl<'a: 'de, 'de, KEYS, VALUES> MapAccess<'de> for IterateMap<'a, KEYS, VALUES>
 where
-    KEYS: Iterator<Item = &'a ImmutableString>,
+    KEYS: Iterator<Item = &'a MapKey>,
     VALUES: Iterator<Item = &'a Dynamic>,
 {
     type Error = Box<EvalAltResult>;
@@ -543,12 +659,15 @@ where
         &mut self,
         seed: K,
     ) -> Result<Option<K::Value>, Box<EvalAltResult>> {
-        // Deserialize each `ImmutableString` key coming out of the keys iterator.
+        // Deserialize each `MapKey` key coming out of the keys iterator.
         match self.keys.next() {
             None => Ok(None),
-            Some(item) => seed
-                .deserialize(&mut ImmutableStringDeserializer::from_str(item))
+            Some(MapKey::Str(s)) => seed
+                .deserialize(&mut ImmutableStringDeserializer::from_str(s))
                 .map(Some),
+            Some(MapKey::Int(n)) => seed.deserialize((*n).into_deserializer()).map(Some),
+            Some(MapKey::Char(ch)) => seed.deserialize((*ch).into_deserializer()).map(Some),
+            Some(MapKey::Bool(b)) => seed.deserialize((*b).into_deserializer()).map(Some),
         }
     }
 
@@ -1,6 +1,7 @@
 //! Implementations of [`serde::Deserialize`].
 
-use crate::stdlib::{fmt, string::ToString};
+use super::ser::make_large_int;
+use crate::stdlib::{convert::TryFrom, fmt, string::ToString};
 use crate::{Dynamic, ImmutableString, INT};
 use serde::de::{Deserialize, Deserializer, Error, MapAccess, SeqAccess, Visitor};
 
@@ -8,7 +9,7 @@ use serde::de::{Deserialize, Deserializer, Error, MapAccess, SeqAccess, Visitor}
 use crate::Array;
 
 #[cfg(not(feature = "no_object"))]
-use crate::Map;
+use crate::{stdlib::format, Map};
 
 struct DynamicVisitor;
 
@@ -34,8 +35,8 @@ impl<'d> Visitor<'d> for DynamicVisitor {
         #[cfg(not(feature = "only_i32"))]
         return Ok(v.into());
         #[cfg(feature = "only_i32")]
-        if v > i32::MAX as i64 {
-            return Ok(Dynamic::from(v));
+        if v > i32::MAX as i64 || v < i32::MIN as i64 {
+            return Ok(make_large_int(v));
         } else {
             return self.visit_i32(v as i32);
         }
@@ -51,7 +52,7 @@ impl<'d> Visitor<'d> for DynamicVisitor {
         return Ok(INT::from(v).into());
         #[cfg(feature = "only_i32")]
         if v > i32::MAX as u32 {
-            return Ok(Dynamic::from(v));
+            return Ok(make_large_int(v));
         } else {
             return self.visit_i32(v as i32);
         }
@@ -59,17 +60,29 @@ impl<'d> Visitor<'d> for DynamicVisitor {
     fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
         #[cfg(not(feature = "only_i32"))]
         if v > i64::MAX as u64 {
-            return Ok(Dynamic::from(v));
+            return Ok(make_large_int(v));
         } else {
             return self.visit_i64(v as i64);
         }
         #[cfg(feature = "only_i32")]
         if v > i32::MAX as u64 {
-            return Ok(Dynamic::from(v));
+            return Ok(make_large_int(v));
         } else {
             return self.visit_i32(v as i32);
         }
     }
+    fn visit_i128<E: Error>(self, v: i128) -> Result<Self::Value, E> {
+        match INT::try_from(v) {
+            Ok(v) => Ok(v.into()),
+            Err(_) => Ok(make_large_int(v)),
+        }
+    }
+    fn visit_u128<E: Error>(self, v: u128) -> Result<Self::Value, E> {
+        match INT::try_from(v) {
+            Ok(v) => Ok(v.into()),
+            Err(_) => Ok(make_large_int(v)),
+        }
+    }
 
     #[cfg(not(feature = "no_float"))]
     fn visit_f32<E: Error>(self, v: f32) -> Result<Self::Value, E> {
@@ -120,6 +133,15 @@ impl<'d> Visitor<'d> for DynamicVisitor {
         Ok(v.into())
     }
 
+    #[cfg(not(feature = "no_index"))]
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Dynamic::from(v.to_vec()))
+    }
+    #[cfg(not(feature = "no_index"))]
+    fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Dynamic::from(v))
+    }
+
     fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
         Ok(Dynamic::UNIT)
     }
@@ -143,8 +165,14 @@ impl<'d> Visitor<'d> for DynamicVisitor {
     fn visit_map<M: MapAccess<'d>>(self, mut map: M) -> Result<Self::Value, M::Error> {
         let mut m: Map = Default::default();
 
-        while let Some((k, v)) = map.next_entry()? {
-            m.insert(k, v);
+        while let Some((k, v)) = map.next_entry::<Dynamic, Dynamic>()? {
+            let key = k.as_map_key().map_err(|typ| {
+                Error::custom(format!(
+                    "map key must be a string, integer, character or boolean, not {}",
+                    typ
+                ))
+            })?;
+            m.insert(key, v);
         }
 
         Ok(m.into())
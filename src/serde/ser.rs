@@ -31,6 +31,20 @@ impl DynamicSerializer {
     }
 }
 
+/// Convert an integer that is too large for [`INT`][crate::INT] into a [`Dynamic`][crate::Dynamic].
+#[cfg(not(feature = "bigint"))]
+#[inline(always)]
+pub(crate) fn make_large_int<T: crate::dynamic::Variant + Clone>(v: T) -> Dynamic {
+    Dynamic::from(v)
+}
+
+/// Convert an integer that is too large for [`INT`][crate::INT] into a big integer.
+#[cfg(feature = "bigint")]
+#[inline(always)]
+pub(crate) fn make_large_int<T: Into<num_bigint::BigInt>>(v: T) -> Dynamic {
+    v.into().into()
+}
+
 /// Serialize a Rust type that implements [`serde::Serialize`] into a [`Dynamic`][crate::Dynamic].
 ///
 /// # Example
@@ -41,7 +55,7 @@ impl DynamicSerializer {
 /// # #[cfg(not(feature = "no_object"))]
 /// # #[cfg(not(feature = "no_float"))]
 /// # {
-/// use rhai::{Dynamic, Array, Map, INT};
+/// use rhai::{Dynamic, Array, Map, MapKey, INT};
 /// use rhai::serde::to_dynamic;
 /// use serde::Serialize;
 ///
@@ -72,9 +86,9 @@ impl DynamicSerializer {
 /// assert!(value.is::<Map>());
 ///
 /// let map = value.cast::<Map>();
-/// let point = map["d"].read_lock::<Map>().unwrap();
-/// assert_eq!(*point["x"].read_lock::<f64>().unwrap(), 123.456);
-/// assert_eq!(*point["y"].read_lock::<f64>().unwrap(), 999.0);
+/// let point = map[&MapKey::from("d")].read_lock::<Map>().unwrap();
+/// assert_eq!(*point[&MapKey::from("x")].read_lock::<f64>().unwrap(), 123.456);
+/// assert_eq!(*point[&MapKey::from("y")].read_lock::<f64>().unwrap(), 999.0);
 /// # }
 /// # Ok(())
 /// # }
@@ -136,8 +150,8 @@ impl Serializer for &mut DynamicSerializer {
         #[cfg(not(feature = "only_i32"))]
         return Ok(v.into());
         #[cfg(feature = "only_i32")]
-        if v > i32::MAX as i64 {
-            return Ok(Dynamic::from(v));
+        if v > i32::MAX as i64 || v < i32::MIN as i64 {
+            return Ok(make_large_int(v));
         } else {
             return self.serialize_i32(v as i32);
         }
@@ -145,14 +159,14 @@ impl Serializer for &mut DynamicSerializer {
 
     fn serialize_i128(self, v: i128) -> Result<Self::Ok, Box<EvalAltResult>> {
         #[cfg(not(feature = "only_i32"))]
-        if v > i64::MAX as i128 {
-            return Ok(Dynamic::from(v));
+        if v > i64::MAX as i128 || v < i64::MIN as i128 {
+            return Ok(make_large_int(v));
         } else {
             return self.serialize_i64(v as i64);
         }
         #[cfg(feature = "only_i32")]
-        if v > i32::MAX as i128 {
-            return Ok(Dynamic::from(v));
+        if v > i32::MAX as i128 || v < i32::MIN as i128 {
+            return Ok(make_large_int(v));
         } else {
             return self.serialize_i32(v as i32);
         }
@@ -177,7 +191,7 @@ impl Serializer for &mut DynamicSerializer {
         return self.serialize_i64(i64::from(v));
         #[cfg(feature = "only_i32")]
         if v > i32::MAX as u32 {
-            return Ok(Dynamic::from(v));
+            return Ok(make_large_int(v));
         } else {
             return self.serialize_i32(v as i32);
         }
@@ -186,13 +200,13 @@ impl Serializer for &mut DynamicSerializer {
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Box<EvalAltResult>> {
         #[cfg(not(feature = "only_i32"))]
         if v > i64::MAX as u64 {
-            return Ok(Dynamic::from(v));
+            return Ok(make_large_int(v));
         } else {
             return self.serialize_i64(v as i64);
         }
         #[cfg(feature = "only_i32")]
         if v > i32::MAX as u64 {
-            return Ok(Dynamic::from(v));
+            return Ok(make_large_int(v));
         } else {
             return self.serialize_i32(v as i32);
         }
@@ -201,13 +215,13 @@ impl Serializer for &mut DynamicSerializer {
     fn serialize_u128(self, v: u128) -> Result<Self::Ok, Box<EvalAltResult>> {
         #[cfg(not(feature = "only_i32"))]
         if v > i64::MAX as u128 {
-            return Ok(Dynamic::from(v));
+            return Ok(make_large_int(v));
         } else {
             return self.serialize_i64(v as i64);
         }
         #[cfg(feature = "only_i32")]
         if v > i32::MAX as u128 {
-            return Ok(Dynamic::from(v));
+            return Ok(make_large_int(v));
         } else {
             return self.serialize_i32(v as i32);
         }
@@ -540,10 +554,10 @@ impl SerializeMap for DynamicSerializer {
         #[cfg(not(feature = "no_object"))]
         {
             let key = crate::stdlib::mem::take(&mut self._key)
-                .take_immutable_string()
+                .as_map_key()
                 .map_err(|typ| {
                     EvalAltResult::ErrorMismatchDataType(
-                        "string".into(),
+                        "string, integer, character or boolean".into(),
                         typ.into(),
                         Position::NONE,
                     )
@@ -570,8 +584,12 @@ impl SerializeMap for DynamicSerializer {
         #[cfg(not(feature = "no_object"))]
         {
             let _key: Dynamic = _key.serialize(&mut *self)?;
-            let _key = _key.take_immutable_string().map_err(|typ| {
-                EvalAltResult::ErrorMismatchDataType("string".into(), typ.into(), Position::NONE)
+            let _key = _key.as_map_key().map_err(|typ| {
+                EvalAltResult::ErrorMismatchDataType(
+                    "string, integer, character or boolean".into(),
+                    typ.into(),
+                    Position::NONE,
+                )
             })?;
             let _value = _value.serialize(&mut *self)?;
             let map = self._value.downcast_mut::<Map>().unwrap();
@@ -3,6 +3,9 @@
 use crate::dynamic::{Union, Variant};
 use crate::stdlib::string::ToString;
 use crate::{Dynamic, ImmutableString};
+
+#[cfg(not(feature = "no_object"))]
+use crate::MapKey;
 use serde::ser::{Serialize, SerializeMap, Serializer};
 
 impl Serialize for Dynamic {
@@ -48,8 +51,25 @@ impl Serialize for Dynamic {
                 }
             }
 
+            #[cfg(feature = "bigint")]
+            Union::BigInt(x, _) => {
+                use num_traits::ToPrimitive;
+
+                if let Some(v) = x.to_i64() {
+                    ser.serialize_i64(v)
+                } else if let Some(v) = x.to_u64() {
+                    ser.serialize_u64(v)
+                } else {
+                    ser.serialize_str(&x.to_string())
+                }
+            }
+
             #[cfg(not(feature = "no_index"))]
             Union::Array(a, _) => (**a).serialize(ser),
+            #[cfg(not(feature = "no_index"))]
+            Union::Blob(a, _) => ser.serialize_bytes(a),
+            #[cfg(not(feature = "no_index"))]
+            Union::Set(a, _) => ser.collect_seq(a.iter()),
             #[cfg(not(feature = "no_object"))]
             Union::Map(m, _) => {
                 let mut map = ser.serialize_map(Some(m.len()))?;
@@ -79,3 +99,18 @@ impl Serialize for ImmutableString {
         ser.serialize_str(self.as_str())
     }
 }
+
+#[cfg(not(feature = "no_object"))]
+impl Serialize for MapKey {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Str(s) => ser.serialize_str(s.as_str()),
+            Self::Char(c) => ser.serialize_str(&c.to_string()),
+            #[cfg(not(feature = "only_i32"))]
+            Self::Int(x) => ser.serialize_i64(*x),
+            #[cfg(feature = "only_i32")]
+            Self::Int(x) => ser.serialize_i32(*x),
+            Self::Bool(x) => ser.serialize_bool(*x),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Module defining the [`Set`] type.
+#![cfg(not(feature = "no_index"))]
+
+use crate::stdlib::{
+    collections::{hash_set, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
+    iter::Map,
+};
+use crate::utils::get_hasher;
+use crate::{Dynamic, StaticVec};
+
+/// A hashable [`Dynamic`] value held inside a [`Set`].
+///
+/// Two values are equal only if they are of the same type and hold the same data.
+#[derive(Clone)]
+#[repr(transparent)]
+pub struct SetItem(Dynamic);
+
+impl SetItem {
+    /// View a [`Dynamic`] reference as a [`SetItem`] reference, for lookups.
+    #[inline(always)]
+    fn from_ref(value: &Dynamic) -> &Self {
+        // SAFETY: `SetItem` is a transparent wrapper around `Dynamic`.
+        unsafe { &*(value as *const Dynamic as *const Self) }
+    }
+}
+
+impl Hash for SetItem {
+    #[inline(always)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl PartialEq for SetItem {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.is_same_hashable(&other.0)
+    }
+}
+
+impl Eq for SetItem {}
+
+/// Hash set of hashable [`Dynamic`] values.
+///
+/// Only values that are [hashable][Dynamic::is_hashable] can be held in a set.
+/// Values are equal only if they are of the same type and hold the same data,
+/// so `1` and `1.0` are different elements.
+///
+/// Not available under `no_index`.
+#[derive(Clone, Default)]
+pub struct Set(HashSet<SetItem>);
+
+impl Set {
+    /// Create a new, empty [`Set`].
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Number of elements in the [`Set`].
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Is the [`Set`] empty?
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Remove all elements from the [`Set`].
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+    /// Does the [`Set`] contain a value?
+    ///
+    /// Always [`false`] if the value is not hashable.
+    #[inline(always)]
+    pub fn contains(&self, value: &Dynamic) -> bool {
+        value.is_hashable() && self.0.contains(SetItem::from_ref(value))
+    }
+    /// Add a value to the [`Set`].
+    ///
+    /// Returns [`true`] if the value was not already in the set.
+    ///
+    /// # Errors
+    ///
+    /// Returns the name of the value's type if it is not hashable.
+    #[inline]
+    pub fn insert(&mut self, value: Dynamic) -> Result<bool, &'static str> {
+        let value = value.flatten();
+
+        if value.is_hashable() {
+            Ok(self.0.insert(SetItem(value)))
+        } else {
+            Err(value.type_name())
+        }
+    }
+    /// Remove a value from the [`Set`].
+    ///
+    /// Returns [`true`] if the value was in the set.
+    #[inline(always)]
+    pub fn remove(&mut self, value: &Dynamic) -> bool {
+        value.is_hashable() && self.0.remove(SetItem::from_ref(value))
+    }
+    /// Iterate through all the elements of the [`Set`], in no particular order.
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = &Dynamic> {
+        self.0.iter().map(|item| &item.0)
+    }
+    /// Create a new [`Set`] with all the elements in either this set or another.
+    #[inline(always)]
+    pub fn union(&self, other: &Self) -> Self {
+        Self(self.0.union(&other.0).cloned().collect())
+    }
+    /// Create a new [`Set`] with only the elements in both this set and another.
+    #[inline(always)]
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self(self.0.intersection(&other.0).cloned().collect())
+    }
+    /// Create a new [`Set`] with the elements in this set but not in another.
+    #[inline(always)]
+    pub fn difference(&self, other: &Self) -> Self {
+        Self(self.0.difference(&other.0).cloned().collect())
+    }
+}
+
+impl PartialEq for Set {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Set {}
+
+impl Hash for Set {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hash the elements in a fixed order so that equal sets hash the same
+        let mut hashes: StaticVec<u64> = self
+            .0
+            .iter()
+            .map(|item| {
+                let mut hasher = get_hasher();
+                item.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect();
+        hashes.sort_unstable();
+        hashes.hash(state);
+    }
+}
+
+impl fmt::Debug for Set {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl IntoIterator for Set {
+    type Item = Dynamic;
+    type IntoIter = Map<hash_set::IntoIter<SetItem>, fn(SetItem) -> Dynamic>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0
+            .into_iter()
+            .map((|item| item.0) as fn(SetItem) -> Dynamic)
+    }
+}
+
+impl<'a> IntoIterator for &'a Set {
+    type Item = &'a Dynamic;
+    type IntoIter = Map<hash_set::Iter<'a, SetItem>, fn(&'a SetItem) -> &'a Dynamic>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0
+            .iter()
+            .map((|item| &item.0) as fn(&'a SetItem) -> &'a Dynamic)
+    }
+}
@@ -11,14 +11,17 @@ mod inner {
     #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
     pub use core::{i128, u128};
 
-    #[cfg(feature = "sync")]
-    pub use alloc::sync;
+    pub mod sync {
+        pub use alloc::sync::*;
+        pub use core::sync::atomic;
+    }
 
     pub use alloc::{borrow, boxed, format, rc, string, vec};
 
     pub use core_error as error;
 
     pub mod collections {
+        pub use alloc::collections::{BTreeMap, VecDeque};
         pub use hashbrown::{hash_map, hash_set, HashMap, HashSet};
     }
 }
@@ -21,6 +21,9 @@ use crate::ast::FloatWrapper;
 #[cfg(feature = "decimal")]
 use rust_decimal::Decimal;
 
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
 #[cfg(not(feature = "no_function"))]
 use crate::engine::KEYWORD_IS_DEF_FN;
 
@@ -203,6 +206,11 @@ pub enum Token {
     /// Requires the `decimal` feature.
     #[cfg(feature = "decimal")]
     DecimalConstant(Decimal),
+    /// A [`BigInt`] constant, for integer literals too large for `INT`.
+    ///
+    /// Requires the `bigint` feature.
+    #[cfg(feature = "bigint")]
+    BigIntConstant(BigInt),
     /// An identifier.
     Identifier(String),
     /// A character constant.
@@ -393,6 +401,8 @@ impl Token {
             FloatConstant(f) => f.to_string().into(),
             #[cfg(feature = "decimal")]
             DecimalConstant(d) => d.to_string().into(),
+            #[cfg(feature = "bigint")]
+            BigIntConstant(n) => n.to_string().into(),
             StringConstant(_) => "string".into(),
             CharConstant(c) => c.to_string().into(),
             Null => "null".into(),
@@ -1217,18 +1227,34 @@ fn get_next_token_inner(
                 if let Some(radix) = radix_base {
                     let out: String = result.iter().skip(2).filter(|&&c| c != NUM_SEP).collect();
 
+                    let num = INT::from_str_radix(&out, radix).map(Token::IntegerConstant);
+
+                    // Then try a big integer if it is too large
+                    #[cfg(feature = "bigint")]
+                    let num = num.or_else(|err| {
+                        BigInt::parse_bytes(out.as_bytes(), radix)
+                            .map(Token::BigIntConstant)
+                            .ok_or(err)
+                    });
+
                     return Some((
-                        INT::from_str_radix(&out, radix)
-                            .map(Token::IntegerConstant)
-                            .unwrap_or_else(|_| {
-                                Token::LexError(LERR::MalformedNumber(result.into_iter().collect()))
-                            }),
+                        num.unwrap_or_else(|_| {
+                            Token::LexError(LERR::MalformedNumber(result.into_iter().collect()))
+                        }),
                         start_pos,
                     ));
                 } else {
                     let out: String = result.iter().filter(|&&c| c != NUM_SEP).collect();
                     let num = INT::from_str(&out).map(Token::IntegerConstant);
 
+                    // Then try a big integer if it is too large
+                    #[cfg(feature = "bigint")]
+                    let num = num.or_else(|err| {
+                        BigInt::from_str(&out)
+                            .map(Token::BigIntConstant)
+                            .map_err(|_| err)
+                    });
+
                     // If integer parsing is unnecessary, try float instead
                     #[cfg(not(feature = "no_float"))]
                     let num =
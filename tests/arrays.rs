@@ -237,3 +237,220 @@ fn test_arrays_map_reduce() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_closure"))]
+fn test_arrays_callbacks() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("double", |x: INT| x * 2);
+    engine.register_fn("scale", |x: INT, i: INT| x * i);
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let x = [1, 2, 3];
+                let y = x.map(Fn("double"));
+                y[0] + y[1] + y[2]
+            "#
+        )?,
+        12
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let x = [1, 2, 3];
+                let y = x.map(Fn("scale"));
+                y[0] + y[1] + y[2]
+            "#
+        )?,
+        8
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn add(x, y, i) { x + y + i }
+                let x = [1, 2, 3];
+                let y = x.map(Fn("add").curry(10));
+                y[0] + y[1] + y[2]
+            "#
+        )?,
+        39
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let k = 100;
+                let x = [1, 2, 3];
+                x.reduce(|sum, v| if sum.type_of() == "null" { v + k } else { sum + v + k })
+            "#
+        )?,
+        306
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let x = [3, 1, 2];
+                x.sort(|a, b| b - a);
+                x[0] * 100 + x[1] * 10 + x[2]
+            "#
+        )?,
+        321
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let x = [1, 2, 3, 4, 5];
+                let y = x.drain(|v, i| i % 2 == 0);
+                x.len() * 10 + y.len()
+            "#
+        )?,
+        23
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(
+                r#"
+                    let x = [1, 2, 3];
+                    let y = x.map(|v| if v > 2 { throw 42 } else { v });
+                    y.len()
+                "#
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(fn_name, _, _, _) if fn_name == "map"
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_arrays_more() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ints = |a: Array| a.into_iter().map(|v| v.cast::<INT>()).collect::<Vec<_>>();
+
+    assert_eq!(
+        ints(engine.eval::<Array>("let x = [3, 1, 2]; x.sort(); x")?),
+        [1, 2, 3]
+    );
+    assert_eq!(
+        ints(engine.eval::<Array>("let x = [1, 1, 2, 2, 3, 1]; x.dedup(); x")?),
+        [1, 2, 3, 1]
+    );
+    assert_eq!(
+        engine.eval::<INT>("let z = [1, 2, 3].zip([4, 5, 6]); z[2][1]")?,
+        6
+    );
+    assert_eq!(engine.eval::<INT>("[1, 2, 3, 4, 5].chunks(2).len()")?, 3);
+    assert_eq!(
+        ints(engine.eval::<Array>("let w = [1, 2, 3, 4].windows(3); w[1]")?),
+        [2, 3, 4]
+    );
+    assert_eq!(
+        ints(engine.eval::<Array>("[1, [2, 3], [], 4].flatten()")?),
+        [1, 2, 3, 4]
+    );
+    assert_eq!(engine.eval::<INT>("[1, 2, 3].sum()")?, 6);
+    assert_eq!(engine.eval::<INT>("[].sum()")?, 0);
+    assert_eq!(engine.eval::<INT>("[3, 1, 2].min()")?, 1);
+    assert_eq!(engine.eval::<String>(r#"["b", "c", "a"].max()"#)?, "c");
+    assert!(engine.eval::<bool>("[].max() == null")?);
+    assert_eq!(
+        engine.eval::<String>(r#"[1, "a", 'b', true].join("-")"#)?,
+        "1-a-b-true"
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_closure"))]
+fn test_arrays_more_callbacks() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ints = |a: Array| a.into_iter().map(|v| v.cast::<INT>()).collect::<Vec<_>>();
+
+    assert_eq!(
+        ints(engine.eval::<Array>(
+            r"
+                let x = [3, -4, 1, -2];
+                x.sort_by(|v| if v < 0 { -v } else { v });
+                x
+            "
+        )?),
+        [1, -2, 3, -4]
+    );
+    assert_eq!(
+        ints(
+            engine
+                .eval::<Array>("let x = [1, 2, 11, 12, 3]; x.dedup(|a, b| a / 10 == b / 10); x")?
+        ),
+        [1, 11, 3]
+    );
+    assert_eq!(
+        ints(engine.eval::<Array>("[1, 2].flat_map(|v| [v, v * 10])")?),
+        [1, 10, 2, 20]
+    );
+    assert_eq!(
+        ints(engine.eval::<Array>("[1, 2, 3].zip([10, 20], |x, y| x + y)")?),
+        [11, 22]
+    );
+    assert_eq!(engine.eval::<INT>("[1, 2, 3].find(|v| v > 1)")?, 2);
+    assert!(engine.eval::<bool>("[1, 2, 3].find(|v| v > 5) == null")?);
+    assert_eq!(
+        engine.eval::<INT>("[1, 2, 3].find_map(|v, i| if i > 0 { v * 100 })")?,
+        200
+    );
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let total = 0;
+                let add = |v| total += v;
+                [1, 2, 3].for_each(add);
+                total
+            "
+        )?,
+        6
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("[1, 2, 3].find(|v| v.foo())")
+            .expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(f, _, _, _) if f == "find"
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "unchecked"))]
+#[cfg(not(feature = "no_object"))]
+fn test_arrays_more_limits() {
+    let mut engine = Engine::new();
+    engine.set_max_array_size(10);
+
+    assert!(matches!(
+        *engine
+            .eval::<Array>("[1, 2, 3, 4, 5, 6].windows(3)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<Array>("[[1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11]].flatten()")
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+}
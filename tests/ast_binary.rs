@@ -0,0 +1,243 @@
+use rhai::{Engine, EvalAltResult, Module, Scope, AST, INT};
+
+#[test]
+fn test_ast_binary() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let script = r#"
+        let x = 40;
+        let s = "hello";
+        let c = 'x';
+        let total = 0;
+        const LIMIT = 10;
+
+        for i in range(0, LIMIT) {
+            switch i {
+                0 => total += 1,
+                1 => { total += 2; }
+                2 => { total += 2; }
+                _ => { if i % 2 == 0 { continue; } total -= 1; }
+            }
+        }
+
+        let n = 0;
+        do { n += 1; } until n >= 3;
+        while n > 0 { n -= 1; if n == 1 { break; } }
+
+        let caught = 0;
+        try { caught = 42; throw caught; } catch (err) { }
+
+        x + 2 == caught && len(s) == 5 && c == 'x' && total == 1 && n == 1
+    "#;
+
+    let mut ast = engine.compile(script)?;
+    ast.set_source("test");
+
+    let bytes = ast.to_bytes()?;
+    let ast2 = AST::from_bytes(&bytes)?;
+
+    assert_eq!(ast2.source(), Some("test"));
+    assert!(engine.eval_ast::<bool>(&ast)?);
+    assert!(engine.eval_ast::<bool>(&ast2)?);
+
+    // Serializing is stable
+    assert_eq!(ast2.to_bytes()?.len(), bytes.len());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_object"))]
+fn test_ast_binary_values() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            let a = [1, 2, [3, "x"], #{ a: 1, b: [true] }];
+            let m = #{ x: a, y: 42, "z": 'c' };
+            m.x[3].b[0] = false;
+            m.x.len() + m.x[2].len() + m.keys().len()
+        "#,
+    )?;
+
+    let ast = AST::from_bytes(&ast.to_bytes()?)?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 9);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_ast_binary_functions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            /// Add one.
+            fn inc(x) { x + 1 }
+            private fn double(x) { x * 2 }
+            fn calc(x) { let f = Fn("inc"); double(call(f, x)) }
+
+            let add = |x| x + inc(0);
+
+            calc(19) + call(add, 0)
+        "#,
+    )?;
+
+    let ast2 = AST::from_bytes(&ast.to_bytes()?)?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 41);
+    assert_eq!(engine.eval_ast::<INT>(&ast2)?, 41);
+    assert_eq!(ast2.iter_functions().count(), ast.iter_functions().count());
+
+    #[cfg(not(feature = "no_closure"))]
+    #[cfg(not(feature = "no_object"))]
+    {
+        let ast = engine.compile("let y = 41; let f = || y + 1; y = 0; call(f)")?;
+
+        assert_eq!(
+            engine.eval_ast::<INT>(&AST::from_bytes(&ast.to_bytes()?)?)?,
+            1
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+#[cfg(not(feature = "no_function"))]
+fn test_ast_binary_modules() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::{DummyModuleResolver, StaticModuleResolver};
+
+    let mut engine = Engine::new();
+
+    let module_ast = engine.compile(
+        r#"
+            fn answer(x) { x + 40 }
+            private fn hidden() { 0 }
+            const BASE = 40;
+            export BASE;
+        "#,
+    )?;
+    let module = Module::eval_ast_as_new(Scope::new(), &module_ast, &engine)?;
+
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("hello", module);
+    engine.set_module_resolver(resolver);
+
+    let ast = engine.compile_into_self_contained(
+        &mut Scope::new(),
+        r#"
+            import "hello" as h;
+            h::answer(2) + h::BASE - 40
+        "#,
+    )?;
+
+    let bytes = ast.to_bytes()?;
+
+    engine.set_module_resolver(DummyModuleResolver::new());
+
+    assert_eq!(engine.eval_ast::<INT>(&AST::from_bytes(&bytes)?)?, 42);
+
+    // Native Rust functions cannot be serialized
+    let mut module = Module::new();
+    module.set_fn_1("native", |x: INT| Ok(x));
+
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("native", module);
+    engine.set_module_resolver(resolver);
+
+    let ast = engine
+        .compile_into_self_contained(&mut Scope::new(), r#"import "native" as n; n::native(42)"#)?;
+
+    assert!(matches!(
+        *ast.to_bytes().expect_err("should error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_ast_binary_errors() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let bytes = engine.compile("let x = 42; x")?.to_bytes()?;
+
+    assert_eq!(engine.eval_ast::<INT>(&AST::from_bytes(&bytes)?)?, 42);
+
+    // Truncated data
+    assert!(AST::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+    // Trailing data
+    let mut data = bytes.clone();
+    data.push(0);
+    assert!(AST::from_bytes(&data).is_err());
+
+    // Not an AST
+    assert!(AST::from_bytes(b"hello, world!").is_err());
+
+    // Different format version
+    let mut data = bytes.clone();
+    data[4] = data[4].wrapping_add(1);
+    assert!(AST::from_bytes(&data)
+        .expect_err("should error")
+        .to_string()
+        .contains("format version"));
+
+    // Different features
+    let mut data = bytes.clone();
+    data[6] ^= 1;
+    assert!(AST::from_bytes(&data)
+        .expect_err("should error")
+        .to_string()
+        .contains("no_float"));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_index"))]
+#[test]
+fn test_ast_binary_nesting() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Deeply-nested but valid data
+    #[cfg(not(feature = "unchecked"))]
+    engine.set_max_expr_depths(
+        0,
+        #[cfg(not(feature = "no_function"))]
+        0,
+    );
+
+    let script = format!("len({}42{})", "[".repeat(40), "]".repeat(40));
+    let bytes = engine.compile(&script)?.to_bytes()?;
+    assert_eq!(engine.eval_ast::<INT>(&AST::from_bytes(&bytes)?)?, 1);
+
+    // Malformed data with a million nested arrays
+    let empty = engine.compile("")?.to_bytes()?;
+    let bytes = engine.compile("[]")?.to_bytes()?;
+
+    // The number of statements is the first difference, followed by the statement tag
+    let start = empty
+        .iter()
+        .zip(bytes.iter())
+        .position(|(a, b)| a != b)
+        .expect("should differ")
+        + 2;
+
+    let mut data = bytes[..start].to_vec();
+    for _ in 0..1_000_000 {
+        data.extend_from_slice(&[7, 1]);
+    }
+    data.extend_from_slice(&bytes[start..]);
+
+    assert!(AST::from_bytes(&data)
+        .expect_err("should error")
+        .to_string()
+        .contains("nested too deeply"));
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use futures::executor::block_on;
+use rhai::{Engine, EvalAltResult, RegisterAsyncFn, RegisterFn, Scope, INT};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// A future that is pending the first time it is polled.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+#[test]
+fn test_async_fn() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+
+    engine.register_async_fn("fetch", move |x: INT| {
+        counter.fetch_add(1, Ordering::SeqCst);
+
+        async move {
+            YieldOnce(false).await;
+            Ok(x * 10)
+        }
+    });
+
+    assert_eq!(block_on(engine.eval_async::<INT>("fetch(4) + 2"))?, 42);
+    assert_eq!(calls.swap(0, Ordering::SeqCst), 1);
+
+    let mut scope = Scope::new();
+    scope.push("sum", 0 as INT);
+
+    assert_eq!(
+        block_on(engine.eval_with_scope_async::<INT>(
+            &mut scope,
+            r"
+                let x = fetch(1);
+                for i in range(0, 3) { sum += fetch(i) + x; }
+                sum
+            "
+        ))?,
+        60
+    );
+    assert_eq!(calls.swap(0, Ordering::SeqCst), 4);
+    assert_eq!(scope.get_value::<INT>("sum"), Some(60));
+
+    assert!(matches!(
+        *engine.eval::<INT>("fetch(1)").expect_err("should suspend"),
+        EvalAltResult::ErrorSuspended(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_async_fn_side_effects() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    engine.register_async_fn("fetch", move |x: INT| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            YieldOnce(false).await;
+            Ok(x)
+        }
+    });
+
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let counter = ticks.clone();
+    engine.register_fn("tick", move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let prints = Arc::new(AtomicUsize::new(0));
+    let counter = prints.clone();
+    engine.on_print(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    assert_eq!(
+        block_on(engine.eval_async::<INT>(
+            r#"
+                let sum = 0;
+                for i in range(0, 1000) {
+                    tick();
+                    print("before");
+                    sum += fetch(i);
+                    print("after");
+                }
+                sum
+            "#
+        ))?,
+        499500
+    );
+
+    assert_eq!(calls.load(Ordering::SeqCst), 1000);
+    assert_eq!(ticks.load(Ordering::SeqCst), 1000);
+    assert_eq!(prints.load(Ordering::SeqCst), 2000);
+
+    #[cfg(not(feature = "no_object"))]
+    assert!(matches!(
+        *block_on(engine.eval_async::<INT>("let x = 1; x.fetch()")).expect_err("should error"),
+        EvalAltResult::ErrorSuspended(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_async_fn_error() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_async_fn("lookup", |key: String| async move {
+        if key.is_empty() {
+            Err("empty key".into())
+        } else {
+            Ok(key.len() as INT)
+        }
+    });
+
+    assert_eq!(block_on(engine.eval_async::<INT>(r#"lookup("hello")"#))?, 5);
+
+    let err = block_on(engine.eval_async::<INT>(r#"let x = 1; x + lookup("")"#))
+        .expect_err("should error");
+
+    assert!(matches!(*err, EvalAltResult::ErrorRuntime(_, pos) if pos.position() == Some(16)));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_async_call_fn() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+
+    engine.register_async_fn("double", move |x: INT| {
+        counter.fetch_add(1, Ordering::SeqCst);
+
+        async move {
+            YieldOnce(false).await;
+            Ok(x * 2)
+        }
+    });
+
+    let ast = engine.compile(
+        r"
+            fn calc(x, y) {
+                let a = double(x);
+                a + double(y) + helper()
+            }
+            fn helper() { double(0) + 1 }
+        ",
+    )?;
+
+    let mut scope = Scope::new();
+
+    assert_eq!(
+        block_on(engine.call_fn_async::<INT>(&mut scope, &ast, "calc", (10 as INT, 10 as INT)))?,
+        41
+    );
+
+    assert_eq!(scope.len(), 0);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    assert!(matches!(
+        *block_on(engine.call_fn_async::<INT>(&mut scope, &ast, "foo", ()))
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(_, _)
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+#![cfg(feature = "bigint")]
+use num_bigint::BigInt;
+use rhai::{Engine, EvalAltResult, AST, INT};
+use std::str::FromStr;
+
+#[test]
+fn test_bigint() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let big = |s: &str| BigInt::from_str(s).unwrap();
+
+    assert_eq!(
+        engine.eval::<String>("type_of(123456789012345678901234567890)")?,
+        "bigint"
+    );
+    assert_eq!(
+        engine.eval::<BigInt>("0x1_0000_0000_0000_0000_0000")?,
+        big("1208925819614629174706176")
+    );
+    assert_eq!(
+        engine.eval::<BigInt>("123456789012345678901234567890 + 10")?,
+        big("123456789012345678901234567900")
+    );
+    assert_eq!(
+        engine.eval::<BigInt>("10 - 123456789012345678901234567890")?,
+        big("-123456789012345678901234567880")
+    );
+    assert_eq!(
+        engine.eval::<BigInt>("let x = to_bigint(2); x **= 100; x")?,
+        big("1267650600228229401496703205376")
+    );
+    assert_eq!(
+        engine.eval::<BigInt>("to_bigint(1) << 70")?,
+        big("1180591620717411303424")
+    );
+    assert_eq!(
+        engine.eval::<BigInt>("-123456789012345678901234567890 % 7")?,
+        big("-123456789012345678901234567890") % 7
+    );
+    assert!(engine.eval::<bool>("123456789012345678901234567890 > 42")?);
+    assert!(engine.eval::<bool>("to_bigint(42) == 42")?);
+    assert!(engine.eval::<bool>("to_bigint(42) != to_bigint(43)")?);
+
+    assert!(matches!(
+        *engine
+            .eval::<BigInt>("123456789012345678901234567890 / 0")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<BigInt>("to_bigint(2) ** -1")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_bigint_conversions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(engine.eval::<INT>("to_bigint(42).to_int()")?, 42);
+    assert!(matches!(
+        *engine
+            .eval::<INT>("123456789012345678901234567890.to_int()")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _)
+    ));
+    assert_eq!(
+        engine.eval::<BigInt>(r#"parse_bigint("-123456789012345678901234567890")"#)?,
+        BigInt::from_str("-123456789012345678901234567890").unwrap()
+    );
+    assert_eq!(
+        engine.eval::<BigInt>(r#"parse_bigint("ff", 16)"#)?,
+        BigInt::from(255)
+    );
+    assert!(engine.eval::<BigInt>(r#"parse_bigint("hello")"#).is_err());
+    assert_eq!(
+        engine.eval::<String>("123456789012345678901234567890.to_string()")?,
+        "123456789012345678901234567890"
+    );
+    assert_eq!(engine.eval::<String>("to_bigint(255).to_string(16)")?, "ff");
+
+    #[cfg(not(feature = "no_float"))]
+    assert_eq!(engine.eval::<BigInt>("to_bigint(1e3)")?, BigInt::from(1000));
+
+    #[cfg(feature = "decimal")]
+    assert_eq!(
+        engine.eval::<String>("to_bigint(42).to_decimal().to_string()")?,
+        "42"
+    );
+
+    Ok(())
+}
+
+#[cfg(not(feature = "unchecked"))]
+#[test]
+fn test_bigint_limits() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_string_size(100);
+
+    assert!(matches!(
+        *engine
+            .eval::<BigInt>("to_bigint(2) ** 4000000000")
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<BigInt>("let x = to_bigint(1); x <<= 4000000000; x")
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<BigInt>("let x = to_bigint(10) ** 60; x * x")
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert_eq!(
+        engine.eval::<BigInt>("to_bigint(10) ** 99")?,
+        BigInt::from(10).pow(99)
+    );
+    assert_eq!(
+        engine.eval::<BigInt>("to_bigint(0) << 4000000000")?,
+        BigInt::from(0)
+    );
+    assert_eq!(
+        engine.eval::<BigInt>("to_bigint(1) ** 4000000000")?,
+        BigInt::from(1)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_bigint_binary_ast() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile("-123456789012345678901234567890 + 0x1_0000_0000_0000_0000_0000")?;
+    let ast = AST::from_bytes(&ast.to_bytes()?)?;
+
+    assert_eq!(
+        engine.eval_ast::<BigInt>(&ast)?,
+        BigInt::from_str("1208925819614629174706176").unwrap()
+            - BigInt::from_str("123456789012345678901234567890").unwrap()
+    );
+
+    Ok(())
+}
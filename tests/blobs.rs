@@ -0,0 +1,100 @@
+#![cfg(not(feature = "no_index"))]
+use rhai::{Blob, Engine, EvalAltResult, INT};
+
+#[test]
+fn test_blobs() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(engine.eval::<String>("type_of(blob())")?, "blob");
+    assert_eq!(engine.eval::<INT>("let x = blob(10); len(x)")?, 10);
+    assert_eq!(engine.eval::<INT>("let x = blob(10, 0x42); x[3]")?, 0x42);
+    assert_eq!(
+        engine.eval::<INT>("let x = blob(3); x[1] = 0x1ff; x[1]")?,
+        0xff
+    );
+    assert_eq!(
+        engine.eval::<Blob>("let x = blob(); x += 1; x += 2; x + blob(1, 3)")?,
+        vec![1, 2, 3]
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = blob(4, 1); let s = 0; for b in x { s += b; } s")?,
+        4
+    );
+    assert!(engine.eval::<bool>("blob(3, 7) == blob(3, 7)")?);
+    assert!(matches!(
+        *engine
+            .eval::<INT>("let x = blob(3); x[3]")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArrayBounds(3, 3, _)
+    ));
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        assert_eq!(
+            engine.eval::<Blob>(
+                r"
+                    let x = blob();
+                    x.push(1);
+                    x.append(blob(2, 2));
+                    x.insert(0, 9);
+                    x.remove(1);
+                    x
+                "
+            )?,
+            vec![9, 2, 2]
+        );
+        assert_eq!(
+            engine.eval::<Blob>("let x = to_blob(\"hello\"); x.extract(1, 3)")?,
+            b"ell".to_vec()
+        );
+        assert_eq!(
+            engine.eval::<Blob>("let x = to_blob(\"hello\"); let y = x.split(2); y + x")?,
+            b"llohe".to_vec()
+        );
+        assert_eq!(
+            engine.eval::<String>(r#"let x = to_blob("hello"); x.as_string()"#)?,
+            "hello"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_blobs_parse() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        engine
+            .eval::<INT>("let x = blob(4); x.write_le_int(0, 2, 0x1234); x.parse_le_int(0, 2)")?,
+        0x1234
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = blob(4); x.write_be_int(1, 2, 0x1234); x[1]")?,
+        0x12
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = blob(4); x.write_le_int(1, 2, 0x1234); x[1]")?,
+        0x34
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = blob(2, 0xff); x.parse_be_int(0, 2)")?,
+        0xffff
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let x = parse_hex("00a1ff"); x.to_hex()"#)?,
+        "00a1ff"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let x = to_blob("hello"); x.to_base64()"#)?,
+        "aGVsbG8="
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let x = parse_base64("aGVsbG8"); x.as_string()"#)?,
+        "hello"
+    );
+    assert!(engine.eval::<Blob>(r#"parse_hex("0g")"#).is_err());
+
+    Ok(())
+}
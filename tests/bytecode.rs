@@ -0,0 +1,277 @@
+use rhai::{Bytecode, Dynamic, Engine, EvalAltResult, Scope, INT};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Evaluate a script both via the tree-walking evaluator and via bytecode,
+/// checking that the results, the scopes afterwards and the numbers of operations are identical.
+fn check(engine: &mut Engine, script: &str) -> Result<String, Box<EvalAltResult>> {
+    let operations = Arc::new(AtomicU64::new(0));
+    let counter = operations.clone();
+
+    engine.on_progress(move |count| {
+        counter.store(count, Ordering::SeqCst);
+        None
+    });
+
+    let ast = engine.compile(script)?;
+    let bytecode = Bytecode::compile(&ast);
+
+    let mut scope = Scope::new();
+    scope.push("z", 0 as INT);
+
+    let mut bytecode_scope = scope.clone();
+
+    let result = engine
+        .eval_ast_with_scope::<Dynamic>(&mut scope, &ast)
+        .map(|v| v.to_string())
+        .map_err(|err| err.to_string());
+    let ast_operations = operations.swap(0, Ordering::SeqCst);
+
+    let bytecode_result = engine
+        .eval_bytecode_with_scope::<Dynamic>(&mut bytecode_scope, &bytecode)
+        .map(|v| v.to_string())
+        .map_err(|err| err.to_string());
+    let bytecode_operations = operations.swap(0, Ordering::SeqCst);
+
+    assert_eq!(result, bytecode_result, "{}", script);
+    assert_eq!(ast_operations, bytecode_operations, "{}", script);
+
+    let dump = |scope: &Scope| {
+        scope
+            .iter()
+            .map(|(name, _, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(dump(&scope), dump(&bytecode_scope), "{}", script);
+
+    Ok(result.unwrap_or_else(|err| err))
+}
+
+#[test]
+fn test_bytecode() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(check(&mut engine, "")?, "null");
+    assert_eq!(check(&mut engine, "40 + 2")?, "42");
+    assert_eq!(
+        check(&mut engine, "let x = 0; while x < 42 { x += 1; } x")?,
+        "42"
+    );
+    assert_eq!(
+        check(&mut engine, "let x = 42; if x > 0 { x } else { -x }")?,
+        "42"
+    );
+    assert_eq!(check(&mut engine, "let x = -1; if x > 0 { x }")?, "null");
+    assert_eq!(
+        check(&mut engine, "let x = 0; do { x += 2; } while x < 42; x")?,
+        "42"
+    );
+    assert_eq!(
+        check(&mut engine, "let x = 0; do { x += 2; } until x >= 42; x")?,
+        "42"
+    );
+    assert_eq!(
+        check(
+            &mut engine,
+            "let x = 0; loop { x += 1; if x == 42 { break; } } x"
+        )?,
+        "42"
+    );
+    assert_eq!(
+        check(
+            &mut engine,
+            r"
+                let total = 0;
+                let i = 0;
+                while i < 10 {
+                    i += 1;
+                    let j = 0;
+                    { let k = i; z = k; }
+                    while true {
+                        j += 1;
+                        if j > i { break; }
+                        if j % 2 == 0 { continue; }
+                        let t = j;
+                        total += t;
+                    }
+                    for k in range(0, 3) {
+                        if k == 1 { continue; }
+                        total += 1;
+                    }
+                    switch i {
+                        5 => { continue; }
+                        _ => {}
+                    }
+                    total += 100;
+                }
+                total
+            "
+        )?,
+        "1030"
+    );
+    assert_eq!(
+        check(
+            &mut engine,
+            "let x = 0; { let y = 1; x = y; } { let x = 2; z = x; } x"
+        )?,
+        "1"
+    );
+    assert_eq!(
+        check(
+            &mut engine,
+            "let x = 0; while true { let y = 1; x += 1; if x > 5 { return x * y; } } 0"
+        )?,
+        "6"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_bytecode_errors() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(check(&mut engine, "let x = 1; if x { 1 }")?.contains("Data type is incorrect"));
+    assert!(check(
+        &mut engine,
+        "let x = 0; while x < 10 { let y = x; x += 1; if x == 5 { throw y; } }"
+    )?
+    .contains("Runtime error: 4"));
+    assert!(check(
+        &mut engine,
+        "let x = 0; try { while true { x += 1; if x > 3 { throw x; } } } catch (e) { z = e; } x"
+    )?
+    .contains("4"));
+
+    #[cfg(not(feature = "unchecked"))]
+    {
+        engine.set_max_operations(50);
+
+        assert!(check(&mut engine, "let x = 0; loop { x += 1; }")?.contains("Too many operations"));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_bytecode_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        check(
+            &mut engine,
+            r"
+                fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+                let x = 0;
+                let i = 0;
+                while i < 6 { x += fib(i); i += 1; }
+                x
+            "
+        )?,
+        "12"
+    );
+    assert_eq!(
+        check(
+            &mut engine,
+            r#"
+                fn sum(n, acc) { if n == 0 { return acc; } sum(n - 1, acc + n) }
+                fn add(x, y) { x + y }
+                let f = curry(Fn("add"), 40);
+                let x = 2;
+                z = call(f, x);
+                sum(100, 0) + z
+            "#
+        )?,
+        "5092"
+    );
+    assert!(check(
+        &mut engine,
+        r#"
+            fn fail(x) { if x > 2 { throw "oops"; } fail(x + 1) }
+            let r = 0;
+            try { fail(0); } catch { r = 1; }
+            fail(r)
+        "#
+    )?
+    .contains("oops"));
+
+    #[cfg(not(feature = "no_optimize"))]
+    {
+        engine.set_optimization_level(rhai::OptimizationLevel::Full);
+
+        assert_eq!(
+            check(
+                &mut engine,
+                "fn inc(x) { x + 1 } let x = 1; inc(x) + inc(40)"
+            )?,
+            "43"
+        );
+        assert!(check(
+            &mut engine,
+            "fn fail(x) { no_such_fn(x) } try { fail(1) } catch { } fail(2)"
+        )?
+        .contains("in call to function fail"));
+
+        engine.set_optimization_level(rhai::OptimizationLevel::Simple);
+    }
+
+    #[cfg(not(feature = "unchecked"))]
+    {
+        engine.set_max_call_levels(10);
+
+        assert!(check(&mut engine, "fn f(x) { 1 + f(x + 1) } f(0)")?.contains("Stack overflow"));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "unchecked"))]
+#[test]
+fn test_bytecode_call_frames() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_call_levels(100_000);
+
+    // Calls to script-defined functions do not recurse in the virtual machine
+    let bytecode = Bytecode::compile(
+        &engine
+            .compile("fn count(n) { if n == 0 { 0 } else { 1 + count(n - 1) } } count(50000)")?,
+    );
+
+    assert_eq!(engine.eval_bytecode::<INT>(&bytecode)?, 50000);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_module"))]
+#[test]
+fn test_bytecode_modules() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let mut module = rhai::Module::new();
+    module.set_fn_1("answer", |x: INT| Ok(x + 40));
+    module.set_var("value", 2 as INT);
+
+    let mut resolver = rhai::module_resolvers::StaticModuleResolver::new();
+    resolver.insert("hello", module);
+    engine.set_module_resolver(resolver);
+
+    assert_eq!(
+        check(
+            &mut engine,
+            r#"
+                import "hello" as h;
+                let x = 0;
+                if true {
+                    import "hello" as g;
+                    x = g::answer(g::value);
+                }
+                x + h::value
+            "#
+        )?,
+        "44"
+    );
+
+    Ok(())
+}
@@ -8,7 +8,7 @@ use std::mem::take;
 use std::rc::Rc;
 
 #[cfg(not(feature = "no_object"))]
-use rhai::Map;
+use rhai::{Map, MapKey};
 
 #[test]
 fn test_fn_ptr_curry_call() -> Result<(), Box<EvalAltResult>> {
@@ -301,7 +301,7 @@ fn test_closures_shared_obj() -> Result<(), Box<EvalAltResult>> {
 
     // Make closure
     let f = move |p1: TestStruct, p2: TestStruct| -> Result<(), Box<EvalAltResult>> {
-        let action_ptr = res["action"].clone().cast::<FnPtr>();
+        let action_ptr = res[&MapKey::from("action")].clone().cast::<FnPtr>();
         let name = action_ptr.fn_name();
         engine.call_fn(&mut Scope::new(), &ast, name, (p1, p2))
     };
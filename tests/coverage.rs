@@ -0,0 +1,126 @@
+use rhai::{Engine, EvalAltResult, Position, INT};
+
+#[test]
+fn test_coverage_statements() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(engine.coverage().is_none());
+
+    engine.enable_coverage(true);
+
+    let mut ast = engine.compile(
+        r"
+            let x = 42;
+            let y = 0;
+            while x > 40 {
+                x -= 1;
+            }
+            if x < 0 {
+                y = 1;
+            }
+            x
+        ",
+    )?;
+    ast.set_source("test");
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 40);
+
+    let coverage = engine.coverage().unwrap();
+
+    assert_eq!(coverage.iter_sources().collect::<Vec<_>>(), vec!["test"]);
+    assert_eq!(coverage.statement_hits("test", Position::new(2, 13)), Some(1));
+    assert_eq!(coverage.statement_hits("test", Position::new(5, 19)), Some(2));
+    assert_eq!(coverage.statement_hits("test", Position::new(8, 19)), Some(0));
+    assert_eq!(coverage.statement_hits("test", Position::new(1, 1)), None);
+
+    engine.clear_coverage();
+    assert!(engine.coverage().unwrap().is_empty());
+
+    engine.enable_coverage(false);
+    assert!(engine.coverage().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_coverage_branches() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.enable_coverage(true);
+
+    let ast = engine.compile(
+        r#"
+            let total = 0;
+            for x in range(0, 5) {
+                if x % 2 == 0 { total += 1; } else { total += 10; }
+                switch x {
+                    1 => total += 100,
+                    3 => total += 1000,
+                    _ => {}
+                }
+            }
+            if total > 100000 { total = 0; }
+            total
+        "#,
+    )?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 1123);
+
+    let coverage = engine.coverage().unwrap();
+
+    assert_eq!(
+        coverage.branch_hits("", Position::new(4, 17)),
+        Some(&[3, 2][..])
+    );
+    assert_eq!(
+        coverage.branch_hits("", Position::new(5, 17)),
+        Some(&[1, 1, 3][..])
+    );
+    assert_eq!(
+        coverage.branch_hits("", Position::new(11, 13)),
+        Some(&[0, 1][..])
+    );
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_coverage_lcov() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.enable_coverage(true);
+
+    let mut ast = engine.compile(
+        r"
+            fn foo(x) {
+                if x > 0 { x } else { -x }
+            }
+            fn bar() {
+                42
+            }
+            foo(-1)
+        ",
+    )?;
+    ast.set_source("script.rhai");
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 1);
+
+    let lcov = engine.coverage().unwrap().to_lcov();
+
+    assert_eq!(
+        lcov,
+        "TN:\n\
+         SF:script.rhai\n\
+         BRDA:3,0,0,0\n\
+         BRDA:3,0,1,1\n\
+         BRF:2\n\
+         BRH:1\n\
+         DA:3,1\n\
+         DA:6,0\n\
+         DA:8,1\n\
+         LF:3\n\
+         LH:2\n\
+         end_of_record\n"
+    );
+
+    Ok(())
+}
@@ -1,8 +1,8 @@
 #![cfg(not(feature = "unchecked"))]
-use rhai::{Engine, EvalAltResult, ParseErrorType};
+use rhai::{Engine, EvalAltResult, ParseErrorType, Scope};
 
 #[cfg(not(feature = "no_index"))]
-use rhai::Array;
+use rhai::{Array, Blob, Set};
 
 #[cfg(not(feature = "no_object"))]
 use rhai::Map;
@@ -101,6 +101,32 @@ fn test_max_array_size() -> Result<(), Box<EvalAltResult>> {
         EvalAltResult::ErrorDataTooLarge(_, _)
     ));
 
+    assert!(matches!(
+        *engine.eval::<Blob>("blob(100)").expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
+    assert!(matches!(
+        *engine
+            .eval::<Blob>("let x = blob(10); x += 1; x")
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
+    assert!(matches!(
+        *engine
+            .eval::<Set>("set([1,2,3,4,5,6]) + set([7,8,9,10,11])")
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
+    assert!(matches!(
+        *engine
+            .eval::<Set>("let x = set([1,2,3,4,5,6,7,8,9]); insert(x, 10); insert(x, 11); x")
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
     #[cfg(not(feature = "no_object"))]
     assert!(matches!(
         *engine
@@ -273,3 +299,134 @@ fn test_max_map_size() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_max_memory() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_memory(1000);
+
+    assert_eq!(engine.max_memory(), 1000);
+
+    engine.eval::<()>(
+        r#"
+            let x = "hello";
+            let y = "world";
+        "#,
+    )?;
+
+    // Many medium-sized values which are each within the string size limit
+    engine.set_max_string_size(200);
+
+    assert!(matches!(
+        *engine
+            .eval::<()>(
+                r#"
+                    let s = "0123456789";
+                    s.pad(150, '!');
+                    let a = s + "a";
+                    let b = s + "b";
+                    let c = s + "c";
+                    let d = s + "d";
+                    let e = s + "e";
+                    let f = s + "f";
+                    let g = s + "g";
+                "#
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
+    // Temporary values count towards the limit
+    assert!(matches!(
+        *engine
+            .eval::<String>(
+                r#"
+                    let s = "0123456789";
+                    s.pad(150, '!');
+                    s + s + s + s + s + s + s
+                "#
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
+    engine.set_max_memory(0);
+
+    engine.eval::<()>(
+        r#"
+            let s = "0123456789";
+            s.pad(150, '!');
+            let a = s + "a";
+            let b = s + "b";
+            let c = s + "c";
+            let d = s + "d";
+            let e = s + "e";
+            let f = s + "f";
+            let g = s + "g";
+        "#,
+    )?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_function"))]
+fn test_max_memory_function_calls() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_memory(300_000);
+
+    let lib = r#"
+        fn make() {
+            let a = [];
+            for i in range(0, 2000) { push(a, "item number " + i); }
+            a
+        }
+    "#;
+
+    assert_eq!(
+        engine.eval::<rhai::INT>(&format!("{} let a = make(); len(a)", lib))?,
+        2000
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<()>(&format!("{} let a = make(); let b = make();", lib))
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
+    // Variables of the calling function count towards the limit
+    assert!(matches!(
+        *engine
+            .eval::<rhai::INT>(&format!(
+                "{} fn g() {{ let b = make(); len(b) }} let a = make(); g()",
+                lib
+            ))
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_scope_memory_usage() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let mut scope = Scope::new();
+
+    let empty = scope.memory_usage();
+
+    engine.eval_with_scope::<()>(&mut scope, r#"let x = "hello, world!";"#)?;
+
+    let small = scope.memory_usage();
+    assert!(small > empty + 13);
+
+    engine.eval_with_scope::<()>(&mut scope, r#"let y = x.pad(1000, '!');"#)?;
+
+    assert!(scope.memory_usage() > small + 1000);
+
+    Ok(())
+}
@@ -1,5 +1,9 @@
 #![cfg(not(feature = "no_function"))]
-use rhai::{Engine, EvalAltResult, FnNamespace, Module, ParseErrorType, RegisterFn, INT};
+use rhai::{Engine, EvalAltResult, FnNamespace, Module, ParseErrorType, RegisterFn, Scope, INT};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
 #[test]
 fn test_functions() -> Result<(), Box<EvalAltResult>> {
@@ -92,6 +96,165 @@ fn test_functions_namespaces() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+fn test_functions_resolution_cache() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("test", |x: INT| x + 1);
+
+    let mut ast = engine.compile("test(41)")?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Registering functions invalidates the cached resolution
+    engine.register_fn("test", |x: INT| x * 2);
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 82);
+    assert_eq!(engine.eval_ast::<INT>(&ast.clone())?, 82);
+
+    // Changing the functions of the AST invalidates the cached resolution
+    ast.combine(engine.compile("fn test(x) { x - 1 }")?);
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 40);
+
+    ast.clear_functions();
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 82);
+
+    // The cached resolution is specific to the engine
+    let mut engine2 = Engine::new();
+    engine2.register_fn("test", |x: INT| x - 2);
+
+    assert_eq!(engine2.eval_ast::<INT>(&ast)?, 39);
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 82);
+
+    #[cfg(not(feature = "no_module"))]
+    {
+        let mut m = Module::new();
+        let hash = m.set_fn_1("test2", |x: INT| Ok(x + 100));
+        m.update_fn_namespace(hash, FnNamespace::Global);
+
+        let mut resolver = rhai::module_resolvers::StaticModuleResolver::new();
+        resolver.insert("hello", m);
+        engine.set_module_resolver(resolver);
+
+        let ast = engine.compile("let x = test(41); import \"hello\" as h; x + test2(0)")?;
+
+        assert_eq!(engine.eval_ast::<INT>(&ast)?, 182);
+        assert_eq!(engine.eval_ast::<INT>(&ast)?, 182);
+
+        let ast = engine.compile("test2(0)")?;
+
+        assert!(engine.eval_ast::<INT>(&ast).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_functions_memoization() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+
+    // Counting calls is not a side effect that matters here
+    engine.register_pure_fn("count", move |x: INT| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        x
+    });
+
+    let ast = engine.compile(
+        r"
+            fn pure(x) { let y = count(x); y * 2 }
+            fn impure(x) { print(x); pure(x) }
+            fn outer(x) { impure(x) }
+            pure(21) + pure(21) + pure(42)
+        ",
+    )?;
+
+    // Memoization is disabled by default
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 168);
+    assert_eq!(count.swap(0, Ordering::Relaxed), 3);
+
+    engine.set_max_fn_memo_size(10);
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 168);
+    assert_eq!(count.swap(0, Ordering::Relaxed), 2);
+
+    // Results are kept across evaluations
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 168);
+    assert_eq!(count.swap(0, Ordering::Relaxed), 0);
+
+    ast.clear_fn_memo_cache();
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 168);
+    assert_eq!(count.swap(0, Ordering::Relaxed), 2);
+
+    // Functions with side effects are not memoized
+    assert_eq!(
+        engine.call_fn::<INT>(&mut Scope::new(), &ast, "outer", (1 as INT,))?,
+        2
+    );
+    assert_eq!(
+        engine.call_fn::<INT>(&mut Scope::new(), &ast, "outer", (1 as INT,))?,
+        2
+    );
+    assert_eq!(count.swap(0, Ordering::Relaxed), 1);
+
+    // Arguments that cannot be hashed are not memoized
+    let ast = engine.compile("fn pure(x) { count(1) } pure(Fn(\"x\")) + pure(Fn(\"x\"))")?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 2);
+    assert_eq!(count.swap(0, Ordering::Relaxed), 2);
+
+    // The cache is bounded
+    engine.set_max_fn_memo_size(1);
+
+    let ast = engine.compile("fn pure(x) { count(x) } pure(1) + pure(2) + pure(1) + pure(1)")?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 5);
+    assert_eq!(count.swap(0, Ordering::Relaxed), 3);
+
+    // Native functions are not pure unless registered as such
+    engine.set_max_fn_memo_size(100);
+
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let ticker = ticks.clone();
+
+    engine.register_fn("tick", move |x: INT| {
+        x + ticker.fetch_add(1, Ordering::Relaxed) as INT
+    });
+
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine
+            .eval::<rhai::Array>("fn f(x) { tick(x) } [f(0), f(0), f(0)]")?
+            .into_iter()
+            .map(|v| v.as_int().unwrap())
+            .collect::<Vec<_>>(),
+        vec![0, 1, 2]
+    );
+    assert_eq!(
+        engine.eval::<INT>("fn f(x) { tick(x) + 1 } fn g(x) { f(x) } g(0) - g(0)")?,
+        -1
+    );
+
+    #[cfg(not(feature = "no_std"))]
+    {
+        let seconds = Arc::new(AtomicUsize::new(0));
+        engine.set_clock(move || {
+            rhai::DateTime::from_timestamp(seconds.fetch_add(1, Ordering::Relaxed) as i64, 0)
+                .unwrap()
+        });
+
+        assert!(engine.eval::<bool>("fn f(x) { now() } f(0) != f(0)")?);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_function_pointers() -> Result<(), Box<EvalAltResult>> {
     let engine = Engine::new();
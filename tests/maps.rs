@@ -1,6 +1,6 @@
 #![cfg(not(feature = "no_object"))]
 
-use rhai::{Engine, EvalAltResult, Map, ParseErrorType, Scope, INT};
+use rhai::{Engine, EvalAltResult, Map, MapKey, ParseErrorType, Scope, INT};
 
 #[test]
 fn test_map_indexing() -> Result<(), Box<EvalAltResult>> {
@@ -88,15 +88,113 @@ fn test_map_indexing() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_map_keys() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(r#"let x = #{1: "a", -2: "b", 'c': "c", true: "d"}; x[-2]"#)?,
+        "b"
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"let x = #{1: 1, "1": 2}; x[1] * 10 + x["1"]"#)?,
+        12
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = #{}; x['a'] = 1; x[true] = 2; x['a'] + x[true]")?,
+        3
+    );
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let counts = #{};
+
+                for n in [1, 2, 2, 3, 3, 3] {
+                    if n in counts { counts[n] += 1; } else { counts[n] = 1; }
+                }
+
+                counts[3] * 10 + counts.len()
+            "
+        )?,
+        33
+    );
+    assert!(engine.eval::<bool>("let x = #{42: 1}; x.has(42) && !x.has('z')")?);
+    assert_eq!(
+        engine.eval::<INT>("let x = #{42: 1, false: 2}; x.remove(false) + x.len()")?,
+        3
+    );
+    assert_eq!(engine.eval::<INT>("keys(#{42: 1})[0]")?, 42);
+    assert_eq!(
+        engine.eval::<String>("#{42: 'x'}.to_string()")?,
+        "#{42: 'x'}"
+    );
+
+    assert!(matches!(
+        *engine.compile("#{1: 1, 1: 2}").expect_err("should error").0,
+        ParseErrorType::DuplicatedProperty(p) if p == "1"
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<()>("let x = #{a: 1}; let k = [1]; x[k]")
+            .expect_err("should error"),
+        EvalAltResult::ErrorMismatchDataType(_, t, _) if t == "array"
+    ));
+
+    let map = engine.eval::<Map>(r#"#{1: "a", 'b': true}"#)?;
+    assert_eq!(map[&MapKey::Int(1)].clone().cast::<String>(), "a");
+    assert!(map[&MapKey::Char('b')].as_bool().unwrap());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "ordered_map")]
+fn test_map_ordered() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                let x = #{z: 1, a: 2, m: 3, "b": 4};
+                x.q = 5;
+                x.remove("a");
+                x.z = 0;
+                x.to_string()
+            "#
+        )?,
+        r#"#{"z": 0, "m": 3, "b": 4, "q": 5}"#
+    );
+
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                let x = #{c: 1, 2: 2, 'a': 3} + #{b: 4, c: 5};
+                let s = "";
+                for k in keys(x) { s += k; }
+                s
+            "#
+        )?,
+        "c2ab"
+    );
+
+    let map = engine.parse_json(r#"{"z": 1, "y": 2, "x": 3, "a": 4}"#, false)?;
+    let keys: Vec<_> = map.keys().map(|k| k.to_string()).collect();
+    assert_eq!(keys, ["z", "y", "x", "a"]);
+
+    Ok(())
+}
+
 #[test]
 fn test_map_assign() -> Result<(), Box<EvalAltResult>> {
     let engine = Engine::new();
 
     let x = engine.eval::<Map>(r#"let x = #{a: 1, b: true, "c$": "hello"}; x"#)?;
 
-    assert_eq!(x["a"].clone().cast::<INT>(), 1);
-    assert_eq!(x["b"].clone().cast::<bool>(), true);
-    assert_eq!(x["c$"].clone().cast::<String>(), "hello");
+    assert_eq!(x[&MapKey::from("a")].clone().cast::<INT>(), 1);
+    assert_eq!(x[&MapKey::from("b")].clone().cast::<bool>(), true);
+    assert_eq!(x[&MapKey::from("c$")].clone().cast::<String>(), "hello");
 
     Ok(())
 }
@@ -107,9 +205,9 @@ fn test_map_return() -> Result<(), Box<EvalAltResult>> {
 
     let x = engine.eval::<Map>(r#"#{a: 1, b: true, "c$": "hello"}"#)?;
 
-    assert_eq!(x["a"].clone().cast::<INT>(), 1);
-    assert_eq!(x["b"].clone().cast::<bool>(), true);
-    assert_eq!(x["c$"].clone().cast::<String>(), "hello");
+    assert_eq!(x[&MapKey::from("a")].clone().cast::<INT>(), 1);
+    assert_eq!(x[&MapKey::from("b")].clone().cast::<bool>(), true);
+    assert_eq!(x[&MapKey::from("c$")].clone().cast::<String>(), "hello");
 
     Ok(())
 }
@@ -150,13 +248,16 @@ fn test_map_json() -> Result<(), Box<EvalAltResult>> {
 
     let map = engine.parse_json(json, true)?;
 
-    assert!(!map.contains_key("x"));
+    assert!(!map.contains_key(&MapKey::from("x")));
 
-    assert_eq!(map["a"].clone().cast::<INT>(), 1);
-    assert_eq!(map["b"].clone().cast::<bool>(), true);
-    assert_eq!(map["c"].clone().cast::<INT>(), 42);
-    assert_eq!(map["$d e f!"].clone().cast::<String>(), "hello");
-    assert_eq!(map["z"].clone().cast::<()>(), ());
+    assert_eq!(map[&MapKey::from("a")].clone().cast::<INT>(), 1);
+    assert_eq!(map[&MapKey::from("b")].clone().cast::<bool>(), true);
+    assert_eq!(map[&MapKey::from("c")].clone().cast::<INT>(), 42);
+    assert_eq!(
+        map[&MapKey::from("$d e f!")].clone().cast::<String>(),
+        "hello"
+    );
+    assert_eq!(map[&MapKey::from("z")].clone().cast::<()>(), ());
 
     #[cfg(not(feature = "no_index"))]
     {
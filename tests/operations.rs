@@ -134,3 +134,164 @@ fn test_max_operations_progress() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[cfg(not(feature = "no_index"))]
+#[test]
+fn test_max_operations_arrays() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(500);
+
+    assert_eq!(
+        engine.eval::<INT>("let a = []; pad(a, 100, 1); sum(a)")?,
+        100
+    );
+
+    for script in &[
+        "let a = []; pad(a, 1000, 1); sum(a)",
+        "let a = []; pad(a, 1000, 1); max(a)",
+        "let a = []; pad(a, 1000, 1); join(a, \",\")",
+        "let a = []; pad(a, 1000, 1); len(windows(a, 2))",
+        "let a = []; pad(a, 1000, 1); dedup(a)",
+        #[cfg(not(feature = "no_function"))]
+        "let a = []; pad(a, 1000, 1); sort(a, |x, y| x - y)",
+    ] {
+        assert!(
+            matches!(
+                *engine.eval::<()>(script).expect_err("should error"),
+                EvalAltResult::ErrorTooManyOperations(_)
+            ),
+            "{}",
+            script
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_std"))]
+#[test]
+fn test_max_execution_time() -> Result<(), Box<EvalAltResult>> {
+    use rhai::TerminationReason;
+    use std::time::Duration;
+
+    let mut engine = Engine::new();
+    engine.set_max_execution_time(Duration::from_millis(50));
+
+    assert_eq!(engine.max_execution_time(), Duration::from_millis(50));
+
+    engine.eval::<()>("let x = 0; while x < 20 { x += 1; }")?;
+
+    match *engine
+        .eval::<()>("let x = 0; loop { x += 1; }")
+        .expect_err("should error")
+    {
+        EvalAltResult::ErrorTerminated(token, _) => {
+            assert_eq!(
+                token.cast::<TerminationReason>(),
+                TerminationReason::TimedOut
+            )
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_std"))]
+#[test]
+fn test_termination_handle() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{RegisterFn, TerminationReason};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    let mut engine = Engine::new();
+    let handle = engine.termination_handle();
+
+    let started = Arc::new(AtomicBool::new(false));
+    let flag = started.clone();
+    engine.register_fn("start", move || flag.store(true, Ordering::SeqCst));
+
+    // Terminating with no script running has no effect on scripts started afterwards
+    handle.terminate();
+    assert_eq!(engine.eval::<INT>("40 + 2")?, 42);
+
+    let thread = thread::spawn(move || {
+        while !started.load(Ordering::SeqCst) {
+            thread::yield_now();
+        }
+        handle.terminate();
+    });
+
+    match *engine
+        .eval::<()>("let x = 0; start(); loop { x += 1; }")
+        .expect_err("should error")
+    {
+        EvalAltResult::ErrorTerminated(token, _) => {
+            assert_eq!(
+                token.cast::<TerminationReason>(),
+                TerminationReason::Cancelled
+            )
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+
+    thread.join().unwrap();
+
+    assert_eq!(engine.eval::<INT>("40 + 2")?, 42);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_std"))]
+#[cfg(feature = "sync")]
+#[test]
+fn test_termination_handle_concurrent() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{RegisterFn, TerminationReason};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    const NUM_THREADS: usize = 4;
+
+    let mut engine = Engine::new();
+    let handle = engine.termination_handle();
+
+    let started = Arc::new(AtomicUsize::new(0));
+    let counter = started.clone();
+    engine.register_fn("start", move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let engine = Arc::new(engine);
+
+    let threads: Vec<_> = (0..NUM_THREADS)
+        .map(|_| {
+            let engine = engine.clone();
+            thread::spawn(move || engine.eval::<()>("let x = 0; start(); loop { x += 1; }"))
+        })
+        .collect();
+
+    while started.load(Ordering::SeqCst) < NUM_THREADS {
+        thread::yield_now();
+    }
+
+    // A single request terminates all running scripts
+    handle.terminate();
+
+    for thread in threads {
+        match *thread.join().unwrap().expect_err("should error") {
+            EvalAltResult::ErrorTerminated(token, _) => {
+                assert_eq!(
+                    token.cast::<TerminationReason>(),
+                    TerminationReason::Cancelled
+                )
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    assert_eq!(engine.eval::<INT>("40 + 2")?, 42);
+
+    Ok(())
+}
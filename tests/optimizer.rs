@@ -1,6 +1,6 @@
 #![cfg(not(feature = "no_optimize"))]
 
-use rhai::{Engine, EvalAltResult, OptimizationLevel, RegisterFn, INT};
+use rhai::{Dynamic, Engine, EvalAltResult, OptimizationLevel, RegisterFn, INT};
 
 #[test]
 fn test_optimizer_run() -> Result<(), Box<EvalAltResult>> {
@@ -67,6 +67,12 @@ fn test_optimizer_parse() -> Result<(), Box<EvalAltResult>> {
 
     assert!(format!("{:?}", ast).starts_with("AST { source: None, body: [], functions: Module("));
 
+    // A variable shadows a constant of the same name
+    assert_eq!(
+        engine.eval::<INT>("const x = 123; let y = { let x = 5; x }; y")?,
+        5
+    );
+
     engine.set_optimization_level(OptimizationLevel::Full);
 
     let ast = engine.compile("abs(-42)")?;
@@ -76,3 +82,236 @@ fn test_optimizer_parse() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_optimizer_inline_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::Full);
+
+    let script = r"
+        fn add(x, y) { let z = x + y; z * 2 }
+        fn inc(x) { x += 1; x }
+        fn fact(n) { if n <= 1 { 1 } else { n * fact(n - 1) } }
+        let x = 1;
+        let y = 10;
+        let a = 5;
+        add(a, y + 1) + add(y, x) + inc(x) + x + fact(a)
+    ";
+
+    let ast = engine.compile(script)?;
+    let body = format!("{:?}", ast);
+    let body = body.split("functions:").next().unwrap();
+
+    // Small non-recursive function called with arguments that do not refer to
+    // variables named after its preceding parameters - inlined
+    assert!(!body.contains(r#"name: "inc""#));
+    // Argument `x` is named after the preceding parameter `x` - not inlined
+    assert!(body.contains(r#"name: "add""#));
+    // Recursive functions - not inlined
+    assert!(body.contains(r#"name: "fact""#));
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 32 + 22 + 2 + 1 + 120);
+
+    // Method calls - not inlined
+    #[cfg(not(feature = "no_object"))]
+    {
+        let ast = engine.compile("fn get() { this + 1 } let y = 10; y.get()")?;
+        let body = format!("{:?}", ast);
+        let body = body.split("functions:").next().unwrap();
+
+        assert!(body.contains(r#"name: "get""#));
+        assert_eq!(engine.eval_ast::<INT>(&ast)?, 11);
+    }
+
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    assert_eq!(engine.eval::<INT>(script)?, 32 + 22 + 2 + 1 + 120);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_optimizer_inline_functions_results() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let scripts = [
+        r#"fn f(a) { is_def_var("y") } let y = 1; f(0)"#,
+        r#"fn f(a, b) { b } f(0, is_def_var("a"))"#,
+        r#"fn f(a) { is_def_fn("f", 1) } f(0)"#,
+        r#"fn f(a) { throw a; } f("oops")"#,
+        r#"fn f(a) { throw a; } try { f("oops") } catch (err) { err }"#,
+        r#"fn g(a) { throw a; } fn f(a) { g(a) } f("oops")"#,
+        "fn f(a) { no_such_fn(a) } f(1)",
+        "fn g(a) { no_such_fn(a) } fn f(a) { g(a) } f(1)",
+        "fn f(a) { a * 2 } f(f(1) + 1)",
+        #[cfg(not(feature = "unchecked"))]
+        "fn f(a) { a / 0 } f(1)",
+    ];
+
+    for script in &scripts {
+        engine.set_optimization_level(OptimizationLevel::None);
+        let expected = format!("{:?}", engine.eval::<Dynamic>(script));
+
+        engine.set_optimization_level(OptimizationLevel::Full);
+        let actual = format!("{:?}", engine.eval::<Dynamic>(script));
+
+        assert_eq!(actual, expected, "{}", script);
+    }
+
+    // Errors in inlined functions are wrapped as for function calls
+    let ast = engine.compile("fn f(a) { no_such_fn(a) } f(1)")?;
+    assert!(format!("{:?}", ast).contains("InlinedFnCall"));
+
+    assert!(matches!(
+        *engine.eval_ast::<INT>(&ast).expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(fn_name, _, err, _)
+            if fn_name == "f" && matches!(*err, EvalAltResult::ErrorFunctionNotFound(_, _))
+    ));
+
+    #[cfg(not(feature = "unchecked"))]
+    {
+        engine.set_max_call_levels(0);
+
+        assert!(matches!(
+            *engine
+                .eval_ast::<INT>(&engine.compile("fn f(a) { a + 1 } f(1)")?)
+                .expect_err("should error"),
+            EvalAltResult::ErrorStackOverflow(_)
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_object"))]
+#[test]
+fn test_optimizer_hoist_invariants() -> Result<(), Box<EvalAltResult>> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone)]
+    struct Sensor;
+
+    let reads = Arc::new(AtomicUsize::new(0));
+    let counter = reads.clone();
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::Full);
+
+    engine
+        .register_type::<Sensor>()
+        .register_fn("new_sensor", || Sensor)
+        .register_get("value", move |_: &mut Sensor| {
+            counter.fetch_add(1, Ordering::SeqCst) as INT
+        });
+
+    // Loop-invariant expression - hoisted
+    let ast = engine.compile(
+        r"
+            let config = #{ limits: #{ max: 10 } };
+            let sum = 0;
+            let i = 0;
+            while i < 5 { sum += config.limits.max * 2; i += 1; }
+            sum
+        ",
+    )?;
+    assert!(format!("{:?}", ast).contains("$hoisted$"));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 100);
+
+    // Custom getters may have side effects - not hoisted
+    let ast = engine.compile(
+        "let s = new_sensor(); let sum = 0; for i in range(0, 5) { sum += s.value; } sum",
+    )?;
+    assert!(!format!("{:?}", ast).contains("$hoisted$"));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 0 + 1 + 2 + 3 + 4);
+    assert_eq!(reads.load(Ordering::SeqCst), 5);
+
+    // Loops running zero times - hoisted expressions never evaluated
+    assert_eq!(
+        engine.eval::<INT>("let x = 1; let y = 0; let s = 0; while s < 0 { s += x / y; } s")?,
+        0
+    );
+    assert_eq!(
+        engine.eval::<INT>(
+            "let x = 1; let y = 0; let s = 0; for i in range(0, 0) { s += x / y; } s"
+        )?,
+        0
+    );
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "only_i32"))]
+    #[cfg(not(feature = "unchecked"))]
+    assert_eq!(
+        engine.eval::<INT>("let s = 0; for i in [] { s += 9223372036854775807 + 1; } s")?,
+        0
+    );
+    #[cfg(not(feature = "no_function"))]
+    assert_eq!(
+        engine.eval::<INT>("fn f(x, y) { let s = 0; while s < 0 { s += x / y; } s } f(1, 0)")?,
+        0
+    );
+
+    // Native functions not marked pure - not hoisted
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let counter = ticks.clone();
+    engine.register_fn("tick", move |n: INT| {
+        counter.fetch_add(1, Ordering::SeqCst) as INT * n
+    });
+
+    let ast = engine.compile("let n = 1; let s = 0; for i in range(0, 3) { s += tick(n); } s")?;
+    assert!(!format!("{:?}", ast).contains("$hoisted$"));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 0 + 1 + 2);
+
+    // Expressions guarded by conditions within the loop - not hoisted
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"let c = 42; let s = 0; loop { if type_of(c) != "map" { break; } s += c.v; } s"#
+        )?,
+        0
+    );
+
+    // Variables changed or declared within the loop - not invariant
+    assert_eq!(
+        engine.eval::<INT>(
+            "let x = #{ v: 1 }; let s = 0; for i in range(0, 3) { s += x.v * 2; x.v += 1; } s"
+        )?,
+        12
+    );
+    assert_eq!(
+        engine.eval::<INT>(
+            "let x = 5; let s = 0; for i in range(0, 3) { let x = i; s += x * 2; } s"
+        )?,
+        6
+    );
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            "let a = [1]; let i = 0; while i < 3 { a.push(a.len()); i += 1; } a.len()"
+        )?,
+        4
+    );
+
+    // Variables captured by closures may be changed by any function call
+    #[cfg(not(feature = "no_closure"))]
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            "
+                let x = #{ v: 1 };
+                let f = |n| x.v += n;
+                let s = 0;
+                for i in range(0, 3) { s += x.v * 2; [1].map(f); }
+                s
+            "
+        )?,
+        12
+    );
+
+    // Common sub-expressions - evaluated once
+    let ast = engine.compile("let a = #{ x: 2 }; (a.x + 1) * (a.x + 2)")?;
+    assert!(format!("{:?}", ast).contains("$hoisted$"));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 12);
+
+    Ok(())
+}
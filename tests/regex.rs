@@ -0,0 +1,136 @@
+use rhai::{Engine, EvalAltResult, Regex};
+
+#[test]
+fn test_regex() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(engine.eval::<String>(r#"type_of(regex("a+"))"#)?, "regex");
+    assert!(engine.eval::<bool>(r#"matches("hello world", "w.r")"#)?);
+    assert!(!engine.eval::<bool>(r#"matches("hello world", "^world")"#)?);
+    assert!(engine.eval::<bool>(r#"matches("Hello", "(?i)^hello$")"#)?);
+    assert!(engine.eval::<bool>(r#"matches("a1b2", regex("^([a-z]\\d)+$"))"#)?);
+    assert_eq!(
+        engine.eval::<String>(r#"find("abc 123 def 45", "\\d+")"#)?,
+        "123"
+    );
+    assert!(engine.eval::<bool>(r#"find("abc", "\\d") == null"#)?);
+    assert_eq!(engine.eval::<String>(r#"find("aaa", "a+?")"#)?, "a");
+    assert_eq!(
+        engine.eval::<String>(r#"find("naïve café", "\\w+é")"#)?,
+        "café"
+    );
+
+    let mut text = r#"let s = "2021-03-14 and 2022-12-25";"#.to_string();
+    text.push_str(r#" replace(s, regex("(?P<y>\\d+)-(\\d+)-(\\d+)"), "$3/$2/${y}"); s"#);
+    assert_eq!(engine.eval::<String>(&text)?, "14/03/2021 and 25/12/2022");
+    assert_eq!(
+        engine.eval::<String>(r#"let s = "a-b"; replace(s, regex("-"), "$$"); s"#)?,
+        "a$b"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let s = "abc"; replace(s, regex(""), "-"); s"#)?,
+        "-a-b-c-"
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<bool>(r#"matches("abc", "a(b")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _)
+    ));
+    assert!(engine.eval::<bool>(r#"matches("abc", "(a)\\1")"#).is_err());
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_index"))]
+#[test]
+fn test_regex_arrays() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        engine
+            .eval::<rhai::Array>(r#"find_all("a1 b22 c333", "\\d+")"#)?
+            .into_iter()
+            .map(|v| v.cast::<String>())
+            .collect::<Vec<_>>(),
+        ["1", "22", "333"]
+    );
+    assert_eq!(
+        engine
+            .eval::<rhai::Array>(r#"split("one, two;three", regex("[,;]\\s*"))"#)?
+            .into_iter()
+            .map(|v| v.cast::<String>())
+            .collect::<Vec<_>>(),
+        ["one", "two", "three"]
+    );
+    assert_eq!(
+        engine.eval::<rhai::INT>(r#"len(find_all("abc", "x*"))"#)?,
+        4
+    );
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_object"))]
+#[test]
+fn test_regex_captures() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                let m = captures("key = value", "(?P<key>\\w+)\\s*=\\s*(?<value>\\w+)");
+                m.key + ":" + m.value + ":" + m[0]
+            "#
+        )?,
+        "key:value:key = value"
+    );
+    assert!(engine.eval::<bool>(r#"let m = captures("ab", "a(x)?b"); m[1] == null"#)?);
+    assert!(engine.eval::<bool>(r#"captures("ab", "x") == null"#)?);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "unchecked"))]
+#[test]
+fn test_regex_limits() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.set_max_regex_steps(10_000);
+
+    assert!(matches!(
+        *engine
+            .eval::<bool>(r#"matches("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "^(a*)*b")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert!(engine.eval::<bool>(r#"matches("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaab", "^(a*)*b")"#)?);
+
+    engine.set_max_regex_size(50);
+
+    assert!(engine.eval::<bool>(r#"matches("abc", "b")"#)?);
+    assert!(matches!(
+        *engine
+            .eval::<bool>(r#"matches("abc", "a{100}")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
+    // A pattern already in the cache is still checked against the new limit
+    engine.set_max_regex_size(2);
+    assert!(engine.eval::<bool>(r#"matches("abc", "b")"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_regex_native() {
+    let regex = Regex::new(r"^\d{3}-\d{4}$").unwrap();
+
+    assert_eq!(regex.as_str(), r"^\d{3}-\d{4}$");
+    assert!(regex.is_match("555-1234"));
+    assert!(!regex.is_match("5555-1234"));
+    assert!(Regex::new("[a-").is_err());
+}
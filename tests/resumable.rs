@@ -0,0 +1,225 @@
+use rhai::{
+    Continuation, Dynamic, Engine, EvalAltResult, Execution, Position, RegisterFn,
+    RegisterResultFn, Scope, INT,
+};
+use std::sync::{Arc, RwLock};
+
+fn make_engine() -> Engine {
+    let mut engine = Engine::new();
+
+    engine.register_result_fn(
+        "wait_for",
+        |event: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+            EvalAltResult::ErrorSuspended(event.into(), Position::NONE).into()
+        },
+    );
+
+    engine
+}
+
+fn expect_suspended(execution: Execution) -> Continuation {
+    match execution {
+        Execution::Suspended(continuation) => *continuation,
+        Execution::Completed(result) => panic!("should suspend, but completed with {}", result),
+    }
+}
+
+fn expect_completed(execution: Execution) -> Dynamic {
+    match execution {
+        Execution::Completed(result) => result,
+        Execution::Suspended(continuation) => panic!(
+            "should complete, but suspended with {}",
+            continuation.token()
+        ),
+    }
+}
+
+#[test]
+fn test_resumable() -> Result<(), Box<EvalAltResult>> {
+    let engine = make_engine();
+
+    let ast = engine.compile(
+        r#"
+            let x = 40;
+            x += wait_for("door_open");
+            let y = wait_for("lever_pulled") * 2;
+            x + y
+        "#,
+    )?;
+
+    let mut scope = Scope::new();
+
+    let continuation = expect_suspended(engine.eval_ast_resumable(&mut scope, &ast)?);
+    assert_eq!(continuation.token().clone().cast::<String>(), "door_open");
+    assert_eq!(continuation.position(), Position::new(3, 18));
+    assert_eq!(scope.get_value::<INT>("x"), Some(40));
+
+    let continuation =
+        expect_suspended(engine.resume(&mut scope, continuation, Dynamic::from(2 as INT))?);
+    assert_eq!(
+        continuation.token().clone().cast::<String>(),
+        "lever_pulled"
+    );
+    assert_eq!(scope.get_value::<INT>("x"), Some(42));
+
+    let result =
+        expect_completed(engine.resume(&mut scope, continuation, Dynamic::from(21 as INT))?);
+    assert_eq!(result.cast::<INT>(), 84);
+    assert_eq!(scope.get_value::<INT>("y"), Some(42));
+
+    assert!(matches!(
+        *engine.eval_ast::<INT>(&ast).expect_err("should suspend"),
+        EvalAltResult::ErrorSuspended(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_resumable_side_effects() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine();
+
+    let logbook = Arc::new(RwLock::new(Vec::<String>::new()));
+    let log = logbook.clone();
+    engine.on_print(move |s| log.write().unwrap().push(s.to_string()));
+
+    let ticks = Arc::new(RwLock::new(0 as INT));
+    let counter = ticks.clone();
+    engine.register_fn("tick", move || {
+        *counter.write().unwrap() += 1;
+        *counter.read().unwrap()
+    });
+
+    let ast = engine.compile(
+        r#"
+            let x = 0;
+            {
+                x += tick();
+                print("start");
+                try {
+                    x += wait_for("a") + tick() * 1000 + wait_for("b");
+                    print("resumed");
+                } catch {
+                    x = -1;
+                }
+            }
+            x
+        "#,
+    )?;
+
+    let mut scope = Scope::new();
+
+    let continuation = expect_suspended(engine.eval_ast_resumable(&mut scope, &ast)?);
+    assert_eq!(continuation.token().clone().cast::<String>(), "a");
+
+    let continuation =
+        expect_suspended(engine.resume(&mut scope, continuation, Dynamic::from(10 as INT))?);
+    assert_eq!(continuation.token().clone().cast::<String>(), "b");
+
+    let result =
+        expect_completed(engine.resume(&mut scope, continuation, Dynamic::from(100 as INT))?);
+    assert_eq!(result.cast::<INT>(), 2111);
+
+    assert_eq!(*ticks.read().unwrap(), 2);
+    assert_eq!(*logbook.read().unwrap(), vec!["start", "resumed"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_resumable_loop() -> Result<(), Box<EvalAltResult>> {
+    let engine = make_engine();
+
+    let ast = engine.compile(
+        r#"
+            let total = 0;
+            loop {
+                let value = wait_for("next");
+                if value == 0 { break; }
+                total += value;
+            }
+            total
+        "#,
+    )?;
+
+    let mut scope = Scope::new();
+
+    let mut execution = engine.eval_ast_resumable(&mut scope, &ast)?;
+
+    for n in (0..=10000).rev() {
+        let continuation = expect_suspended(execution);
+        assert_eq!(continuation.token().clone().cast::<String>(), "next");
+        execution = engine.resume(&mut scope, continuation, Dynamic::from(n as INT))?;
+    }
+
+    assert_eq!(expect_completed(execution).cast::<INT>(), 50005000);
+
+    Ok(())
+}
+
+#[test]
+fn test_resumable_not_resumable() -> Result<(), Box<EvalAltResult>> {
+    let engine = make_engine();
+
+    let mut scope = Scope::new();
+
+    // Evaluation cannot be resumed from within `eval`
+    let ast = engine.compile(r#"eval("wait_for(\"a\")")"#)?;
+
+    assert!(matches!(
+        *engine
+            .eval_ast_resumable(&mut scope, &ast)
+            .expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(fn_name, _, err, _)
+            if fn_name == "eval" && matches!(*err, EvalAltResult::ErrorSuspended(_, _))
+    ));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_resumable_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine();
+
+    let ast = engine.compile(
+        r#"
+            fn countdown(n) {
+                let m = n * 2;
+                if n == 0 { wait_for("done") } else { countdown(n - 1) + m }
+            }
+            countdown(5)
+        "#,
+    )?;
+
+    let mut scope = Scope::new();
+
+    let continuation = expect_suspended(engine.eval_ast_resumable(&mut scope, &ast)?);
+    assert_eq!(continuation.token().clone().cast::<String>(), "done");
+
+    let result =
+        expect_completed(engine.resume(&mut scope, continuation, Dynamic::from(100 as INT))?);
+    assert_eq!(result.cast::<INT>(), 130);
+
+    #[cfg(not(feature = "unchecked"))]
+    {
+        engine.set_max_call_levels(3);
+
+        assert!(matches!(
+            *engine
+                .eval_ast_resumable(&mut scope, &ast)
+                .expect_err("should error"),
+            EvalAltResult::ErrorStackOverflow(_)
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_resumable_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Continuation>();
+}
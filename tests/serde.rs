@@ -6,17 +6,22 @@ use rhai::{
 };
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
 #[cfg(not(feature = "no_index"))]
-use rhai::Array;
+use rhai::{Array, Blob};
 #[cfg(not(feature = "no_object"))]
-use rhai::Map;
+use rhai::{Map, MapKey};
 #[cfg(feature = "decimal")]
 use rust_decimal::Decimal;
 
 #[test]
 fn test_serde_ser_primary_types() -> Result<(), Box<EvalAltResult>> {
     assert!(to_dynamic(42_u64)?.is::<INT>());
+    #[cfg(not(feature = "bigint"))]
     assert!(to_dynamic(u64::MAX)?.is::<u64>());
+    #[cfg(feature = "bigint")]
+    assert!(to_dynamic(u64::MAX)?.is::<BigInt>());
     assert!(to_dynamic(42 as INT)?.is::<INT>());
     assert!(to_dynamic(true)?.is::<bool>());
     assert!(to_dynamic(())?.is::<()>());
@@ -65,6 +70,30 @@ fn test_serde_ser_array() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_serde_blob() -> Result<(), Box<EvalAltResult>> {
+    struct Bytes(&'static [u8]);
+
+    impl Serialize for Bytes {
+        fn serialize<S: serde::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+            ser.serialize_bytes(self.0)
+        }
+    }
+
+    let engine = Engine::new();
+
+    let r = engine.eval::<Dynamic>("let x = blob(3, 42); x")?;
+    let data: Vec<u8> = from_dynamic(&r)?;
+    assert_eq!(data, vec![42, 42, 42]);
+
+    let d = to_dynamic(Bytes(&[1, 2, 3]))?;
+    assert!(d.is::<Blob>());
+    assert_eq!(vec![1, 2, 3], d.cast::<Blob>());
+
+    Ok(())
+}
+
 #[test]
 #[cfg(not(feature = "no_index"))]
 #[cfg(not(feature = "no_object"))]
@@ -93,12 +122,12 @@ fn test_serde_ser_struct() -> Result<(), Box<EvalAltResult>> {
     assert!(d.is::<Map>());
 
     let mut map = d.cast::<Map>();
-    let obj = map.remove("obj").unwrap().cast::<Map>();
-    let mut seq = map.remove("seq").unwrap().cast::<Array>();
+    let obj = map.remove(&MapKey::from("obj")).unwrap().cast::<Map>();
+    let mut seq = map.remove(&MapKey::from("seq")).unwrap().cast::<Array>();
 
-    assert_eq!(Ok(123), obj["a"].as_int());
-    assert!(obj["b"].as_bool().unwrap());
-    assert_eq!(Ok(42), map["int"].as_int());
+    assert_eq!(Ok(123), obj[&MapKey::from("a")].as_int());
+    assert!(obj[&MapKey::from("b")].as_bool().unwrap());
+    assert_eq!(Ok(42), map[&MapKey::from("int")].as_int());
     assert_eq!(seq.len(), 3);
     assert_eq!("kitty", seq.remove(1).take_string().unwrap());
 
@@ -152,20 +181,26 @@ fn test_serde_ser_externally_tagged_enum() -> Result<(), Box<EvalAltResult>> {
     #[cfg(not(feature = "no_index"))]
     {
         let mut map = to_dynamic(MyEnum::VariantUnitTuple())?.cast::<Map>();
-        let content = map.remove("VariantUnitTuple").unwrap().cast::<Array>();
+        let content = map
+            .remove(&MapKey::from("VariantUnitTuple"))
+            .unwrap()
+            .cast::<Array>();
         assert!(map.is_empty());
         assert!(content.is_empty());
     }
 
     let mut map = to_dynamic(MyEnum::VariantNewtype(123))?.cast::<Map>();
-    let content = map.remove("VariantNewtype").unwrap();
+    let content = map.remove(&MapKey::from("VariantNewtype")).unwrap();
     assert!(map.is_empty());
     assert_eq!(Ok(123), content.as_int());
 
     #[cfg(not(feature = "no_index"))]
     {
         let mut map = to_dynamic(MyEnum::VariantTuple(123, 456))?.cast::<Map>();
-        let content = map.remove("VariantTuple").unwrap().cast::<Array>();
+        let content = map
+            .remove(&MapKey::from("VariantTuple"))
+            .unwrap()
+            .cast::<Array>();
         assert!(map.is_empty());
         assert_eq!(2, content.len());
         assert_eq!(Ok(123), content[0].as_int());
@@ -173,14 +208,23 @@ fn test_serde_ser_externally_tagged_enum() -> Result<(), Box<EvalAltResult>> {
     }
 
     let mut map = to_dynamic(MyEnum::VariantEmptyStruct {})?.cast::<Map>();
-    let map_inner = map.remove("VariantEmptyStruct").unwrap().cast::<Map>();
+    let map_inner = map
+        .remove(&MapKey::from("VariantEmptyStruct"))
+        .unwrap()
+        .cast::<Map>();
     assert!(map.is_empty());
     assert!(map_inner.is_empty());
 
     let mut map = to_dynamic(MyEnum::VariantStruct { a: 123 })?.cast::<Map>();
-    let mut map_inner = map.remove("VariantStruct").unwrap().cast::<Map>();
+    let mut map_inner = map
+        .remove(&MapKey::from("VariantStruct"))
+        .unwrap()
+        .cast::<Map>();
     assert!(map.is_empty());
-    assert_eq!(Ok(123), map_inner.remove("a").unwrap().as_int());
+    assert_eq!(
+        Ok(123),
+        map_inner.remove(&MapKey::from("a")).unwrap().as_int()
+    );
     assert!(map_inner.is_empty());
 
     Ok(())
@@ -199,7 +243,7 @@ fn test_serde_ser_internally_tagged_enum() -> Result<(), Box<EvalAltResult>> {
     let mut map = to_dynamic(MyEnum::VariantEmptyStruct {})?.cast::<Map>();
     assert_eq!(
         "VariantEmptyStruct",
-        map.remove("tag")
+        map.remove(&MapKey::from("tag"))
             .unwrap()
             .take_immutable_string()
             .unwrap()
@@ -210,13 +254,13 @@ fn test_serde_ser_internally_tagged_enum() -> Result<(), Box<EvalAltResult>> {
     let mut map = to_dynamic(MyEnum::VariantStruct { a: 123 })?.cast::<Map>();
     assert_eq!(
         "VariantStruct",
-        map.remove("tag")
+        map.remove(&MapKey::from("tag"))
             .unwrap()
             .take_immutable_string()
             .unwrap()
             .as_str()
     );
-    assert_eq!(Ok(123), map.remove("a").unwrap().as_int());
+    assert_eq!(Ok(123), map.remove(&MapKey::from("a")).unwrap().as_int());
     assert!(map.is_empty());
 
     Ok(())
@@ -243,7 +287,7 @@ fn test_serde_ser_adjacently_tagged_enum() -> Result<(), Box<EvalAltResult>> {
     let mut map = to_dynamic(MyEnum::VariantUnit)?.cast::<Map>();
     assert_eq!(
         "VariantUnit",
-        map.remove("tag")
+        map.remove(&MapKey::from("tag"))
             .unwrap()
             .take_immutable_string()
             .unwrap()
@@ -256,13 +300,16 @@ fn test_serde_ser_adjacently_tagged_enum() -> Result<(), Box<EvalAltResult>> {
         let mut map = to_dynamic(MyEnum::VariantUnitTuple())?.cast::<Map>();
         assert_eq!(
             "VariantUnitTuple",
-            map.remove("tag")
+            map.remove(&MapKey::from("tag"))
                 .unwrap()
                 .take_immutable_string()
                 .unwrap()
                 .as_str()
         );
-        let content = map.remove("content").unwrap().cast::<Array>();
+        let content = map
+            .remove(&MapKey::from("content"))
+            .unwrap()
+            .cast::<Array>();
         assert!(map.is_empty());
         assert!(content.is_empty());
     }
@@ -270,13 +317,13 @@ fn test_serde_ser_adjacently_tagged_enum() -> Result<(), Box<EvalAltResult>> {
     let mut map = to_dynamic(MyEnum::VariantNewtype(123))?.cast::<Map>();
     assert_eq!(
         "VariantNewtype",
-        map.remove("tag")
+        map.remove(&MapKey::from("tag"))
             .unwrap()
             .take_immutable_string()
             .unwrap()
             .as_str()
     );
-    let content = map.remove("content").unwrap();
+    let content = map.remove(&MapKey::from("content")).unwrap();
     assert!(map.is_empty());
     assert_eq!(Ok(123), content.as_int());
 
@@ -285,13 +332,16 @@ fn test_serde_ser_adjacently_tagged_enum() -> Result<(), Box<EvalAltResult>> {
         let mut map = to_dynamic(MyEnum::VariantTuple(123, 456))?.cast::<Map>();
         assert_eq!(
             "VariantTuple",
-            map.remove("tag")
+            map.remove(&MapKey::from("tag"))
                 .unwrap()
                 .take_immutable_string()
                 .unwrap()
                 .as_str()
         );
-        let content = map.remove("content").unwrap().cast::<Array>();
+        let content = map
+            .remove(&MapKey::from("content"))
+            .unwrap()
+            .cast::<Array>();
         assert!(map.is_empty());
         assert_eq!(2, content.len());
         assert_eq!(Ok(123), content[0].as_int());
@@ -301,24 +351,30 @@ fn test_serde_ser_adjacently_tagged_enum() -> Result<(), Box<EvalAltResult>> {
     let mut map = to_dynamic(MyEnum::VariantEmptyStruct {})?.cast::<Map>();
     assert_eq!(
         "VariantEmptyStruct",
-        map.remove("tag")
+        map.remove(&MapKey::from("tag"))
             .unwrap()
             .take_immutable_string()
             .unwrap()
             .as_str()
     );
-    let map_inner = map.remove("content").unwrap().cast::<Map>();
+    let map_inner = map.remove(&MapKey::from("content")).unwrap().cast::<Map>();
     assert!(map.is_empty());
     assert!(map_inner.is_empty());
 
     let mut map = to_dynamic(MyEnum::VariantStruct { a: 123 })?.cast::<Map>();
     assert_eq!(
         "VariantStruct",
-        map.remove("tag").unwrap().take_string().unwrap()
+        map.remove(&MapKey::from("tag"))
+            .unwrap()
+            .take_string()
+            .unwrap()
     );
-    let mut map_inner = map.remove("content").unwrap().cast::<Map>();
+    let mut map_inner = map.remove(&MapKey::from("content")).unwrap().cast::<Map>();
     assert!(map.is_empty());
-    assert_eq!(Ok(123), map_inner.remove("a").unwrap().as_int());
+    assert_eq!(
+        Ok(123),
+        map_inner.remove(&MapKey::from("a")).unwrap().as_int()
+    );
     assert!(map_inner.is_empty());
 
     Ok(())
@@ -339,11 +395,11 @@ fn test_serde_ser_untagged_enum() -> Result<(), Box<EvalAltResult>> {
     assert!(map.is_empty());
 
     let mut map = to_dynamic(MyEnum::VariantStruct1 { a: 123 })?.cast::<Map>();
-    assert_eq!(Ok(123), map.remove("a").unwrap().as_int());
+    assert_eq!(Ok(123), map.remove(&MapKey::from("a")).unwrap().as_int());
     assert!(map.is_empty());
 
     let mut map = to_dynamic(MyEnum::VariantStruct2 { b: 123 })?.cast::<Map>();
-    assert_eq!(Ok(123), map.remove("b").unwrap().as_int());
+    assert_eq!(Ok(123), map.remove(&MapKey::from("b")).unwrap().as_int());
     assert!(map.is_empty());
 
     Ok(())
@@ -379,6 +435,26 @@ fn test_serde_de_primary_types() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+#[cfg(feature = "bigint")]
+fn test_serde_bigint() -> Result<(), Box<EvalAltResult>> {
+    let x = to_dynamic(u128::MAX)?;
+    assert_eq!(x.clone().cast::<BigInt>(), BigInt::from(u128::MAX));
+    assert_eq!(u128::MAX, from_dynamic::<u128>(&x)?);
+    assert!(from_dynamic::<u64>(&x).is_err());
+    assert_eq!(42, from_dynamic::<u8>(&Dynamic::from(BigInt::from(42)))?);
+
+    let engine = Engine::new();
+    let x = engine.eval::<Dynamic>("-123456789012345678901234567890")?;
+    assert_eq!(-123456789012345678901234567890, from_dynamic::<i128>(&x)?);
+    assert_eq!(
+        "-123456789012345678901234567890",
+        from_dynamic::<String>(&to_dynamic(&x)?)?
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_serde_de_integer_types() -> Result<(), Box<EvalAltResult>> {
     assert_eq!(42, from_dynamic::<i8>(&Dynamic::from(42 as INT))?);
@@ -440,6 +516,82 @@ fn test_serde_de_struct() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_serde_map_keys() -> Result<(), Box<EvalAltResult>> {
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Deserialize)]
+    struct Hello {
+        a: INT,
+    }
+
+    let engine = Engine::new();
+
+    let mut data: BTreeMap<INT, String> = BTreeMap::new();
+    data.insert(42, "hello".into());
+
+    let d = to_dynamic(&data)?;
+    let map = d.read_lock::<Map>().unwrap();
+    assert_eq!(map[&MapKey::Int(42)].clone().cast::<String>(), "hello");
+
+    let result = engine.eval::<Dynamic>(r#"#{1: "a", 2: "b"}"#)?;
+    let data: BTreeMap<INT, String> = from_dynamic(&result)?;
+    assert_eq!(data[&2], "b");
+
+    let result = engine.eval::<Dynamic>(r#"#{true: 1, false: 2}"#)?;
+    let data: BTreeMap<bool, INT> = from_dynamic(&result)?;
+    assert_eq!(data[&false], 2);
+
+    // String-only targets reject non-string keys
+    let result = engine.eval::<Dynamic>(r#"#{1: "a"}"#)?;
+    assert!(from_dynamic::<BTreeMap<String, String>>(&result).is_err());
+    let result = engine.eval::<Dynamic>("#{a: 1, 2: 3}")?;
+    assert!(from_dynamic::<Hello>(&result).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "ordered_map")]
+fn test_serde_ordered_map() -> Result<(), Box<EvalAltResult>> {
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Test {
+        zebra: INT,
+        apple: bool,
+        mango: String,
+    }
+
+    let x = Test {
+        zebra: 42,
+        apple: true,
+        mango: "hello".into(),
+    };
+
+    let d = to_dynamic(&x)?;
+    let keys: Vec<_> = d
+        .read_lock::<Map>()
+        .unwrap()
+        .keys()
+        .map(|k| k.to_string())
+        .collect();
+    assert_eq!(keys, ["zebra", "apple", "mango"]);
+
+    let engine = Engine::new();
+    let d = engine.eval::<Dynamic>(r#"#{ zebra: 42, apple: true, mango: "hello" }"#)?;
+    let value: Dynamic = from_dynamic(&d)?;
+    let keys: Vec<_> = value
+        .read_lock::<Map>()
+        .unwrap()
+        .keys()
+        .map(|k| k.to_string())
+        .collect();
+    assert_eq!(keys, ["zebra", "apple", "mango"]);
+    assert_eq!(from_dynamic::<Test>(&d)?, x);
+
+    Ok(())
+}
+
 #[test]
 #[cfg(not(feature = "no_index"))]
 #[cfg(not(feature = "no_object"))]
@@ -734,10 +886,10 @@ fn test_serde_json() -> serde_json::Result<()> {
 
     let mut m = d2.cast::<Map>();
 
-    assert_eq!(m["a"].as_int().unwrap(), 123);
-    assert!(m["c"].as_bool().unwrap());
+    assert_eq!(m[&MapKey::from("a")].as_int().unwrap(), 123);
+    assert!(m[&MapKey::from("c")].as_bool().unwrap());
 
-    let a = m.remove("b").unwrap().cast::<Array>();
+    let a = m.remove(&MapKey::from("b")).unwrap().cast::<Array>();
 
     assert_eq!(a.len(), 3);
     assert_eq!(format!("{:?}", a), "[1, 2, 3]");
@@ -0,0 +1,97 @@
+#![cfg(not(feature = "no_index"))]
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Set, INT};
+
+#[test]
+fn test_sets() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(engine.eval::<String>("type_of(set())")?, "set");
+    assert_eq!(engine.eval::<INT>("len(set([1, 2, 2, 3, 1]))")?, 3);
+    assert!(engine.eval::<bool>("let x = set(); insert(x, 42)")?);
+    assert!(!engine.eval::<bool>("let x = set([42]); insert(x, 42)")?);
+    assert!(engine.eval::<bool>("let x = set([1, 2]); remove(x, 2)")?);
+    assert!(engine.eval::<bool>(r#"let x = set(["a", 'b']); contains(x, 'b')"#)?);
+    assert!(engine.eval::<bool>(r#"let x = set(["a", 'b']); "a" in x"#)?);
+    assert!(!engine.eval::<bool>(r#"let x = set(["a", 'b']); 'a' in x"#)?);
+    assert!(engine.eval::<bool>("let x = set([[1, 2], 3]); [1, 2] in x")?);
+    assert!(engine.eval::<bool>("set([1, 2, 3]) == set([3, 2, 1])")?);
+    assert!(engine.eval::<bool>("set([1, 2]) != set([1, 2, 3])")?);
+    assert_eq!(
+        engine.eval::<INT>("let x = set(); x += 1; x += 2; x += 1; x -= 2; len(x)")?,
+        1
+    );
+    assert_eq!(
+        engine.eval::<INT>("let s = 0; for n in set([1, 2, 3, 3]) { s += n; } s")?,
+        6
+    );
+
+    #[cfg(not(feature = "no_float"))]
+    assert_eq!(engine.eval::<INT>("len(set([1, 1.0]))")?, 2);
+
+    assert!(matches!(
+        *engine
+            .eval::<Set>("set([Fn(\"foo\")])")
+            .expect_err("should error"),
+        EvalAltResult::ErrorMismatchDataType(_, _, _)
+    ));
+
+    let mut a = engine.eval::<Array>("to_array(set([3, 1, 2, 1]))")?;
+    a.sort_by_key(|v| v.as_int().unwrap());
+    assert_eq!(
+        a.into_iter()
+            .map(|v| v.as_int().unwrap())
+            .collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_sets_operations() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let to_ints = |set: Set| {
+        let mut v: Vec<INT> = set.into_iter().map(|v| v.as_int().unwrap()).collect();
+        v.sort();
+        v
+    };
+
+    assert_eq!(
+        to_ints(engine.eval("union(set([1, 2, 3]), set([3, 4]))")?),
+        vec![1, 2, 3, 4]
+    );
+    assert_eq!(
+        to_ints(engine.eval("set([1, 2, 3]) + set([3, 4])")?),
+        vec![1, 2, 3, 4]
+    );
+    assert_eq!(
+        to_ints(engine.eval("intersection(set([1, 2, 3]), set([2, 3, 4]))")?),
+        vec![2, 3]
+    );
+    assert_eq!(
+        to_ints(engine.eval("set([1, 2, 3]) & set([2, 3, 4])")?),
+        vec![2, 3]
+    );
+    assert_eq!(
+        to_ints(engine.eval("difference(set([1, 2, 3]), set([2, 3, 4]))")?),
+        vec![1]
+    );
+    assert_eq!(
+        to_ints(engine.eval("let x = set([1, 2]); x += set([5]); x -= set([1]); x")?),
+        vec![2, 5]
+    );
+
+    assert_eq!(engine.eval::<String>("to_string(set([42]))")?, "{42}");
+    assert!(engine.eval::<bool>("len(set([set([1, 2]), set([2, 1])])) == 1")?);
+
+    let mut set = Set::new();
+    assert!(set.insert(Dynamic::from("hello"))?);
+    assert!(set.insert(Dynamic::from(42 as INT))?);
+    assert!(!set.insert(Dynamic::from(42 as INT))?);
+    assert!(set.insert(Dynamic::from(Array::new())).is_ok());
+    assert!(set.contains(&Dynamic::from("hello")));
+    assert_eq!(set.len(), 3);
+
+    Ok(())
+}
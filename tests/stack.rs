@@ -35,6 +35,102 @@ fn test_stack_overflow_fn_calls() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_stack_tail_calls() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let max = engine.max_call_levels() as INT;
+
+    assert_eq!(
+        engine.eval::<INT>(&format!(
+            r"
+                fn foo(n, acc) {{ if n == 0 {{ acc }} else {{ foo(n - 1, acc + n) }} }}
+                foo({}, 0)
+            ",
+            max * 10
+        ))?,
+        max * 10 * (max * 10 + 1) / 2
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(&format!(
+            r"
+                fn foo(n) {{
+                    if n <= 0 {{ return 42; }}
+                    let x = n - 1;
+                    return foo(x);
+                }}
+                foo({})
+            ",
+            max * 10
+        ))?,
+        42
+    );
+
+    // Not in tail position
+    assert!(matches!(
+        *engine
+            .eval::<INT>(&format!(
+                r"
+                    fn foo(n) {{ if n == 0 {{ 0 }} else {{ let x = foo(n - 1); x }} }}
+                    foo({})
+                ",
+                max + 1
+            ))
+            .expect_err("should error"),
+        EvalAltResult::ErrorStackOverflow(_)
+    ));
+
+    let err = engine
+        .eval::<INT>(&format!(
+            r#"
+                fn foo(n) {{ if n == 0 {{ throw "boom"; }} else {{ foo(n - 1) }} }}
+                foo({})
+            "#,
+            max * 10
+        ))
+        .expect_err("should error");
+
+    assert!(matches!(
+        *err,
+        EvalAltResult::ErrorInFunctionCall(ref fn_name, _, ref err, _)
+            if fn_name == "foo" && matches!(**err, EvalAltResult::ErrorRuntime(_, pos) if pos.line() == Some(2))
+    ));
+
+    // Errors within tail calls are wrapped once for the outermost call, instead of once per call
+    let script = r#"fn foo(n) { if n == 0 { throw "boom"; } else { foo(n - 1) } } foo(3)"#;
+
+    assert!(matches!(
+        *engine.eval::<INT>(script).expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(ref fn_name, _, _, _) if fn_name == "foo"
+    ));
+
+    let script =
+        r#"fn foo(n) { if n == 0 { throw "boom"; } else { let x = foo(n - 1); x } } foo(3)"#;
+
+    assert!(matches!(
+        *engine.eval::<INT>(script).expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(ref fn_name, _, _, _) if fn_name == "foo < foo < foo < foo"
+    ));
+
+    engine.set_max_operations(500);
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(
+                r"
+                    fn foo(n) { foo(n + 1) }
+                    foo(0)
+                "
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorTooManyOperations(_)
+    ));
+
+    Ok(())
+}
+
 #[test]
 fn test_stack_overflow_parsing() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
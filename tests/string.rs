@@ -1,4 +1,5 @@
-use rhai::{Engine, EvalAltResult, ImmutableString, RegisterFn, Scope, INT};
+use rhai::{Dynamic, Engine, EvalAltResult, ImmutableString, RegisterFn, Scope, INT};
+use std::borrow::Borrow;
 
 #[test]
 fn test_string() -> Result<(), Box<EvalAltResult>> {
@@ -83,6 +84,66 @@ fn test_string_mut() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+fn test_string_short() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let mut s: ImmutableString = "abc".into();
+    let s2 = s.clone();
+    s += "defghijklmn";
+    assert_eq!(s, "abcdefghijklmn");
+    assert_eq!(s2, "abc");
+    s -= String::from("defghijklmn");
+    assert_eq!(s, "abc");
+    assert_eq!(s, s2);
+    assert_eq!(s.into_owned(), "abc");
+
+    let mut s: ImmutableString = "❤❤".into();
+    s.make_mut().push('❤');
+    assert_eq!(s.as_str(), "❤❤❤");
+    assert_eq!(s.chars().count(), 3);
+    assert_eq!(ImmutableString::default(), "");
+
+    // Changing a string in place never changes other strings with the same contents
+    let s1: ImmutableString = "ab".into();
+    let mut s2: ImmutableString = String::from("ab").into();
+    s2.make_mut().push('c');
+    assert_eq!(s1, "ab");
+    assert_eq!(s2, "abc");
+    assert_eq!(ImmutableString::from("ab"), "ab");
+
+    let s: ImmutableString = "xyz".into();
+    let r: &String = s.as_ref();
+    assert_eq!(r, "xyz");
+    let r: &String = s.borrow();
+    assert_eq!(r, "xyz");
+    assert_eq!(
+        Dynamic::from(s).read_lock::<String>().unwrap().as_str(),
+        "xyz"
+    );
+
+    assert_eq!(
+        engine.eval::<String>(r#"let s = ""; for i in range(0, 5) { s += "x" + i } s"#)?,
+        "x0x1x2x3x4"
+    );
+
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let x = #{};
+                x["a" + "b"] = 1;
+                x["ab"] += 41;
+                x.ab
+            "#
+        )?,
+        42
+    );
+
+    Ok(())
+}
+
 #[cfg(not(feature = "no_object"))]
 #[test]
 fn test_string_substring() -> Result<(), Box<EvalAltResult>> {
@@ -255,3 +316,173 @@ fn test_string_split() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+fn test_string_format() -> Result<(), Box<EvalAltResult>> {
+    #[derive(Debug, Clone)]
+    struct Point(INT, INT);
+
+    let mut engine = Engine::new();
+
+    engine
+        .register_fn("point", |x: INT, y: INT| Point(x, y))
+        .register_fn("to_string", |p: &mut Point| format!("({}, {})", p.0, p.1))
+        .register_fn("to_debug", |p: &mut Point| {
+            format!("Point({}, {})", p.0, p.1)
+        });
+
+    assert_eq!(
+        engine.eval::<String>(r#"format("[{:<6}] [{:^7}] [{:*>5}]", "ab", "mid", 42)"#)?,
+        "[ab    ] [  mid  ] [***42]"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"format("{:+05} {:#x} {:#b} {:X}", 42, 255, 5, 255)"#)?,
+        "+0042 0xff 0b101 FF"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"format("{1} {0} {} {{}}", "a", "b")"#)?,
+        "b a a {}"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"format("{:?} {:.2}", "hi", "hello")"#)?,
+        r#""hi" he"#
+    );
+    assert_eq!(
+        engine.eval::<String>(
+            r#"format("{} / {:?} / {:>10}", point(1, 2), point(3, 4), point(5, 6))"#
+        )?,
+        "(1, 2) / Point(3, 4) /     (5, 6)"
+    );
+
+    #[cfg(not(feature = "no_float"))]
+    assert_eq!(
+        engine.eval::<String>(r#"format("{:>8.2} {} {:08.3}", 3.14159, "pi", -1.5)"#)?,
+        "    3.14 pi -001.500"
+    );
+
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        engine.eval::<String>(
+            r#"format("{name} is {age:03} [{:w$}]", "x", #{ name: "Bob", age: 7, w: 3 })"#
+        )?,
+        "Bob is 007 [x  ]"
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"format("{:x}", "oops")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorMismatchDataType(_, _, _)
+    ));
+    assert!(engine.eval::<String>(r#"format("{} {}", 1)"#).is_err());
+    assert!(engine.eval::<String>(r#"format("{", 1)"#).is_err());
+    assert!(engine.eval::<String>(r#"format("{:q}", 1)"#).is_err());
+
+    #[cfg(not(feature = "no_float"))]
+    assert!(engine
+        .eval::<String>(r#"format("{:.200000000}", 1.0)"#)
+        .is_err());
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"format("a{:❤>9223372036854775807}", 1)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"format("a{:>18446744073709551615}", 1)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "unchecked"))]
+#[test]
+fn test_string_format_limits() {
+    let mut engine = Engine::new();
+    engine.set_max_string_size(100);
+
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"format("{:>1000}", 1)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"format("{:.1000}", "x")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert_eq!(
+        engine.eval::<String>(r#"format("{:>5}", 1)"#).unwrap(),
+        "    1"
+    );
+}
+
+#[test]
+fn test_string_more() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(engine.eval::<String>(r#"to_upper("Straße")"#)?, "STRASSE");
+    assert_eq!(engine.eval::<String>(r#"to_lower("ÀBC")"#)?, "àbc");
+    assert_eq!(engine.eval::<char>("to_upper('é')")?, 'É');
+    assert!(engine.eval::<bool>("is_upper('X') && is_lower('x')")?);
+    assert!(engine.eval::<bool>(r#"starts_with("hello", "he") && starts_with("hello", 'h')"#)?);
+    assert!(engine.eval::<bool>(r#"ends_with("hello", "lo") && !ends_with("hello", 'x')"#)?);
+    assert!(engine.eval::<bool>(r#"contains("hello", "ell") && contains("hello", 'o')"#)?);
+    assert!(engine.eval::<bool>(r#""ell" in "hello""#)?);
+    assert!(engine.eval::<bool>(r#"eq_ignore_case("ΣΊΣΥΦΟΣ", "σίσυφοσ")"#)?);
+    assert_eq!(
+        engine.eval::<INT>(r#"compare_ignore_case("apple", "BANANA")"#)?,
+        -1
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"compare_ignore_case("Apple", "aPPLE")"#)?,
+        0
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let s = "  hi  "; trim_start(s); s + "|""#)?,
+        "hi  |"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let s = "  hi  "; trim_end(s); s + "|""#)?,
+        "  hi|"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let s = "héllo"; reverse(s); s"#)?,
+        "olléh"
+    );
+    assert_eq!(engine.eval::<char>(r#"char_at("héllo", 1)"#)?, 'é');
+    assert!(engine.eval::<bool>(r#"char_at("héllo", 5) == null"#)?);
+    assert_eq!(engine.eval::<String>(r#"repeat("ab", 3)"#)?, "ababab");
+    assert_eq!(engine.eval::<String>(r#"repeat("ab", -1)"#)?, "");
+
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(engine.eval::<INT>(r#"len(lines("a\nb\r\nc"))"#)?, 3);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "unchecked"))]
+#[test]
+fn test_string_more_limits() {
+    let mut engine = Engine::new();
+    engine.set_max_string_size(10);
+
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"repeat("abc", 4)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"to_upper("ŉŉŉŉ")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, _)
+    ));
+    assert!(engine.eval::<String>(r#"repeat("abc", 3)"#).is_ok());
+}
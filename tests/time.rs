@@ -1,14 +1,11 @@
 #![cfg(not(feature = "no_std"))]
 #![cfg(not(target_arch = "wasm32"))]
 
-use rhai::{Engine, EvalAltResult};
+use rhai::{DateTime, Engine, EvalAltResult, TimeSpan, INT};
 
 #[cfg(not(feature = "no_float"))]
 use rhai::FLOAT;
 
-#[cfg(feature = "no_float")]
-use rhai::INT;
-
 #[test]
 fn test_timestamp() -> Result<(), Box<EvalAltResult>> {
     let engine = Engine::new();
@@ -107,3 +104,140 @@ fn test_timestamp_op() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+fn test_datetime() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.set_clock(|| DateTime::from_timestamp(1614834367, 26_490_000).unwrap());
+
+    assert_eq!(engine.eval::<String>("type_of(now())")?, "datetime");
+    assert_eq!(
+        engine.eval::<String>("to_string(now())")?,
+        "2021-03-04T05:06:07.026490Z"
+    );
+    assert_eq!(
+        engine.eval::<DateTime>("now()")?,
+        DateTime::from_timestamp(1614834367, 26_490_000).unwrap()
+    );
+    assert_eq!(
+        engine.eval::<INT>("let d = now(); year(d) * 10000 + month(d) * 100 + day(d)")?,
+        20210304
+    );
+    assert_eq!(
+        engine.eval::<INT>("let d = now(); hour(d) * 10000 + minute(d) * 100 + second(d)")?,
+        50607
+    );
+    assert_eq!(engine.eval::<INT>("weekday(now())")?, 4);
+    assert_eq!(engine.eval::<INT>("day_of_year(now())")?, 63);
+    assert_eq!(engine.eval::<INT>("timestamp(now())")?, 1614834367);
+    assert_eq!(engine.eval::<INT>("nanosecond(now())")?, 26_490_000);
+
+    assert_eq!(
+        engine.eval::<String>(r#"format(now(), "%A %e %B %Y, %I:%M:%S.%3f %p %:z")"#)?,
+        "Thursday  4 March 2021, 05:06:07.026 AM +00:00"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"format(with_offset(now(), "-09:30"), "%F %T %z")"#)?,
+        "2021-03-03 19:36:07 -0930"
+    );
+    assert!(engine.eval::<String>(r#"format(now(), "%Q")"#).is_err());
+
+    assert_eq!(
+        engine
+            .eval::<String>(r#"to_string(to_utc(parse_datetime("2021-03-04T05:06:07+08:00")))"#)?,
+        "2021-03-03T21:06:07Z"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"to_string(parse_datetime("2020-02-29"))"#)?,
+        "2020-02-29T00:00:00Z"
+    );
+    assert!(engine.eval::<bool>(
+        r#"parse_datetime("2021-03-04 13:06:07.5+0800") == datetime(2021, 3, 4, 5, 6, 7) + milliseconds(500)"#
+    )?);
+    assert!(engine
+        .eval::<DateTime>(r#"parse_datetime("2021-02-29")"#)
+        .is_err());
+    assert!(engine
+        .eval::<DateTime>(r#"parse_datetime("2021-03-04T25:00")"#)
+        .is_err());
+    assert!(engine.eval::<DateTime>("datetime(2021, 13, 1)").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_datetime_arithmetic() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>("to_string(add_months(datetime(2021, 1, 31), 1))")?,
+        "2021-02-28T00:00:00Z"
+    );
+    assert_eq!(
+        engine.eval::<String>("to_string(add_years(datetime(2020, 2, 29), -4))")?,
+        "2016-02-29T00:00:00Z"
+    );
+    assert_eq!(
+        engine.eval::<String>("to_string(add_days(datetime(2021, 12, 31, 23, 0, 0), 1))")?,
+        "2022-01-01T23:00:00Z"
+    );
+    assert_eq!(
+        engine.eval::<String>(
+            "let d = datetime(2021, 3, 4); d += days(1) + hours(2); d -= minutes(30); to_string(d)"
+        )?,
+        "2021-03-05T01:30:00Z"
+    );
+    assert_eq!(
+        engine
+            .eval::<String>("to_string(datetime(2021, 3, 4) - datetime(2021, 3, 1, 12, 0, 0))")?,
+        "P2DT12H"
+    );
+    assert_eq!(
+        engine.eval::<INT>("hours(datetime(2021, 3, 1) - datetime(2021, 3, 4))")?,
+        -72
+    );
+    assert!(engine.eval::<bool>("datetime(2021, 3, 4) > datetime(2021, 3, 3)")?);
+    assert!(engine
+        .eval::<bool>(r#"datetime(2021, 3, 4) == with_offset(datetime(2021, 3, 4), "+08:00")"#)?);
+    assert!(engine
+        .eval::<DateTime>("datetime(9999, 12, 31) + days(1)")
+        .is_err());
+
+    assert_eq!(engine.eval::<String>("type_of(seconds(1))")?, "duration");
+    assert_eq!(engine.eval::<String>("to_string(seconds(0))")?, "PT0S");
+    assert_eq!(
+        engine.eval::<String>("to_string(-milliseconds(90061500))")?,
+        "-P1DT1H1M1.5S"
+    );
+    assert_eq!(engine.eval::<INT>("minutes(hours(1) * 3 / 2)")?, 90);
+    assert_eq!(engine.eval::<INT>("milliseconds(abs(-seconds(5)))")?, 5000);
+    assert!(engine.eval::<bool>("minutes(1) < seconds(61)")?);
+    assert!(engine.eval::<TimeSpan>("seconds(1) / 0").is_err());
+    assert_eq!(
+        engine.eval::<TimeSpan>("days(1) - hours(1)")?,
+        TimeSpan::from_secs(23 * 3600)
+    );
+
+    #[cfg(not(feature = "no_float"))]
+    assert_eq!(engine.eval::<FLOAT>("to_float(seconds(1.25))")?, 1.25);
+
+    #[cfg(not(feature = "only_i32"))]
+    for script in &[
+        "days(9223372036854775807)",
+        "days(-9223372036854775807 - 1)",
+        "hours(9223372036854775807)",
+        "hours(-9223372036854775807 - 1)",
+        "minutes(9223372036854775807)",
+        "minutes(-9223372036854775807 - 1)",
+        "with_offset(datetime(2021, 3, 4), 9223372036854775807)",
+        "with_offset(datetime(2021, 3, 4), -9223372036854775807 - 1)",
+    ] {
+        assert!(matches!(
+            *engine.consume(script).expect_err("should error"),
+            EvalAltResult::ErrorArithmetic(_, _)
+        ));
+    }
+
+    Ok(())
+}
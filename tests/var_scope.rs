@@ -120,3 +120,48 @@ fn test_var_resolver() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+fn test_var_scope_external() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let mut scope = Scope::new();
+    scope.push("x", 1 as INT);
+    scope.push("y", 2 as INT);
+    scope.push("x", 40 as INT);
+
+    let ast = engine.compile("let z = 0; for i in range(0, 3) { z += x + y; } z")?;
+
+    assert_eq!(engine.eval_ast_with_scope::<INT>(&mut scope, &ast)?, 126);
+
+    scope.rewind(1);
+    scope.push("x", 10 as INT);
+    scope.push("y", 20 as INT);
+
+    assert_eq!(engine.eval_ast_with_scope::<INT>(&mut scope, &ast)?, 90);
+
+    let mut scope = Scope::new();
+    scope.push("x", 42 as INT);
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, r#"let y = x; eval("let x = 1"); x + y"#)?,
+        43
+    );
+
+    #[cfg(not(feature = "no_function"))]
+    #[cfg(not(feature = "no_closure"))]
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let x = 1;
+                let f = |y| { let z = 0; for i in range(0, 3) { z += x * y; } z };
+                x = 2;
+                f.call(3)
+            "#
+        )?,
+        18
+    );
+
+    Ok(())
+}